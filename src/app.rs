@@ -1,4 +1,5 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 use clap::{ArgAction, Parser, ValueHint};
 
@@ -64,6 +65,30 @@ pub struct Cli {
     #[arg(long, value_name = "NUM")]
     pub depth: Option<usize>,
 
+    /// List directories as they exist at a git revision (commit, tag, branch, HEAD~N)
+    #[arg(long, value_name = "REV")]
+    pub at: Option<String>,
+
+    /// Date each entry by the last commit that changed it (used with --at)
+    #[arg(long, requires = "at")]
+    pub last_modified: bool,
+
+    /// List files from the git index instead of walking the disk
+    #[arg(long, conflicts_with_all = ["at", "no_tracked"])]
+    pub tracked: bool,
+
+    /// Also list untracked files that are not ignored by git (used with --tracked)
+    #[arg(long, requires = "tracked")]
+    pub untracked: bool,
+
+    /// Walk the disk even when --llm or the config file would list tracked files
+    #[arg(long)]
+    pub no_tracked: bool,
+
+    /// Print the git repository's branch, upstream, ahead/behind and stash counts first
+    #[arg(long)]
+    pub repo_info: bool,
+
     /// Display directories themselves, and not their contents (recursively when used with --tree)
     #[arg(short, long, conflicts_with = "recursive")]
     pub directory_only: bool,
@@ -80,14 +105,35 @@ pub struct Cli {
     #[arg(long)]
     pub total_size: bool,
 
+    /// Report the apparent size of files or the space they use on disk [default: apparent]
+    #[arg(long, value_name = "MODE", value_parser = ["apparent", "disk"])]
+    pub size_mode: Option<String>,
+
     /// How to display date [default: date] [possible values: date, locale, relative, +date-time-format]
     #[arg(long, value_parser = validate_date_argument)]
     pub date: Option<String>,
 
-    /// Sort by time modified
+    /// Sort by time modified, or by the timestamp chosen with --time
     #[arg(short = 't', long)]
     pub timesort: bool,
 
+    /// Which timestamp to show and sort by [default: modified]
+    #[arg(
+        long,
+        value_name = "WORD",
+        value_parser = ["modified", "accessed", "changed", "birth"],
+        overrides_with_all = ["access_time", "change_time"]
+    )]
+    pub time: Option<String>,
+
+    /// Show and sort by the time of last access. Same as --time=accessed
+    #[arg(short = 'u', overrides_with_all = ["time", "change_time"])]
+    pub access_time: bool,
+
+    /// Show and sort by the time of last status change. Same as --time=changed
+    #[arg(short = 'c', overrides_with_all = ["time", "access_time"])]
+    pub change_time: bool,
+
     /// Sort by size
     #[arg(short = 'S', long)]
     pub sizesort: bool,
@@ -108,7 +154,7 @@ pub struct Cli {
     #[arg(
         long,
         value_name = "TYPE",
-        value_parser = ["size", "time", "version", "extension", "git", "none"],
+        value_parser = ["size", "time", "version", "extension", "git", "mime", "none"],
         overrides_with_all = ["timesort", "sizesort", "extensionsort", "versionsort", "gitsort", "no_sort"]
     )]
     pub sort: Option<String>,
@@ -133,7 +179,7 @@ pub struct Cli {
     #[arg(
     long,
     value_delimiter = ',',
-    value_parser = ["permission", "user", "group", "context", "size", "date", "name", "inode", "links", "git"],
+    value_parser = ["permission", "user", "group", "context", "size", "date", "name", "inode", "links", "git", "fs", "matches", "hash", "mime", "media", "binary"],
     )]
     pub blocks: Vec<String>,
 
@@ -145,11 +191,80 @@ pub struct Cli {
     #[arg(long)]
     pub no_symlink: bool,
 
-    /// Do not display files/directories with names matching the glob pattern(s).
+    /// Do not display files/directories matching the glob pattern(s), on top of the
+    /// built-in list. Like .gitignore: 'docs/build' matches from the listed directory,
+    /// 'cache/' only directories and '!Cargo.lock' shows what an earlier pattern hides.
     /// More than one can be specified by repeating the argument
     #[arg(short = 'I', long, value_name = "PATTERN")]
     pub ignore_glob: Vec<String>,
 
+    /// Display the files/directories matching the glob pattern(s) even when an ignore
+    /// pattern or a .sapignore file hides them
+    #[arg(long, value_name = "PATTERN")]
+    pub include: Vec<String>,
+
+    /// Do not hide the built-in patterns: the build directories of the projects detected in
+    /// each input directory, lock files and binary files
+    #[arg(long)]
+    pub no_default_ignores: bool,
+
+    /// Print the ignore patterns in effect in each input directory, with the file or flag
+    /// each comes from, instead of listing it
+    #[arg(long)]
+    pub print_ignores: bool,
+
+    /// In trees, end each directory whose entries were ignored with a dimmed
+    /// '… +N ignored' row
+    #[arg(long)]
+    pub show_ignored: bool,
+
+    /// List the entries the ignore rules hide, each with the pattern and the file or flag
+    /// it comes from, instead of the visible ones
+    #[arg(long)]
+    pub explain_ignores: bool,
+
+    /// Only list the entries matching EXPR, like 'ext == rs && size > 10K' or
+    /// 'mtime < 7d || git == modified'. Trees keep the directories leading to a match
+    #[arg(long = "where", value_name = "EXPR")]
+    pub filter: Option<String>,
+
+    /// Only list the files whose contents match the regular expression PATTERN, with the
+    /// directories leading to them
+    #[arg(long, value_name = "PATTERN")]
+    pub contains: Option<String>,
+
+    /// Match the --contains pattern as a literal string
+    #[arg(long, requires = "contains")]
+    pub contains_fixed: bool,
+
+    /// How --contains treats letter case; "smart" ignores it unless the pattern has an
+    /// uppercase letter [default: sensitive]
+    #[arg(long, value_name = "MODE", value_parser = ["sensitive", "insensitive", "smart"], requires = "contains")]
+    pub contains_case: Option<String>,
+
+    /// Skip the files larger than SIZE when searching contents, like 512K or 16M [default: 4M]
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, requires = "contains")]
+    pub contains_max_size: Option<u64>,
+
+    /// Add the numbers of the first matching lines to the --llm records
+    #[arg(long, requires = "contains")]
+    pub contains_lines: bool,
+
+    /// Instead of listing, group the files below the inputs that have the same contents and
+    /// print each group with the space its extra copies waste. `sap dupes` is the same,
+    /// unless the current directory holds a file named `dupes`
+    #[arg(long)]
+    pub duplicates: bool,
+
+    /// Instead of listing, print one digest of each input that only changes with the names,
+    /// modes and contents of the files below it that are not ignored
+    #[arg(long)]
+    pub fingerprint: bool,
+
+    /// Which algorithm hashes file contents for the hash block and --fingerprint [default: blake3]
+    #[arg(long, value_name = "ALGORITHM", value_parser = ["blake3", "sha256"])]
+    pub hash_algorithm: Option<String>,
+
     /// Display the index number of each file
     #[arg(short, long)]
     pub inode: bool,
@@ -164,6 +279,10 @@ pub struct Cli {
     #[arg(short = 'L', long)]
     pub dereference: bool,
 
+    /// Do not descend into directories on other file systems
+    #[arg(short = 'x', long)]
+    pub one_file_system: bool,
+
     /// Print security context (label) of each file
     #[arg(short = 'Z', long)]
     pub context: bool,
@@ -193,6 +312,29 @@ pub struct Cli {
     #[arg(short = 'N', long)]
     pub literal: bool,
 
+    /// How many threads walk directories: a number, 0 for one per CPU, or "serial" to walk on
+    /// the main thread in a deterministic order [default: 0]
+    #[arg(long, value_name = "N", value_parser = validate_threads_argument)]
+    pub threads: Option<String>,
+
+    /// How long, in milliseconds, a walk waits for a busy thread pool
+    #[arg(long, value_name = "MS")]
+    pub busy_timeout: Option<u64>,
+
+    /// Serve directories unchanged since the last run from the scan cache, or read them all
+    /// again with "refresh" [default: off]
+    #[arg(long, value_name = "MODE", value_parser = ["on", "refresh", "off"])]
+    pub cache: Option<String>,
+
+    /// Stop the walk after listing NUM entries, and mark the directories it cut
+    #[arg(long, value_name = "NUM")]
+    pub max_entries: Option<usize>,
+
+    /// Stop the walk after DURATION, in seconds or with an ms, s or m suffix, and mark the
+    /// directories it cut
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+
     /// Output JSON Lines format for LLM consumption
     #[arg(long)]
     pub llm: bool,
@@ -220,6 +362,56 @@ fn validate_date_argument(arg: &str) -> Result<String, String> {
     }
 }
 
+fn validate_threads_argument(arg: &str) -> Result<String, String> {
+    if arg == "serial" || arg.parse::<usize>().is_ok() {
+        Result::Ok(arg.to_owned())
+    } else {
+        Result::Err("possible values: serial, a number of threads".to_owned())
+    }
+}
+
+/// Parse a duration in seconds, like "1.5", or with an "ms", "s" or "m" suffix
+pub fn parse_duration(arg: &str) -> Result<Duration, String> {
+    let (number, scale) = if let Some(ms) = arg.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(s) = arg.strip_suffix('s') {
+        (s, 1.0)
+    } else if let Some(m) = arg.strip_suffix('m') {
+        (m, 60.0)
+    } else {
+        (arg, 1.0)
+    };
+
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|value| Duration::try_from_secs_f64(value * scale).ok())
+        .ok_or_else(|| "expected a duration like 30, 1.5s, 500ms or 2m".to_owned())
+}
+
+/// Parse a size in bytes, like "4096", or with a K, M, G or T suffix in powers of 1024, like
+/// "1.5M"; "KB" and "KiB" spell the same suffix
+pub fn parse_size(arg: &str) -> Result<u64, String> {
+    let split = arg
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(arg.len());
+    let (number, unit) = arg.split_at(split);
+    let exponent = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => Some(0),
+        "k" | "kb" | "kib" => Some(1),
+        "m" | "mb" | "mib" => Some(2),
+        "g" | "gb" | "gib" => Some(3),
+        "t" | "tb" | "tib" => Some(4),
+        _ => None,
+    };
+
+    exponent
+        .zip(number.parse::<f64>().ok())
+        .map(|(exponent, value)| (value * 1024f64.powi(exponent)) as u64)
+        .ok_or_else(|| "expected a size like 4096, 10K, 1.5M or 2G".to_owned())
+}
+
 pub fn validate_time_format(formatter: &str) -> Result<String, String> {
     let mut chars = formatter.chars();
     loop {
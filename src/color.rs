@@ -70,11 +70,33 @@ pub enum Elem {
         valid: bool,
     },
 
+    /// File system type and source
+    Filesystem {
+        valid: bool,
+    },
+
+    /// Blocks read from the contents of files
+    Matches,
+    Hash,
+    Mime,
+    Media,
+    Binary,
+
     TreeEdge,
 
     GitStatus {
         status: GitStatus,
     },
+
+    /// Repository header
+    GitRepoRoot,
+    GitBranch,
+    GitDetached,
+    GitUpstream,
+    GitAhead,
+    GitBehind,
+    GitStash,
+    GitOperation,
 }
 
 impl Elem {
@@ -140,6 +162,13 @@ impl Elem {
             Elem::TreeEdge => theme.tree_edge,
             Elem::Links { valid: false } => theme.links.invalid,
             Elem::Links { valid: true } => theme.links.valid,
+            Elem::Filesystem { valid: false } => theme.filesystem.invalid,
+            Elem::Filesystem { valid: true } => theme.filesystem.valid,
+            Elem::Matches => theme.content.matches,
+            Elem::Hash => theme.content.hash,
+            Elem::Mime => theme.content.mime,
+            Elem::Media => theme.content.media,
+            Elem::Binary => theme.content.binary,
 
             Elem::GitStatus {
                 status: GitStatus::Default,
@@ -174,6 +203,15 @@ impl Elem {
             Elem::GitStatus {
                 status: GitStatus::GitConflicted,
             } => theme.git_status.conflicted,
+
+            Elem::GitRepoRoot => theme.git_repo.root,
+            Elem::GitBranch => theme.git_repo.branch,
+            Elem::GitDetached => theme.git_repo.detached,
+            Elem::GitUpstream => theme.git_repo.upstream,
+            Elem::GitAhead => theme.git_repo.ahead,
+            Elem::GitBehind => theme.git_repo.behind,
+            Elem::GitStash => theme.git_repo.stash,
+            Elem::GitOperation => theme.git_repo.operation,
         }
     }
 }
@@ -376,6 +414,7 @@ impl Colors {
         &self,
         file_type: &FileType,
         extension: Option<&str>,
+        mime: Option<&str>,
         git_status: Option<&GitFileStatus>,
         has_error: bool,
         draw_attention: bool,
@@ -407,7 +446,7 @@ impl Colors {
             
             // Evaluate rules in order - first match wins
             for rule in &theme.render_rules {
-                if rule.matches(file_type, extension, simple_git_status, error_status, highlight) {
+                if rule.matches(file_type, extension, mime, simple_git_status, error_status, highlight) {
                     return self.apply_rule_actions(&rule.display, file_type);
                 }
             }
@@ -1,11 +1,16 @@
 //! This module provides methods to handle the program's config files and
 //! operations related to this.
+use crate::flags::cache::CacheMode;
+use crate::flags::contains::ContainsCase;
 use crate::flags::display::Display;
+use crate::flags::hash_algorithm::HashAlgorithm;
 use crate::flags::icons::{IconOption, IconTheme};
 use crate::flags::layout::Layout;
 use crate::flags::permission::PermissionFlag;
 use crate::flags::size::SizeFlag;
+use crate::flags::size_mode::SizeMode;
 use crate::flags::sorting::{DirGrouping, SortColumn};
+use crate::flags::time::TimeField;
 use crate::flags::HyperlinkOption;
 use crate::flags::{ColorOption, ThemeOption};
 use crate::print_error;
@@ -26,26 +31,41 @@ use std::io;
 pub struct Config {
     pub classic: Option<bool>,
     pub blocks: Option<Vec<String>>,
+    pub cache: Option<Cache>,
     pub color: Option<Color>,
+    pub contains: Option<Contains>,
     pub date: Option<String>,
+    pub default_ignores: Option<bool>,
     pub dereference: Option<bool>,
     pub display: Option<Display>,
+    pub hash_algorithm: Option<HashAlgorithm>,
     pub icons: Option<Icons>,
     pub ignore_globs: Option<Vec<String>>,
+    pub include_globs: Option<Vec<String>>,
     pub indicators: Option<bool>,
     pub layout: Option<Layout>,
+    pub one_file_system: Option<bool>,
     pub recursion: Option<Recursion>,
+    pub repo_info: Option<bool>,
+    pub scan_limits: Option<ScanLimits>,
     pub size: Option<SizeFlag>,
+    pub size_mode: Option<SizeMode>,
+    pub show_ignored: Option<bool>,
     pub permission: Option<PermissionFlag>,
     pub sorting: Option<Sorting>,
     pub no_symlink: Option<bool>,
     pub total_size: Option<bool>,
+    pub tracked: Option<bool>,
     pub symlink_arrow: Option<String>,
+    pub threads: Option<Threads>,
+    pub time: Option<TimeField>,
     pub hyperlink: Option<HyperlinkOption>,
     pub header: Option<bool>,
     pub literal: Option<bool>,
     pub truncate_owner: Option<TruncateOwner>,
     pub llm: Option<bool>,
+    #[serde(rename = "where")]
+    pub filter: Option<String>,
 }
 
 #[derive(Eq, PartialEq, Debug, Deserialize)]
@@ -75,6 +95,36 @@ pub struct Sorting {
     pub dir_grouping: Option<DirGrouping>,
 }
 
+#[derive(Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Threads {
+    pub count: Option<usize>,
+    pub serial: Option<bool>,
+    pub busy_timeout: Option<u64>,
+}
+
+#[derive(Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Cache {
+    pub mode: Option<CacheMode>,
+    pub max_size: Option<u64>,
+}
+
+#[derive(Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Contains {
+    pub case: Option<ContainsCase>,
+    pub max_size: Option<String>,
+    pub line_numbers: Option<bool>,
+}
+
+#[derive(Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScanLimits {
+    pub max_entries: Option<usize>,
+    pub timeout: Option<String>,
+}
+
 #[derive(Eq, PartialEq, Debug, Deserialize)]
 pub struct TruncateOwner {
     pub after: Option<usize>,
@@ -126,26 +176,40 @@ impl Config {
         Self {
             classic: None,
             blocks: None,
+            cache: None,
             color: None,
+            contains: None,
             date: None,
+            default_ignores: None,
             dereference: None,
             display: None,
+            hash_algorithm: None,
             icons: None,
             ignore_globs: None,
+            include_globs: None,
             indicators: None,
             layout: None,
+            one_file_system: None,
             recursion: None,
+            repo_info: None,
+            scan_limits: None,
             size: None,
+            size_mode: None,
+            show_ignored: None,
             permission: None,
             sorting: None,
             no_symlink: None,
             total_size: None,
+            tracked: None,
             symlink_arrow: None,
+            threads: None,
+            time: None,
             hyperlink: None,
             header: None,
             literal: None,
             truncate_owner: None,
             llm: None,
+            filter: None,
         }
     }
 
@@ -247,7 +311,8 @@ classic: false
 # == Blocks ==
 # This specifies the columns and their order when using the long and the tree
 # layout.
-# Possible values: permission, user, group, context, size, date, name, inode, git
+# Possible values: permission, user, group, context, size, date, name, inode, git, fs,
+# matches, hash, mime, media, binary
 blocks:
   - permission
   - user
@@ -278,6 +343,12 @@ color:
 # Possible values: date, locale, relative, +<date_format>
 # date: date
 
+# == Time ==
+# Which timestamp the date column shows and the "time" sorting uses. Times the
+# platform or file system does not record are shown as unavailable.
+# Possible values: modified, accessed, changed, birth
+time: modified
+
 # == Dereference ==
 # Whether to dereference symbolic links.
 # Possible values: false, true
@@ -302,15 +373,40 @@ icons:
   separator: " "
 
 # == Ignore Globs ==
-# A list of globs to ignore when listing.
-# Default includes common build dirs, dependencies, and large files.
-# To override defaults and use custom patterns:
+# A list of globs to ignore when listing, added to the built-in list: version
+# control, editor and OS files, and the build and dependency dirs of the
+# projects found in each listed directory (target/ next to a Cargo.toml,
+# node_modules/ next to a package.json, ...). `sap
+# --print-ignores` shows the list for a directory. Patterns work like .gitignore:
+# "docs/build" matches from the listed directory, "cache/" only directories,
+# and "!Cargo.lock" shows what an earlier pattern hides. .sapignore files in
+# the listed directories are read too, and win over this list.
 # ignore-globs:
-#   - .git
-#   - node_modules
 #   - "*.tmp"
-# To disable all default patterns and start fresh:
-# ignore-globs: []
+#   - "src/**/generated/*.rs"
+#   - "!Cargo.lock"
+# Globs to show even when an ignore pattern or a .sapignore file hides them.
+# include-globs:
+#   - ".vscode"
+# To disable the built-in list and start fresh:
+# default-ignores: false
+# End each tree directory whose entries were ignored with a "… +N ignored" row.
+# show-ignored: false
+
+# == Contains ==
+# How --contains searches file contents. Binary files and files larger than
+# max-size are skipped.
+contains:
+  # Possible values: sensitive, insensitive, smart
+  case: sensitive
+  max-size: 4M
+  # Add the numbers of the first matching lines to the --llm records.
+  line-numbers: false
+
+# == Where ==
+# Only list the entries matching this expression, as with --where. Fields:
+# name, path, ext, type, size, mtime, atime, owner, perms, git and depth.
+# where: "ext in (rs, toml) && mtime < 7d"
 
 # == Indicators ==
 # Whether to add indicator characters to certain listed files.
@@ -323,6 +419,11 @@ indicators: false
 # Possible values: grid, tree, oneline
 layout: grid
 
+# == One file system ==
+# Whether to stay on the file system of each input path when descending.
+# Possible values: false, true
+one-file-system: false
+
 # == Recursion ==
 recursion:
   # Whether to enable recursion.
@@ -332,11 +433,28 @@ recursion:
   # it unspecified for (virtually) infinite.
   # depth: 3
 
+# == Repo info ==
+# Whether to print the git repository summary (branch, upstream, stashes)
+# before the listing.
+# Possible values: false, true
+repo-info: false
+
 # == Size ==
 # Specifies the format of the size column.
 # Possible values: default, short, bytes
 size: default
 
+# == Size mode ==
+# Which size to report: the length of the content, or the space allocated on
+# disk. Files sharing an inode through hard links are counted once in totals.
+# Possible values: apparent, disk
+size-mode: apparent
+
+# == Hash algorithm ==
+# Which algorithm hashes file contents for the `hash` block and --fingerprint.
+# Possible values: blake3, sha256
+hash-algorithm: blake3
+
 # == Permission ==
 # Specify the format of the permission column.
 # Possible value: rwx, octal, attributes, disable
@@ -345,7 +463,7 @@ size: default
 # == Sorting ==
 sorting:
   # Specify what to sort by.
-  # Possible values: extension, name, time, size, version
+  # Possible values: extension, name, time, size, version, mime
   column: name
   # Whether to reverse the sorting.
   # Possible values: false, true
@@ -365,6 +483,13 @@ no-symlink: false
 # Possible values: false, true
 total-size: false
 
+# == Tracked ==
+# Whether to list the files of the git index instead of walking the disk, as
+# with --tracked; --llm listings always do. Inputs outside a git work tree are
+# walked on disk; pass --no-tracked to walk the disk for one listing.
+# Possible values: false, true
+tracked: false
+
 # == Hyperlink ==
 # Whether to display the total size of directories.
 # Possible values: always, auto, never
@@ -374,6 +499,37 @@ hyperlink: never
 # Specifies how the symlink arrow display, chars in both ascii and utf8
 symlink-arrow: ⇒
 
+# == Threads ==
+# How directories are walked in parallel. Every walk shares one thread pool.
+# The SAP_THREADS environment variable ("serial" or a count) overrides it.
+threads:
+  # Number of threads in the pool, 0 for one per CPU.
+  count: 0
+  # Whether to walk on the main thread, in a deterministic order.
+  # Possible values: false, true
+  serial: false
+  # How long, in milliseconds, a walk waits for a busy thread pool. Leave it
+  # unspecified for the default of one second.
+  # busy-timeout: 1000
+
+# == Scan limits ==
+# Stop the walk after this many entries or this long, in seconds or with an
+# ms, s or m suffix. The listing collected so far is printed with the cut
+# directories marked, and sap exits with code 3. Unlimited when unspecified.
+scan-limits:
+  # max-entries: 100000
+  # timeout: 30s
+
+# == Cache ==
+# Keep the listing of every walked directory under $XDG_CACHE_HOME/sap/scan and
+# serve the directories unchanged since the last run from it.
+cache:
+  # Possible values: off, on, refresh
+  mode: off
+  # Size of the cache directory, in MiB, after which the least recently used
+  # caches are removed.
+  max-size: 64
+
 # == Literal ==
 # Whether to print entry names without quoting
 # Possible values: false, true
@@ -1,13 +1,21 @@
 use crate::color::Colors;
 use crate::display;
+use crate::flags::blocks::Block;
 use crate::flags::{
-    ColorOption, Flags, HyperlinkOption, Layout, Literal, SortOrder, ThemeOption,
+    ColorOption, Display, Flags, HyperlinkOption, IconOption, IgnoreReport, Layout, Literal,
+    SapIgnores, SizeMode, SortColumn, SortOrder, ThemeOption, ThreadMode,
 };
+use crate::git::RepoInfo;
 use crate::icon::Icons;
 
 use crate::meta::Meta;
-use crate::{print_output, sort, ExitCode};
-use std::path::PathBuf;
+use crate::stream::{
+    ContentHasher, ContentSearch, ContentSniffer, DuplicateFinder, DuplicateGroup, EntryFilter, MetaFields, OutputEvent,
+    ScanCache, ScanLimit, SizeAggregator, SniffOptions, Stat, StreamError, WalkOptions,
+};
+use crate::{print_error, print_output, sort, ExitCode};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
 
 #[cfg(not(target_os = "windows"))]
 use std::io;
@@ -24,6 +32,9 @@ pub struct Core {
     colors: Colors,
     git_theme: GitTheme,
     sorters: Vec<(SortOrder, sort::SortFn)>,
+    order: sort::EntryOrder,
+    fields: MetaFields,
+    walk: WalkOptions,
 }
 
 impl Core {
@@ -48,6 +59,11 @@ impl Core {
         };
 
         let icon_when = flags.icons.when;
+        let decorated = color_theme != ThemeOption::NoColor
+            || !matches!(
+                (tty_available, icon_when),
+                (_, IconOption::Never) | (false, IconOption::Auto)
+            );
         let icon_theme = flags.icons.theme.clone();
 
         // TODO: Rework this so that flags passed downstream does not
@@ -77,7 +93,21 @@ impl Core {
             flags.literal = Literal(true);
         };
 
+        // Ignored entries are counted for agents, and shown only as tree rows
+        let report = &mut flags.ignore_globs.report;
+        if flags.llm.is_enabled() && *report == IgnoreReport::Off {
+            *report = IgnoreReport::Count;
+        } else if !flags.llm.is_enabled() && flags.layout != Layout::Tree && *report == IgnoreReport::Count {
+            *report = IgnoreReport::Off;
+        }
+
+        // One pool for every walk, instead of one per input path
+        flags.threads.build_global_pool();
+
         let sorters = sort::assemble_sorters(&flags);
+        let order = sort::EntryOrder::from_flags(&flags);
+        let fields = MetaFields::from_flags(&flags);
+        let walk = walk_options(&flags, decorated);
 
         Self {
             flags,
@@ -85,47 +115,211 @@ impl Core {
             icons: Icons::new(tty_available, icon_when, icon_theme, icon_separator),
             git_theme: GitTheme::new(),
             sorters,
+            order,
+            fields,
+            walk,
         }
     }
 
     pub async fn run(self, paths: Vec<PathBuf>) -> ExitCode {
+        if self.flags.ignore_globs.print {
+            self.print_ignores(&paths);
+            return ExitCode::OK;
+        }
+
         // Determine traversal depth based on flags (copied from fetch() logic)
         let depth = match self.flags.layout {
+            // Only the inputs are printed, with the fingerprint of their whole subtree
+            _ if self.flags.fingerprint.0 => 0,
+            // Copies are looked for in the whole subtree
+            _ if self.flags.duplicates.0 => self.flags.recursion.depth,
             Layout::Tree => self.flags.recursion.depth,
+            // List the inputs themselves, and not their contents
+            _ if self.flags.display == Display::DirectoryOnly => 0,
             _ if self.flags.recursion.enabled => self.flags.recursion.depth,
             _ => 1,
         };
+        // Totals and fingerprints need the whole subtree; entries past `depth` are only walked
+        // for their sizes and hashes
+        let hashes = self.flags.fingerprint.0 || self.flags.blocks.0.contains(&Block::Hash);
+        let walk_depth = if self.flags.total_size.0 || hashes { usize::MAX } else { depth };
+
+        // Following links needs the cycle checks of the walk; the scan cache is shared with the
+        // hasher, which keeps file digests in it
+        if self.flags.cache.is_enabled() && self.flags.dereference.0 {
+            print_error!("the scan cache is not used with --dereference, which reads every directory.");
+        }
+        let scan_cache = (self.flags.cache.is_enabled() && !self.flags.dereference.0)
+            .then(|| ScanCache::new(self.flags.cache));
+
+        // Build streaming pipeline, from a git tree object when `--at` is given
+        let file_stream = if self.flags.revision.is_enabled() {
+            crate::stream::FileStream::from_revision(
+                paths.clone(),
+                &self.flags.revision,
+                walk_depth,
+                &self.flags.ignore_globs,
+                self.flags.display,
+                &self.order,
+            )
+        } else if self.flags.tracked.enabled {
+            crate::stream::FileStream::from_index(
+                paths.clone(),
+                self.flags.tracked,
+                walk_depth,
+                &self.flags.ignore_globs,
+                self.flags.display,
+                self.walk,
+                &self.order,
+            )
+        } else if let Some(cache) = &scan_cache {
+            crate::stream::FileStream::from_cache(
+                paths.clone(),
+                cache,
+                walk_depth,
+                &self.flags.ignore_globs,
+                self.flags.display,
+                self.walk,
+                &self.order,
+            )
+        } else {
+            crate::stream::FileStream::new(
+                paths.clone(),
+                walk_depth,
+                &self.flags.ignore_globs,
+                self.flags.display,
+                self.walk,
+                &self.order,
+            )
+        };
 
-        // Build streaming pipeline
-        let file_stream = crate::stream::FileStream::new(
-            paths.clone(),
-            depth,
-            &self.flags.ignore_globs,
-            self.flags.display,
-        );
+        // Cut the walk itself, before sizes are totaled and entries held back
+        let mut truncated = None;
+        let file_stream = if self.flags.scan_limits.is_enabled() {
+            let limit = ScanLimit::new(file_stream, self.flags.scan_limits, walk_depth);
+            truncated = Some(limit.truncated());
+            crate::stream::FileStream::from_source(limit)
+        } else {
+            file_stream
+        };
+
+        let serial = self.flags.threads.mode == ThreadMode::Serial;
+        let file_stream = if hashes {
+            // The aggregator totals sizes from the entries past `depth`, so they are kept for it
+            let keep_depth = if self.flags.total_size.0 { usize::MAX } else { depth };
+            crate::stream::FileStream::from_source(ContentHasher::new(
+                file_stream,
+                self.flags.hash_algorithm,
+                keep_depth,
+                serial,
+                scan_cache.clone(),
+            ))
+        } else {
+            file_stream
+        };
+
+        let file_stream = if self.flags.total_size.0 || self.flags.size_mode == SizeMode::Disk {
+            crate::stream::FileStream::from_source(SizeAggregator::new(
+                file_stream,
+                self.flags.size_mode,
+                self.flags.total_size.0,
+                depth,
+            ))
+        } else {
+            file_stream
+        };
+
+        let filter = &self.flags.where_filter.0;
+        let contents = self.flags.contains.is_enabled();
+        let file_stream = if contents {
+            let search = ContentSearch::new(file_stream, &self.flags.contains, filter.as_ref(), serial);
+            crate::stream::FileStream::from_source(search)
+        } else {
+            file_stream
+        };
+
+        // Filter after the sizes were totaled, so directories count what the filter hides
+        let file_stream = if filter.is_some() || contents {
+            crate::stream::FileStream::from_source(EntryFilter::new(file_stream, filter.clone(), contents))
+        } else {
+            file_stream
+        };
+
+        // Only the entries left are read
+        let blocks = &self.flags.blocks.0;
+        let media = blocks.contains(&Block::Media);
+        let sniff = SniffOptions {
+            content_type: media || blocks.contains(&Block::Mime) || self.flags.sorting.column == SortColumn::Mime,
+            media,
+            binary: blocks.contains(&Block::Binary),
+        };
+        let file_stream = if sniff != SniffOptions::default() {
+            crate::stream::FileStream::from_source(ContentSniffer::new(file_stream, sniff, serial))
+        } else {
+            file_stream
+        };
+
+        let headers = self.repo_headers(&paths);
 
         // Route to appropriate output mode
-        if self.flags.llm.is_enabled() {
-            self.display_llm_stream(file_stream).await
-        } else if self.flags.layout == Layout::Tree {
-            self.display_tree_stream(file_stream, &paths).await
+        let mut exit_code = if self.flags.duplicates.0 {
+            self.display_duplicates(file_stream).await
+        } else if self.flags.fingerprint.0 {
+            self.display_fingerprints(file_stream).await
+        } else if self.flags.llm.is_enabled() {
+            self.display_llm_stream(file_stream, headers).await
+        } else if self.flags.ignore_globs.report == IgnoreReport::Explain {
+            self.display_explain_stream(file_stream).await
         } else {
-            // Grid/OneLine modes: buffer temporarily (can optimize with GridAccumulator later)
-            self.display_buffered(file_stream).await
+            self.display_stream(file_stream, headers, &paths, depth).await
+        };
+
+        if truncated.is_some_and(|truncated| truncated.load(Ordering::Relaxed)) {
+            exit_code.set_if_greater(ExitCode::Truncated);
+        }
+        exit_code
+    }
+
+    async fn display_stream(
+        &self,
+        file_stream: crate::stream::FileStream,
+        headers: Vec<OutputEvent>,
+        paths: &[PathBuf],
+        depth: usize,
+    ) -> ExitCode {
+        for header in &headers {
+            if let OutputEvent::DirectoryHeader {
+                repo: Some(repo), ..
+            } = header
+            {
+                print_output!("{}", display::repo_header(repo, &self.colors, &self.git_theme));
+            }
+        }
+
+        if self.flags.layout == Layout::Tree {
+            self.display_tree_stream(file_stream).await
+        } else {
+            // Like `ls`, sections are titled once there is more than one
+            let show_headers = self.flags.recursion.enabled || paths.len() > 1;
+            self.display_grid_stream(file_stream, depth, show_headers).await
         }
     }
 
     async fn display_llm_stream(
         &self,
         file_stream: crate::stream::FileStream,
+        headers: Vec<OutputEvent>,
     ) -> ExitCode {
         use futures::StreamExt;
         use crate::stream::AggregatedChatStream;
 
-        let chat_stream = AggregatedChatStream::new(
-            file_stream,
-            self.flags.llm.objective.clone(),
-            self.flags.llm.current_task.clone(),
+        let chat_stream = headers.into_iter().fold(
+            AggregatedChatStream::new(
+                file_stream,
+                self.flags.llm.objective.clone(),
+                self.flags.llm.current_task.clone(),
+            ),
+            AggregatedChatStream::with_header,
         );
 
         let mut stream = Box::pin(chat_stream);
@@ -146,22 +340,21 @@ impl Core {
         exit_code
     }
 
-    async fn display_tree_stream(
-        &self,
-        file_stream: crate::stream::FileStream,
-        _paths: &[PathBuf],
-    ) -> ExitCode {
+    /// Group the walked files with the same contents, and print the groups, or with `--llm`
+    /// one JSONL record per group, then a summary
+    async fn display_duplicates(&self, mut file_stream: crate::stream::FileStream) -> ExitCode {
         use futures::StreamExt;
-        use std::collections::HashMap;
 
-        // Buffer all entries and organize hierarchically
-        let mut entries = Vec::new();
+        let mut finder = DuplicateFinder::default();
         let mut exit_code = ExitCode::OK;
-
-        let mut stream = Box::pin(file_stream);
-        while let Some(result) = stream.next().await {
+        while let Some(result) = file_stream.next().await {
             match result {
-                Ok(entry) => entries.push(entry),
+                Ok(entry) => finder.push(entry),
+                Err(StreamError::Truncated(truncation)) => print_error!("{}.", truncation),
+                Err(StreamError::Link(e)) => {
+                    print_error!("{}.", e);
+                    exit_code.set_if_greater(ExitCode::MinorIssue);
+                }
                 Err(e) => {
                     eprintln!("Stream error: {}", e);
                     exit_code.set_if_greater(ExitCode::MinorIssue);
@@ -169,85 +362,189 @@ impl Core {
             }
         }
 
-        // Sort by depth descending so we process deepest children first
-        // This ensures children have their descendants before being cloned to parents
-        entries.sort_by(|a, b| b.depth.cmp(&a.depth));
-
-        // Convert entries to Meta and build hierarchy
-        let mut meta_map: HashMap<PathBuf, Meta> = HashMap::new();
-        for entry in &entries {
-            let meta = entry.to_meta(self.flags.permission);
-            meta_map.insert(entry.path.clone(), meta);
+        // The files are hashed on the rayon pool, which this thread waits for
+        let (groups, errors) = finder.finish();
+        for (path, err) in &errors {
+            if self.flags.llm.is_enabled() {
+                println!("{}", DuplicateGroup::error_to_json(path, err));
+            } else {
+                print_error!("{}: {}.", path.display(), err);
+            }
+            exit_code.set_if_greater(ExitCode::MinorIssue);
         }
 
-        // Build tree structure by attaching children to parents
-        // First pass: identify which paths have parents in the map
-        let mut child_paths = std::collections::HashSet::new();
-        for entry in &entries {
-            if let Some(parent_path) = entry.path.parent() {
-                if meta_map.contains_key(parent_path) {
-                    child_paths.insert(entry.path.clone());
-                }
+        if self.flags.llm.is_enabled() {
+            for group in &groups {
+                println!("{}", group.to_json());
             }
+            println!("{}", DuplicateGroup::summary_to_json(&groups));
+        } else {
+            for group in &groups {
+                print_output!("{}", display::duplicate_group(group, &self.flags, &self.colors));
+            }
+            print_output!("{}", display::duplicates_summary(&groups, &self.flags, &self.colors));
         }
+        exit_code
+    }
 
-        // Second pass: build parent-child relationships
-        for entry in &entries {
-            if let Some(parent_path) = entry.path.parent() {
-                if child_paths.contains(&entry.path) {
-                    let child_meta = meta_map.get(&entry.path).unwrap().clone();
-                    if let Some(parent_meta) = meta_map.get_mut(parent_path) {
-                        if parent_meta.content.is_none() {
-                            parent_meta.content = Some(Vec::new());
-                        }
-                        if let Some(content) = &mut parent_meta.content {
-                            content.push(child_meta);
-                        }
+    /// Print the fingerprint of each input as `algorithm:hex  path`, like `sha256sum` does, or
+    /// with `--llm` one JSONL record per input
+    async fn display_fingerprints(&self, mut file_stream: crate::stream::FileStream) -> ExitCode {
+        use futures::StreamExt;
+
+        let mut exit_code = ExitCode::OK;
+        while let Some(result) = file_stream.next().await {
+            match result {
+                Ok(entry) => match &entry.hash {
+                    Some(hash) if self.flags.llm.is_enabled() => println!(
+                        "{}",
+                        serde_json::json!({
+                            "kind": "fingerprint",
+                            "path": entry.path.to_string_lossy(),
+                            "hash": hash,
+                        })
+                    ),
+                    Some(hash) => {
+                        print_output!("{}  {}\n", hash, entry.path.display());
                     }
+                    None => {
+                        print_error!("{}: no fingerprint, as some entries below it could not be read.", entry.path.display());
+                        exit_code.set_if_greater(ExitCode::MinorIssue);
+                    }
+                },
+                Err(StreamError::Truncated(truncation)) => print_error!("{}.", truncation),
+                Err(StreamError::Link(e)) => {
+                    print_error!("{}.", e);
+                    exit_code.set_if_greater(ExitCode::MinorIssue);
+                }
+                Err(e) => {
+                    eprintln!("Stream error: {}", e);
+                    exit_code.set_if_greater(ExitCode::MinorIssue);
                 }
             }
         }
 
-        // Third pass: collect root metas (those not in child_paths)
-        let mut root_metas = Vec::new();
-        for entry in &entries {
-            if !child_paths.contains(&entry.path) {
-                if let Some(meta) = meta_map.get(&entry.path) {
-                    root_metas.push(meta.clone());
+        exit_code
+    }
+
+    /// List the entries the ignore rules hid, each with the rule that hid it, instead of the
+    /// visible ones
+    async fn display_explain_stream(&self, mut file_stream: crate::stream::FileStream) -> ExitCode {
+        use futures::StreamExt;
+
+        let mut exit_code = ExitCode::OK;
+        while let Some(result) = file_stream.next().await {
+            match result {
+                Ok(entry) => {
+                    for (name, rule) in entry.ignored.iter().flat_map(|ignored| &ignored.entries) {
+                        print_output!("{}", display::explained(&entry.path.join(name), rule, &self.colors));
+                    }
+                }
+                Err(StreamError::Truncated(truncation)) => print_error!("{}.", truncation),
+                Err(StreamError::Link(e)) => {
+                    print_error!("{}.", e);
+                    exit_code.set_if_greater(ExitCode::MinorIssue);
+                }
+                Err(e) => {
+                    eprintln!("Stream error: {}", e);
+                    exit_code.set_if_greater(ExitCode::MinorIssue);
                 }
             }
         }
 
-        // Sort root metas
-        self.sort(&mut root_metas);
+        exit_code
+    }
 
-        // Display using existing tree display logic
-        let output = display::tree(
-            &root_metas,
-            &self.flags,
-            &self.colors,
-            &self.icons,
-            &self.git_theme,
-        );
+    async fn display_tree_stream(&self, file_stream: crate::stream::FileStream) -> ExitCode {
+        use futures::StreamExt;
+        use crate::stream::TreeAccumulator;
+
+        let mut rows = display::TreeRows::new(&self.flags, &self.colors, &self.icons, &self.git_theme);
+        let mut exit_code = ExitCode::OK;
+
+        // Rows are printed as the walk reaches them, or held to line up their columns; siblings
+        // already come in display order
+        let mut stream = TreeAccumulator::new(file_stream);
+        while let Some(result) = stream.next().await {
+            match result {
+                Ok(OutputEvent::TreeNode { entry, prefix }) => {
+                    print_output!("{}", rows.entry(entry.to_meta(&self.fields), prefix));
+                }
+                Ok(OutputEvent::Truncated { prefix }) => {
+                    print_output!("{}", rows.truncated(&prefix));
+                }
+                Ok(OutputEvent::Ignored { ignored, prefix }) => {
+                    print_output!("{}", rows.ignored(&prefix, ignored.count));
+                }
+                Ok(_) => {}
+                Err(StreamError::Truncated(truncation)) => print_error!("{}.", truncation),
+                Err(StreamError::Link(e)) => {
+                    print_error!("{}.", e);
+                    exit_code.set_if_greater(ExitCode::MinorIssue);
+                }
+                Err(e) => {
+                    eprintln!("Stream error: {}", e);
+                    exit_code.set_if_greater(ExitCode::MinorIssue);
+                }
+            }
+        }
+        print_output!("{}", rows.finish());
 
-        print_output!("{}", output);
         exit_code
     }
 
-    async fn display_buffered(
+    async fn display_grid_stream(
         &self,
         file_stream: crate::stream::FileStream,
+        depth: usize,
+        show_headers: bool,
     ) -> ExitCode {
         use futures::StreamExt;
+        use crate::stream::GridAccumulator;
 
-        // Buffer entries from stream
-        let mut entries = Vec::new();
         let mut exit_code = ExitCode::OK;
+        let mut rows: Vec<Meta> = Vec::new();
+        let mut first = true;
+        let mut truncated = false;
 
-        let mut stream = Box::pin(file_stream);
+        // Each section is printed once its directory was fully walked
+        let dot_entries = matches!(self.flags.display, Display::All | Display::SystemProtected);
+        let mut stream = GridAccumulator::new(file_stream, depth, dot_entries, self.walk.git);
         while let Some(result) = stream.next().await {
             match result {
-                Ok(entry) => entries.push(entry),
+                Ok(OutputEvent::DirectoryHeader { path, .. }) => {
+                    if show_headers {
+                        print_output!("{}", display::section_header(&path, first));
+                        first = false;
+                    }
+                }
+                Ok(OutputEvent::FileRow { entry }) => {
+                    rows.push(entry.to_meta(&self.fields));
+                }
+                Ok(OutputEvent::Truncated { .. }) => truncated = true,
+                Ok(OutputEvent::SectionEnd) => {
+                    self.sort(&mut rows);
+                    let output = display::grid(
+                        &rows,
+                        &self.flags,
+                        &self.colors,
+                        &self.icons,
+                        &self.git_theme,
+                    );
+                    print_output!("{}", output);
+                    let cut = std::mem::take(&mut truncated);
+                    if cut {
+                        print_output!("{}", display::truncated(&self.colors));
+                    }
+                    first &= rows.is_empty() && !cut;
+                    rows.clear();
+                }
+                Ok(_) => {}
+                Err(StreamError::Truncated(truncation)) => print_error!("{}.", truncation),
+                Err(StreamError::Link(e)) => {
+                    print_error!("{}.", e);
+                    exit_code.set_if_greater(ExitCode::MinorIssue);
+                }
                 Err(e) => {
                     eprintln!("Stream error: {}", e);
                     exit_code.set_if_greater(ExitCode::MinorIssue);
@@ -255,29 +552,47 @@ impl Core {
             }
         }
 
-        // Convert FileEntry to Meta
-        let mut metas: Vec<Meta> = entries
-            .iter()
-            .map(|entry| entry.to_meta(self.flags.permission))
-            .collect();
-
-        // Sort using configured sorters
-        self.sort(&mut metas);
-
-        // Display using existing grid/oneline display logic
-        let output = display::grid(
-            &metas,
-            &self.flags,
-            &self.colors,
-            &self.icons,
-            &self.git_theme,
-        );
-
-        print_output!("{}", output);
         exit_code
     }
 
+    /// Print the ignore patterns in effect in each input directory, titled once there is more
+    /// than one
+    fn print_ignores(&self, paths: &[PathBuf]) {
+        for (idx, path) in paths.iter().enumerate() {
+            if paths.len() > 1 {
+                print_output!("{}", display::section_header(path, idx == 0));
+            }
+            let globs = self.flags.ignore_globs.for_root(path);
+            let mut sapignores = SapIgnores::default();
+            sapignores.read(path, Path::new(""));
+            let patterns: Vec<_> = globs.patterns(&sapignores).collect();
+            print_output!("{}", display::ignore_patterns(&patterns, &self.colors));
+        }
+    }
 
+    /// One header per repository containing an input path, when `--repo-info` is enabled
+    fn repo_headers(&self, paths: &[PathBuf]) -> Vec<OutputEvent> {
+        if !self.flags.repo_info.0 {
+            return Vec::new();
+        }
+
+        let mut roots = Vec::new();
+        let mut headers = Vec::new();
+        for path in paths {
+            let Some(repo) = RepoInfo::discover(path) else {
+                continue;
+            };
+            if roots.contains(&repo.root) {
+                continue;
+            }
+            roots.push(repo.root.clone());
+            headers.push(OutputEvent::DirectoryHeader {
+                path: path.clone(),
+                repo: Some(repo),
+            });
+        }
+        headers
+    }
 
     fn sort(&self, metas: &mut Vec<Meta>) {
         metas.sort_unstable_by(|a, b| sort::by_meta(&self.sorters, a, b));
@@ -293,3 +608,42 @@ impl Core {
 
 
 }
+
+/// Options of the disk walk. Entries are only stat'ed when a block, the sort column, `--llm`
+/// or a walk option reads their metadata; otherwise `readdir` file types are enough, with a
+/// stat for what colors and icons show when they are `decorated`
+fn walk_options(flags: &Flags, decorated: bool) -> WalkOptions {
+    let stat_all = flags.blocks.0.iter().any(|block| {
+        !matches!(
+            block,
+            Block::Name | Block::GitStatus | Block::Matches | Block::Mime | Block::Media | Block::Binary
+        )
+    }) || matches!(flags.sorting.column, SortColumn::Size | SortColumn::Time)
+        || flags.total_size.0
+        || flags.size_mode == SizeMode::Disk
+        || flags.llm.is_enabled()
+        || flags.one_file_system.0
+        || flags.display_indicators.0
+        || flags.where_filter.needs_stat()
+        || flags.duplicates.0
+        || flags.fingerprint.0;
+
+    WalkOptions {
+        dereference: flags.dereference.0,
+        one_file_system: flags.one_file_system.0,
+        threads: flags.threads,
+        sniff: flags.sorting.column == SortColumn::Mime,
+        git: flags.blocks.0.contains(&Block::GitStatus)
+            || flags.sorting.column == SortColumn::GitStatus
+            || flags.where_filter.needs_git()
+            || flags.llm.is_enabled(),
+        stat: if stat_all {
+            Stat::All
+        } else {
+            Stat::TypesOnly {
+                exec_bit: decorated,
+                link_target: decorated,
+            }
+        },
+    }
+}
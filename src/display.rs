@@ -1,19 +1,20 @@
 use crate::color::{Colors, Elem};
 use crate::flags::blocks::Block;
-use crate::flags::{Display, Flags, HyperlinkOption, Layout};
+use crate::flags::{Display, Flags, HyperlinkOption, IgnoreMatch, IgnoreOrigin, Layout};
+use crate::git::{RepoHead, RepoInfo};
 use crate::git_theme::GitTheme;
 use crate::icon::Icons;
 use crate::meta::name::DisplayOption;
-use crate::meta::{FileType, Meta, OwnerCache};
+use crate::meta::{FileType, Meta, OwnerCache, Size};
+use crate::stream::DuplicateGroup;
 use std::collections::HashMap;
+use std::path::Path;
 use term_grid::{Alignment, Cell, Direction, Filling, Grid, GridOptions};
 use terminal_size::terminal_size;
 use unicode_width::UnicodeWidthStr;
 
-const EDGE: &str = "\u{251c}\u{2500}\u{2500}"; // "├──"
-const LINE: &str = "\u{2502}  "; // "│  "
-const CORNER: &str = "\u{2514}\u{2500}\u{2500}"; // "└──"
-const BLANK: &str = "   ";
+/// How many hex digits of a digest the `hash` block shows
+const HASH_DIGITS: usize = 12;
 
 pub fn grid(
     metas: &[Meta],
@@ -38,44 +39,262 @@ pub fn grid(
     )
 }
 
-pub fn tree(
-    metas: &[Meta],
-    flags: &Flags,
-    colors: &Colors,
-    icons: &Icons,
-    git_theme: &GitTheme,
-) -> String {
-    let mut grid = Grid::new(GridOptions {
-        filling: Filling::Spaces(2),
-        direction: Direction::LeftToRight,
-    });
+/// The `path:` line opening a directory's section in a recursive or multi-input listing,
+/// separated from the previous section by a blank line
+pub fn section_header(path: &Path, first: bool) -> String {
+    if first {
+        format!("{}:\n", path.to_string_lossy())
+    } else {
+        format!("\n{}:\n", path.to_string_lossy())
+    }
+}
 
-    let padding_rules = get_padding_rules(metas, flags);
-    let mut index = 0;
-    for (i, block) in flags.blocks.0.iter().enumerate() {
-        if block == &Block::Name {
-            index = i;
-            break;
+/// The note closing a directory whose listing was cut by `--max-entries` or `--timeout`
+pub fn truncated(colors: &Colors) -> String {
+    format!("{}\n", colors.colorize("\u{2026} truncated", &Elem::NoAccess))
+}
+
+/// The dimmed note closing a directory whose entries were hidden by the ignore rules
+pub fn ignored(count: usize, colors: &Colors) -> String {
+    format!("{}\n", colors.colorize(format!("\u{2026} +{} ignored", count), &Elem::TreeEdge))
+}
+
+/// The `--explain-ignores` line of an entry hidden by the ignore rules: its path, then the
+/// pattern that hid it and where the pattern comes from
+pub fn explained(path: &Path, rule: &IgnoreMatch, colors: &Colors) -> String {
+    format!(
+        "{}  {}\n",
+        path.to_string_lossy(),
+        colors.colorize(format!("{} ({})", rule.pattern, rule.origin), &Elem::TreeEdge)
+    )
+}
+
+/// The `--print-ignores` listing: one pattern per line, from the weakest to the strongest,
+/// with where it comes from
+pub fn ignore_patterns(patterns: &[(&str, &IgnoreOrigin)], colors: &Colors) -> String {
+    let width = patterns.iter().map(|(pattern, _)| pattern.width()).max().unwrap_or(0);
+    patterns
+        .iter()
+        .map(|(pattern, origin)| {
+            format!(
+                "{}{}  {}\n",
+                pattern,
+                " ".repeat(width - pattern.width()),
+                colors.colorize(origin.to_string(), &Elem::TreeEdge)
+            )
+        })
+        .collect()
+}
+
+/// A `--duplicates` group: the number of copies, the size of each and the space the extra
+/// copies waste, then the path of each copy, with its other hard links after it
+pub fn duplicate_group(group: &DuplicateGroup, flags: &Flags, colors: &Colors) -> String {
+    let mut output = format!(
+        "{} copies of {}, {} wasted\n",
+        group.files.len(),
+        Size::new(group.size).render(colors, flags, None),
+        Size::new(group.wasted()).render(colors, flags, None)
+    );
+    for paths in &group.files {
+        output += &format!("  {}", paths[0].to_string_lossy());
+        for link in &paths[1..] {
+            output += &format!(
+                " {}",
+                colors.colorize(format!("= {}", link.to_string_lossy()), &Elem::TreeEdge)
+            );
         }
+        output.push('\n');
     }
+    output.push('\n');
+    output
+}
 
-    let owner_cache = OwnerCache::default();
+/// The line closing the `--duplicates` groups, with their totals
+pub fn duplicates_summary(groups: &[DuplicateGroup], flags: &Flags, colors: &Colors) -> String {
+    let copies: usize = groups.iter().map(|group| group.files.len() - 1).sum();
+    let wasted: u64 = groups.iter().map(DuplicateGroup::wasted).sum();
+    format!(
+        "{} groups, {} extra copies, {} wasted\n",
+        groups.len(),
+        copies,
+        Size::new(wasted).render(colors, flags, None)
+    )
+}
 
-    for cell in inner_display_tree(
-        metas,
-        &owner_cache,
-        flags,
-        colors,
-        icons,
-        git_theme,
-        (0, ""),
-        &padding_rules,
-        index,
-    ) {
-        grid.add(cell);
+/// Renders tree rows as the tree accumulator emits them.
+///
+/// With the name as the only block, each row is printed as soon as it arrives. With more
+/// blocks, the columns are padded to the widest cell of the whole tree, like grid sections are,
+/// so the rows are held until [TreeRows::finish].
+pub struct TreeRows<'a> {
+    flags: &'a Flags,
+    colors: &'a Colors,
+    icons: &'a Icons,
+    git_theme: &'a GitTheme,
+    owner_cache: OwnerCache,
+    name_index: usize,
+    /// The rows waiting for the widths of their columns, with the entries of their
+    /// [TreeRow::Entry] rows in order; [None] when rows are printed at once
+    held: Option<(Vec<TreeRow>, Vec<Meta>)>,
+}
+
+/// A held tree row: the next entry, or the note closing a directory, after their tree edges
+enum TreeRow {
+    Entry(String),
+    Note(String, String),
+}
+
+impl<'a> TreeRows<'a> {
+    pub fn new(flags: &'a Flags, colors: &'a Colors, icons: &'a Icons, git_theme: &'a GitTheme) -> Self {
+        Self {
+            flags,
+            colors,
+            icons,
+            git_theme,
+            owner_cache: OwnerCache::default(),
+            name_index: flags.blocks.0.iter().position(|b| b == &Block::Name).unwrap_or(0),
+            held: (flags.blocks.0.len() > 1).then(Default::default),
+        }
     }
 
-    grid.fit_into_columns(1).to_string()
+    /// The row of `meta`, with `prefix` holding its tree edges, or nothing while rows are held
+    pub fn entry(&mut self, meta: Meta, prefix: String) -> String {
+        match &mut self.held {
+            Some((rows, metas)) => {
+                rows.push(TreeRow::Entry(prefix));
+                metas.push(meta);
+                String::new()
+            }
+            None => self.blocks(&meta, &prefix, &HashMap::new()).concat() + "\n",
+        }
+    }
+
+    /// The last row of a cut directory, with its edge in the name column
+    pub fn truncated(&mut self, prefix: &str) -> String {
+        self.note(prefix, truncated(self.colors))
+    }
+
+    /// The last row of a directory whose entries were hidden by the ignore rules
+    pub fn ignored(&mut self, prefix: &str, count: usize) -> String {
+        self.note(prefix, ignored(count, self.colors))
+    }
+
+    fn note(&mut self, prefix: &str, note: String) -> String {
+        let prefix = self.colors.colorize(prefix, &Elem::TreeEdge).to_string();
+        match &mut self.held {
+            Some((rows, _)) => {
+                rows.push(TreeRow::Note(prefix, note));
+                String::new()
+            }
+            None => prefix + &note,
+        }
+    }
+
+    /// The held rows, with their columns lined up
+    pub fn finish(mut self) -> String {
+        let Some((rows, metas)) = self.held.take() else {
+            return String::new();
+        };
+        let padding_rules = get_padding_rules(&metas, self.flags);
+
+        let hyperlink = self.flags.hyperlink == HyperlinkOption::Always;
+        let mut widths = vec![0; self.flags.blocks.0.len()];
+        let mut metas = metas.iter();
+        let cells: Vec<Vec<String>> = rows
+            .iter()
+            .filter_map(|row| match row {
+                TreeRow::Entry(prefix) => Some(self.blocks(metas.next()?, prefix, &padding_rules)),
+                TreeRow::Note(..) => None,
+            })
+            .inspect(|blocks| {
+                for (width, block) in widths.iter_mut().zip(blocks) {
+                    *width = (*width).max(get_visible_width(block, hyperlink));
+                }
+            })
+            .collect();
+
+        let indent: usize = widths.iter().take(self.name_index).map(|width| width + 2).sum();
+        let mut cells = cells.into_iter();
+        let mut output = String::new();
+        for row in rows {
+            match row {
+                TreeRow::Entry(_) => {
+                    let Some(blocks) = cells.next() else {
+                        break;
+                    };
+                    let last = blocks.len().saturating_sub(1);
+                    for (idx, block) in blocks.into_iter().enumerate() {
+                        output += &block;
+                        if idx != last {
+                            let width = get_visible_width(&block, hyperlink);
+                            output += &" ".repeat(widths[idx] - width + 2);
+                        }
+                    }
+                    output.push('\n');
+                }
+                TreeRow::Note(prefix, note) => {
+                    output += &" ".repeat(indent);
+                    output += &prefix;
+                    output += &note;
+                }
+            }
+        }
+        output
+    }
+
+    fn blocks(&self, meta: &Meta, prefix: &str, padding_rules: &HashMap<Block, usize>) -> Vec<String> {
+        get_output(
+            meta,
+            &self.owner_cache,
+            self.colors,
+            self.icons,
+            self.git_theme,
+            self.flags,
+            &DisplayOption::FileName,
+            padding_rules,
+            (self.name_index, prefix),
+        )
+    }
+}
+
+/// Render the `--repo-info` line: root, branch or detached HEAD, upstream with ahead/behind
+/// counts, stash count and any operation in progress
+pub fn repo_header(repo: &RepoInfo, colors: &Colors, git_theme: &GitTheme) -> String {
+    let mut parts = vec![colors
+        .colorize(repo.root.to_string_lossy(), &Elem::GitRepoRoot)
+        .to_string()];
+
+    parts.push(match &repo.head {
+        RepoHead::Branch(name) => colors.colorize(name, &Elem::GitBranch).to_string(),
+        RepoHead::Detached(id) => colors
+            .colorize(format!("{} {}", git_theme.detached(), id), &Elem::GitDetached)
+            .to_string(),
+    });
+
+    if let Some(upstream) = &repo.upstream {
+        parts.push(format!(
+            "\u{2192} {}",
+            colors.colorize(upstream, &Elem::GitUpstream)
+        ));
+    }
+    if let Some(ahead) = repo.ahead.filter(|ahead| *ahead > 0) {
+        let ahead = format!("{}{}", git_theme.ahead(), ahead);
+        parts.push(colors.colorize(ahead, &Elem::GitAhead).to_string());
+    }
+    if let Some(behind) = repo.behind.filter(|behind| *behind > 0) {
+        let behind = format!("{}{}", git_theme.behind(), behind);
+        parts.push(colors.colorize(behind, &Elem::GitBehind).to_string());
+    }
+    if repo.stashes > 0 {
+        let stashes = format!("{}{}", git_theme.stash(), repo.stashes);
+        parts.push(colors.colorize(stashes, &Elem::GitStash).to_string());
+    }
+    if let Some(operation) = repo.operation {
+        let operation = format!("({} in progress)", operation);
+        parts.push(colors.colorize(operation, &Elem::GitOperation).to_string());
+    }
+
+    parts.join(" ") + "\n"
 }
 
 #[allow(clippy::too_many_arguments)] // should wrap flags, colors, icons, git_theme into one struct
@@ -230,80 +449,6 @@ fn add_header(flags: &Flags, cells: &[Cell], grid: &mut Grid) {
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-fn inner_display_tree(
-    metas: &[Meta],
-    owner_cache: &OwnerCache,
-    flags: &Flags,
-    colors: &Colors,
-    icons: &Icons,
-    git_theme: &GitTheme,
-    tree_depth_prefix: (usize, &str),
-    padding_rules: &HashMap<Block, usize>,
-    tree_index: usize,
-) -> Vec<Cell> {
-    let mut cells = Vec::new();
-    let last_idx = metas.len();
-
-    for (idx, meta) in metas.iter().enumerate() {
-        let current_prefix = if tree_depth_prefix.0 > 0 {
-            if idx + 1 != last_idx {
-                // is last folder elem
-                format!("{}{} ", tree_depth_prefix.1, EDGE)
-            } else {
-                format!("{}{} ", tree_depth_prefix.1, CORNER)
-            }
-        } else {
-            tree_depth_prefix.1.to_string()
-        };
-
-        for block in get_output(
-            meta,
-            owner_cache,
-            colors,
-            icons,
-            git_theme,
-            flags,
-            &DisplayOption::FileName,
-            padding_rules,
-            (tree_index, &current_prefix),
-        ) {
-            cells.push(Cell {
-                width: get_visible_width(&block, flags.hyperlink == HyperlinkOption::Always),
-                contents: block,
-                alignment: Alignment::Left,
-            });
-        }
-
-        if let Some(content) = &meta.content {
-            let new_prefix = if tree_depth_prefix.0 > 0 {
-                if idx + 1 != last_idx {
-                    // is last folder elem
-                    format!("{}{} ", tree_depth_prefix.1, LINE)
-                } else {
-                    format!("{}{} ", tree_depth_prefix.1, BLANK)
-                }
-            } else {
-                tree_depth_prefix.1.to_string()
-            };
-
-            cells.extend(inner_display_tree(
-                content,
-                owner_cache,
-                flags,
-                colors,
-                icons,
-                git_theme,
-                (tree_depth_prefix.0 + 1, &new_prefix),
-                padding_rules,
-                tree_index,
-            ));
-        }
-    }
-
-    cells
-}
-
 fn should_display_folder_path(depth: usize, metas: &[Meta]) -> bool {
     if depth > 0 {
         true
@@ -355,6 +500,38 @@ fn get_output(
                 Some(links) => links.render(colors),
                 None => colorize_missing("?"),
             }),
+            Block::Filesystem => block_vec.push(match &meta.filesystem {
+                Some(filesystem) => filesystem.render(colors),
+                None => colorize_missing("?"),
+            }),
+            // Only the files searched by --contains have a count
+            Block::Matches => block_vec.push(match meta.matches {
+                Some(count) => colors.colorize(count.to_string(), &Elem::Matches),
+                None => colorize_missing("-"),
+            }),
+            // The first digits are enough to tell entries apart; --llm has the whole digest
+            Block::Hash => block_vec.push(match &meta.hash {
+                Some(hash) => {
+                    let digest = hash.split_once(':').map_or(hash.as_str(), |(_, digest)| digest);
+                    colors.colorize(digest.chars().take(HASH_DIGITS).collect::<String>(), &Elem::Hash)
+                }
+                None => colorize_missing("-"),
+            }),
+            // Only the files that were read have a type
+            Block::Mime => block_vec.push(match meta.name.content_type() {
+                Some(content_type) => colors.colorize(content_type.to_string(), &Elem::Mime),
+                None => colorize_missing("-"),
+            }),
+            // Only the images, audio and video whose headers parsed have media fields
+            Block::Media => block_vec.push(match &meta.media {
+                Some(media) => colors.colorize(media.to_string(), &Elem::Media),
+                None => colorize_missing("-"),
+            }),
+            // Only the ELF files have binary headers
+            Block::Binary => block_vec.push(match &meta.binary {
+                Some(binary) => binary.render(colors),
+                None => colorize_missing("-"),
+            }),
             Block::Permission => {
                 block_vec.extend([
                     meta.file_type.render(colors),
@@ -0,0 +1,1065 @@
+//! The `--where` expression language: a predicate over entry attributes, parsed once into a
+//! [Filter] and evaluated against every entry of the walk.
+//!
+//! ```text
+//! expr       := and { ("||" | "or") and }
+//! and        := unary { ("&&" | "and") unary }
+//! unary      := ("!" | "not") unary | "(" expr ")" | comparison
+//! comparison := field op value | field ["not"] "in" "(" value { "," value } ")"
+//! op         := "==" | "=" | "!=" | "<" | "<=" | ">" | ">=" | "~" | "!~"
+//! ```
+//!
+//! Values are bare words or quoted strings. Sizes take a K, M, G or T suffix (powers of 1024),
+//! ages an s, m, h, d, w or y suffix, and dates the YYYY-MM-DD form: `mtime < 7d` keeps what
+//! changed in the last week, `mtime < 2024-01-01` what did not change since 2024.
+
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use globset::{Glob, GlobMatcher};
+
+use crate::flags::TimeField;
+use crate::git::GitStatus;
+use crate::meta::{FileType, GitFileStatus};
+use crate::stream::FileEntry;
+
+/// A `--where` expression that could not be parsed, with the span of the offending token
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    message: String,
+    span: Range<usize>,
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// The message followed by the expression, with the offending token underlined
+    pub fn render(&self, source: &str) -> String {
+        let start = source[..self.span.start].chars().count();
+        let width = source[self.span.clone()].chars().count().max(1);
+        format!(
+            "{}\n  {}\n  {}{}",
+            self.message,
+            source,
+            " ".repeat(start),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.span.start)
+    }
+}
+
+/// A compiled `--where` expression
+#[derive(Clone, Debug)]
+pub struct Filter {
+    expr: Expr,
+    /// Ages are measured from the time the expression was compiled, so a listing is consistent
+    now: SystemTime,
+}
+
+impl Filter {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            source,
+            tokens: tokenize(source)?,
+            pos: 0,
+        };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(ParseError::new(
+                "expected `&&`, `||` or the end of the expression",
+                token.span.clone(),
+            ));
+        }
+
+        Ok(Self {
+            expr,
+            now: SystemTime::now(),
+        })
+    }
+
+    /// Whether `entry` is selected. Its git status is only set when the walk was asked to
+    /// look it up, see [needs_git](Filter::needs_git).
+    pub fn matches(&self, entry: &FileEntry) -> bool {
+        self.expr.eval(entry, self.now)
+    }
+
+    /// Whether the expression reads stat data rather than the `readdir` file type and name
+    pub fn needs_stat(&self) -> bool {
+        self.expr.any(&|test| {
+            !matches!(
+                test,
+                Test::Text { .. } | Test::Depth { .. } | Test::Git { .. } | Test::Type { .. }
+            ) || matches!(test, Test::Type { types, .. } if types.contains(&EntryType::Exec))
+        })
+    }
+
+    /// Whether the expression reads the git status of entries
+    pub fn needs_git(&self) -> bool {
+        self.expr.any(&|test| matches!(test, Test::Git { .. }))
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Test(Test),
+}
+
+impl Expr {
+    fn eval(&self, entry: &FileEntry, now: SystemTime) -> bool {
+        match self {
+            Expr::And(left, right) => left.eval(entry, now) && right.eval(entry, now),
+            Expr::Or(left, right) => left.eval(entry, now) || right.eval(entry, now),
+            Expr::Not(expr) => !expr.eval(entry, now),
+            Expr::Test(test) => test.eval(entry, now),
+        }
+    }
+
+    fn any(&self, predicate: &dyn Fn(&Test) -> bool) -> bool {
+        match self {
+            Expr::And(left, right) | Expr::Or(left, right) => left.any(predicate) || right.any(predicate),
+            Expr::Not(expr) => expr.any(predicate),
+            Expr::Test(test) => predicate(test),
+        }
+    }
+}
+
+/// A single comparison. Equality tests hold a list of values, for `in`, and `negate` for `!=`
+/// and `not in`.
+#[derive(Clone, Debug)]
+enum Test {
+    Text { field: Field, matcher: TextMatcher, negate: bool },
+    Type { types: Vec<EntryType>, negate: bool },
+    Size { cmp: Cmp, bytes: u64 },
+    Depth { cmp: Cmp, depth: usize },
+    Age { field: TimeField, cmp: Cmp, age: Duration },
+    Date { field: TimeField, cmp: Cmp, start: SystemTime, end: SystemTime },
+    Owner { uids: Vec<u32>, negate: bool },
+    Perms { modes: Vec<u32>, negate: bool },
+    Git { states: Vec<GitState>, negate: bool },
+}
+
+impl Test {
+    fn eval(&self, entry: &FileEntry, now: SystemTime) -> bool {
+        match self {
+            Test::Text { field, matcher, negate } => {
+                let value = match field {
+                    Field::Name => Some(entry.name.clone()),
+                    Field::Path => Some(relative_path(&entry.path)),
+                    _ => entry
+                        .path
+                        .extension()
+                        .map(|ext| ext.to_string_lossy().to_lowercase()),
+                };
+                value.is_some_and(|value| matcher.is_match(&value)) != *negate
+            }
+            Test::Type { types, negate } => {
+                types.iter().any(|kind| kind.is(entry.file_type)) != *negate
+            }
+            Test::Size { cmp, bytes } => entry.size_bytes().is_some_and(|size| cmp.test(size, *bytes)),
+            Test::Depth { cmp, depth } => cmp.test(entry.depth, *depth),
+            Test::Age { field, cmp, age } => entry.time(*field).is_some_and(|time| {
+                cmp.test(now.duration_since(time).unwrap_or_default(), *age)
+            }),
+            Test::Date {
+                field,
+                cmp,
+                start,
+                end,
+            } => entry.time(*field).is_some_and(|time| match cmp {
+                Cmp::Eq => *start <= time && time < *end,
+                Cmp::Ne => time < *start || *end <= time,
+                Cmp::Lt => time < *start,
+                Cmp::Le => time < *end,
+                Cmp::Gt => *end <= time,
+                Cmp::Ge => *start <= time,
+            }),
+            Test::Owner { uids, negate } => uid(entry).is_some_and(|uid| uids.contains(&uid) != *negate),
+            Test::Perms { modes, negate } => {
+                mode(entry).is_some_and(|mode| modes.contains(&(mode & 0o7777)) != *negate)
+            }
+            Test::Git { states, negate } => {
+                let status = entry.git_status.as_ref();
+                states.iter().any(|state| status.is_some_and(|status| state.is(status))) != *negate
+            }
+        }
+    }
+}
+
+/// The path as matched by the `path` field, without a leading `./`
+fn relative_path(path: &Path) -> String {
+    let path = path.strip_prefix(".").unwrap_or(path);
+    path.to_string_lossy().into_owned()
+}
+
+#[cfg(unix)]
+fn uid(entry: &FileEntry) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    match (&entry.metadata, &entry.cached) {
+        (Some(metadata), _) => Some(metadata.uid()),
+        (None, Some(stat)) => Some(stat.uid),
+        (None, None) => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn uid(_entry: &FileEntry) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn mode(entry: &FileEntry) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    match (&entry.metadata, &entry.cached, &entry.object) {
+        (Some(metadata), _, _) => Some(metadata.mode()),
+        (None, Some(stat), _) => Some(stat.mode),
+        (None, None, Some(object)) => Some(object.mode),
+        (None, None, None) => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn mode(_entry: &FileEntry) -> Option<u32> {
+    None
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Cmp {
+    fn test<T: PartialOrd>(self, value: T, operand: T) -> bool {
+        match self {
+            Cmp::Eq => value == operand,
+            Cmp::Ne => value != operand,
+            Cmp::Lt => value < operand,
+            Cmp::Le => value <= operand,
+            Cmp::Gt => value > operand,
+            Cmp::Ge => value >= operand,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum TextMatcher {
+    Exact(Vec<String>),
+    Glob(GlobMatcher),
+}
+
+impl TextMatcher {
+    fn is_match(&self, value: &str) -> bool {
+        match self {
+            TextMatcher::Exact(values) => values.iter().any(|exact| exact == value),
+            TextMatcher::Glob(glob) => glob.is_match(value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EntryType {
+    File,
+    Exec,
+    Dir,
+    Symlink,
+    Pipe,
+    Socket,
+    Char,
+    Block,
+    Special,
+}
+
+impl EntryType {
+    const NAMES: &'static str = "file, exec, dir, symlink, pipe, socket, char, block or special";
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "file" | "f" => EntryType::File,
+            "exec" | "x" => EntryType::Exec,
+            "dir" | "directory" | "d" => EntryType::Dir,
+            "symlink" | "link" | "l" => EntryType::Symlink,
+            "pipe" | "fifo" | "p" => EntryType::Pipe,
+            "socket" | "s" => EntryType::Socket,
+            "char" | "c" => EntryType::Char,
+            "block" | "b" => EntryType::Block,
+            "special" => EntryType::Special,
+            _ => return None,
+        })
+    }
+
+    fn is(self, file_type: FileType) -> bool {
+        match self {
+            EntryType::File => matches!(file_type, FileType::File { .. }),
+            EntryType::Exec => matches!(file_type, FileType::File { exec: true, .. }),
+            EntryType::Dir => matches!(file_type, FileType::Directory { .. }),
+            EntryType::Symlink => matches!(file_type, FileType::SymLink { .. }),
+            EntryType::Pipe => file_type == FileType::Pipe,
+            EntryType::Socket => file_type == FileType::Socket,
+            EntryType::Char => file_type == FileType::CharDevice,
+            EntryType::Block => file_type == FileType::BlockDevice,
+            EntryType::Special => file_type == FileType::Special,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum GitState {
+    Clean,
+    New,
+    Untracked,
+    Modified,
+    Deleted,
+    Renamed,
+    Typechange,
+    Ignored,
+    Conflicted,
+}
+
+impl GitState {
+    const NAMES: &'static str =
+        "clean, new, untracked, modified, deleted, renamed, typechange, ignored or conflicted";
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "clean" | "unmodified" => GitState::Clean,
+            "new" => GitState::New,
+            "untracked" => GitState::Untracked,
+            "modified" => GitState::Modified,
+            "deleted" => GitState::Deleted,
+            "renamed" => GitState::Renamed,
+            "typechange" => GitState::Typechange,
+            "ignored" => GitState::Ignored,
+            "conflicted" => GitState::Conflicted,
+            _ => return None,
+        })
+    }
+
+    fn is(self, status: &GitFileStatus) -> bool {
+        let either = |wanted: &[GitStatus]| wanted.contains(&status.index) || wanted.contains(&status.workdir);
+        match self {
+            GitState::Clean => [status.index, status.workdir]
+                .iter()
+                .all(|state| matches!(state, GitStatus::Default | GitStatus::Unmodified)),
+            GitState::New => either(&[GitStatus::NewInIndex, GitStatus::NewInWorkdir]),
+            GitState::Untracked => status.workdir == GitStatus::NewInWorkdir,
+            GitState::Modified => either(&[GitStatus::Modified]),
+            GitState::Deleted => either(&[GitStatus::Deleted]),
+            GitState::Renamed => either(&[GitStatus::Renamed]),
+            GitState::Typechange => either(&[GitStatus::Typechange]),
+            GitState::Ignored => either(&[GitStatus::Ignored]),
+            GitState::Conflicted => either(&[GitStatus::Conflicted, GitStatus::GitConflicted]),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Name,
+    Path,
+    Ext,
+    Type,
+    Size,
+    Mtime,
+    Atime,
+    Owner,
+    Perms,
+    Git,
+    Depth,
+}
+
+impl Field {
+    const ALL: [(&'static str, Field); 11] = [
+        ("name", Field::Name),
+        ("path", Field::Path),
+        ("ext", Field::Ext),
+        ("type", Field::Type),
+        ("size", Field::Size),
+        ("mtime", Field::Mtime),
+        ("atime", Field::Atime),
+        ("owner", Field::Owner),
+        ("perms", Field::Perms),
+        ("git", Field::Git),
+        ("depth", Field::Depth),
+    ];
+    const NAMES: &'static str = "name, path, ext, type, size, mtime, atime, owner, perms, git or depth";
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|(known, _)| *known == name).map(|(_, field)| *field)
+    }
+
+    fn name(self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(_, field)| *field == self)
+            .map_or("", |(name, _)| name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Match,
+    NotMatch,
+    In,
+    NotIn,
+}
+
+impl Op {
+    fn cmp(self) -> Option<Cmp> {
+        Some(match self {
+            Op::Eq => Cmp::Eq,
+            Op::Ne => Cmp::Ne,
+            Op::Lt => Cmp::Lt,
+            Op::Le => Cmp::Le,
+            Op::Gt => Cmp::Gt,
+            Op::Ge => Cmp::Ge,
+            _ => return None,
+        })
+    }
+
+    /// Whether the operator selects the entries that do not match its values
+    fn negated(self) -> bool {
+        matches!(self, Op::Ne | Op::NotMatch | Op::NotIn)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+    Comma,
+}
+
+struct Spanned {
+    token: Token,
+    span: Range<usize>,
+}
+
+/// Operators, longest first so `<=` is not read as `<`
+const SYMBOLS: &[(&str, Token)] = &[
+    ("&&", Token::And),
+    ("||", Token::Or),
+    ("==", Token::Op(Op::Eq)),
+    ("!=", Token::Op(Op::Ne)),
+    ("!~", Token::Op(Op::NotMatch)),
+    ("<=", Token::Op(Op::Le)),
+    (">=", Token::Op(Op::Ge)),
+    ("<", Token::Op(Op::Lt)),
+    (">", Token::Op(Op::Gt)),
+    ("=", Token::Op(Op::Eq)),
+    ("~", Token::Op(Op::Match)),
+    ("!", Token::Not),
+    ("(", Token::LParen),
+    (")", Token::RParen),
+    (",", Token::Comma),
+];
+
+fn tokenize(source: &str) -> Result<Vec<Spanned>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while let Some(c) = source[pos..].chars().next() {
+        let rest = &source[pos..];
+        if c.is_whitespace() {
+            pos += c.len_utf8();
+            continue;
+        }
+
+        let (token, len) = if let Some((text, token)) = SYMBOLS.iter().find(|(text, _)| rest.starts_with(text)) {
+            (token.clone(), text.len())
+        } else if c == '"' || c == '\'' {
+            let end = rest[1..]
+                .find(c)
+                .ok_or_else(|| ParseError::new("unterminated string", pos..source.len()))?;
+            (Token::Str(rest[1..=end].to_string()), end + 2)
+        } else {
+            let len = rest
+                .find(|c: char| c.is_whitespace() || "&|=!<>~(),\"'".contains(c))
+                .unwrap_or(rest.len());
+            if len == 0 {
+                return Err(ParseError::new(format!("unexpected `{}`", c), pos..pos + c.len_utf8()));
+            }
+            (Token::Word(rest[..len].to_string()), len)
+        };
+        tokens.push(Spanned {
+            token,
+            span: pos..pos + len,
+        });
+        pos += len;
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Spanned>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|spanned| &spanned.token)
+    }
+
+    /// The span of the next token, or an empty span at the end of the expression
+    fn span(&self) -> Range<usize> {
+        self.tokens
+            .get(self.pos)
+            .map(|spanned| spanned.span.clone())
+            .unwrap_or(self.source.len()..self.source.len())
+    }
+
+    fn next(&mut self) -> Option<Spanned> {
+        let spanned = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        Some(Spanned {
+            token: spanned.token.clone(),
+            span: spanned.span.clone(),
+        })
+    }
+
+    /// Consume the next token when it is `token`, or the keyword spelling it
+    fn eat(&mut self, token: Token, keyword: &str) -> bool {
+        let found = match self.peek() {
+            Some(Token::Word(word)) => word == keyword,
+            Some(next) => *next == token,
+            None => false,
+        };
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// Consume the next token when it is the bare word `word`
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(next)) if next == word);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, token: Token, text: &str) -> Result<(), ParseError> {
+        if self.peek() == Some(&token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(ParseError::new(format!("expected `{}`", text), self.span()))
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_and()?;
+        while self.eat(Token::Or, "or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_unary()?;
+        while self.eat(Token::And, "and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        if self.eat(Token::Not, "not") {
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let expr = self.parse_or()?;
+            self.expect(Token::RParen, ")")?;
+            return Ok(expr);
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ParseError> {
+        let span = self.span();
+        let field = match self.next() {
+            Some(Spanned {
+                token: Token::Word(word),
+                ..
+            }) => Field::from_name(&word).ok_or_else(|| {
+                ParseError::new(
+                    format!("unknown field `{}`, expected {}", word, Field::NAMES),
+                    span.clone(),
+                )
+            })?,
+            _ => return Err(ParseError::new(format!("expected a field: {}", Field::NAMES), span)),
+        };
+
+        let op_span = self.span();
+        let op = match self.next().map(|spanned| spanned.token) {
+            Some(Token::Op(op)) => op,
+            Some(Token::Word(word)) if word == "in" => Op::In,
+            Some(Token::Word(word)) if word == "not" && self.eat_word("in") => Op::NotIn,
+            _ => return Err(ParseError::new("expected a comparison operator", op_span)),
+        };
+
+        let values = if matches!(op, Op::In | Op::NotIn) {
+            self.expect(Token::LParen, "(")?;
+            let mut values = vec![self.parse_value()?];
+            while self.peek() == Some(&Token::Comma) {
+                self.pos += 1;
+                values.push(self.parse_value()?);
+            }
+            self.expect(Token::RParen, ")")?;
+            values
+        } else {
+            vec![self.parse_value()?]
+        };
+
+        compile(field, op, op_span, values).map(Expr::Test)
+    }
+
+    fn parse_value(&mut self) -> Result<(String, Range<usize>), ParseError> {
+        let span = self.span();
+        match self.next().map(|spanned| spanned.token) {
+            Some(Token::Word(value) | Token::Str(value)) => Ok((value, span)),
+            _ => Err(ParseError::new("expected a value", span)),
+        }
+    }
+}
+
+/// Check the operator and values against the field, and build the test
+fn compile(
+    field: Field,
+    op: Op,
+    op_span: Range<usize>,
+    values: Vec<(String, Range<usize>)>,
+) -> Result<Test, ParseError> {
+    let negate = op.negated();
+    let unsupported = || {
+        ParseError::new(
+            format!("this operator does not apply to {}", field.name()),
+            op_span.clone(),
+        )
+    };
+    let equality = matches!(op, Op::Eq | Op::Ne | Op::In | Op::NotIn);
+
+    let (value, span) = values[0].clone();
+    match field {
+        Field::Name | Field::Path | Field::Ext => {
+            let normalize = |value: &str| match field {
+                Field::Ext => value.trim_start_matches('.').to_lowercase(),
+                _ => value.to_string(),
+            };
+            let matcher = if matches!(op, Op::Match | Op::NotMatch) {
+                let glob = Glob::new(&normalize(&value))
+                    .map_err(|err| ParseError::new(format!("invalid glob: {}", err.kind()), span))?;
+                TextMatcher::Glob(glob.compile_matcher())
+            } else if equality {
+                TextMatcher::Exact(values.iter().map(|(value, _)| normalize(value)).collect())
+            } else {
+                return Err(unsupported());
+            };
+            Ok(Test::Text {
+                field,
+                matcher,
+                negate,
+            })
+        }
+        Field::Type if equality => Ok(Test::Type {
+            types: each(&values, EntryType::from_name, EntryType::NAMES)?,
+            negate,
+        }),
+        Field::Git if equality => Ok(Test::Git {
+            states: each(&values, GitState::from_name, GitState::NAMES)?,
+            negate,
+        }),
+        Field::Owner if equality => Ok(Test::Owner {
+            uids: each(&values, user_id, "a user name or id")?,
+            negate,
+        }),
+        Field::Perms if equality => Ok(Test::Perms {
+            modes: each(&values, parse_mode, "an octal mode like 644")?,
+            negate,
+        }),
+        Field::Size => {
+            let cmp = op.cmp().ok_or_else(unsupported)?;
+            let bytes = each(&values, |value| crate::app::parse_size(value).ok(), "a size like 10K, 1.5M or 2G")?[0];
+            Ok(Test::Size { cmp, bytes })
+        }
+        Field::Depth => {
+            let cmp = op.cmp().ok_or_else(unsupported)?;
+            let depth = each(&values, |value| value.parse().ok(), "a depth")?[0];
+            Ok(Test::Depth { cmp, depth })
+        }
+        Field::Mtime | Field::Atime => {
+            let cmp = op.cmp().ok_or_else(unsupported)?;
+            let time_field = match field {
+                Field::Mtime => TimeField::Modified,
+                _ => TimeField::Accessed,
+            };
+            if let Some((start, end)) = parse_date(&value) {
+                return Ok(Test::Date {
+                    field: time_field,
+                    cmp,
+                    start,
+                    end,
+                });
+            }
+            let age = parse_age(&value)
+                .ok_or_else(|| ParseError::new("expected an age like 30m, 7d or 2w, or a date like 2024-01-31", span))?;
+            if matches!(cmp, Cmp::Eq | Cmp::Ne) {
+                return Err(ParseError::new("ages can only be compared with <, <=, > or >=", op_span));
+            }
+            Ok(Test::Age {
+                field: time_field,
+                cmp,
+                age,
+            })
+        }
+        Field::Type | Field::Git | Field::Owner | Field::Perms => Err(unsupported()),
+    }
+}
+
+/// Parse every value, reporting the first invalid one
+fn each<T>(
+    values: &[(String, Range<usize>)],
+    parse: impl Fn(&str) -> Option<T>,
+    expected: &str,
+) -> Result<Vec<T>, ParseError> {
+    values
+        .iter()
+        .map(|(value, span)| {
+            parse(value).ok_or_else(|| ParseError::new(format!("expected {}", expected), span.clone()))
+        })
+        .collect()
+}
+
+/// Permission bits in octal, like `644` or `4755`
+fn parse_mode(value: &str) -> Option<u32> {
+    u32::from_str_radix(value, 8).ok().filter(|mode| *mode <= 0o7777)
+}
+
+/// An age with an s, m, h, d, w or y suffix, like `7d`
+fn parse_age(value: &str) -> Option<Duration> {
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let (number, unit) = value.split_at(split);
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        "y" => 365 * 86400,
+        _ => return None,
+    };
+    let number: f64 = number.parse().ok()?;
+    Duration::try_from_secs_f64(number * seconds as f64).ok()
+}
+
+/// The local day `value` names, in the YYYY-MM-DD form, as its first and last instants
+fn parse_date(value: &str) -> Option<(SystemTime, SystemTime)> {
+    use chrono::{Local, NaiveDate};
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let start = date.and_hms_opt(0, 0, 0)?.and_local_timezone(Local).earliest()?;
+    let end = date.succ_opt()?.and_hms_opt(0, 0, 0)?.and_local_timezone(Local).earliest()?;
+    Some((start.into(), end.into()))
+}
+
+#[cfg(unix)]
+fn user_id(value: &str) -> Option<u32> {
+    value
+        .parse()
+        .ok()
+        .or_else(|| users::get_user_by_name(value).map(|user| user.uid()))
+}
+
+#[cfg(not(unix))]
+fn user_id(value: &str) -> Option<u32> {
+    value.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{self, File, FileTimes};
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use futures::StreamExt;
+    use tempfile::{tempdir, TempDir};
+
+    use super::Filter;
+    use crate::git::GitStatus;
+    use crate::meta::GitFileStatus;
+    use crate::stream::{test_walk, FileEntry};
+
+    /// `root/` holding `a.rs` (2 KiB), `b.txt` (10 bytes, changed 10 days ago) and `sub/c.rs`
+    /// (changed and read on 2020-06-15 at noon UTC)
+    fn tree() -> TempDir {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.rs"), vec![b'a'; 2048]).unwrap();
+        fs::write(root.join("b.txt"), "0123456789").unwrap();
+        fs::write(root.join("sub/c.rs"), "").unwrap();
+
+        let set_times = |path: &Path, time: SystemTime| {
+            let file = File::options().write(true).open(path).unwrap();
+            file.set_times(FileTimes::new().set_modified(time).set_accessed(time))
+                .unwrap();
+        };
+        set_times(&root.join("b.txt"), SystemTime::now() - Duration::from_secs(10 * 86400));
+        set_times(&root.join("sub/c.rs"), SystemTime::UNIX_EPOCH + Duration::from_secs(1_592_222_400));
+        dir
+    }
+
+    fn entries(dir: &TempDir) -> Vec<FileEntry> {
+        let walk = test_walk(&dir.path().join("root"));
+        futures::executor::block_on(walk.collect::<Vec<_>>())
+            .into_iter()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    /// The names of the entries `source` selects, sorted
+    fn select(dir: &TempDir, source: &str) -> Vec<String> {
+        let filter = Filter::parse(source).unwrap();
+        let mut names: Vec<String> = entries(dir)
+            .into_iter()
+            .filter(|entry| filter.matches(entry))
+            .map(|entry| entry.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// The offset and rendering of the error `source` fails with
+    fn error(source: &str) -> (String, String) {
+        let err = Filter::parse(source).unwrap_err();
+        (err.to_string(), err.render(source))
+    }
+
+    #[test]
+    fn test_precedence() {
+        let dir = tree();
+        // `&&` binds tighter than `||`, `!` tighter than `&&`
+        assert_eq!(select(&dir, "name == a.rs || name == b.txt && depth == 0"), ["a.rs"]);
+        assert_eq!(select(&dir, "(name == a.rs || name == b.txt) && depth == 0"), Vec::<String>::new());
+        assert_eq!(select(&dir, "not name == a.rs && depth == 1"), ["b.txt", "sub"]);
+        assert_eq!(select(&dir, "! (name == a.rs and depth == 1) and depth == 1"), ["b.txt", "sub"]);
+        assert_eq!(select(&dir, "depth == 2 or name == a.rs and type == dir"), ["c.rs"]);
+    }
+
+    #[test]
+    fn test_text_fields() {
+        let dir = tree();
+        assert_eq!(select(&dir, "name == a.rs"), ["a.rs"]);
+        assert_eq!(select(&dir, "name != a.rs && depth > 0"), ["b.txt", "c.rs", "sub"]);
+        assert_eq!(select(&dir, "name ~ '*.rs'"), ["a.rs", "c.rs"]);
+        assert_eq!(select(&dir, "name !~ '*.rs' && depth > 0"), ["b.txt", "sub"]);
+        assert_eq!(select(&dir, "name in (b.txt, sub)"), ["b.txt", "sub"]);
+        assert_eq!(select(&dir, "name not in (b.txt, sub) && depth > 0"), ["a.rs", "c.rs"]);
+        assert_eq!(select(&dir, "path ~ '*/sub/*'"), ["c.rs"]);
+        assert_eq!(select(&dir, "ext == .RS"), ["a.rs", "c.rs"]);
+        assert_eq!(select(&dir, "ext in (txt, md)"), ["b.txt"]);
+        assert_eq!(select(&dir, "ext !~ 'r*' && type == file"), ["b.txt"]);
+    }
+
+    #[test]
+    fn test_type() {
+        let dir = tree();
+        assert_eq!(select(&dir, "type == dir"), ["root", "sub"]);
+        assert_eq!(select(&dir, "type != d"), ["a.rs", "b.txt", "c.rs"]);
+        assert_eq!(select(&dir, "type in (symlink, pipe, socket)"), Vec::<String>::new());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_fields() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let dir = tree();
+        let root = dir.path().join("root");
+        fs::set_permissions(root.join("a.rs"), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::set_permissions(root.join("b.txt"), fs::Permissions::from_mode(0o600)).unwrap();
+        let uid = fs::metadata(&root).unwrap().uid();
+
+        assert_eq!(select(&dir, "type == exec"), ["a.rs"]);
+        assert_eq!(select(&dir, "perms == 600"), ["b.txt"]);
+        assert_eq!(select(&dir, "perms in (755, 600) && type == file"), ["a.rs", "b.txt"]);
+        assert_eq!(select(&dir, "perms != 755 && type == file"), ["b.txt", "c.rs"]);
+        assert_eq!(select(&dir, &format!("owner == {} && depth == 1", uid)), ["a.rs", "b.txt", "sub"]);
+        assert_eq!(select(&dir, &format!("owner != {}", uid)), Vec::<String>::new());
+        assert_eq!(select(&dir, &format!("owner in ({}, 65534) && depth == 2", uid)), ["c.rs"]);
+    }
+
+    #[test]
+    fn test_size_units() {
+        let dir = tree();
+        assert_eq!(select(&dir, "size == 2K"), ["a.rs"]);
+        assert_eq!(select(&dir, "size == 2048"), ["a.rs"]);
+        assert_eq!(select(&dir, "size >= 1.5K && type == file"), ["a.rs"]);
+        assert_eq!(select(&dir, "size < 0.5M && type == file"), ["a.rs", "b.txt", "c.rs"]);
+        assert_eq!(select(&dir, "size <= 10 && type == file"), ["b.txt", "c.rs"]);
+        assert_eq!(select(&dir, "size > 10 && size != 2k && type == file"), Vec::<String>::new());
+        assert_eq!(select(&dir, "size < 1G && size > 1T"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_depth() {
+        let dir = tree();
+        assert_eq!(select(&dir, "depth == 0"), ["root"]);
+        assert_eq!(select(&dir, "depth != 1"), ["c.rs", "root"]);
+        assert_eq!(select(&dir, "depth >= 2"), ["c.rs"]);
+        assert_eq!(select(&dir, "depth < 1"), ["root"]);
+        assert_eq!(select(&dir, "depth <= 1 && depth > 0"), ["a.rs", "b.txt", "sub"]);
+    }
+
+    #[test]
+    fn test_times() {
+        let dir = tree();
+        // Ages, in every unit
+        assert_eq!(select(&dir, "mtime > 7d && mtime < 1y"), ["b.txt"]);
+        assert_eq!(select(&dir, "mtime < 1w && type == file"), ["a.rs"]);
+        assert_eq!(select(&dir, "mtime <= 600s && type == file"), ["a.rs"]);
+        assert_eq!(select(&dir, "mtime >= 9d && mtime < 264h"), ["b.txt"]);
+        assert_eq!(select(&dir, "atime > 30m && atime < 20000m && type == file"), ["b.txt"]);
+        // Dates, as whole local days
+        assert_eq!(select(&dir, "mtime == 2020-06-15"), ["c.rs"]);
+        assert_eq!(select(&dir, "atime == 2020-06-15"), ["c.rs"]);
+        assert_eq!(select(&dir, "mtime < 2021-01-01"), ["c.rs"]);
+        assert_eq!(select(&dir, "mtime <= 2020-06-15 || mtime > 2020-06-16 && type != file"), ["c.rs", "root", "sub"]);
+        assert_eq!(select(&dir, "mtime >= 2020-06-16 && type == file"), ["a.rs", "b.txt"]);
+        assert_eq!(select(&dir, "mtime != 2020-06-15 && type == file"), ["a.rs", "b.txt"]);
+    }
+
+    #[test]
+    fn test_git() {
+        use GitStatus::*;
+
+        let dir = tree();
+        let mut entry = entries(&dir).remove(1);
+        let mut status = |source: &str, index, workdir| {
+            entry.git_status = Some(GitFileStatus { index, workdir });
+            Filter::parse(source).unwrap().matches(&entry)
+        };
+
+        assert!(status("git == modified", Unmodified, Modified));
+        assert!(status("git == modified", Modified, Unmodified));
+        assert!(status("git == untracked", Unmodified, NewInWorkdir));
+        assert!(!status("git == untracked", NewInIndex, Unmodified));
+        assert!(status("git in (new, renamed)", NewInIndex, Unmodified));
+        assert!(status("git not in (new, renamed)", Unmodified, Deleted));
+        assert!(status("git == deleted", Deleted, Unmodified));
+        assert!(status("git == typechange", Unmodified, Typechange));
+        assert!(status("git == ignored", Unmodified, Ignored));
+        assert!(status("git == conflicted", Conflicted, Conflicted));
+        assert!(status("git == clean", Unmodified, Unmodified));
+        assert!(status("git == unmodified", Default, Default));
+        assert!(!status("git != clean", Unmodified, Unmodified));
+
+        // Without a status, as outside of a work tree, no state matches
+        entry.git_status = None;
+        let filter = Filter::parse("git == clean").unwrap();
+        assert!(!filter.matches(&entry));
+        assert!(Filter::parse("git != clean").unwrap().matches(&entry));
+        assert!(filter.needs_git());
+        assert!(!filter.needs_stat());
+    }
+
+    #[test]
+    fn test_operators_against_fields() {
+        for (source, message) in [
+            ("name < a", "this operator does not apply to name at offset 5"),
+            ("ext >= rs", "this operator does not apply to ext at offset 4"),
+            ("type ~ dir", "this operator does not apply to type at offset 5"),
+            ("size ~ 1K", "this operator does not apply to size at offset 5"),
+            ("size in (1K, 2K)", "this operator does not apply to size at offset 5"),
+            ("depth !~ 1", "this operator does not apply to depth at offset 6"),
+            ("owner > 0", "this operator does not apply to owner at offset 6"),
+            ("perms <= 644", "this operator does not apply to perms at offset 6"),
+            ("git < modified", "this operator does not apply to git at offset 4"),
+            ("mtime ~ 7d", "this operator does not apply to mtime at offset 6"),
+            ("atime == 7d", "ages can only be compared with <, <=, > or >= at offset 6"),
+        ] {
+            assert_eq!(error(source).0, message, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        for (source, message) in [
+            ("size > 10Q", "expected a size like 10K, 1.5M or 2G at offset 7"),
+            ("depth == deep", "expected a depth at offset 9"),
+            ("perms == 999", "expected an octal mode like 644 at offset 9"),
+            ("type in (file, folder)", "expected file, exec, dir, symlink, pipe, socket, char, block or special at offset 15"),
+            ("git == dirty", "expected clean, new, untracked, modified, deleted, renamed, typechange, ignored or conflicted at offset 7"),
+            ("mtime > 7 days", "expected an age like 30m, 7d or 2w, or a date like 2024-01-31 at offset 8"),
+            ("mtime < 2024-13-01", "expected an age like 30m, 7d or 2w, or a date like 2024-01-31 at offset 8"),
+        ] {
+            assert_eq!(error(source).0, message, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_error_carets() {
+        // Under the unknown field
+        let (_, rendered) = error("ext == rs && colour == red");
+        assert!(rendered.ends_with("\n  ext == rs && colour == red\n               ^^^^^^"), "{}", rendered);
+
+        // Past the end of the expression
+        let (message, rendered) = error("name ==");
+        assert_eq!(message, "expected a value at offset 7");
+        assert!(rendered.ends_with("\n  name ==\n         ^"), "{}", rendered);
+
+        let (message, rendered) = error("(name == a.rs");
+        assert_eq!(message, "expected `)` at offset 13");
+        assert!(rendered.ends_with("\n  (name == a.rs\n               ^"), "{}", rendered);
+
+        // From the quote to the end
+        let (message, rendered) = error("name == 'a.rs");
+        assert_eq!(message, "unterminated string at offset 8");
+        assert!(rendered.ends_with("\n          ^^^^^"), "{}", rendered);
+
+        // Under the trailing token
+        let (message, rendered) = error("name == a.rs b.rs");
+        assert_eq!(message, "expected `&&`, `||` or the end of the expression at offset 13");
+        assert!(rendered.ends_with("\n               ^^^^"), "{}", rendered);
+
+        // Counted in characters, not bytes
+        let (message, rendered) = error("name == 'é' && size > lots");
+        assert_eq!(message, "expected a size like 10K, 1.5M or 2G at offset 23");
+        assert!(rendered.ends_with("\n  name == 'é' && size > lots\n                        ^^^^"), "{}", rendered);
+
+        let (message, _) = error("name == a.rs & depth == 1");
+        assert_eq!(message, "unexpected `&` at offset 13");
+    }
+}
@@ -1,8 +1,13 @@
 pub mod blocks;
+pub mod cache;
 pub mod color;
+pub mod contains;
 pub mod date;
 pub mod dereference;
+pub mod duplicates;
 pub mod display;
+pub mod fingerprint;
+pub mod hash_algorithm;
 pub mod header;
 pub mod hyperlink;
 pub mod icons;
@@ -11,42 +16,67 @@ pub mod indicators;
 pub mod layout;
 pub mod literal;
 pub mod llm;
+pub mod one_file_system;
 pub mod permission;
 pub mod recursion;
+pub mod repo_info;
+pub mod revision;
+pub mod scan_limits;
 pub mod size;
+pub mod size_mode;
 pub mod sorting;
 pub mod symlink_arrow;
 pub mod symlinks;
+pub mod threads;
+pub mod time;
 pub mod total_size;
+pub mod tracked;
 pub mod truncate_owner;
+pub mod where_filter;
 
 pub use blocks::Blocks;
+pub use cache::{CacheFlag, CacheMode};
 pub use color::Color;
 pub use color::{ColorOption, ThemeOption};
+pub use contains::Contains;
 pub use date::DateFlag;
 pub use dereference::Dereference;
+pub use duplicates::Duplicates;
 pub use display::Display;
+pub use fingerprint::Fingerprint;
+pub use hash_algorithm::HashAlgorithm;
 pub use header::Header;
 pub use hyperlink::HyperlinkOption;
 pub use icons::IconOption;
 pub use icons::IconTheme;
 pub use icons::Icons;
-pub use ignore_globs::IgnoreGlobs;
+pub use ignore_globs::{
+    IgnoreGlobs, IgnoreMatch, IgnoreOrigin, IgnoreReport, IgnoredChildren, SapIgnores,
+};
 pub use indicators::Indicators;
 pub use layout::Layout;
 pub use literal::Literal;
 pub use llm::LlmOutput;
+pub use one_file_system::OneFileSystem;
 pub use permission::PermissionFlag;
 pub use recursion::Recursion;
+pub use repo_info::RepoInfoFlag;
+pub use revision::Revision;
+pub use scan_limits::ScanLimits;
 pub use size::SizeFlag;
+pub use size_mode::SizeMode;
 pub use sorting::DirGrouping;
 pub use sorting::SortColumn;
 pub use sorting::SortOrder;
 pub use sorting::Sorting;
 pub use symlink_arrow::SymlinkArrow;
 pub use symlinks::NoSymlink;
+pub use threads::{ThreadMode, Threads};
+pub use time::TimeField;
 pub use total_size::TotalSize;
+pub use tracked::Tracked;
 pub use truncate_owner::TruncateOwner;
+pub use where_filter::WhereFilter;
 
 use crate::app::Cli;
 use crate::config_file::Config;
@@ -60,27 +90,40 @@ use yaml_rust::Yaml;
 #[derive(Clone, Debug, Default)]
 pub struct Flags {
     pub blocks: Blocks,
+    pub cache: CacheFlag,
     pub color: Color,
+    pub contains: Contains,
     pub date: DateFlag,
     pub dereference: Dereference,
     pub display: Display,
+    pub duplicates: Duplicates,
     pub display_indicators: Indicators,
+    pub fingerprint: Fingerprint,
+    pub hash_algorithm: HashAlgorithm,
     pub icons: Icons,
     pub ignore_globs: IgnoreGlobs,
     pub layout: Layout,
     pub no_symlink: NoSymlink,
+    pub one_file_system: OneFileSystem,
     pub recursion: Recursion,
+    pub repo_info: RepoInfoFlag,
+    pub revision: Revision,
+    pub scan_limits: ScanLimits,
     pub size: SizeFlag,
+    pub size_mode: SizeMode,
     pub permission: PermissionFlag,
     pub sorting: Sorting,
-    #[allow(dead_code)] // Planned for integration with streaming
     pub total_size: TotalSize,
+    pub tracked: Tracked,
     pub symlink_arrow: SymlinkArrow,
+    pub threads: Threads,
+    pub time: TimeField,
     pub hyperlink: HyperlinkOption,
     pub header: Header,
     pub literal: Literal,
     pub truncate_owner: TruncateOwner,
     pub llm: LlmOutput,
+    pub where_filter: WhereFilter,
     #[allow(dead_code)] // Planned for integration with streaming
     pub git: bool,
 }
@@ -90,31 +133,46 @@ impl Flags {
     ///
     /// # Errors
     ///
-    /// This can return an [Error], when either the building of the ignore globs or the parsing of
-    /// the recursion depth parameter fails.
+    /// This can return an [Error], when either the building of the ignore globs, the parsing of
+    /// the recursion depth parameter, the `--contains` pattern or the `--where` expression
+    /// fails.
     pub fn configure_from(cli: &Cli, config: &Config) -> Result<Self, Error> {
         Ok(Self {
             blocks: Blocks::configure_from(cli, config),
+            cache: CacheFlag::configure_from(cli, config),
             color: Color::configure_from(cli, config),
+            contains: Contains::configure_from(cli, config)?,
             date: DateFlag::configure_from(cli, config),
             dereference: Dereference::configure_from(cli, config),
+            duplicates: Duplicates::configure_from(cli, config),
             display: Display::configure_from(cli, config),
+            fingerprint: Fingerprint::configure_from(cli, config),
+            hash_algorithm: HashAlgorithm::configure_from(cli, config),
             layout: Layout::configure_from(cli, config),
             size: SizeFlag::configure_from(cli, config),
+            size_mode: SizeMode::configure_from(cli, config),
             permission: PermissionFlag::configure_from(cli, config),
             display_indicators: Indicators::configure_from(cli, config),
             icons: Icons::configure_from(cli, config),
             ignore_globs: IgnoreGlobs::configure_from(cli, config)?,
             no_symlink: NoSymlink::configure_from(cli, config),
+            one_file_system: OneFileSystem::configure_from(cli, config),
             recursion: Recursion::configure_from(cli, config),
+            repo_info: RepoInfoFlag::configure_from(cli, config),
+            revision: Revision::configure_from(cli, config),
+            scan_limits: ScanLimits::configure_from(cli, config),
             sorting: Sorting::configure_from(cli, config),
             total_size: TotalSize::configure_from(cli, config),
+            tracked: Tracked::configure_from(cli, config),
             symlink_arrow: SymlinkArrow::configure_from(cli, config),
+            threads: Threads::configure_from(cli, config),
+            time: TimeField::configure_from(cli, config),
             hyperlink: HyperlinkOption::configure_from(cli, config),
             header: Header::configure_from(cli, config),
             literal: Literal::configure_from(cli, config),
             truncate_owner: TruncateOwner::configure_from(cli, config),
             llm: LlmOutput::configure_from(cli, config),
+            where_filter: WhereFilter::configure_from(cli, config)?,
             git: cli.git,
         })
     }
@@ -206,6 +206,12 @@ pub enum Block {
     INode,
     Links,
     GitStatus,
+    Filesystem,
+    Matches,
+    Hash,
+    Mime,
+    Media,
+    Binary,
 }
 
 impl Block {
@@ -222,6 +228,12 @@ impl Block {
             Block::Date => "Date Modified",
             Block::Name => "Name",
             Block::GitStatus => "Git",
+            Block::Filesystem => "Filesystem",
+            Block::Matches => "Matches",
+            Block::Hash => "Hash",
+            Block::Mime => "Type",
+            Block::Media => "Media",
+            Block::Binary => "Binary",
         }
     }
 }
@@ -242,6 +254,12 @@ impl TryFrom<&str> for Block {
             "inode" => Ok(Self::INode),
             "links" => Ok(Self::Links),
             "git" => Ok(Self::GitStatus),
+            "fs" => Ok(Self::Filesystem),
+            "matches" => Ok(Self::Matches),
+            "hash" => Ok(Self::Hash),
+            "mime" => Ok(Self::Mime),
+            "media" => Ok(Self::Media),
+            "binary" => Ok(Self::Binary),
             _ => Err(format!("Not a valid block name: {string}")),
         }
     }
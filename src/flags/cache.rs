@@ -0,0 +1,136 @@
+//! This module defines the [CacheFlag] options. To set them up from [Cli], a [Config] and
+//! their [Default] values, use the [configure_from](CacheFlag::configure_from) method.
+
+use crate::app::Cli;
+use crate::config_file::Config;
+
+use serde::Deserialize;
+
+/// How large the scan cache directory may grow by default, in MiB.
+const DEFAULT_MAX_SIZE: u64 = 64;
+
+/// The options relating to the on-disk scan cache.
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub struct CacheFlag {
+    /// Whether walks read and update the cache.
+    pub mode: CacheMode,
+    /// The size of the cache directory after which the least recently used caches are
+    /// removed, in bytes.
+    pub max_size: u64,
+}
+
+impl Default for CacheFlag {
+    fn default() -> Self {
+        Self {
+            mode: CacheMode::default(),
+            max_size: DEFAULT_MAX_SIZE * 1024 * 1024,
+        }
+    }
+}
+
+/// Whether directory listings are served from the scan cache.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum CacheMode {
+    /// Walk the disk without a cache.
+    #[default]
+    Off,
+    /// Serve unchanged directories from the cache and store the others.
+    On,
+    /// Read every directory again and replace the cache.
+    Refresh,
+}
+
+impl CacheMode {
+    fn from_arg_str(value: &str) -> Self {
+        match value {
+            "off" => Self::Off,
+            "on" => Self::On,
+            "refresh" => Self::Refresh,
+            // Invalid value should be handled by `clap` when building an `Cli`
+            other => unreachable!("Invalid value '{other}' for 'cache'"),
+        }
+    }
+}
+
+impl CacheFlag {
+    /// Get the `CacheFlag` from either [Cli], a [Config] or the [Default] value. The mode is
+    /// taken from `Cli::cache` first, then from `Config::cache.mode`; the size limit only
+    /// from `Config::cache.max-size`, in MiB.
+    pub fn configure_from(cli: &Cli, config: &Config) -> Self {
+        let cache = config.cache.as_ref();
+
+        Self {
+            mode: cli
+                .cache
+                .as_deref()
+                .map(CacheMode::from_arg_str)
+                .or_else(|| cache.and_then(|cache| cache.mode))
+                .unwrap_or_default(),
+            max_size: cache
+                .and_then(|cache| cache.max_size)
+                .unwrap_or(DEFAULT_MAX_SIZE)
+                .saturating_mul(1024 * 1024),
+        }
+    }
+
+    /// Whether walks go through the cache
+    pub fn is_enabled(&self) -> bool {
+        self.mode != CacheMode::Off
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::{CacheFlag, CacheMode};
+
+    use crate::app::Cli;
+    use crate::config_file::{self, Config};
+
+    fn config_cache(mode: Option<CacheMode>, max_size: Option<u64>) -> Config {
+        let mut c = Config::with_none();
+        c.cache = Some(config_file::Cache { mode, max_size });
+        c
+    }
+
+    #[test]
+    fn test_default() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let cache = CacheFlag::configure_from(&cli, &Config::with_none());
+        assert_eq!(CacheMode::Off, cache.mode);
+        assert_eq!(64 * 1024 * 1024, cache.max_size);
+        assert!(!cache.is_enabled());
+    }
+
+    #[test]
+    fn test_from_cli_refresh() {
+        let argv = ["lsd", "--cache", "refresh"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            CacheMode::Refresh,
+            CacheFlag::configure_from(&cli, &config_cache(Some(CacheMode::On), None)).mode
+        );
+    }
+
+    #[test]
+    fn test_from_cli_off() {
+        let argv = ["lsd", "--cache", "off"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            CacheMode::Off,
+            CacheFlag::configure_from(&cli, &config_cache(Some(CacheMode::On), None)).mode
+        );
+    }
+
+    #[test]
+    fn test_from_config() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let cache = CacheFlag::configure_from(&cli, &config_cache(Some(CacheMode::On), Some(8)));
+        assert_eq!(CacheMode::On, cache.mode);
+        assert_eq!(8 * 1024 * 1024, cache.max_size);
+    }
+}
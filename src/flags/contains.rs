@@ -0,0 +1,227 @@
+//! This module defines the [Contains] options. To set them up from [Cli], a [Config] and
+//! their [Default] values, use the [configure_from](Contains::configure_from) method.
+
+use crate::app::{self, Cli};
+use crate::config_file::Config;
+use crate::print_error;
+
+use clap::error::ErrorKind;
+use clap::Error;
+use regex::bytes::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+/// The largest file searched by default, in bytes.
+const DEFAULT_MAX_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The options of the content search.
+#[derive(Clone, Debug)]
+pub struct Contains {
+    /// The compiled `--contains` pattern, `None` when contents are not searched. It is matched
+    /// against whole files, so `^` and `$` match at line boundaries.
+    pub regex: Option<Regex>,
+    /// Files larger than this, in bytes, are not searched.
+    pub max_size: u64,
+    /// Whether the first matching line numbers are kept.
+    pub line_numbers: bool,
+}
+
+impl Default for Contains {
+    fn default() -> Self {
+        Self {
+            regex: None,
+            max_size: DEFAULT_MAX_SIZE,
+            line_numbers: false,
+        }
+    }
+}
+
+/// How the pattern treats letter case.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContainsCase {
+    #[default]
+    Sensitive,
+    Insensitive,
+    /// Insensitive unless the pattern has an uppercase letter.
+    Smart,
+}
+
+impl ContainsCase {
+    fn from_arg_str(value: &str) -> Self {
+        match value {
+            "sensitive" => Self::Sensitive,
+            "insensitive" => Self::Insensitive,
+            "smart" => Self::Smart,
+            // Invalid value should be handled by `clap` when building an `Cli`
+            other => unreachable!("Invalid value '{other}' for 'contains-case'"),
+        }
+    }
+
+    fn ignores_case(self, pattern: &str) -> bool {
+        match self {
+            Self::Sensitive => false,
+            Self::Insensitive => true,
+            Self::Smart => !pattern.chars().any(char::is_uppercase),
+        }
+    }
+}
+
+impl Contains {
+    /// Get the `Contains` options from [Cli] and a [Config]. The pattern and
+    /// `--contains-fixed` only come from [Cli]; the case, size limit and line numbers are taken
+    /// from [Cli] first, then from the `Config::contains` section.
+    ///
+    /// # Errors
+    ///
+    /// If the pattern is not a valid regular expression.
+    pub fn configure_from(cli: &Cli, config: &Config) -> Result<Self, Error> {
+        let contains = config.contains.as_ref();
+        let case = cli
+            .contains_case
+            .as_deref()
+            .map(ContainsCase::from_arg_str)
+            .or_else(|| contains.and_then(|contains| contains.case))
+            .unwrap_or_default();
+
+        let regex = match &cli.contains {
+            Some(pattern) => {
+                let source = if cli.contains_fixed {
+                    regex::escape(pattern)
+                } else {
+                    pattern.clone()
+                };
+                let regex = RegexBuilder::new(&source)
+                    .case_insensitive(case.ignores_case(pattern))
+                    .multi_line(true)
+                    .build()
+                    .map_err(|err| {
+                        Error::raw(ErrorKind::ValueValidation, format!("invalid --contains pattern: {}\n", err))
+                    })?;
+                Some(regex)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            regex,
+            max_size: cli
+                .contains_max_size
+                .or_else(|| {
+                    let value = contains?.max_size.as_deref()?;
+                    app::parse_size(value)
+                        .map_err(|err| print_error!("Not a valid contains.max-size: {}, {}.", value, err))
+                        .ok()
+                })
+                .unwrap_or(DEFAULT_MAX_SIZE),
+            line_numbers: cli.contains_lines
+                || contains.and_then(|contains| contains.line_numbers).unwrap_or(false),
+        })
+    }
+
+    /// Whether file contents are searched
+    pub fn is_enabled(&self) -> bool {
+        self.regex.is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::{Contains, ContainsCase};
+
+    use crate::app::{self, Cli};
+    use crate::config_file::{self, Config};
+
+    fn config_contains(case: Option<ContainsCase>, max_size: Option<&str>) -> Config {
+        let mut c = Config::with_none();
+        c.contains = Some(config_file::Contains {
+            case,
+            max_size: max_size.map(String::from),
+            line_numbers: Some(true),
+        });
+        c
+    }
+
+    fn is_match(contains: &Contains, text: &str) -> bool {
+        contains.regex.as_ref().unwrap().is_match(text.as_bytes())
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(Ok(4096), app::parse_size("4096"));
+        assert_eq!(Ok(10 * 1024), app::parse_size("10K"));
+        assert_eq!(Ok(1536 * 1024), app::parse_size("1.5MiB"));
+        assert_eq!(Ok(2 * 1024 * 1024 * 1024), app::parse_size("2g"));
+        assert!(app::parse_size("big").is_err());
+        assert!(app::parse_size("10Q").is_err());
+    }
+
+    #[test]
+    fn test_from_cli() {
+        let argv = ["lsd", "--contains", "Auth.*Token", "--contains-max-size", "16M"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let contains = Contains::configure_from(&cli, &Config::with_none()).unwrap();
+        assert!(contains.is_enabled());
+        assert!(is_match(&contains, "let AuthToken = 1;"));
+        assert!(!is_match(&contains, "authtoken"));
+        assert_eq!(16 * 1024 * 1024, contains.max_size);
+        assert!(!contains.line_numbers);
+    }
+
+    #[test]
+    fn test_from_cli_fixed() {
+        let argv = ["lsd", "--contains", "a.b(", "--contains-fixed"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let contains = Contains::configure_from(&cli, &Config::with_none()).unwrap();
+        assert!(is_match(&contains, "x = a.b(1)"));
+        assert!(!is_match(&contains, "x = axb(1)"));
+    }
+
+    #[test]
+    fn test_from_cli_invalid_pattern() {
+        let argv = ["lsd", "--contains", "a.b("];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert!(Contains::configure_from(&cli, &Config::with_none()).is_err());
+    }
+
+    #[test]
+    fn test_fixed_requires_pattern() {
+        let argv = ["lsd", "--contains-fixed"];
+        assert!(Cli::try_parse_from(argv).is_err());
+    }
+
+    #[test]
+    fn test_case() {
+        let argv = ["lsd", "--contains", "token", "--contains-case", "smart"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let contains = Contains::configure_from(&cli, &Config::with_none()).unwrap();
+        assert!(is_match(&contains, "AuthToken"));
+
+        let argv = ["lsd", "--contains", "Token", "--contains-case", "smart"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let contains = Contains::configure_from(&cli, &Config::with_none()).unwrap();
+        assert!(!is_match(&contains, "authtoken"));
+    }
+
+    #[test]
+    fn test_from_config() {
+        let argv = ["lsd", "--contains", "token"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let config = config_contains(Some(ContainsCase::Insensitive), Some("512K"));
+        let contains = Contains::configure_from(&cli, &config).unwrap();
+        assert!(is_match(&contains, "TOKEN"));
+        assert_eq!(512 * 1024, contains.max_size);
+        assert!(contains.line_numbers);
+    }
+
+    #[test]
+    fn test_default() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let contains = Contains::configure_from(&cli, &Config::with_none()).unwrap();
+        assert!(!contains.is_enabled());
+        assert_eq!(4 * 1024 * 1024, contains.max_size);
+        assert!(!contains.line_numbers);
+    }
+}
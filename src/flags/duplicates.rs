@@ -0,0 +1,71 @@
+//! This module defines the [Duplicates] flag. To set it up from [Cli], a [Config] and its
+//! [Default] value, use the [configure_from](Configurable::configure_from) method.
+
+use super::Configurable;
+
+use crate::app::Cli;
+use crate::config_file::Config;
+
+/// The flag showing whether to group the files with the same contents instead of listing.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Default)]
+pub struct Duplicates(pub bool);
+
+impl Configurable<Self> for Duplicates {
+    /// Get a potential `Duplicates` value from [Cli].
+    ///
+    /// If the "duplicates" argument is passed, this returns a `Duplicates` with value `true`
+    /// in a [Some]. Otherwise this returns [None].
+    fn from_cli(cli: &Cli) -> Option<Self> {
+        if cli.duplicates {
+            Some(Self(true))
+        } else {
+            None
+        }
+    }
+
+    /// The duplicate finder replaces the listing, so it is only turned on from [Cli]; this
+    /// always returns [None].
+    fn from_config(_config: &Config) -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::Duplicates;
+
+    use crate::app::Cli;
+    use crate::config_file::Config;
+    use crate::flags::Configurable;
+
+    #[test]
+    fn test_from_cli_none() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(None, Duplicates::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_true() {
+        let argv = ["lsd", "--duplicates"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(Duplicates(true)), Duplicates::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_config_none() {
+        assert_eq!(None, Duplicates::from_config(&Config::with_none()));
+    }
+
+    #[test]
+    fn test_configure_default() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            Duplicates(false),
+            Duplicates::configure_from(&cli, &Config::with_none())
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! This module defines the [Fingerprint] flag. To set it up from [Cli], a [Config] and its
+//! [Default] value, use the [configure_from](Configurable::configure_from) method.
+
+use super::Configurable;
+
+use crate::app::Cli;
+use crate::config_file::Config;
+
+/// The flag showing whether to print one digest of each input instead of listing.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Default)]
+pub struct Fingerprint(pub bool);
+
+impl Configurable<Self> for Fingerprint {
+    /// Get a potential `Fingerprint` value from [Cli].
+    ///
+    /// If the "fingerprint" argument is passed, this returns a `Fingerprint` with value `true`
+    /// in a [Some]. Otherwise this returns [None].
+    fn from_cli(cli: &Cli) -> Option<Self> {
+        if cli.fingerprint {
+            Some(Self(true))
+        } else {
+            None
+        }
+    }
+
+    /// The fingerprint replaces the listing, so it is only turned on from [Cli]; this always
+    /// returns [None].
+    fn from_config(_config: &Config) -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::Fingerprint;
+
+    use crate::app::Cli;
+    use crate::config_file::Config;
+    use crate::flags::Configurable;
+
+    #[test]
+    fn test_from_cli_none() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(None, Fingerprint::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_true() {
+        let argv = ["lsd", "--fingerprint"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(Fingerprint(true)), Fingerprint::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_config_none() {
+        assert_eq!(None, Fingerprint::from_config(&Config::with_none()));
+    }
+
+    #[test]
+    fn test_configure_default() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            Fingerprint(false),
+            Fingerprint::configure_from(&cli, &Config::with_none())
+        );
+    }
+}
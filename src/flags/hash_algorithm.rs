@@ -0,0 +1,107 @@
+//! This module defines the [HashAlgorithm] flag. To set it up from [Cli], a [Config] and its
+//! [Default] value, use the [configure_from](Configurable::configure_from) method.
+
+use super::Configurable;
+
+use crate::app::Cli;
+use crate::config_file::Config;
+
+use serde::Deserialize;
+
+/// The flag showing which algorithm hashes file contents for the `hash` block and
+/// `--fingerprint`.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum HashAlgorithm {
+    /// The variant to hash with BLAKE3, the fastest.
+    #[default]
+    Blake3,
+    /// The variant to hash with SHA-256, for digests other tools can check.
+    Sha256,
+}
+
+impl HashAlgorithm {
+    fn from_arg_str(value: &str) -> Self {
+        match value {
+            "blake3" => Self::Blake3,
+            "sha256" => Self::Sha256,
+            // Invalid value should be handled by `clap` when building an `Cli`
+            other => unreachable!("Invalid value '{other}' for 'hash-algorithm'"),
+        }
+    }
+
+    /// The name digests are prefixed with, like `blake3:`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Blake3 => "blake3",
+            Self::Sha256 => "sha256",
+        }
+    }
+}
+
+impl Configurable<Self> for HashAlgorithm {
+    /// Get a potential `HashAlgorithm` variant from [Cli].
+    ///
+    /// If the "hash-algorithm" argument is passed, the corresponding `HashAlgorithm` variant is
+    /// returned in a [Some]. Otherwise this returns [None].
+    fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.hash_algorithm.as_deref().map(Self::from_arg_str)
+    }
+
+    /// Get a potential `HashAlgorithm` variant from a [Config].
+    ///
+    /// If the `Config::hash-algorithm` has value, this returns it in a [Some].
+    /// Otherwise this returns [None].
+    fn from_config(config: &Config) -> Option<Self> {
+        config.hash_algorithm
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::HashAlgorithm;
+
+    use crate::app::Cli;
+    use crate::config_file::Config;
+    use crate::flags::Configurable;
+
+    #[test]
+    fn test_default() {
+        assert_eq!(HashAlgorithm::Blake3, HashAlgorithm::default());
+    }
+
+    #[test]
+    fn test_from_cli_none() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(None, HashAlgorithm::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_sha256() {
+        let argv = ["lsd", "--hash-algorithm", "sha256"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(HashAlgorithm::Sha256), HashAlgorithm::from_cli(&cli));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_cli_unknown() {
+        let argv = ["lsd", "--hash-algorithm", "md5"];
+        let _ = Cli::try_parse_from(argv).unwrap();
+    }
+
+    #[test]
+    fn test_from_config_none() {
+        assert_eq!(None, HashAlgorithm::from_config(&Config::with_none()));
+    }
+
+    #[test]
+    fn test_from_config_sha256() {
+        let mut c = Config::with_none();
+        c.hash_algorithm = Some(HashAlgorithm::Sha256);
+        assert_eq!(Some(HashAlgorithm::Sha256), HashAlgorithm::from_config(&c));
+    }
+}
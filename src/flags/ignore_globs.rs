@@ -1,398 +1,655 @@
 //! This module defines the [IgnoreGlobs]. To set it up from [Cli], a [Config] and its
 //! [Default] value, use the [configure_from](IgnoreGlobs::configure_from) method.
+//!
+//! Patterns follow `.gitignore` rules: a pattern without a `/` matches names at any depth,
+//! one with a `/` matches paths from the listed directory (or from the directory of the
+//! `.sapignore` it comes from), a trailing `/` only matches directories and a leading `!`
+//! shows again what an earlier pattern hid. The last matching pattern wins.
 
 use crate::app::Cli;
 use crate::config_file::Config;
+use crate::print_error;
 
 use clap::error::ErrorKind;
 use clap::Error;
-use globset::{Glob, GlobSet, GlobSetBuilder};
-use std::collections::HashSet;
-use std::ffi::OsStr;
-use std::path::Path;
-
-/// The struct holding optimized glob matching structures.
-/// Uses HashSets for O(1) extension and exact name lookups,
-/// falling back to GlobSet only for complex patterns.
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The name of the per-directory ignore files.
+pub const SAPIGNORE: &str = ".sapignore";
+
+/// The ignore rules, in layers. The `--ignore-glob` and `--include` patterns come first and
+/// win over the `.sapignore` files of the walked directories, which win over the
+/// `ignore-globs` of the config file, which win over the built-in list.
+///
+/// The built-in list hides version control data and editor and OS files everywhere, and the
+/// build output of the projects detected in the walked directory by their marker files, like
+/// `target/` next to a `Cargo.toml`. Each walk gets its own rules from
+/// [for_root](IgnoreGlobs::for_root).
 #[derive(Clone, Debug)]
 pub struct IgnoreGlobs {
-    extensions: HashSet<String>,
-    exact_names: HashSet<String>,
-    complex_globs: GlobSet,
+    /// `--ignore-glob` patterns, then the includes as negated patterns.
+    overrides: Arc<Rules>,
+    /// The config file's patterns.
+    config: Arc<Rules>,
+    /// The built-in patterns for the walked directory, empty when they are disabled.
+    builtin: Arc<Rules>,
+    /// Whether the built-in patterns apply.
+    defaults: bool,
+    /// What the walks tell about the entries the rules hide.
+    pub report: IgnoreReport,
+    /// Print the patterns in effect in each input directory instead of listing it.
+    pub print: bool,
+}
+
+/// What the walks tell about the entries the ignore rules hide.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IgnoreReport {
+    /// Nothing: ignored entries are dropped.
+    #[default]
+    Off,
+    /// Each directory with ignored children gets an entry after its visible ones with their
+    /// count, for `--show-ignored` trees and `--llm`.
+    Count,
+    /// The entry also names each ignored child and the rule that hid it, for
+    /// `--explain-ignores`.
+    Explain,
+}
+
+/// Where an ignore pattern comes from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IgnoreOrigin {
+    /// The universal part of the built-in list.
+    Default,
+    /// The built-in patterns of the project with this marker file.
+    Project(&'static str),
+    /// The `ignore-globs` of the config file.
+    Config,
+    /// `--ignore-glob` or `--include`.
+    Flag,
+    /// A line of a `.sapignore` file.
+    SapIgnore { path: PathBuf, line: usize },
+}
+
+impl fmt::Display for IgnoreOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "built-in"),
+            Self::Project(marker) => write!(f, "built-in for {}", marker),
+            Self::Config => write!(f, "config file"),
+            Self::Flag => write!(f, "--ignore-glob"),
+            Self::SapIgnore { path, line } => write!(f, "{}:{}", path.display(), line),
+        }
+    }
+}
+
+/// The pattern that hid an entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IgnoreMatch {
+    pub pattern: String,
+    pub origin: IgnoreOrigin,
+}
+
+/// The children of a directory hidden by the ignore rules.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IgnoredChildren {
+    pub count: usize,
+    /// The name of each hidden child and the rule that hid it, with
+    /// [IgnoreReport::Explain].
+    pub entries: Vec<(String, IgnoreMatch)>,
 }
 
 impl IgnoreGlobs {
-    /// Returns a value from either [Cli], a [Config] or a [Default] value. The first value
-    /// that is not [None] is used. The order of precedence for the value used is:
-    /// - [from_cli](IgnoreGlobs::from_cli)
-    /// - [from_config](IgnoreGlobs::from_config)
-    /// - [Default::default]
+    /// Get the `IgnoreGlobs` from [Cli] and a [Config]. Unlike most flags the two are
+    /// combined: the config's `ignore-globs` extend the built-in list, which
+    /// `--no-default-ignores` or `default-ignores: false` turn off, and `--ignore-glob`,
+    /// `--include` and the config's `include-globs` are matched before both.
     ///
     /// # Errors
     ///
-    /// If either of the [Glob::new] or [GlobSetBuilder.build] methods return an [Err].
+    /// If a pattern is not a valid glob.
     pub fn configure_from(cli: &Cli, config: &Config) -> Result<Self, Error> {
-        if let Some(value) = Self::from_cli(cli) {
-            return value;
-        }
+        let defaults = !cli.no_default_ignores && config.default_ignores.unwrap_or(true);
+        let config_patterns = config
+            .ignore_globs
+            .iter()
+            .flatten()
+            .map(|pattern| (pattern.clone(), IgnoreOrigin::Config));
 
-        if let Some(value) = Self::from_config(config) {
-            return value;
+        let includes = config.include_globs.iter().flatten().chain(&cli.include);
+        let overrides = cli
+            .ignore_glob
+            .iter()
+            .cloned()
+            .chain(includes.map(|pattern| format!("!{}", pattern)))
+            .map(|pattern| (pattern, IgnoreOrigin::Flag));
+
+        let report = if cli.explain_ignores {
+            IgnoreReport::Explain
+        } else if cli.show_ignored || config.show_ignored == Some(true) {
+            IgnoreReport::Count
+        } else {
+            IgnoreReport::Off
+        };
+
+        let invalid = |err: String| Error::raw(ErrorKind::ValueValidation, format!("{}\n", err));
+        Ok(Self {
+            overrides: Arc::new(Rules::from_patterns(PathBuf::new(), overrides).map_err(invalid)?),
+            config: Arc::new(Rules::from_patterns(PathBuf::new(), config_patterns).map_err(invalid)?),
+            builtin: Arc::new(if defaults { builtin_rules(|_| false) } else { Rules::new(PathBuf::new()) }),
+            defaults,
+            report,
+            print: cli.print_ignores,
+        })
+    }
+
+    /// The rules for a walk of `root`, with the built-in patterns of the projects whose
+    /// marker files `root` contains.
+    pub fn for_root(&self, root: &Path) -> Self {
+        self.for_projects(|marker| root.join(marker).exists())
+    }
+
+    /// The rules with the built-in patterns of the projects whose marker file `has_marker`
+    /// accepts, for walks that do not read the disk.
+    pub fn for_projects(&self, has_marker: impl Fn(&str) -> bool) -> Self {
+        let mut globs = self.clone();
+        if self.defaults {
+            globs.builtin = Arc::new(builtin_rules(has_marker));
         }
+        globs
+    }
 
-        Ok(Default::default())
-    }
-
-    /// Build IgnoreGlobs from an iterator of pattern strings.
-    /// 
-    /// Classifies each pattern into extensions, exact names, or complex globs
-    /// for optimized O(1) or O(k) matching where k << total patterns.
-    fn from_patterns<'a, I>(patterns: I) -> Result<Self, Error>
-    where
-        I: IntoIterator<Item = &'a str>,
-    {
-        let mut extensions = HashSet::new();
-        let mut exact_names = HashSet::new();
-        let mut complex_builder = GlobSetBuilder::new();
-
-        for pattern in patterns {
-            match Self::classify_pattern(pattern)? {
-                PatternType::Extension(ext) => {
-                    extensions.insert(ext);
-                }
-                PatternType::ExactName(name) => {
-                    exact_names.insert(name);
-                }
-                PatternType::Complex(glob) => {
-                    complex_builder.add(glob);
-                }
+    /// The patterns in effect with the `.sapignore` rules of a directory and their origins,
+    /// from the weakest to the strongest: a later pattern wins over an earlier one.
+    pub fn patterns<'a>(
+        &'a self,
+        sapignores: &'a SapIgnores,
+    ) -> impl Iterator<Item = (&'a str, &'a IgnoreOrigin)> {
+        std::iter::once(&self.builtin)
+            .chain(std::iter::once(&self.config))
+            .chain(sapignores.0.iter())
+            .chain(std::iter::once(&self.overrides))
+            .flat_map(|rules| rules.rules.iter())
+            .map(|rule| (rule.pattern.as_str(), &rule.origin))
+    }
+
+    /// Whether the entry at `relative`, a path from the walked directory, is hidden by the
+    /// rules, with the `.sapignore` rules of its parent directory.
+    ///
+    /// Performance: O(1) for names, extensions and exact names, O(k) for the remaining
+    /// glob patterns.
+    pub fn is_ignored(&self, sapignores: &SapIgnores, relative: &Path, is_dir: bool) -> bool {
+        self.last_match(sapignores, relative, is_dir)
+            .is_some_and(|rule| !rule.negated)
+    }
+
+    /// The pattern hiding the entry at `relative`, or `None` when it is shown.
+    pub fn explain(
+        &self,
+        sapignores: &SapIgnores,
+        relative: &Path,
+        is_dir: bool,
+    ) -> Option<IgnoreMatch> {
+        self.last_match(sapignores, relative, is_dir)
+            .filter(|rule| !rule.negated)
+            .map(|rule| IgnoreMatch {
+                pattern: rule.pattern.clone(),
+                origin: rule.origin.clone(),
+            })
+    }
+
+    /// Whether the entry at `relative` is hidden, like [is_ignored](Self::is_ignored), adding
+    /// it to `ignored`, the hidden children of its parent, as the [IgnoreReport] asks.
+    pub fn hides(
+        &self,
+        sapignores: &SapIgnores,
+        relative: &Path,
+        is_dir: bool,
+        ignored: &mut IgnoredChildren,
+    ) -> bool {
+        if self.report != IgnoreReport::Explain {
+            let hidden = self.is_ignored(sapignores, relative, is_dir);
+            if hidden && self.report == IgnoreReport::Count {
+                ignored.count += 1;
             }
+            return hidden;
         }
 
-        let complex_globs = complex_builder
-            .build()
-            .map_err(|err| Error::raw(ErrorKind::ValueValidation, err))?;
+        let Some(rule) = self.explain(sapignores, relative, is_dir) else {
+            return false;
+        };
+        let name = relative.file_name().unwrap_or(relative.as_os_str());
+        ignored.count += 1;
+        ignored.entries.push((name.to_string_lossy().to_string(), rule));
+        true
+    }
+
+    /// The last pattern matching the entry, in the first layer where one does.
+    fn last_match<'a>(&'a self, sapignores: &'a SapIgnores, relative: &Path, is_dir: bool) -> Option<&'a Rule> {
+        let name = relative
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
 
-        Ok(Self {
-            extensions,
-            exact_names,
-            complex_globs,
+        std::iter::once(&self.overrides)
+            .chain(sapignores.0.iter().rev())
+            .chain(std::iter::once(&self.config))
+            .chain(std::iter::once(&self.builtin))
+            .find_map(|rules| rules.last_match(relative, &name, is_dir))
+    }
+}
+
+/// The default value of `IgnoreGlobs` hides the universal built-in patterns.
+impl Default for IgnoreGlobs {
+    fn default() -> Self {
+        Self {
+            overrides: Arc::new(Rules::new(PathBuf::new())),
+            config: Arc::new(Rules::new(PathBuf::new())),
+            builtin: Arc::new(builtin_rules(|_| false)),
+            defaults: true,
+            report: IgnoreReport::Off,
+            print: false,
+        }
+    }
+}
+
+/// The universal built-in patterns, then those of the projects whose marker file
+/// `has_marker` accepts.
+fn builtin_rules(has_marker: impl Fn(&str) -> bool) -> Rules {
+    let universal = UNIVERSAL_PATTERNS
+        .iter()
+        .map(|pattern| (pattern.to_string(), IgnoreOrigin::Default));
+    let projects = PROJECT_PATTERNS
+        .iter()
+        .filter_map(|(markers, patterns)| {
+            let marker = markers.iter().copied().find(|marker| has_marker(marker))?;
+            Some(patterns.iter().map(move |pattern| (pattern.to_string(), IgnoreOrigin::Project(marker))))
         })
+        .flatten();
+    // The built-in patterns are valid globs
+    Rules::from_patterns(PathBuf::new(), universal.chain(projects))
+        .unwrap_or_else(|_| Rules::new(PathBuf::new()))
+}
+
+/// The `.sapignore` rules in effect in a directory of a walk: those of the directory and of
+/// its parents up to the walked one, innermost last.
+#[derive(Clone, Debug, Default)]
+pub struct SapIgnores(Vec<Arc<Rules>>);
+
+impl SapIgnores {
+    /// Add the rules of the `.sapignore` in `dir`, if there is one, for its children.
+    /// `relative` is the path of `dir` from the walked directory.
+    pub fn read(&mut self, dir: &Path, relative: &Path) {
+        let path = dir.join(SAPIGNORE);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => self.push(&path, relative, &text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => print_error!("{}: {}.", path.display(), err),
+        }
     }
 
-    /// Get a potential [IgnoreGlobs] from [Cli].
+    /// Add the rules read from `source`, the `.sapignore` of the directory at `relative`.
     ///
-    /// If the "ignore-glob" argument has been passed, this returns a [Result] in a [Some] with
-    /// either the built [IgnoreGlobs] or an [Error], if any error was encountered while creating the
-    /// [IgnoreGlobs]. If the argument has not been passed, this returns [None].
-    fn from_cli(cli: &Cli) -> Option<Result<Self, Error>> {
-        if cli.ignore_glob.is_empty() {
-            return None;
+    /// Blank lines and lines starting with `#` are skipped; the lines that are not valid
+    /// globs are reported and skipped.
+    pub fn push(&mut self, source: &Path, relative: &Path, text: &str) {
+        let mut rules = Rules::new(relative.to_path_buf());
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let origin = IgnoreOrigin::SapIgnore {
+                path: source.to_path_buf(),
+                line: number + 1,
+            };
+            if let Err(err) = rules.add(line, origin) {
+                print_error!("{}:{}: {}.", source.display(), number + 1, err);
+            }
         }
+        if !rules.rules.is_empty() {
+            self.0.push(Arc::new(rules.build()));
+        }
+    }
+}
 
-        Some(Self::from_patterns(cli.ignore_glob.iter().map(String::as_str)))
+/// A parsed ignore pattern.
+#[derive(Clone, Debug)]
+struct Rule {
+    /// The pattern as written.
+    pattern: String,
+    origin: IgnoreOrigin,
+    /// Shows the entries it matches instead of hiding them.
+    negated: bool,
+    /// Only matches directories.
+    dir_only: bool,
+}
+
+/// Globs of one kind with the index of the [Rule] each one comes from.
+#[derive(Clone, Debug)]
+struct IndexedGlobs {
+    set: GlobSet,
+    rules: Vec<usize>,
+}
+
+impl IndexedGlobs {
+    fn build(globs: Vec<(Glob, usize)>) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        let mut rules = Vec::with_capacity(globs.len());
+        for (glob, rule) in globs {
+            builder.add(glob);
+            rules.push(rule);
+        }
+        Self {
+            // The globs were compiled one by one, so the set builds
+            set: builder.build().unwrap_or_else(|_| GlobSet::empty()),
+            rules,
+        }
     }
+}
 
-    /// Get a potential [IgnoreGlobs] from a [Config].
-    ///
-    /// If the `Config::ignore-globs` contains an Array of Strings,
-    /// each of its values is used to build the [GlobSet]. If the building
-    /// succeeds, the [IgnoreGlobs] is returned in the [Result] in a [Some]. If any error is
-    /// encountered while building, an [Error] is returned in the Result instead. If the Config does
-    /// not contain such a key, this returns [None].
-    fn from_config(config: &Config) -> Option<Result<Self, Error>> {
-        let globs = config.ignore_globs.as_ref()?;
-        Some(Self::from_patterns(globs.iter().map(String::as_str)))
+/// An ordered list of patterns, classified for optimized matching: HashMaps for O(1)
+/// extension and exact name lookups, falling back to GlobSets only for complex patterns.
+/// Each lookup gives the index of the last pattern of its kind that matched.
+#[derive(Clone, Debug)]
+struct Rules {
+    /// The directory the path patterns start from, relative to the walked directory.
+    base: PathBuf,
+    rules: Vec<Rule>,
+    /// Whether any rule is negated; when none is, any match hides the entry.
+    negations: bool,
+    extensions: HashMap<String, usize>,
+    exact_names: HashMap<String, usize>,
+    /// Exact names with a trailing `/`.
+    exact_dirs: HashMap<String, usize>,
+    /// Globs matched against the name, while they are being added.
+    name_globs: Vec<(Glob, usize)>,
+    /// Globs matched against the path from `base`, while they are being added.
+    path_globs: Vec<(Glob, usize)>,
+    names: IndexedGlobs,
+    paths: IndexedGlobs,
+}
+
+/// Pattern classification types for optimized matching
+enum PatternType {
+    Extension(String),
+    ExactName(String),
+    Name(Glob),
+    Path(Glob),
+}
+
+impl Rules {
+    fn new(base: PathBuf) -> Self {
+        Self {
+            base,
+            rules: Vec::new(),
+            negations: false,
+            extensions: HashMap::new(),
+            exact_names: HashMap::new(),
+            exact_dirs: HashMap::new(),
+            name_globs: Vec::new(),
+            path_globs: Vec::new(),
+            names: IndexedGlobs::build(Vec::new()),
+            paths: IndexedGlobs::build(Vec::new()),
+        }
     }
 
-    /// Create a [Glob] from a provided pattern.
-    ///
-    /// This method is mainly a helper to wrap the handling of potential errors.
-    fn create_glob(pattern: &str) -> Result<Glob, Error> {
-        Glob::new(pattern).map_err(|err| Error::raw(ErrorKind::ValueValidation, err))
+    /// Build the rules for the patterns, which start from `base`.
+    fn from_patterns(
+        base: PathBuf,
+        patterns: impl IntoIterator<Item = (String, IgnoreOrigin)>,
+    ) -> Result<Self, String> {
+        let mut rules = Self::new(base);
+        for (pattern, origin) in patterns {
+            rules.add(&pattern, origin)?;
+        }
+        Ok(rules.build())
     }
 
+    /// Parse and classify a pattern, after the ones already added.
+    fn add(&mut self, pattern: &str, origin: IgnoreOrigin) -> Result<(), String> {
+        let (negated, body) = match pattern.strip_prefix('!') {
+            Some(body) => (true, body),
+            None => (
+                false,
+                pattern
+                    .strip_prefix('\\')
+                    .filter(|body| body.starts_with(['!', '#']))
+                    .unwrap_or(pattern),
+            ),
+        };
+        let dir_only = body.ends_with('/');
+        let body = body.trim_end_matches('/');
+        let anchored = body.contains('/');
+        let body = body.trim_start_matches('/');
+        if body.is_empty() {
+            return Err(format!("empty ignore pattern '{}'", pattern));
+        }
+
+        let index = self.rules.len();
+        match Self::classify_pattern(body, anchored, dir_only)? {
+            PatternType::Extension(ext) => {
+                self.extensions.insert(ext, index);
+            }
+            PatternType::ExactName(name) if dir_only => {
+                self.exact_dirs.insert(name, index);
+            }
+            PatternType::ExactName(name) => {
+                self.exact_names.insert(name, index);
+            }
+            PatternType::Name(glob) => self.name_globs.push((glob, index)),
+            PatternType::Path(glob) => self.path_globs.push((glob, index)),
+        }
+        self.negations |= negated;
+        self.rules.push(Rule {
+            pattern: pattern.to_string(),
+            origin,
+            negated,
+            dir_only,
+        });
+        Ok(())
+    }
 
+    /// Compile the glob patterns added so far.
+    fn build(mut self) -> Self {
+        self.names = IndexedGlobs::build(std::mem::take(&mut self.name_globs));
+        self.paths = IndexedGlobs::build(std::mem::take(&mut self.path_globs));
+        self
+    }
 
-    /// Classify a glob pattern into one of three categories for optimized matching.
+    /// Classify a glob pattern for optimized matching.
     ///
-    /// - Extension patterns (*.ext) → extracted extension for O(1) HashSet lookup
-    /// - Exact names (no wildcards) → exact string for O(1) HashSet lookup
-    /// - Complex patterns → Glob for full regex matching
-    fn classify_pattern(pattern: &str) -> Result<PatternType, Error> {
-        if pattern.starts_with("*.") && !pattern[2..].contains(['*', '?', '[', ']', '.']) {
+    /// - Extension patterns (*.ext) → extracted extension for O(1) HashMap lookup
+    /// - Exact names (no wildcards) → exact string for O(1) HashMap lookup
+    /// - Other names → Glob matched against the name
+    /// - Patterns with a `/` → Glob matched against the path, where `*` stops at a `/`
+    fn classify_pattern(
+        pattern: &str,
+        anchored: bool,
+        dir_only: bool,
+    ) -> Result<PatternType, String> {
+        if anchored {
+            GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .map(PatternType::Path)
+                .map_err(|err| err.to_string())
+        } else if pattern.starts_with("*.")
+            && !dir_only
+            && !pattern[2..].contains(['*', '?', '[', ']', '.', '{', '\\'])
+        {
             // Simple extension pattern like "*.jpg" -> extract "jpg" (lowercase for case-insensitive)
             // Note: Multi-dot patterns like "*.tar.gz" are excluded (contain '.') because
             // Path::extension() only returns the last component ("gz" not "tar.gz")
             Ok(PatternType::Extension(pattern[2..].to_lowercase()))
-        } else if !pattern.contains(['*', '?', '[', ']']) {
+        } else if !pattern.contains(['*', '?', '[', ']', '{', '\\']) {
             // No glob metacharacters = exact match (preserve case)
             Ok(PatternType::ExactName(pattern.to_string()))
         } else {
-            // Complex pattern needs full glob matching
-            Self::create_glob(pattern).map(PatternType::Complex)
+            Glob::new(pattern)
+                .map(PatternType::Name)
+                .map_err(|err| err.to_string())
         }
     }
 
-    /// Optimized glob matching using fast paths for extensions and exact names.
-    ///
-    /// Performance: O(1) for extensions and exact names, O(k) for complex patterns where k << 147.
-    pub fn is_match(&self, name: &OsStr) -> bool {
-        let name_str = name.to_string_lossy();
-        
+    /// The last rule matching the entry, or `None` when no rule matches.
+    fn last_match(&self, relative: &Path, name: &str, is_dir: bool) -> Option<&Rule> {
+        let mut last: Option<usize> = None;
+
         // Fast path 1: Extension check (O(1))
         // Most files have extensions, check this first
-        if let Some(ext) = Path::new(name_str.as_ref()).extension() {
-            let ext_lower = ext.to_string_lossy().to_lowercase();
-            if self.extensions.contains(&ext_lower) {
-                return true;
-            }
+        if !self.extensions.is_empty()
+            && let Some(ext) = Path::new(name).extension()
+        {
+            last = last.max(
+                self.extensions
+                    .get(&ext.to_string_lossy().to_lowercase())
+                    .copied(),
+            );
         }
-        
+
         // Fast path 2: Exact name check (O(1))
         // Check for exact directory/file name matches
-        if self.exact_names.contains(name_str.as_ref()) {
-            return true;
+        last = last.max(self.exact_names.get(name).copied());
+        if is_dir {
+            last = last.max(self.exact_dirs.get(name).copied());
+        }
+        if let Some(index) = last
+            && !self.negations
+        {
+            return Some(&self.rules[index]);
         }
-        
-        // Slow path: Complex patterns (~15 patterns instead of 147)
-        self.complex_globs.is_match(name)
+
+        // Slow path: Complex patterns, usually a handful
+        let path = relative.strip_prefix(&self.base).ok();
+        for (globs, candidate) in [(&self.names, Some(Path::new(name))), (&self.paths, path)] {
+            let Some(candidate) = candidate.filter(|candidate| globs.set.is_match(candidate))
+            else {
+                continue;
+            };
+            for index in globs.set.matches(candidate) {
+                let rule = globs.rules[index];
+                if is_dir || !self.rules[rule].dir_only {
+                    last = last.max(Some(rule));
+                }
+            }
+        }
+
+        last.map(|index| &self.rules[index])
     }
 }
 
-/// Pattern classification types for optimized matching
-enum PatternType {
-    Extension(String),
-    ExactName(String),
-    Complex(Glob),
-}
+/// The built-in patterns hidden in every directory: version control data and the files
+/// editors and operating systems leave behind.
+const UNIVERSAL_PATTERNS: &[&str] = &[
+    // Version control directories
+    ".git",
+    ".svn",
+    ".hg",
+    ".bzr",
+    // IDE and editor files
+    ".idea",
+    ".vscode",
+    "*.swp",
+    "*.swo",
+    "*~",
+    // OS files
+    ".DS_Store",
+    "Thumbs.db",
+];
 
-/// The default value of `IgnoreGlobs` contains patterns for common build directories
-/// and large files that are typically not useful for LLM-assisted coding.
-impl Default for IgnoreGlobs {
-    fn default() -> Self {
-        // Comprehensive ignore patterns based on scrypt/code2term.rs
-        let patterns = [
-            // Version control directories
-            ".git",
-            ".svn", 
-            ".hg",
-            ".bzr",
-            
-            // Build and dependency directories
-            "node_modules",
-            "target",           // Rust
-            "dist",
-            "build",
-            "vendor",           // Go, PHP, etc.
-            "out",
-            ".next",            // Next.js
-            ".nuxt",            // Nuxt.js
-            ".output",          // Various build tools
-            "_build",           // Documentation builds
-            "site",             // Documentation sites
-            
-            // Python
-            "__pycache__",
-            "*.pyc",
-            "*.pyo",
-            ".pytest_cache",
-            ".mypy_cache",
-            ".ruff_cache",
-            ".tox",
-            ".hypothesis",
-            "venv",
-            ".venv",
-            "env",
-            ".env",
-            "*.egg-info",
-            
-            // IDE and editor files
-            ".idea",
-            ".vscode",
-            "*.swp",
-            "*.swo",
-            "*~",
-            ".DS_Store",
-            "Thumbs.db",
-            
-            // Package managers
-            ".yarn",
+/// The built-in patterns of each kind of project, hidden in a walked directory that holds one
+/// of its marker files. Names like `build`, `vendor` or `site` are only build output in some
+/// ecosystems: Go commits its `vendor` directory, so it has no entry here.
+const PROJECT_PATTERNS: &[(&[&str], &[&str])] = &[
+    (&["Cargo.toml"], &["target/"]),
+    (
+        &["package.json"],
+        &[
+            "node_modules/",
+            ".next/",   // Next.js
+            ".nuxt/",   // Nuxt.js
+            ".output/", // Nitro
+            ".svelte-kit/",
+            "dist/",
+            "out/",
+            "coverage/",
+            ".nyc_output/",
+            ".yarn/",
+            ".pnp",
             ".pnp.*",
-            ".npm",
-            
-            // Coverage and test reports
-            "coverage",
-            ".coverage",
-            "*.cover",
-            ".nyc_output",
-            "*.lcov",
-            
-            // Compiled object files
-            "*.o",
-            "*.so",
-            "*.dll",
-            "*.exe",
-            "*.bin",
-            "*.class",          // Java
-            
-            // Logs and databases
-            "*.log",
-            "*.sqlite",
-            "*.db",
-            
-            // Lock files (often large and not needed for reading)
-            "*.lock",
-            "package-lock.json",
-            "yarn.lock",
-            "Cargo.lock",       // Can be large in workspaces
-            "poetry.lock",
-            "Pipfile.lock",
-            
-            // Archives and compressed files
-            "*.zip",
-            "*.tar",
-            "*.tar.gz",
-            "*.tar.bz2",
-            "*.tar.xz",
-            "*.rar",
-            "*.7z",
-            "*.gz",
-            "*.bz2",
-            "*.xz",
-            
-            // Large binary/data files
-            "*.iso",
-            "*.dmg",
-            "*.pkg",
-            "*.deb",
-            "*.rpm",
-            "*.msi",
-            "*.exe",
-            "*.app",
-            
-            // Media files
-            // Images
-            "*.jpg",
-            "*.jpeg",
-            "*.png",
-            "*.gif",
-            "*.bmp",
-            "*.ico",
-            "*.svg",
-            "*.webp",
-            "*.tiff",
-            "*.tif",
-            "*.psd",
-            "*.ai",
-            "*.eps",
-            
-            // Videos
-            "*.mp4",
-            "*.mov",
-            "*.avi",
-            "*.mkv",
-            "*.webm",
-            "*.flv",
-            "*.wmv",
-            "*.mpg",
-            "*.mpeg",
-            "*.m4v",
-            "*.3gp",
-            
-            // Audio
-            "*.mp3",
-            "*.wav",
-            "*.ogg",
-            "*.flac",
-            "*.aac",
-            "*.wma",
-            "*.m4a",
-            "*.opus",
-            
-            // Documents
-            "*.pdf",
-            "*.docx",
-            "*.doc",
-            "*.xlsx",
-            "*.xls",
-            "*.pptx",
-            "*.ppt",
-            "*.odt",
-            "*.ods",
-            "*.odp",
-            
-            // Data files
-            "*.pkl",            // Python pickle
-            "*.npy",            // NumPy
-            "*.npz",            
-            "*.parquet",        // Apache Parquet
-            "*.hdf5",           // HDF5
-            "*.h5",
-            "*.mat",            // MATLAB
-            "*.feather",        // Feather format
-            "*.msgpack",        // MessagePack
-            
-            // Other common excludes
-            ".cache",
-            ".parcel-cache",
-            ".turbo",
-            ".vercel",
-            ".netlify",
-            ".serverless",
-            ".terraform",
+            ".npm/",
+            "bower_components/",
+            "jspm_packages/",
+            ".parcel-cache/",
+            ".turbo/",
+            ".vercel/",
+            ".netlify/",
+            ".serverless/",
+            ".sass-cache/",
             "*.min.js",
             "*.min.css",
-            "*.map",            // Source maps
-            ".sass-cache",
-            ".gradle",
-            ".m2",              // Maven
-            ".stack-work",      // Haskell Stack
-            ".cabal-sandbox",   // Haskell Cabal
-            "bower_components", // Bower
-            "jspm_packages",    // JSPM
-            ".pnp",             // Yarn PnP
+            "*.map", // Source maps
+            "*.lcov",
             "*.pid",
             "*.seed",
-            "*.pid.lock",
-        ];
-        
-        // Classify patterns for optimized matching
-        let mut extensions = HashSet::new();
-        let mut exact_names = HashSet::new();
-        let mut complex_builder = GlobSetBuilder::new();
-        
-        for pattern in patterns {
-            match Self::classify_pattern(pattern) {
-                Ok(PatternType::Extension(ext)) => {
-                    extensions.insert(ext);
-                }
-                Ok(PatternType::ExactName(name)) => {
-                    exact_names.insert(name);
-                }
-                Ok(PatternType::Complex(glob)) => {
-                    complex_builder.add(glob);
-                }
-                Err(_) => {} // Skip invalid patterns (should not happen with hardcoded patterns)
-            }
-        }
-        
-        // Build complex globs GlobSet, use empty set if build fails
-        let complex_globs = complex_builder.build().unwrap_or_else(|_| GlobSet::empty());
-        
-        Self {
-            extensions,
-            exact_names,
-            complex_globs,
-        }
-    }
-}
+        ],
+    ),
+    (
+        &["pyproject.toml", "setup.py", "setup.cfg", "requirements.txt", "Pipfile"],
+        &[
+            "__pycache__/",
+            "*.pyc",
+            "*.pyo",
+            ".pytest_cache/",
+            ".mypy_cache/",
+            ".ruff_cache/",
+            ".tox/",
+            ".nox/",
+            ".hypothesis/",
+            "venv/",
+            ".venv/",
+            "env/",
+            "*.egg-info/",
+            "build/",
+            "dist/",
+            ".coverage",
+            "*.cover",
+            "htmlcov/",
+        ],
+    ),
+    (&["composer.json"], &["vendor/"]),
+    (&["Gemfile"], &[".bundle/", "/vendor/bundle/"]),
+    (&["pom.xml"], &["target/", ".m2/", "*.class"]),
+    (
+        &["build.gradle", "build.gradle.kts", "settings.gradle", "settings.gradle.kts"],
+        &["build/", ".gradle/", "*.class"],
+    ),
+    (&["build.sbt"], &["target/", ".bsp/", "*.class"]),
+    (&["CMakeLists.txt"], &["/build/", "CMakeFiles/", "cmake-build-*/", "*.o"]),
+    (&["build.zig"], &["zig-out/", ".zig-cache/", "zig-cache/"]),
+    (&["Package.swift"], &[".build/", ".swiftpm/"]),
+    (&["pubspec.yaml"], &[".dart_tool/", "build/"]),
+    (&["mix.exs"], &["_build/", "deps/"]),
+    (&["dune-project"], &["_build/"]),
+    (&["stack.yaml"], &[".stack-work/"]),
+    (&["cabal.project"], &["dist-newstyle/", ".cabal-sandbox/"]),
+    (&["mkdocs.yml"], &["/site/"]),
+    (&["_config.yml"], &["/_site/", ".jekyll-cache/"]), // Jekyll
+    (&[".terraform.lock.hcl"], &[".terraform/"]),
+];
+
 
 #[cfg(test)]
 mod test {
     use clap::Parser;
+    use std::path::Path;
 
-    use super::IgnoreGlobs;
+    use super::{IgnoreGlobs, IgnoreMatch, IgnoreOrigin, IgnoreReport, IgnoredChildren, SapIgnores};
 
     use crate::app::Cli;
     use crate::config_file::Config;
@@ -403,6 +660,19 @@ mod test {
     // Further no tests for actually returned GlobSets are implemented, because GlobSet does not
     // even implement PartialEq and thus can not be easily compared.
 
+    fn configure(argv: &[&str]) -> IgnoreGlobs {
+        let cli = Cli::try_parse_from(argv).unwrap();
+        IgnoreGlobs::configure_from(&cli, &Config::with_none()).unwrap()
+    }
+
+    fn is_file_ignored(globs: &IgnoreGlobs, path: &str) -> bool {
+        globs.is_ignored(&SapIgnores::default(), Path::new(path), false)
+    }
+
+    fn is_dir_ignored(globs: &IgnoreGlobs, path: &str) -> bool {
+        globs.is_ignored(&SapIgnores::default(), Path::new(path), true)
+    }
+
     #[test]
     fn test_configuration_from_none() {
         let argv = ["lsd"];
@@ -433,79 +703,360 @@ mod test {
     }
 
     #[test]
-    fn test_from_cli_none() {
-        let argv = ["lsd"];
+    fn test_configuration_invalid_glob() {
+        let argv = ["lsd", "--ignore-glob", "src/[a"];
         let cli = Cli::try_parse_from(argv).unwrap();
-        assert!(IgnoreGlobs::from_cli(&cli).is_none());
+        assert!(IgnoreGlobs::configure_from(&cli, &Config::with_none()).is_err());
+    }
+
+    #[test]
+    fn test_args_extend_defaults() {
+        let globs = configure(&["lsd", "--ignore-glob", "*.tmp"]);
+        assert!(is_file_ignored(&globs, "scratch.tmp"));
+        assert!(is_dir_ignored(&globs, ".git"));
+    }
+
+    #[test]
+    fn test_config_extends_defaults() {
+        let cli = Cli::try_parse_from(["lsd"]).unwrap();
+        let mut c = Config::with_none();
+        c.ignore_globs = Some(vec!["*.tmp".into(), "!.DS_Store".into()]);
+        let globs = IgnoreGlobs::configure_from(&cli, &c).unwrap();
+        assert!(is_file_ignored(&globs, "scratch.tmp"));
+        assert!(is_file_ignored(&globs, "notes.txt~"));
+        assert!(!is_file_ignored(&globs, ".DS_Store"));
+    }
+
+    #[test]
+    fn test_no_default_ignores() {
+        let globs = configure(&["lsd", "--no-default-ignores"]).for_projects(|_| true);
+        assert!(!is_dir_ignored(&globs, "target"));
+        assert!(!is_dir_ignored(&globs, ".git"));
+        assert!(!is_file_ignored(&globs, ".DS_Store"));
+
+        let cli = Cli::try_parse_from(["lsd"]).unwrap();
+        let mut c = Config::with_none();
+        c.default_ignores = Some(false);
+        let globs = IgnoreGlobs::configure_from(&cli, &c).unwrap().for_projects(|_| true);
+        assert!(!is_dir_ignored(&globs, "build"));
+    }
+
+    #[test]
+    fn test_include() {
+        let globs = configure(&["lsd", "--include", ".vscode", "--include", "*.swp"]);
+        assert!(!is_dir_ignored(&globs, ".vscode"));
+        assert!(!is_file_ignored(&globs, "src/.main.rs.swp"));
+        assert!(is_dir_ignored(&globs, ".idea"));
+    }
+
+    #[test]
+    fn test_include_wins_over_ignore() {
+        let globs = configure(&["lsd", "-I", "*.md", "--include", "README.md"]);
+        assert!(is_file_ignored(&globs, "docs/guide.md"));
+        assert!(!is_file_ignored(&globs, "README.md"));
+    }
+
+    #[test]
+    fn test_negation_last_match_wins() {
+        let globs = configure(&["lsd", "--no-default-ignores", "-I", "*.log", "-I", "!keep.log"]);
+        assert!(is_file_ignored(&globs, "debug.log"));
+        assert!(!is_file_ignored(&globs, "keep.log"));
+
+        let globs = configure(&["lsd", "--no-default-ignores", "-I", "!keep.log", "-I", "*.log"]);
+        assert!(is_file_ignored(&globs, "keep.log"));
+    }
+
+    #[test]
+    fn test_path_patterns() {
+        let globs = configure(&[
+            "lsd",
+            "--no-default-ignores",
+            "-I",
+            "src/**/generated/*.rs",
+            "-I",
+            "docs/build",
+            "-I",
+            "/notes.txt",
+        ]);
+        assert!(is_file_ignored(&globs, "src/generated/schema.rs"));
+        assert!(is_file_ignored(&globs, "src/api/v1/generated/schema.rs"));
+        assert!(!is_file_ignored(&globs, "src/generated/nested/schema.rs"));
+        assert!(!is_file_ignored(&globs, "lib/generated/schema.rs"));
+        assert!(is_dir_ignored(&globs, "docs/build"));
+        assert!(!is_dir_ignored(&globs, "build"));
+        assert!(!is_dir_ignored(&globs, "site/docs/build"));
+        assert!(is_file_ignored(&globs, "notes.txt"));
+        assert!(!is_file_ignored(&globs, "src/notes.txt"));
+    }
+
+    #[test]
+    fn test_dir_only_patterns() {
+        let globs = configure(&["lsd", "--no-default-ignores", "-I", "cache/", "-I", "tmp*/"]);
+        assert!(is_dir_ignored(&globs, "cache"));
+        assert!(is_dir_ignored(&globs, "src/cache"));
+        assert!(!is_file_ignored(&globs, "cache"));
+        assert!(is_dir_ignored(&globs, "tmp1"));
+        assert!(!is_file_ignored(&globs, "tmp1"));
+    }
+
+    #[test]
+    fn test_sapignore() {
+        let globs = configure(&["lsd"]);
+        let mut sapignores = SapIgnores::default();
+        sapignores.push(
+            Path::new(".sapignore"),
+            Path::new(""),
+            "# generated code\n*.gen.rs\n\n!Cargo.lock\n",
+        );
+        assert!(globs.is_ignored(&sapignores, Path::new("src/api.gen.rs"), false));
+        assert!(!globs.is_ignored(&sapignores, Path::new("Cargo.lock"), false));
+        assert!(globs.is_ignored(&sapignores, Path::new(".git"), true));
+
+        // A nested file wins, and its path patterns start from its directory
+        sapignores.push(Path::new("src/.sapignore"), Path::new("src"), "!*.gen.rs\n/fixtures\n");
+        assert!(!globs.is_ignored(&sapignores, Path::new("src/api.gen.rs"), false));
+        assert!(globs.is_ignored(&sapignores, Path::new("src/fixtures"), true));
+        assert!(!globs.is_ignored(&sapignores, Path::new("src/test/fixtures"), true));
+    }
+
+    #[test]
+    fn test_args_win_over_sapignore() {
+        let globs = configure(&["lsd", "-I", "*.gen.rs"]);
+        let mut sapignores = SapIgnores::default();
+        sapignores.push(Path::new(".sapignore"), Path::new(""), "!*.gen.rs\n");
+        assert!(globs.is_ignored(&sapignores, Path::new("api.gen.rs"), false));
     }
 
     #[test]
-    fn test_from_config_none() {
-        assert!(IgnoreGlobs::from_config(&Config::with_none()).is_none());
+    fn test_escaped_patterns() {
+        let globs = configure(&["lsd", "--no-default-ignores", "-I", "\\!important", "-I", "\\#notes"]);
+        assert!(is_file_ignored(&globs, "!important"));
+        assert!(is_file_ignored(&globs, "#notes"));
+    }
+
+    #[test]
+    fn test_project_patterns() {
+        let globs = configure(&["lsd"]);
+        for dir in ["target", "node_modules", "build", "dist", "vendor", "site", "env", "out"] {
+            assert!(!is_dir_ignored(&globs, dir), "{} is ignored without a project", dir);
+        }
+        assert!(!is_file_ignored(&globs, "firmware.bin"));
+
+        let rust = globs.for_projects(|marker| marker == "Cargo.toml");
+        assert!(is_dir_ignored(&rust, "target"));
+        assert!(is_dir_ignored(&rust, "crates/core/target"));
+        assert!(!is_file_ignored(&rust, "target"));
+        assert!(!is_dir_ignored(&rust, "node_modules"));
+
+        let node = globs.for_projects(|marker| marker == "package.json");
+        assert!(is_dir_ignored(&node, "node_modules"));
+        assert!(is_dir_ignored(&node, ".next"));
+        assert!(!is_dir_ignored(&node, "target"));
+
+        let python = globs.for_projects(|marker| marker == "pyproject.toml");
+        assert!(is_dir_ignored(&python, "pkg/__pycache__"));
+        assert!(is_file_ignored(&python, "pkg/__init__.pyc"));
+        assert!(!is_dir_ignored(&globs, "__pycache__"));
+
+        // Go commits its vendor directory, PHP does not
+        let go = globs.for_projects(|marker| marker == "go.mod");
+        assert!(!is_dir_ignored(&go, "vendor"));
+        let php = globs.for_projects(|marker| marker == "composer.json");
+        assert!(is_dir_ignored(&php, "vendor"));
+
+        let gradle = globs.for_projects(|marker| marker == "build.gradle");
+        assert!(is_dir_ignored(&gradle, "build"));
+        assert!(!is_dir_ignored(&gradle, "buildSrc"));
+    }
+
+    #[test]
+    fn test_config_wins_over_project_patterns() {
+        let cli = Cli::try_parse_from(["lsd"]).unwrap();
+        let mut c = Config::with_none();
+        c.ignore_globs = Some(vec!["!target".into()]);
+        let globs = IgnoreGlobs::configure_from(&cli, &c)
+            .unwrap()
+            .for_projects(|marker| marker == "Cargo.toml");
+        assert!(!is_dir_ignored(&globs, "target"));
+    }
+
+    #[test]
+    fn test_for_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let globs = configure(&["lsd"]);
+        assert!(!is_dir_ignored(&globs.for_root(dir.path()), "node_modules"));
+
+        std::fs::write(dir.path().join("package.json"), "{}").unwrap();
+        assert!(is_dir_ignored(&globs.for_root(dir.path()), "node_modules"));
+    }
+
+    #[test]
+    fn test_patterns() {
+        let cli = Cli::try_parse_from(["lsd", "-I", "*.tmp", "--print-ignores"]).unwrap();
+        let mut c = Config::with_none();
+        c.ignore_globs = Some(vec!["*.bak".into()]);
+        let globs = IgnoreGlobs::configure_from(&cli, &c)
+            .unwrap()
+            .for_projects(|marker| marker == "Cargo.toml");
+        assert!(globs.print);
+
+        let mut sapignores = SapIgnores::default();
+        sapignores.push(Path::new(".sapignore"), Path::new(""), "fixtures/\n");
+        let patterns: Vec<(&str, &IgnoreOrigin)> = globs.patterns(&sapignores).collect();
+        assert_eq!((".git", &IgnoreOrigin::Default), patterns[0]);
+
+        // From the weakest to the strongest
+        let tail: Vec<&str> = patterns.iter().rev().take(4).map(|(pattern, _)| *pattern).collect();
+        assert_eq!(vec!["*.tmp", "fixtures/", "*.bak", "target/"], tail);
+        assert_eq!(&IgnoreOrigin::Project("Cargo.toml"), patterns[patterns.len() - 4].1);
+    }
+
+    #[test]
+    fn test_report_from_args() {
+        assert_eq!(IgnoreReport::Off, configure(&["lsd"]).report);
+        assert_eq!(IgnoreReport::Count, configure(&["lsd", "--show-ignored"]).report);
+        assert_eq!(IgnoreReport::Explain, configure(&["lsd", "--explain-ignores"]).report);
+        assert_eq!(
+            IgnoreReport::Explain,
+            configure(&["lsd", "--show-ignored", "--explain-ignores"]).report
+        );
+    }
+
+    #[test]
+    fn test_report_from_config() {
+        let cli = Cli::try_parse_from(["lsd"]).unwrap();
+        let mut c = Config::with_none();
+        c.show_ignored = Some(true);
+        assert_eq!(IgnoreReport::Count, IgnoreGlobs::configure_from(&cli, &c).unwrap().report);
+    }
+
+    #[test]
+    fn test_explain_origins() {
+        let cli = Cli::try_parse_from(["lsd", "-I", "*.tmp"]).unwrap();
+        let mut c = Config::with_none();
+        c.ignore_globs = Some(vec!["*.bak".into()]);
+        let globs = IgnoreGlobs::configure_from(&cli, &c).unwrap();
+        let mut sapignores = SapIgnores::default();
+        sapignores.push(Path::new("src/.sapignore"), Path::new("src"), "# fixtures\n/fixtures/\n");
+
+        let explain = |path: &str, is_dir: bool| globs.explain(&sapignores, Path::new(path), is_dir);
+        let rule = |pattern: &str, origin: IgnoreOrigin| {
+            Some(IgnoreMatch {
+                pattern: pattern.into(),
+                origin,
+            })
+        };
+        assert_eq!(rule("*.tmp", IgnoreOrigin::Flag), explain("src/a.tmp", false));
+        assert_eq!(rule("*.bak", IgnoreOrigin::Config), explain("src/a.bak", false));
+        assert_eq!(rule(".DS_Store", IgnoreOrigin::Default), explain("src/.DS_Store", false));
+        assert_eq!(
+            rule(
+                "/fixtures/",
+                IgnoreOrigin::SapIgnore {
+                    path: "src/.sapignore".into(),
+                    line: 2,
+                }
+            ),
+            explain("src/fixtures", true)
+        );
+        assert_eq!(None, explain("src/main.rs", false));
+
+        let globs = globs.for_projects(|marker| marker == "Cargo.toml");
+        assert_eq!(
+            rule("target/", IgnoreOrigin::Project("Cargo.toml")),
+            globs.explain(&sapignores, Path::new("target"), true)
+        );
+    }
+
+    #[test]
+    fn test_hides_reports() {
+        let sapignores = SapIgnores::default();
+        let hide = |globs: &IgnoreGlobs, ignored: &mut IgnoredChildren| {
+            for (name, is_dir) in [(".git", true), ("main.rs", false), (".DS_Store", false)] {
+                globs.hides(&sapignores, Path::new(name), is_dir, ignored);
+            }
+        };
+
+        let mut ignored = IgnoredChildren::default();
+        hide(&configure(&["lsd"]), &mut ignored);
+        assert_eq!(IgnoredChildren::default(), ignored);
+
+        hide(&configure(&["lsd", "--show-ignored"]), &mut ignored);
+        assert_eq!(2, ignored.count);
+        assert!(ignored.entries.is_empty());
+
+        let mut ignored = IgnoredChildren::default();
+        hide(&configure(&["lsd", "--explain-ignores"]), &mut ignored);
+        let names: Vec<&str> = ignored.entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(2, ignored.count);
+        assert_eq!(vec![".git", ".DS_Store"], names);
+        assert_eq!(".DS_Store", ignored.entries[1].1.pattern);
     }
 
     #[test]
     fn test_pattern_classification() {
-        use std::ffi::OsStr;
-        
         let globs = IgnoreGlobs::default();
-        
+
         // Test extension matching (should hit fast path)
-        assert!(globs.is_match(OsStr::new("test.jpg")));
-        assert!(globs.is_match(OsStr::new("file.PNG"))); // Case insensitive
-        assert!(globs.is_match(OsStr::new("archive.tar.gz")));
-        
+        assert!(is_file_ignored(&globs, "main.rs.swp"));
+        assert!(is_file_ignored(&globs, "MAIN.RS.SWP")); // Case insensitive
+
         // Test exact name matching (should hit fast path)
-        assert!(globs.is_match(OsStr::new(".git")));
-        assert!(globs.is_match(OsStr::new("node_modules")));
-        assert!(globs.is_match(OsStr::new("target")));
-        
+        assert!(is_dir_ignored(&globs, ".git"));
+        assert!(is_dir_ignored(&globs, ".vscode"));
+        assert!(is_file_ignored(&globs, ".DS_Store"));
+
+        // Media, archives and lock files are listed
+        for name in ["logo.png", "clip.mp4", "manual.pdf", "release.zip", "libz.so", "Cargo.lock"] {
+            assert!(!is_file_ignored(&globs, name), "{} is ignored", name);
+        }
+
         // Test files that should NOT match
-        assert!(!globs.is_match(OsStr::new("README.md")));
-        assert!(!globs.is_match(OsStr::new("src")));
-        assert!(!globs.is_match(OsStr::new("main.rs")));
+        assert!(!is_file_ignored(&globs, "README.md"));
+        assert!(!is_dir_ignored(&globs, "src"));
+        assert!(!is_file_ignored(&globs, "main.rs"));
     }
 
     #[test]
     #[ignore] // Run with: cargo test test_performance_comparison -- --ignored --nocapture
     fn test_performance_comparison() {
-        use std::ffi::OsStr;
         use std::time::Instant;
-        
+
         let globs = IgnoreGlobs::default();
-        
+        let sapignores = SapIgnores::default();
+
         // Test files that hit different code paths
         let test_files = vec![
-            OsStr::new("file.jpg"),      // Extension fast path
-            OsStr::new("image.PNG"),      // Extension fast path (case insensitive)
-            OsStr::new(".git"),           // Exact name fast path
-            OsStr::new("node_modules"),   // Exact name fast path
-            OsStr::new("test.rs"),        // No match (goes through all paths)
-            OsStr::new("README.md"),      // No match
+            Path::new("file.swp"),     // Extension fast path
+            Path::new("image.SWO"),    // Extension fast path (case insensitive)
+            Path::new(".git"),         // Exact name fast path
+            Path::new("node_modules"), // Exact name fast path
+            Path::new("src/test.rs"),  // No match (goes through all paths)
+            Path::new("README.md"),    // No match
         ];
-        
+
         let iterations = 100_000;
         let start = Instant::now();
-        
+
         for _ in 0..iterations {
             for file in &test_files {
-                let _ = globs.is_match(file);
+                let _ = globs.is_ignored(&sapignores, file, false);
             }
         }
-        
+
         let duration = start.elapsed();
         let total_ops = iterations * test_files.len() as u128;
         let ops_per_sec = total_ops as f64 / duration.as_secs_f64();
-        
+
         println!("\nPerformance Results:");
         println!("  Total operations: {}", total_ops);
         println!("  Total time: {:?}", duration);
         println!("  Operations/sec: {:.0}", ops_per_sec);
         println!("  Avg time per operation: {:.2?}", duration / total_ops as u32);
-        
+
         // With the optimization, we expect > 5M ops/sec on modern hardware
         // Old implementation with 147 glob patterns would be much slower
-        assert!(ops_per_sec > 1_000_000.0, 
+        assert!(ops_per_sec > 1_000_000.0,
             "Performance too slow: {} ops/sec", ops_per_sec);
     }
 }
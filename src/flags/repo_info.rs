@@ -0,0 +1,85 @@
+//! This module defines the [RepoInfoFlag]. To set it up from [Cli], a [Config] and its
+//! [Default] value, use the [configure_from](Configurable::configure_from) method.
+
+use super::Configurable;
+
+use crate::app::Cli;
+use crate::config_file::Config;
+
+/// The flag showing whether to print a summary of the git repository before the listing.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Default)]
+pub struct RepoInfoFlag(pub bool);
+
+impl Configurable<Self> for RepoInfoFlag {
+    /// Get a potential `RepoInfoFlag` value from [Cli].
+    ///
+    /// If the "repo-info" argument is passed, or the listing is for an agent ("llm"), this
+    /// returns a `RepoInfoFlag` with value `true` in a [Some]. Otherwise this returns [None].
+    fn from_cli(cli: &Cli) -> Option<Self> {
+        if cli.repo_info || cli.llm {
+            Some(Self(true))
+        } else {
+            None
+        }
+    }
+
+    /// Get a potential `RepoInfoFlag` value from a [Config].
+    ///
+    /// If the `Config::repo-info` has value,
+    /// this returns it as the value of the `RepoInfoFlag`, in a [Some].
+    /// Otherwise this returns [None].
+    fn from_config(config: &Config) -> Option<Self> {
+        config.repo_info.map(Self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::RepoInfoFlag;
+
+    use crate::app::Cli;
+    use crate::config_file::Config;
+    use crate::flags::Configurable;
+
+    #[test]
+    fn test_from_cli_none() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(None, RepoInfoFlag::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_true() {
+        let argv = ["lsd", "--repo-info"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(RepoInfoFlag(true)), RepoInfoFlag::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_llm() {
+        let argv = ["lsd", "--llm"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(RepoInfoFlag(true)), RepoInfoFlag::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_config_none() {
+        assert_eq!(None, RepoInfoFlag::from_config(&Config::with_none()));
+    }
+
+    #[test]
+    fn test_from_config_true() {
+        let mut c = Config::with_none();
+        c.repo_info = Some(true);
+        assert_eq!(Some(RepoInfoFlag(true)), RepoInfoFlag::from_config(&c));
+    }
+
+    #[test]
+    fn test_from_config_false() {
+        let mut c = Config::with_none();
+        c.repo_info = Some(false);
+        assert_eq!(Some(RepoInfoFlag(false)), RepoInfoFlag::from_config(&c));
+    }
+}
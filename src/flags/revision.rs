@@ -0,0 +1,95 @@
+//! This module defines the [Revision] flag. To set it up from [Cli], a [Config] and its
+//! [Default] value, use the [configure_from](Configurable::configure_from) method.
+
+use super::Configurable;
+
+use crate::app::Cli;
+use crate::config_file::Config;
+
+/// The git revision whose tree should be listed instead of the working directory.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Revision {
+    /// The revision spec (commit, tag, branch, `HEAD~N`, ...), if any.
+    pub rev: Option<String>,
+    /// Whether to date each entry by the last commit that changed it.
+    pub last_modified: bool,
+}
+
+impl Revision {
+    pub fn is_enabled(&self) -> bool {
+        self.rev.is_some()
+    }
+}
+
+impl Configurable<Self> for Revision {
+    /// Get a potential `Revision` value from [Cli].
+    ///
+    /// If the "at" argument is passed, this returns a `Revision` holding its value in a [Some].
+    /// Otherwise this returns [None].
+    fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.at.as_ref().map(|rev| Self {
+            rev: Some(rev.clone()),
+            last_modified: cli.last_modified,
+        })
+    }
+
+    /// A revision only makes sense for a single invocation, so it is never read from a [Config].
+    fn from_config(_config: &Config) -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::Revision;
+
+    use crate::app::Cli;
+    use crate::config_file::Config;
+    use crate::flags::Configurable;
+
+    #[test]
+    fn test_from_cli_none() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(None, Revision::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_rev() {
+        let argv = ["lsd", "--at", "HEAD~3"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            Some(Revision {
+                rev: Some("HEAD~3".into()),
+                last_modified: false,
+            }),
+            Revision::from_cli(&cli)
+        );
+    }
+
+    #[test]
+    fn test_from_cli_last_modified() {
+        let argv = ["lsd", "--at", "v1.0", "--last-modified"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            Some(Revision {
+                rev: Some("v1.0".into()),
+                last_modified: true,
+            }),
+            Revision::from_cli(&cli)
+        );
+    }
+
+    #[test]
+    fn test_last_modified_requires_at() {
+        let argv = ["lsd", "--last-modified"];
+        assert!(Cli::try_parse_from(argv).is_err());
+    }
+
+    #[test]
+    fn test_from_config_none() {
+        assert_eq!(None, Revision::from_config(&Config::with_none()));
+    }
+}
@@ -0,0 +1,114 @@
+//! This module defines the [ScanLimits] options. To set them up from [Cli], a [Config] and
+//! their [Default] values, use the [configure_from](ScanLimits::configure_from) method.
+
+use std::time::Duration;
+
+use crate::app::{self, Cli};
+use crate::config_file::Config;
+use crate::print_error;
+
+/// The limits after which a walk is cut short.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Default)]
+pub struct ScanLimits {
+    /// How many entries the walk yields at most.
+    pub max_entries: Option<usize>,
+    /// How long the walk may take.
+    pub timeout: Option<Duration>,
+}
+
+impl ScanLimits {
+    /// Get the `ScanLimits` from either [Cli], a [Config] or the [Default] value. Each limit
+    /// is taken from `Cli::max_entries` and `Cli::timeout` first, then from the
+    /// `Config::scan-limits` section.
+    pub fn configure_from(cli: &Cli, config: &Config) -> Self {
+        let limits = config.scan_limits.as_ref();
+
+        Self {
+            max_entries: cli
+                .max_entries
+                .or_else(|| limits.and_then(|limits| limits.max_entries)),
+            timeout: cli.timeout.or_else(|| {
+                let value = limits?.timeout.as_deref()?;
+                app::parse_duration(value)
+                    .map_err(|err| print_error!("Not a valid scan-limits.timeout: {}, {}.", value, err))
+                    .ok()
+            }),
+        }
+    }
+
+    /// Whether any limit is set
+    pub fn is_enabled(&self) -> bool {
+        self.max_entries.is_some() || self.timeout.is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use std::time::Duration;
+
+    use super::ScanLimits;
+
+    use crate::app::{self, Cli};
+    use crate::config_file::{self, Config};
+
+    fn config_limits(max_entries: Option<usize>, timeout: Option<&str>) -> Config {
+        let mut c = Config::with_none();
+        c.scan_limits = Some(config_file::ScanLimits {
+            max_entries,
+            timeout: timeout.map(String::from),
+        });
+        c
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(Ok(Duration::from_secs(30)), app::parse_duration("30"));
+        assert_eq!(Ok(Duration::from_millis(1500)), app::parse_duration("1.5s"));
+        assert_eq!(Ok(Duration::from_millis(500)), app::parse_duration("500ms"));
+        assert_eq!(Ok(Duration::from_secs(120)), app::parse_duration("2m"));
+        assert!(app::parse_duration("soon").is_err());
+        assert!(app::parse_duration("-1").is_err());
+    }
+
+    #[test]
+    fn test_from_cli() {
+        let argv = ["lsd", "--max-entries", "100", "--timeout", "2s"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            ScanLimits {
+                max_entries: Some(100),
+                timeout: Some(Duration::from_secs(2)),
+            },
+            ScanLimits::configure_from(&cli, &config_limits(Some(5), Some("1m")))
+        );
+    }
+
+    #[test]
+    fn test_from_cli_invalid_timeout() {
+        let argv = ["lsd", "--timeout", "later"];
+        assert!(Cli::try_parse_from(argv).is_err());
+    }
+
+    #[test]
+    fn test_from_config() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            ScanLimits {
+                max_entries: Some(5),
+                timeout: Some(Duration::from_secs(60)),
+            },
+            ScanLimits::configure_from(&cli, &config_limits(Some(5), Some("1m")))
+        );
+    }
+
+    #[test]
+    fn test_default() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let limits = ScanLimits::configure_from(&cli, &Config::with_none());
+        assert_eq!(ScanLimits::default(), limits);
+        assert!(!limits.is_enabled());
+    }
+}
@@ -0,0 +1,98 @@
+//! This module defines the [SizeMode] flag. To set it up from [Cli], a [Config] and its
+//! [Default] value, use the [configure_from](Configurable::configure_from) method.
+
+use super::Configurable;
+
+use crate::app::Cli;
+use crate::config_file::Config;
+
+use serde::Deserialize;
+
+/// The flag showing which size of a file to report.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum SizeMode {
+    /// The variant to show the length of the file content.
+    #[default]
+    Apparent,
+    /// The variant to show the space allocated on disk, from the number of blocks.
+    Disk,
+}
+
+impl SizeMode {
+    fn from_arg_str(value: &str) -> Self {
+        match value {
+            "apparent" => Self::Apparent,
+            "disk" => Self::Disk,
+            // Invalid value should be handled by `clap` when building an `Cli`
+            other => unreachable!("Invalid value '{other}' for 'size-mode'"),
+        }
+    }
+}
+
+impl Configurable<Self> for SizeMode {
+    /// Get a potential `SizeMode` variant from [Cli].
+    ///
+    /// If the "size-mode" argument is passed, the corresponding `SizeMode` variant is returned
+    /// in a [Some]. Otherwise this returns [None].
+    fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.size_mode.as_deref().map(Self::from_arg_str)
+    }
+
+    /// Get a potential `SizeMode` variant from a [Config].
+    ///
+    /// If the `Config::size-mode` has value, this returns it in a [Some].
+    /// Otherwise this returns [None].
+    fn from_config(config: &Config) -> Option<Self> {
+        config.size_mode
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::SizeMode;
+
+    use crate::app::Cli;
+    use crate::config_file::Config;
+    use crate::flags::Configurable;
+
+    #[test]
+    fn test_default() {
+        assert_eq!(SizeMode::Apparent, SizeMode::default());
+    }
+
+    #[test]
+    fn test_from_cli_none() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(None, SizeMode::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_disk() {
+        let argv = ["lsd", "--size-mode", "disk"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(SizeMode::Disk), SizeMode::from_cli(&cli));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_cli_unknown() {
+        let argv = ["lsd", "--size-mode", "unknown"];
+        let _ = Cli::try_parse_from(argv).unwrap();
+    }
+
+    #[test]
+    fn test_from_config_none() {
+        assert_eq!(None, SizeMode::from_config(&Config::with_none()));
+    }
+
+    #[test]
+    fn test_from_config_disk() {
+        let mut c = Config::with_none();
+        c.size_mode = Some(SizeMode::Disk);
+        assert_eq!(Some(SizeMode::Disk), SizeMode::from_config(&c));
+    }
+}
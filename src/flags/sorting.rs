@@ -42,6 +42,8 @@ pub enum SortColumn {
     Size,
     Version,
     GitStatus,
+    /// The sniffed type of the contents, like `text/x-python`.
+    Mime,
 }
 
 impl Configurable<Self> for SortColumn {
@@ -62,6 +64,8 @@ impl Configurable<Self> for SortColumn {
             Some(Self::Version)
         } else if cli.gitsort || sort == Some("git") {
             Some(Self::GitStatus)
+        } else if sort == Some("mime") {
+            Some(Self::Mime)
         } else if cli.no_sort || sort == Some("none") {
             Some(Self::None)
         } else {
@@ -263,6 +267,13 @@ mod test_sort_column {
         assert_eq!(Some(SortColumn::GitStatus), SortColumn::from_cli(&cli));
     }
 
+    #[test]
+    fn test_from_arg_cli_sort_mime() {
+        let argv = ["lsd", "--sort", "mime"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(SortColumn::Mime), SortColumn::from_cli(&cli));
+    }
+
     #[test]
     fn test_multi_sort() {
         let argv = ["lsd", "--sort", "size", "--sort", "time"];
@@ -359,6 +370,17 @@ mod test_sort_column {
         });
         assert_eq!(Some(SortColumn::GitStatus), SortColumn::from_config(&c));
     }
+
+    #[test]
+    fn test_from_config_mime() {
+        let mut c = Config::with_none();
+        c.sorting = Some(Sorting {
+            column: Some(SortColumn::Mime),
+            reverse: None,
+            dir_grouping: None,
+        });
+        assert_eq!(Some(SortColumn::Mime), SortColumn::from_config(&c));
+    }
 }
 
 #[cfg(test)]
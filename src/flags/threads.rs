@@ -0,0 +1,220 @@
+//! This module defines the [Threads] options. To set them up from [Cli], the environment, a
+//! [Config] and their [Default] values, use the [configure_from](Threads::configure_from)
+//! method.
+
+use std::time::Duration;
+
+use crate::app::Cli;
+use crate::config_file::Config;
+use crate::print_error;
+
+/// jwalk's own busy timeout for the default pool
+const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The options relating to parallel traversal.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Default)]
+pub struct Threads {
+    /// The size of the process-wide thread pool, or whether to walk without it.
+    pub mode: ThreadMode,
+    /// How long a walk waits for the busy pool, `None` for jwalk's default.
+    pub busy_timeout: Option<Duration>,
+}
+
+/// How many threads walk directories.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Default)]
+pub enum ThreadMode {
+    /// One thread per CPU.
+    #[default]
+    Auto,
+    /// A pool of this many threads.
+    Count(usize),
+    /// Walk on the calling thread, in a deterministic order.
+    Serial,
+}
+
+impl ThreadMode {
+    /// Parse "serial" or a thread count, where 0 means one thread per CPU.
+    fn from_arg_str(value: &str) -> Option<Self> {
+        match value {
+            "serial" => Some(Self::Serial),
+            _ => match value.parse::<usize>() {
+                Ok(0) => Some(Self::Auto),
+                Ok(count) => Some(Self::Count(count)),
+                Err(_) => None,
+            },
+        }
+    }
+}
+
+impl Threads {
+    /// Get the `Threads` from either [Cli], the environment, a [Config] or the [Default] value.
+    ///
+    /// The mode is determined by [mode_from](Threads::mode_from) and the busy timeout by
+    /// [busy_timeout_from](Threads::busy_timeout_from).
+    pub fn configure_from(cli: &Cli, config: &Config) -> Self {
+        Self {
+            mode: Self::mode_from(cli, config),
+            busy_timeout: Self::busy_timeout_from(cli, config),
+        }
+    }
+
+    /// Get the [ThreadMode] from [Cli], the environment, a [Config] or the [Default] value.
+    /// The first value that is not [None] is used. The order of precedence for the value used
+    /// is:
+    /// - Cli::threads
+    /// - the `SAP_THREADS` environment variable
+    /// - [Config.threads.serial], when `true`
+    /// - [Config.threads.count]
+    /// - [Default::default]
+    fn mode_from(cli: &Cli, config: &Config) -> ThreadMode {
+        if let Some(mode) = cli.threads.as_deref().and_then(ThreadMode::from_arg_str) {
+            return mode;
+        }
+
+        if let Ok(value) = std::env::var("SAP_THREADS") {
+            match ThreadMode::from_arg_str(&value) {
+                Some(mode) => return mode,
+                None => print_error!("Not a valid threads value: {}.", value),
+            }
+        }
+
+        if let Some(threads) = &config.threads {
+            if threads.serial == Some(true) {
+                return ThreadMode::Serial;
+            }
+            if let Some(count) = threads.count {
+                return ThreadMode::from_arg_str(&count.to_string()).unwrap_or_default();
+            }
+        }
+
+        Default::default()
+    }
+
+    /// Get the busy timeout from [Cli] or a [Config], in milliseconds.
+    fn busy_timeout_from(cli: &Cli, config: &Config) -> Option<Duration> {
+        cli.busy_timeout
+            .or_else(|| config.threads.as_ref().and_then(|threads| threads.busy_timeout))
+            .map(Duration::from_millis)
+    }
+
+    /// How jwalk reads directories: on the shared global pool, or serially
+    pub fn parallelism(&self) -> jwalk::Parallelism {
+        match self.mode {
+            ThreadMode::Serial => jwalk::Parallelism::Serial,
+            ThreadMode::Auto | ThreadMode::Count(_) => jwalk::Parallelism::RayonDefaultPool {
+                busy_timeout: self.busy_timeout.unwrap_or(DEFAULT_BUSY_TIMEOUT),
+            },
+        }
+    }
+
+    /// Size the global rayon pool, which every walk and parallel stage shares. This has to
+    /// run before any parallel work starts.
+    pub fn build_global_pool(&self) {
+        let num_threads = match self.mode {
+            // rayon picks one thread per CPU
+            ThreadMode::Auto => 0,
+            ThreadMode::Count(count) => count,
+            ThreadMode::Serial => 1,
+        };
+
+        if let Err(err) = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build_global()
+        {
+            print_error!("Cannot set up the thread pool: {}.", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use std::time::Duration;
+
+    use super::{ThreadMode, Threads};
+
+    use crate::app::Cli;
+    use crate::config_file::{self, Config};
+
+    fn config_threads(count: Option<usize>, serial: Option<bool>, busy_timeout: Option<u64>) -> Config {
+        let mut c = Config::with_none();
+        c.threads = Some(config_file::Threads {
+            count,
+            serial,
+            busy_timeout,
+        });
+        c
+    }
+
+    #[test]
+    fn test_mode_from_arg_str() {
+        assert_eq!(Some(ThreadMode::Serial), ThreadMode::from_arg_str("serial"));
+        assert_eq!(Some(ThreadMode::Auto), ThreadMode::from_arg_str("0"));
+        assert_eq!(Some(ThreadMode::Count(4)), ThreadMode::from_arg_str("4"));
+        assert_eq!(None, ThreadMode::from_arg_str("many"));
+    }
+
+    #[test]
+    fn test_mode_from_cli_count() {
+        let argv = ["lsd", "--threads", "2"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(ThreadMode::Count(2), Threads::mode_from(&cli, &Config::with_none()));
+    }
+
+    #[test]
+    fn test_mode_from_cli_serial() {
+        let argv = ["lsd", "--threads", "serial"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(ThreadMode::Serial, Threads::mode_from(&cli, &config_threads(Some(8), None, None)));
+    }
+
+    #[test]
+    fn test_mode_from_cli_invalid() {
+        let argv = ["lsd", "--threads", "many"];
+        assert!(Cli::try_parse_from(argv).is_err());
+    }
+
+    #[test]
+    fn test_mode_from_config_count() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(ThreadMode::Count(3), Threads::mode_from(&cli, &config_threads(Some(3), None, None)));
+    }
+
+    #[test]
+    fn test_mode_from_config_serial() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            ThreadMode::Serial,
+            Threads::mode_from(&cli, &config_threads(Some(3), Some(true), None))
+        );
+    }
+
+    #[test]
+    fn test_busy_timeout_from_cli() {
+        let argv = ["lsd", "--busy-timeout", "250"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            Some(Duration::from_millis(250)),
+            Threads::busy_timeout_from(&cli, &config_threads(None, None, Some(10)))
+        );
+    }
+
+    #[test]
+    fn test_busy_timeout_from_config() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            Some(Duration::from_millis(10)),
+            Threads::busy_timeout_from(&cli, &config_threads(None, None, Some(10)))
+        );
+    }
+
+    #[test]
+    fn test_busy_timeout_none() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(None, Threads::busy_timeout_from(&cli, &Config::with_none()));
+    }
+}
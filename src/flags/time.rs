@@ -0,0 +1,135 @@
+//! This module defines the [TimeField] flag. To set it up from [Cli], a [Config] and its
+//! [Default] value, use the [configure_from](Configurable::configure_from) method.
+
+use super::Configurable;
+
+use crate::app::Cli;
+use crate::config_file::Config;
+
+use serde::Deserialize;
+
+/// The flag showing which timestamp of a file the date block shows and `-t` sorts by.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeField {
+    /// The variant to use the last modification of the content (mtime).
+    #[default]
+    Modified,
+    /// The variant to use the last access (atime).
+    Accessed,
+    /// The variant to use the last status change (ctime).
+    Changed,
+    /// The variant to use the creation (birth time, from `statx` on Linux).
+    Birth,
+}
+
+impl TimeField {
+    fn from_arg_str(value: &str) -> Self {
+        match value {
+            "modified" => Self::Modified,
+            "accessed" => Self::Accessed,
+            "changed" => Self::Changed,
+            "birth" => Self::Birth,
+            // Invalid value should be handled by `clap` when building an `Cli`
+            other => unreachable!("Invalid value '{other}' for 'time'"),
+        }
+    }
+}
+
+impl Configurable<Self> for TimeField {
+    /// Get a potential `TimeField` variant from [Cli].
+    ///
+    /// If the "time" argument is passed, the corresponding `TimeField` variant is returned in
+    /// a [Some]. The "-u" and "-c" arguments select `Accessed` and `Changed`, like in `ls`.
+    /// Otherwise this returns [None].
+    fn from_cli(cli: &Cli) -> Option<Self> {
+        if let Some(time) = cli.time.as_deref() {
+            Some(Self::from_arg_str(time))
+        } else if cli.access_time {
+            Some(Self::Accessed)
+        } else if cli.change_time {
+            Some(Self::Changed)
+        } else {
+            None
+        }
+    }
+
+    /// Get a potential `TimeField` variant from a [Config].
+    ///
+    /// If the `Config::time` has value, this returns it in a [Some].
+    /// Otherwise this returns [None].
+    fn from_config(config: &Config) -> Option<Self> {
+        config.time
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::TimeField;
+
+    use crate::app::Cli;
+    use crate::config_file::Config;
+    use crate::flags::Configurable;
+
+    #[test]
+    fn test_default() {
+        assert_eq!(TimeField::Modified, TimeField::default());
+    }
+
+    #[test]
+    fn test_from_cli_none() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(None, TimeField::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_birth() {
+        let argv = ["lsd", "--time", "birth"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(TimeField::Birth), TimeField::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_access_short() {
+        let argv = ["lsd", "-u"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(TimeField::Accessed), TimeField::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_change_short() {
+        let argv = ["lsd", "-c"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(TimeField::Changed), TimeField::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_last_wins() {
+        let argv = ["lsd", "-u", "--time", "changed"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(TimeField::Changed), TimeField::from_cli(&cli));
+
+        let argv = ["lsd", "--time", "changed", "-u"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(TimeField::Accessed), TimeField::from_cli(&cli));
+
+        let argv = ["lsd", "-c", "-u"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(TimeField::Accessed), TimeField::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_config_none() {
+        assert_eq!(None, TimeField::from_config(&Config::with_none()));
+    }
+
+    #[test]
+    fn test_from_config_accessed() {
+        let mut c = Config::with_none();
+        c.time = Some(TimeField::Accessed);
+        assert_eq!(Some(TimeField::Accessed), TimeField::from_config(&c));
+    }
+}
@@ -0,0 +1,159 @@
+//! This module defines the [Tracked] flag. To set it up from [Cli], a [Config] and its
+//! [Default] value, use the [configure_from](Configurable::configure_from) method.
+
+use super::Configurable;
+
+use crate::app::Cli;
+use crate::config_file::Config;
+
+/// The flag showing whether to enumerate entries from the git index instead of walking the disk.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Default)]
+pub struct Tracked {
+    /// Whether to read the listing from the git index.
+    pub enabled: bool,
+    /// Whether to add untracked files that are not ignored by git.
+    pub untracked: bool,
+    /// Whether to fall back to a disk walk for inputs outside a git work tree.
+    pub fallback: bool,
+}
+
+impl Configurable<Self> for Tracked {
+    /// Get a potential `Tracked` value from [Cli].
+    ///
+    /// If the "tracked" argument is passed, this returns a `Tracked` that reads the index, adding
+    /// untracked files when "untracked" is passed too. Listings for an agent ("llm") default to
+    /// tracked and untracked files, falling back to a disk walk outside a git work tree. If
+    /// "no-tracked" is passed, this returns a `Tracked` that walks the disk. Otherwise this
+    /// returns [None].
+    fn from_cli(cli: &Cli) -> Option<Self> {
+        if cli.no_tracked {
+            Some(Self::default())
+        } else if cli.tracked {
+            Some(Self {
+                enabled: true,
+                untracked: cli.untracked,
+                fallback: false,
+            })
+        } else if cli.llm {
+            Some(Self {
+                enabled: true,
+                untracked: true,
+                fallback: true,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Get a potential `Tracked` value from a [Config].
+    ///
+    /// If the `Config::tracked` has value, this returns a `Tracked` reading the index when it
+    /// is true, falling back to a disk walk outside a git work tree, so that every listing
+    /// still works. Otherwise this returns [None].
+    fn from_config(config: &Config) -> Option<Self> {
+        config.tracked.map(|enabled| Self {
+            enabled,
+            untracked: false,
+            fallback: enabled,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::Tracked;
+
+    use crate::app::Cli;
+    use crate::config_file::Config;
+    use crate::flags::Configurable;
+
+    fn config_tracked(tracked: bool) -> Config {
+        let mut c = Config::with_none();
+        c.tracked = Some(tracked);
+        c
+    }
+
+    #[test]
+    fn test_from_cli_none() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(None, Tracked::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_tracked() {
+        let argv = ["lsd", "--tracked"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            Some(Tracked {
+                enabled: true,
+                untracked: false,
+                fallback: false,
+            }),
+            Tracked::from_cli(&cli)
+        );
+    }
+
+    #[test]
+    fn test_from_cli_untracked() {
+        let argv = ["lsd", "--tracked", "--untracked"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            Some(Tracked {
+                enabled: true,
+                untracked: true,
+                fallback: false,
+            }),
+            Tracked::from_cli(&cli)
+        );
+    }
+
+    #[test]
+    fn test_from_cli_llm_default() {
+        let argv = ["lsd", "--llm"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(
+            Some(Tracked {
+                enabled: true,
+                untracked: true,
+                fallback: true,
+            }),
+            Tracked::from_cli(&cli)
+        );
+    }
+
+    #[test]
+    fn test_from_cli_llm_no_tracked() {
+        let argv = ["lsd", "--llm", "--no-tracked"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(Tracked::default()), Tracked::from_cli(&cli));
+    }
+
+    #[test]
+    fn test_from_cli_no_tracked() {
+        let argv = ["lsd", "--no-tracked"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert_eq!(Some(Tracked::default()), Tracked::from_cli(&cli));
+        assert_eq!(Tracked::default(), Tracked::configure_from(&cli, &config_tracked(true)));
+    }
+
+    #[test]
+    fn test_from_config_none() {
+        assert_eq!(None, Tracked::from_config(&Config::with_none()));
+    }
+
+    #[test]
+    fn test_from_config_tracked() {
+        assert_eq!(
+            Some(Tracked {
+                enabled: true,
+                untracked: false,
+                fallback: true,
+            }),
+            Tracked::from_config(&config_tracked(true))
+        );
+        assert_eq!(Some(Tracked::default()), Tracked::from_config(&config_tracked(false)));
+    }
+}
@@ -0,0 +1,127 @@
+//! This module defines the [WhereFilter]. To set it up from [Cli], a [Config] and its
+//! [Default] value, use the [configure_from](WhereFilter::configure_from) method.
+
+use crate::app::Cli;
+use crate::config_file::Config;
+use crate::filter::Filter;
+
+use clap::error::ErrorKind;
+use clap::Error;
+
+/// The compiled `--where` expression, if any.
+#[derive(Clone, Debug, Default)]
+pub struct WhereFilter(pub Option<Filter>);
+
+impl WhereFilter {
+    /// Get the `WhereFilter` from `Cli::filter` (`--where`), or from the `Config::where` key.
+    ///
+    /// # Errors
+    ///
+    /// If the expression cannot be parsed; the message points at the offending token.
+    pub fn configure_from(cli: &Cli, config: &Config) -> Result<Self, Error> {
+        let (source, origin) = match (&cli.filter, &config.filter) {
+            (Some(source), _) => (source, "--where"),
+            (None, Some(source)) => (source, "where in the config file"),
+            (None, None) => return Ok(Self::default()),
+        };
+
+        Filter::parse(source).map(|filter| Self(Some(filter))).map_err(|err| {
+            Error::raw(
+                ErrorKind::ValueValidation,
+                format!("invalid {}: {}\n", origin, err.render(source)),
+            )
+        })
+    }
+
+    /// Whether the expression reads stat data the walk would otherwise skip
+    pub fn needs_stat(&self) -> bool {
+        self.0.as_ref().is_some_and(Filter::needs_stat)
+    }
+
+    /// Whether the expression reads the git status of entries
+    pub fn needs_git(&self) -> bool {
+        self.0.as_ref().is_some_and(Filter::needs_git)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::WhereFilter;
+
+    use crate::app::Cli;
+    use crate::config_file::Config;
+
+    fn config_where(source: &str) -> Config {
+        let mut c = Config::with_none();
+        c.filter = Some(source.to_string());
+        c
+    }
+
+    #[test]
+    fn test_from_cli() {
+        let argv = ["lsd", "--where", "ext == rs && size > 10K"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let filter = WhereFilter::configure_from(&cli, &config_where("name ==")).unwrap();
+        assert!(filter.0.is_some());
+        assert!(filter.needs_stat());
+    }
+
+    #[test]
+    fn test_from_config() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let filter = WhereFilter::configure_from(&cli, &config_where("name ~ '*.md' or depth <= 1")).unwrap();
+        assert!(filter.0.is_some());
+        assert!(!filter.needs_stat());
+    }
+
+    #[test]
+    fn test_default() {
+        let argv = ["lsd"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let filter = WhereFilter::configure_from(&cli, &Config::with_none()).unwrap();
+        assert!(filter.0.is_none());
+        assert!(!filter.needs_stat());
+    }
+
+    #[test]
+    fn test_expressions() {
+        for source in [
+            "type == dir",
+            "!(ext in (rs, toml)) && mtime < 2w",
+            "mtime >= 2024-01-31 and not git == clean",
+            "perms != 644 || owner not in (0, 1)",
+            "path !~ \"src/**\"",
+        ] {
+            let cli = Cli::try_parse_from(["lsd", "--where", source]).unwrap();
+            assert!(WhereFilter::configure_from(&cli, &Config::with_none()).is_ok(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_error_points_at_token() {
+        let cli = Cli::try_parse_from(["lsd", "--where", "ext == rs && sise > 1M"]).unwrap();
+        let err = WhereFilter::configure_from(&cli, &Config::with_none()).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("unknown field `sise`"));
+        assert!(message.contains("\n               ^^^^"));
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        for source in [
+            "size > lots",
+            "name < foo",
+            "mtime == 7d",
+            "(type == dir",
+            "type == folder",
+            "ext == rs rs",
+            "name == \"open",
+        ] {
+            let cli = Cli::try_parse_from(["lsd", "--where", source]).unwrap();
+            assert!(WhereFilter::configure_from(&cli, &Config::with_none()).is_err(), "{}", source);
+        }
+    }
+}
@@ -2,7 +2,8 @@
 #![allow(dead_code)]
 
 use crate::meta::git_file_status::GitFileStatus;
-use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
 pub enum GitStatus {
@@ -37,14 +38,29 @@ pub struct GitStatusInfo {
     pub workdir_status: Option<GitStatus>,
 }
 
+/// The git statuses of a work tree, looked up by path
 pub struct GitCache {
-    statuses: Vec<(PathBuf, GitStatusInfo)>,
+    /// Canonical root of the work tree, [None] outside of a repository
+    workdir: Option<PathBuf>,
+    /// Status of each changed path, by canonical path
+    files: HashMap<PathBuf, GitFileStatus>,
+    /// Status of each directory holding changes: the highest index and work tree statuses
+    /// below it
+    dirs: HashMap<PathBuf, GitFileStatus>,
+    /// The path the cache was made for and its canonical form, to find the paths below it
+    /// without resolving each one
+    base: Option<(PathBuf, PathBuf)>,
 }
 
 impl GitCache {
     pub fn new(path: &Path) -> GitCache {
-        // Discover the git repository from the given path
-        let repo = match gix::discover(path) {
+        // Discover the git repository from the given path, or from the directory of a file
+        let dir = match path.parent() {
+            Some(parent) if !path.is_dir() => parent,
+            _ => path,
+        };
+        let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        let repo = match gix::discover(dir) {
             Ok(r) => r,
             Err(_e) => {
                 // Unable to retrieve Git info; it doesn't seem to be a git directory
@@ -135,7 +151,10 @@ impl GitCache {
                 }
             }
 
-            GitCache { statuses }
+            let base = std::fs::canonicalize(path)
+                .ok()
+                .map(|canonical| (path.to_path_buf(), canonical));
+            Self::from_statuses(workdir, statuses, base)
         } else {
             // No workdir
             Self::empty()
@@ -144,7 +163,37 @@ impl GitCache {
 
     pub fn empty() -> Self {
         GitCache {
-            statuses: Vec::new(),
+            workdir: None,
+            files: HashMap::new(),
+            dirs: HashMap::new(),
+            base: None,
+        }
+    }
+
+    /// Index the statuses of the work tree at `workdir`, rolling each one up into the
+    /// directories above it
+    fn from_statuses(
+        workdir: PathBuf,
+        statuses: Vec<(PathBuf, GitStatusInfo)>,
+        base: Option<(PathBuf, PathBuf)>,
+    ) -> Self {
+        let mut files = HashMap::new();
+        let mut dirs: HashMap<PathBuf, GitFileStatus> = HashMap::new();
+        for (path, info) in statuses {
+            let status = GitFileStatus::from_gix_status(&info);
+            for dir in path.ancestors().take_while(|dir| dir.starts_with(&workdir)) {
+                let rollup = dirs.entry(dir.to_path_buf()).or_default();
+                rollup.index = rollup.index.max(status.index);
+                rollup.workdir = rollup.workdir.max(status.workdir);
+            }
+            // A path listed twice keeps its first status
+            files.entry(path).or_insert(status);
+        }
+        GitCache {
+            workdir: Some(workdir),
+            files,
+            dirs,
+            base,
         }
     }
 
@@ -160,23 +209,28 @@ impl GitCache {
         }
     }
 
-    fn inner_get(&self, filepath: &PathBuf, is_directory: bool) -> GitFileStatus {
-        if is_directory {
-            self.statuses
-                .iter()
-                .filter(|&x| x.0.starts_with(filepath))
-                .map(|x| GitFileStatus::from_gix_status(&x.1))
-                .fold(GitFileStatus::default(), |acc, x| GitFileStatus {
-                    index: std::cmp::max(acc.index, x.index),
-                    workdir: std::cmp::max(acc.workdir, x.workdir),
-                })
-        } else {
-            self.statuses
-                .iter()
-                .find(|&x| filepath == &x.0)
-                .map(|e| GitFileStatus::from_gix_status(&e.1))
-                .unwrap_or_default()
-        }
+    /// The status of `path` in the work tree, [None] outside of it. Paths below the one the
+    /// cache was made for are found without resolving them again; a link below it has the
+    /// status of the link, not of its target.
+    pub fn status(&self, path: &Path, is_directory: bool) -> Option<GitFileStatus> {
+        let workdir = self.workdir.as_ref()?;
+        let below = self.base.as_ref().and_then(|(base, canonical)| {
+            let rest = path.strip_prefix(base).ok()?;
+            rest.components()
+                .all(|component| matches!(component, Component::Normal(_)))
+                .then(|| canonical.join(rest))
+        });
+        let path = match below {
+            Some(path) => path,
+            None => std::fs::canonicalize(path).ok()?,
+        };
+        path.starts_with(workdir)
+            .then(|| self.inner_get(&path, is_directory))
+    }
+
+    fn inner_get(&self, filepath: &Path, is_directory: bool) -> GitFileStatus {
+        let statuses = if is_directory { &self.dirs } else { &self.files };
+        statuses.get(filepath).copied().unwrap_or_default()
     }
     
     fn convert_gix_status(item: &gix::status::index_worktree::Item) -> GitStatusInfo {
@@ -271,4 +325,178 @@ impl GitCache {
             },
         }
     }
-}
\ No newline at end of file
+}
+/// Where HEAD points in a repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoHead {
+    /// A branch, which may not have any commit yet
+    Branch(String),
+    /// A commit, abbreviated
+    Detached(String),
+}
+
+/// Summary of the repository containing a listed directory, shown by `--repo-info`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoInfo {
+    pub root: PathBuf,
+    pub head: RepoHead,
+    /// Short name of the branch's upstream, like `origin/main`
+    pub upstream: Option<String>,
+    /// Commits on HEAD that are not on the upstream; `None` without an upstream or when the
+    /// history could not be walked
+    pub ahead: Option<usize>,
+    /// Commits on the upstream that are not on HEAD, `None` like `ahead`
+    pub behind: Option<usize>,
+    pub stashes: usize,
+    /// Operation left in progress, like a rebase or a merge
+    pub operation: Option<&'static str>,
+}
+
+impl RepoInfo {
+    /// Summarize the repository containing `path`, or [None] outside of a repository.
+    pub fn discover(path: &Path) -> Option<Self> {
+        let repo = gix::discover(path).ok()?;
+        let root = repo
+            .workdir()
+            .unwrap_or_else(|| repo.git_dir())
+            .to_path_buf();
+        let root = std::fs::canonicalize(&root).unwrap_or(root);
+
+        let head_id = repo.head_id().ok();
+        let head_name = repo.head_name().ok().flatten();
+
+        let head = match (&head_name, &head_id) {
+            (Some(name), _) => RepoHead::Branch(name.shorten().to_string()),
+            (None, Some(id)) => RepoHead::Detached(id.shorten_or_id().to_string()),
+            (None, None) => return None,
+        };
+        let head_id = head_id.map(|id| id.detach());
+
+        let tracking = head_name.as_ref().and_then(|name| {
+            repo.branch_remote_tracking_ref_name(name.as_ref(), gix::remote::Direction::Fetch)?
+                .ok()
+                .map(|tracking| tracking.into_owned())
+        });
+        let upstream_id = tracking.as_ref().and_then(|tracking| {
+            repo.find_reference(tracking.as_ref())
+                .ok()?
+                .peel_to_id_in_place()
+                .ok()
+                .map(|id| id.detach())
+        });
+
+        let (ahead, behind) = match (head_id, upstream_id) {
+            (Some(head), Some(upstream)) if head != upstream => (
+                count_hidden(&repo, head, upstream),
+                count_hidden(&repo, upstream, head),
+            ),
+            (Some(_), Some(_)) => (Some(0), Some(0)),
+            _ => (None, None),
+        };
+
+        Some(RepoInfo {
+            root,
+            head,
+            upstream: tracking.map(|tracking| tracking.shorten().to_string()),
+            ahead,
+            behind,
+            stashes: stash_count(&repo),
+            operation: repo.state().map(operation_name),
+        })
+    }
+}
+
+/// Commits reachable from `tip` but not from `hidden`, or `None` when a commit could not be
+/// read, like in a shallow or damaged repository
+fn count_hidden(repo: &gix::Repository, tip: gix::ObjectId, hidden: gix::ObjectId) -> Option<usize> {
+    repo.rev_walk([tip])
+        .with_hidden([hidden])
+        .all()
+        .ok()?
+        .try_fold(0, |count, commit| commit.map(|_| count + 1))
+        .ok()
+}
+
+/// Every stash is an entry in the reflog of `refs/stash`
+fn stash_count(repo: &gix::Repository) -> usize {
+    let Ok(Some(stash)) = repo.try_find_reference("refs/stash") else {
+        return 0;
+    };
+    stash
+        .log_iter()
+        .all()
+        .ok()
+        .flatten()
+        .map_or(0, |log| log.count())
+}
+
+fn operation_name(state: gix::state::InProgress) -> &'static str {
+    use gix::state::InProgress;
+
+    match state {
+        InProgress::ApplyMailbox | InProgress::ApplyMailboxRebase => "am",
+        InProgress::Bisect => "bisect",
+        InProgress::CherryPick | InProgress::CherryPickSequence => "cherry-pick",
+        InProgress::Merge => "merge",
+        InProgress::Rebase | InProgress::RebaseInteractive => "rebase",
+        InProgress::Revert | InProgress::RevertSequence => "revert",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::{count_hidden, GitCache, GitStatus, GitStatusInfo, RepoInfo};
+    use crate::meta::GitFileStatus;
+
+    fn info(index_status: Option<GitStatus>, workdir_status: Option<GitStatus>) -> GitStatusInfo {
+        GitStatusInfo {
+            index_status,
+            workdir_status,
+        }
+    }
+
+    #[test]
+    fn test_status_rolls_up_into_directories() {
+        let dir = tempdir().unwrap();
+        let workdir = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(workdir.join("src/deep")).unwrap();
+        let cache = GitCache::from_statuses(
+            workdir.clone(),
+            vec![
+                (workdir.join("src/deep/new.rs"), info(None, Some(GitStatus::NewInWorkdir))),
+                (workdir.join("src/lib.rs"), info(Some(GitStatus::Modified), None)),
+            ],
+            Some((dir.path().to_path_buf(), workdir.clone())),
+        );
+        let status = |path: &str, is_dir| cache.status(&dir.path().join(path), is_dir);
+        let pair = |index, workdir| Some(GitFileStatus { index, workdir });
+
+        assert_eq!(status("src/lib.rs", false), pair(GitStatus::Modified, GitStatus::Unmodified));
+        assert_eq!(status("src/deep", true), pair(GitStatus::Unmodified, GitStatus::NewInWorkdir));
+        assert_eq!(status("src", true), pair(GitStatus::Modified, GitStatus::NewInWorkdir));
+        assert_eq!(status("", true), status("src", true));
+        // `..` is resolved, unchanged paths have no status of their own
+        assert_eq!(status("src/deep/..", true), status("src", true));
+        assert_eq!(status("README.md", false), Some(GitFileStatus::default()));
+        // Outside of the work tree
+        assert_eq!(status("..", true), None);
+    }
+
+    #[test]
+    fn test_counts_need_the_history() {
+        let dir = tempdir().unwrap();
+        let repo = gix::init(dir.path()).unwrap();
+        let missing = |hex: &str| gix::ObjectId::from_hex(hex.as_bytes()).unwrap();
+        let tip = missing("1111111111111111111111111111111111111111");
+        let hidden = missing("2222222222222222222222222222222222222222");
+        assert_eq!(count_hidden(&repo, tip, hidden), None);
+
+        // Nothing to count without an upstream
+        let info = RepoInfo::discover(dir.path()).unwrap();
+        assert_eq!((info.upstream, info.ahead, info.behind), (None, None, None));
+    }
+}
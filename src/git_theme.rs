@@ -31,4 +31,20 @@ impl GitTheme {
         };
         symbol.to_string()
     }
+
+    pub fn ahead(&self) -> &str {
+        &self.symbols.ahead
+    }
+
+    pub fn behind(&self) -> &str {
+        &self.symbols.behind
+    }
+
+    pub fn stash(&self) -> &str {
+        &self.symbols.stash
+    }
+
+    pub fn detached(&self) -> &str {
+        &self.symbols.detached
+    }
 }
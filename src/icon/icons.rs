@@ -99,6 +99,13 @@ impl Icons {
                             .and_then(|ext| t.extension.get(ext.to_lowercase().as_str()))
                         {
                             icon
+                        } else if let Some(icon) = name
+                            .content_type()
+                            .and_then(|content_type| content_type.extension)
+                            .and_then(|ext| t.extension.get(ext))
+                        {
+                            // The sniffed contents, for the files without a known extension
+                            icon
                         } else {
                             match file_type {
                                 FileType::Directory { .. } => &t.filetype.dir,
@@ -132,7 +132,7 @@ impl Shield {
 
         // Get largest directories
         let mut largest_dirs: Vec<(String, usize)> = dir_sizes.into_iter().collect();
-        largest_dirs.sort_by(|a, b| b.1.cmp(&a.1));
+        largest_dirs.sort_by_key(|dir| std::cmp::Reverse(dir.1));
         largest_dirs.truncate(10);
 
         Ok(ShieldSummary {
@@ -10,6 +10,7 @@ mod color;
 mod config_file;
 mod core;
 mod display;
+mod filter;
 mod flags;
 mod git;
 mod git_theme;
@@ -22,6 +23,8 @@ mod stream;
 mod theme;
 
 use clap::Parser;
+use std::ffi::OsString;
+use std::path::Path;
 
 use crate::app::Cli;
 use crate::config_file::Config;
@@ -33,6 +36,8 @@ pub enum ExitCode {
     OK,
     MinorIssue,
     MajorIssue,
+    /// The listing was cut by `--max-entries` or `--timeout`
+    Truncated,
 }
 impl ExitCode {
     pub fn set_if_greater(&mut self, code: ExitCode) {
@@ -89,8 +94,18 @@ macro_rules! print_output {
     };
 }
 
+/// `sap dupes ARGS` is `sap --duplicates ARGS`, unless a file or directory named `dupes` is in
+/// `cwd`: then `sap dupes` lists it, and `sap --duplicates dupes` groups its copies
+fn expand_subcommand(args: impl Iterator<Item = OsString>, cwd: &Path) -> Vec<OsString> {
+    let mut args: Vec<OsString> = args.collect();
+    if args.get(1).is_some_and(|arg| arg == "dupes") && cwd.join("dupes").symlink_metadata().is_err() {
+        args[1] = "--duplicates".into();
+    }
+    args
+}
+
 fn main() {
-    let cli = Cli::parse_from(wild::args_os());
+    let cli = Cli::parse_from(expand_subcommand(wild::args_os(), Path::new(".")));
 
     let config = if cli.ignore_config {
         Config::with_none()
@@ -110,3 +125,36 @@ fn main() {
         .block_on(core.run(cli.inputs));
     std::process::exit(exit_code as i32);
 }
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::expand_subcommand;
+
+    fn expand(args: &[&str], cwd: &std::path::Path) -> Vec<OsString> {
+        expand_subcommand(args.iter().map(OsString::from), cwd)
+    }
+
+    #[test]
+    fn test_expand_dupes() {
+        let dir = tempdir().unwrap();
+        assert_eq!(expand(&["sap", "dupes", "src"], dir.path()), ["sap", "--duplicates", "src"]);
+        assert_eq!(expand(&["sap", "src", "dupes"], dir.path()), ["sap", "src", "dupes"]);
+        assert_eq!(expand(&["sap"], dir.path()), ["sap"]);
+    }
+
+    #[test]
+    fn test_dupes_directory_is_listed() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("dupes")).unwrap();
+        assert_eq!(expand(&["sap", "dupes"], dir.path()), ["sap", "dupes"]);
+        assert_eq!(
+            expand(&["sap", "--duplicates", "dupes"], dir.path()),
+            ["sap", "--duplicates", "dupes"]
+        );
+    }
+}
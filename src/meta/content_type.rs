@@ -0,0 +1,490 @@
+//! Content sniffing: the type of a file from its first bytes rather than from its name
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// How many bytes from the start of a file are sniffed
+pub const SNIFF_LEN: usize = 8192;
+
+/// Signatures at the start of a file: offset, bytes, MIME type and the usual extension
+const MAGIC: &[(usize, &[u8], &str, &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png", "png"),
+    (0, b"\xff\xd8\xff", "image/jpeg", "jpg"),
+    (0, b"GIF87a", "image/gif", "gif"),
+    (0, b"GIF89a", "image/gif", "gif"),
+    (0, b"%PDF-", "application/pdf", "pdf"),
+    (0, b"\x1f\x8b", "application/gzip", "gz"),
+    (0, b"BZh", "application/x-bzip2", "bz2"),
+    (0, b"\xfd7zXZ\x00", "application/x-xz", "xz"),
+    (0, b"\x28\xb5\x2f\xfd", "application/zstd", "zst"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed", "7z"),
+    (0, b"PK\x03\x04", "application/zip", "zip"),
+    (0, b"PK\x05\x06", "application/zip", "zip"),
+    (257, b"ustar", "application/x-tar", "tar"),
+    (0, b"SQLite format 3\x00", "application/vnd.sqlite3", "sqlite"),
+    (0, b"\x00asm", "application/wasm", "wasm"),
+    (0, b"fLaC", "audio/flac", "flac"),
+    (0, b"OggS", "audio/ogg", "ogg"),
+    (0, b"ID3", "audio/mpeg", "mp3"),
+    (0, b"\xfe\xed\xfa\xce", "application/x-mach-binary", "dylib"),
+    (0, b"\xfe\xed\xfa\xcf", "application/x-mach-binary", "dylib"),
+    (0, b"\xce\xfa\xed\xfe", "application/x-mach-binary", "dylib"),
+    (0, b"\xcf\xfa\xed\xfe", "application/x-mach-binary", "dylib"),
+    (0, b"MZ", "application/vnd.microsoft.portable-executable", "exe"),
+];
+
+/// RIFF containers, by the form type at offset 8
+const RIFF_FORMS: &[(&[u8], &str, &str)] = &[
+    (b"WEBP", "image/webp", "webp"),
+    (b"WAVE", "audio/wav", "wav"),
+    (b"AVI ", "video/x-msvideo", "avi"),
+];
+
+/// ISO base media files, by the major brand of their `ftyp` box; other brands are MP4 video
+const ISO_BRANDS: &[(&[u8], &str, &str)] = &[
+    (b"qt  ", "video/quicktime", "mov"),
+    (b"M4A ", "audio/mp4", "m4a"),
+    (b"heic", "image/heic", "heic"),
+    (b"heix", "image/heic", "heic"),
+    (b"mif1", "image/heif", "heif"),
+    (b"avif", "image/avif", "avif"),
+];
+
+/// Interpreters of `#!` lines, by the start of their name, with the MIME type and usual
+/// extension of their scripts
+const INTERPRETERS: &[(&str, &str, &str)] = &[
+    ("bash", "text/x-shellscript", "sh"),
+    ("dash", "text/x-shellscript", "sh"),
+    ("ksh", "text/x-shellscript", "sh"),
+    ("zsh", "text/x-shellscript", "sh"),
+    ("sh", "text/x-shellscript", "sh"),
+    ("fish", "text/x-shellscript", "fish"),
+    ("python", "text/x-python", "py"),
+    ("pypy", "text/x-python", "py"),
+    ("perl", "text/x-perl", "pl"),
+    ("ruby", "text/x-ruby", "rb"),
+    ("node", "text/javascript", "js"),
+    ("deno", "text/javascript", "js"),
+    ("bun", "text/javascript", "js"),
+    ("php", "text/x-php", "php"),
+    ("lua", "text/x-lua", "lua"),
+    ("awk", "text/x-awk", "awk"),
+    ("gawk", "text/x-awk", "awk"),
+    ("tclsh", "text/x-tcl", "tcl"),
+    ("Rscript", "text/x-r", "r"),
+];
+
+/// The encoding of a text file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Encoding {
+    Ascii,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// Bytes that are not UTF-8, but only printable ISO-8859-1 characters
+    Latin1,
+}
+
+impl Encoding {
+    /// The charset name, as in a `Content-Type` header
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ascii => "us-ascii",
+            Self::Utf8 => "utf-8",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16be",
+            Self::Latin1 => "iso-8859-1",
+        }
+    }
+}
+
+/// What the first bytes of a file tell about it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentType {
+    pub mime: &'static str,
+    /// The usual extension of the type, for the files that do not have one
+    pub extension: Option<&'static str>,
+    /// The encoding of text contents, `None` for binary ones
+    pub encoding: Option<Encoding>,
+    /// The interpreter named by the `#!` line of a script
+    pub interpreter: Option<String>,
+}
+
+impl ContentType {
+    /// Sniff the first [SNIFF_LEN] bytes of the file at `path`
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        File::open(path)?.take(SNIFF_LEN as u64).read_to_end(&mut head)?;
+        Ok(Self::sniff(&head))
+    }
+
+    /// The type of contents starting with `head`: a known signature first, then text with its
+    /// encoding and the interpreter of its `#!` line, and binary data otherwise
+    pub fn sniff(head: &[u8]) -> Self {
+        if head.is_empty() {
+            return Self::binary("inode/x-empty", None);
+        }
+        if let Some(elf) = elf_type(head) {
+            return Self::binary(elf, None);
+        }
+        if let Some(class) = java_class(head) {
+            return Self::binary(class, Some("class"));
+        }
+        if let Some((mime, extension)) = container(head) {
+            return Self::binary(mime, Some(extension));
+        }
+        if let Some(&(_, _, mime, extension)) = MAGIC
+            .iter()
+            .find(|(offset, magic, ..)| head.get(*offset..*offset + magic.len()) == Some(*magic))
+        {
+            return Self::binary(mime, Some(extension));
+        }
+
+        let Some((encoding, text)) = text_encoding(head) else {
+            return Self::binary("application/octet-stream", None);
+        };
+        let mut content = Self {
+            mime: "text/plain",
+            extension: None,
+            encoding: Some(encoding),
+            interpreter: None,
+        };
+        if let Some(interpreter) = shebang(text) {
+            let known = INTERPRETERS.iter().find(|(name, ..)| interpreter.starts_with(name));
+            content.mime = known.map_or("text/x-script", |(_, mime, _)| *mime);
+            content.extension = known.map(|(.., extension)| *extension);
+            content.interpreter = Some(interpreter);
+        } else if let Some((mime, extension)) = markup(text) {
+            content.mime = mime;
+            content.extension = Some(extension);
+        }
+        content
+    }
+
+    fn binary(mime: &'static str, extension: Option<&'static str>) -> Self {
+        Self {
+            mime,
+            extension,
+            encoding: None,
+            interpreter: None,
+        }
+    }
+
+    /// Whether the contents start with the ELF magic
+    pub fn is_elf(&self) -> bool {
+        matches!(
+            self.mime,
+            "application/x-object"
+                | "application/x-executable"
+                | "application/x-sharedlib"
+                | "application/x-coredump"
+                | "application/x-elf"
+        )
+    }
+
+    /// Whether the contents are not text in a known encoding; empty files are neither
+    pub fn is_binary(&self) -> bool {
+        self.encoding.is_none() && self.mime != "inode/x-empty"
+    }
+
+    /// The `--llm` record of the type
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut json = serde_json::json!({
+            "type": self.mime,
+            "binary": self.is_binary(),
+        });
+        if let Some(encoding) = self.encoding {
+            json["encoding"] = encoding.name().into();
+        }
+        if let Some(interpreter) = &self.interpreter {
+            json["interpreter"] = interpreter.as_str().into();
+        }
+        json
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.encoding {
+            Some(encoding) => write!(f, "{}; charset={}", self.mime, encoding.name()),
+            None => f.write_str(self.mime),
+        }
+    }
+}
+
+/// The kind of ELF file, from the `e_type` of its header
+fn elf_type(head: &[u8]) -> Option<&'static str> {
+    if !head.starts_with(b"\x7fELF") {
+        return None;
+    }
+    let e_type = match head.get(5) {
+        Some(2) => head.get(16..18).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]])),
+        _ => head.get(16..18).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]])),
+    };
+    Some(match e_type {
+        Some(1) => "application/x-object",
+        Some(2) => "application/x-executable",
+        Some(3) => "application/x-sharedlib",
+        Some(4) => "application/x-coredump",
+        _ => "application/x-elf",
+    })
+}
+
+/// Java classes share their magic number with universal Mach-O binaries, which hold a
+/// handful of architectures where a class has its version
+fn java_class(head: &[u8]) -> Option<&'static str> {
+    let version = head.strip_prefix(b"\xca\xfe\xba\xbe")?.get(..4)?;
+    Some(match u32::from_be_bytes([version[0], version[1], version[2], version[3]]) {
+        0..45 => "application/x-mach-binary",
+        _ => "application/java-vm",
+    })
+}
+
+/// The type of a RIFF, ISO base media or Matroska container, from the form, brand or document
+/// type after its header
+fn container(head: &[u8]) -> Option<(&'static str, &'static str)> {
+    if head.starts_with(b"\x1a\x45\xdf\xa3") {
+        // The EBML header is a few dozen bytes, with the DocType among them
+        let header = &head[..head.len().min(64)];
+        return Some(if header.windows(4).any(|window| window == b"webm") {
+            ("video/webm", "webm")
+        } else {
+            ("video/x-matroska", "mkv")
+        });
+    }
+    if head.starts_with(b"RIFF") {
+        let form = head.get(8..12)?;
+        return RIFF_FORMS
+            .iter()
+            .find(|(tag, ..)| *tag == form)
+            .map(|&(_, mime, extension)| (mime, extension));
+    }
+    if head.get(4..8) == Some(b"ftyp") {
+        let brand = head.get(8..12)?;
+        return Some(
+            ISO_BRANDS
+                .iter()
+                .find(|(tag, ..)| *tag == brand)
+                .map_or(("video/mp4", "mp4"), |&(_, mime, extension)| (mime, extension)),
+        );
+    }
+    None
+}
+
+/// The encoding of `head` and its text, when it holds no control characters but whitespace
+fn text_encoding(head: &[u8]) -> Option<(Encoding, &[u8])> {
+    if let Some(text) = head.strip_prefix(b"\xef\xbb\xbf") {
+        return Some((Encoding::Utf8, text));
+    }
+    if head.starts_with(b"\xff\xfe") {
+        return Some((Encoding::Utf16Le, &[]));
+    }
+    if head.starts_with(b"\xfe\xff") {
+        return Some((Encoding::Utf16Be, &[]));
+    }
+
+    let control = |byte: &u8| matches!(*byte, 0..=8 | 0x0e..=0x1a | 0x1c..=0x1f | 0x7f);
+    if head.iter().any(control) {
+        return None;
+    }
+    if head.is_ascii() {
+        return Some((Encoding::Ascii, head));
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => Some((Encoding::Utf8, head)),
+        // A character cut by the end of the sniffed bytes
+        Err(err) if err.error_len().is_none() && head.len() == SNIFF_LEN => Some((Encoding::Utf8, head)),
+        // The C1 controls are not printable in ISO-8859-1 either
+        Err(_) if !head.iter().any(|byte| (0x80..0xa0).contains(byte)) => Some((Encoding::Latin1, head)),
+        Err(_) => None,
+    }
+}
+
+/// The interpreter of a `#!` line, like `python3` for `#!/usr/bin/env -S python3 -u`
+fn shebang(text: &[u8]) -> Option<String> {
+    let line = text.strip_prefix(b"#!")?;
+    let line = line.split(|byte| *byte == b'\n').next()?;
+    let line = String::from_utf8_lossy(line);
+    let mut words = line.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    let interpreter = match program {
+        "env" => words.find(|word| !word.starts_with('-'))?,
+        program => program,
+    };
+    Some(interpreter.to_string())
+}
+
+/// Markup languages, told by their first tag
+fn markup(text: &[u8]) -> Option<(&'static str, &'static str)> {
+    let start = text.iter().position(|byte| !byte.is_ascii_whitespace())?;
+    let text = &text[start..text.len().min(start + 256)];
+    let lower = text.to_ascii_lowercase();
+    if lower.starts_with(b"<!doctype html") || lower.starts_with(b"<html") {
+        Some(("text/html", "html"))
+    } else if lower.starts_with(b"<svg") || (lower.starts_with(b"<?xml") && lower.windows(4).any(|w| w == b"<svg")) {
+        Some(("image/svg+xml", "svg"))
+    } else if lower.starts_with(b"<?xml") {
+        Some(("text/xml", "xml"))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ContentType, Encoding, SNIFF_LEN};
+
+    /// The MIME type and extension sniffed from `head`
+    fn sniffed(head: &[u8]) -> (&'static str, Option<&'static str>) {
+        let content = ContentType::sniff(head);
+        (content.mime, content.extension)
+    }
+
+    /// `prefix` padded with zeros to `len` bytes
+    fn padded(prefix: &[u8], len: usize) -> Vec<u8> {
+        let mut bytes = prefix.to_vec();
+        bytes.resize(len, 0);
+        bytes
+    }
+
+    #[test]
+    fn test_magic_numbers() {
+        assert_eq!(sniffed(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), ("image/png", Some("png")));
+        assert_eq!(sniffed(b"\xff\xd8\xff\xe0\0\x10JFIF"), ("image/jpeg", Some("jpg")));
+        assert_eq!(sniffed(b"GIF89a\x01\0\x01\0"), ("image/gif", Some("gif")));
+        assert_eq!(sniffed(b"%PDF-1.7\n"), ("application/pdf", Some("pdf")));
+        assert_eq!(sniffed(b"\x1f\x8b\x08\0"), ("application/gzip", Some("gz")));
+        assert_eq!(sniffed(b"PK\x03\x04\x14\0"), ("application/zip", Some("zip")));
+        assert_eq!(sniffed(b"\0asm\x01\0\0\0"), ("application/wasm", Some("wasm")));
+        assert_eq!(sniffed(b"fLaC\0\0\0\x22"), ("audio/flac", Some("flac")));
+        assert_eq!(sniffed(b"MZ\x90\0"), ("application/vnd.microsoft.portable-executable", Some("exe")));
+    }
+
+    #[test]
+    fn test_magic_at_an_offset() {
+        let mut tar = padded(b"file.txt", 257);
+        tar.extend_from_slice(b"ustar\x0000");
+        assert_eq!(sniffed(&tar), ("application/x-tar", Some("tar")));
+    }
+
+    #[test]
+    fn test_containers() {
+        assert_eq!(sniffed(b"RIFF\x24\0\0\0WEBPVP8 "), ("image/webp", Some("webp")));
+        assert_eq!(sniffed(b"RIFF\x24\0\0\0WAVEfmt "), ("audio/wav", Some("wav")));
+        assert_eq!(sniffed(b"\0\0\0\x18ftypisom\0\0\x02\0"), ("video/mp4", Some("mp4")));
+        assert_eq!(sniffed(b"\0\0\0\x14ftypqt  \0\0\0\0"), ("video/quicktime", Some("mov")));
+        assert_eq!(sniffed(b"\0\0\0\x1cftypheic\0\0\0\0"), ("image/heic", Some("heic")));
+
+        let ebml = b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81\x01\x42\x82\x84";
+        assert_eq!(sniffed(&[&ebml[..], b"webm"].concat()), ("video/webm", Some("webm")));
+        assert_eq!(sniffed(&[&ebml[..], b"matroska"].concat()), ("video/x-matroska", Some("mkv")));
+    }
+
+    #[test]
+    fn test_elf_and_universal_binaries() {
+        let mut elf = padded(b"\x7fELF\x02\x01\x01", 16);
+        elf.extend_from_slice(&[3, 0]);
+        assert_eq!(sniffed(&elf), ("application/x-sharedlib", None));
+        let mut big = padded(b"\x7fELF\x01\x02\x01", 16);
+        big.extend_from_slice(&[0, 2]);
+        assert_eq!(sniffed(&big), ("application/x-executable", None));
+        assert!(ContentType::sniff(&big).is_elf());
+
+        // A class has its version where a universal binary has its count of architectures
+        assert_eq!(sniffed(b"\xca\xfe\xba\xbe\0\0\0\x02"), ("application/x-mach-binary", Some("class")));
+        assert_eq!(sniffed(b"\xca\xfe\xba\xbe\0\0\0\x34"), ("application/java-vm", Some("class")));
+    }
+
+    #[test]
+    fn test_truncated_signatures() {
+        // The ELF magic alone, without the type
+        assert_eq!(sniffed(b"\x7fELF"), ("application/x-elf", None));
+        // A RIFF or ISO header cut before the form or brand is not taken for one
+        assert_eq!(sniffed(b"RIFF\x24\0\0\0WE"), ("application/octet-stream", None));
+        assert_eq!(sniffed(b"\0\0\0\x18ftyp"), ("application/octet-stream", None));
+        // Part of the PNG signature
+        assert_eq!(sniffed(b"\x89PNG"), ("application/octet-stream", None));
+        // A Mach-O or class magic without the word after it
+        assert_ne!(sniffed(b"\xca\xfe\xba\xbe").0, "application/java-vm");
+        assert_eq!(sniffed(b"RIFF"), ("text/plain", None));
+    }
+
+    #[test]
+    fn test_empty_and_binary() {
+        let empty = ContentType::sniff(b"");
+        assert_eq!(empty.mime, "inode/x-empty");
+        assert!(!empty.is_binary());
+
+        let binary = ContentType::sniff(b"\x00\x01\x02\x03");
+        assert_eq!(binary.mime, "application/octet-stream");
+        assert_eq!(binary.encoding, None);
+        assert!(binary.is_binary());
+        assert_eq!(binary.to_string(), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_text_encodings() {
+        let encoding = |head: &[u8]| ContentType::sniff(head).encoding;
+        assert_eq!(encoding(b"plain\ttext\r\n"), Some(Encoding::Ascii));
+        assert_eq!(encoding("caf\u{e9}\n".as_bytes()), Some(Encoding::Utf8));
+        assert_eq!(encoding(b"\xef\xbb\xbfbom\n"), Some(Encoding::Utf8));
+        assert_eq!(encoding(b"\xff\xfeh\0i\0"), Some(Encoding::Utf16Le));
+        assert_eq!(encoding(b"\xfe\xff\0h\0i"), Some(Encoding::Utf16Be));
+        assert_eq!(encoding(b"caf\xe9\n"), Some(Encoding::Latin1));
+        // The C1 controls are not text in ISO-8859-1 either
+        assert_eq!(encoding(b"caf\x85\n"), None);
+        // Control characters other than whitespace and escape
+        assert_eq!(encoding(b"text\x07bell"), None);
+        assert_eq!(encoding(b"\x1b[1mbold\x1b[0m"), Some(Encoding::Ascii));
+
+        let text = ContentType::sniff("caf\u{e9}".as_bytes());
+        assert_eq!(text.to_string(), "text/plain; charset=utf-8");
+        assert!(!text.is_binary());
+    }
+
+    #[test]
+    fn test_character_cut_by_the_sniffed_length() {
+        let mut head = vec![b'a'; SNIFF_LEN - 1];
+        head.push(0xc3);
+        assert_eq!(ContentType::sniff(&head).encoding, Some(Encoding::Utf8));
+        // A shorter file ends there, so the byte is not the start of a character
+        assert_eq!(ContentType::sniff(&head[SNIFF_LEN - 8..]).encoding, Some(Encoding::Latin1));
+    }
+
+    #[test]
+    fn test_shebangs() {
+        let script = |head: &[u8]| {
+            let content = ContentType::sniff(head);
+            (content.mime, content.extension, content.interpreter)
+        };
+        assert_eq!(
+            script(b"#!/bin/bash\necho hi\n"),
+            ("text/x-shellscript", Some("sh"), Some("bash".to_string()))
+        );
+        assert_eq!(
+            script(b"#!/usr/bin/env -S python3 -u\n"),
+            ("text/x-python", Some("py"), Some("python3".to_string()))
+        );
+        assert_eq!(
+            script(b"\xef\xbb\xbf#!/usr/bin/node\n"),
+            ("text/javascript", Some("js"), Some("node".to_string()))
+        );
+        assert_eq!(script(b"#!/opt/tool/run\n"), ("text/x-script", None, Some("run".to_string())));
+        // An empty `#!` line, or `env` without a program
+        assert_eq!(script(b"#!\n"), ("text/plain", None, None));
+        assert_eq!(script(b"#!/usr/bin/env -i\n"), ("text/plain", None, None));
+    }
+
+    #[test]
+    fn test_markup() {
+        assert_eq!(sniffed(b"\n  <!DOCTYPE html>\n<html>"), ("text/html", Some("html")));
+        assert_eq!(sniffed(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"), ("image/svg+xml", Some("svg")));
+        assert_eq!(
+            sniffed(b"<?xml version=\"1.0\"?>\n<svg width=\"1\"/>"),
+            ("image/svg+xml", Some("svg"))
+        );
+        assert_eq!(sniffed(b"<?xml version=\"1.0\"?>\n<root/>"), ("text/xml", Some("xml")));
+    }
+}
@@ -1,47 +1,78 @@
 use super::locale::current_locale;
 use crate::color::{ColoredString, Colors, Elem};
-use crate::flags::{DateFlag, Flags};
+use crate::flags::{DateFlag, Flags, TimeField};
 use chrono::{DateTime, Duration, Local};
 use chrono_humanize::HumanTime;
 use std::fs::Metadata;
 
 use std::time::SystemTime;
 
+/// A timestamp, or `Invalid` when it is not recorded; `Invalid` sorts as the oldest
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Date {
-    Date(DateTime<Local>),
     Invalid,
+    Date(DateTime<Local>),
 }
 
 // Note that this is split from the From for Metadata so we can test this one (as we can't mock Metadata)
 impl From<SystemTime> for Date {
+    /// Keeps the sub-second part, for sorting and `%f` formats
     fn from(systime: SystemTime) -> Self {
-        match systime.duration_since(SystemTime::UNIX_EPOCH) {
-            Ok(duration) => {
-                if let Some(datetime) = DateTime::from_timestamp(duration.as_secs() as i64, 0) {
-                    Date::Date(datetime.with_timezone(&Local))
-                } else {
-                    Date::Invalid
-                }
+        let datetime = match systime.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(after) => i64::try_from(after.as_secs())
+                .ok()
+                .and_then(|secs| DateTime::from_timestamp(secs, after.subsec_nanos())),
+            // Before the epoch: whole seconds back, then the nanoseconds forward
+            Err(before) => {
+                let before = before.duration();
+                let nanos = before.subsec_nanos();
+                let secs = before.as_secs() + u64::from(nanos > 0);
+                i64::try_from(secs)
+                    .ok()
+                    .and_then(|secs| DateTime::from_timestamp(-secs, (1_000_000_000 - nanos) % 1_000_000_000))
             }
-            Err(_) => Date::Invalid,
-        }
+        };
+        datetime.map_or(Date::Invalid, |datetime| Date::Date(datetime.with_timezone(&Local)))
     }
 }
 
 impl From<&Metadata> for Date {
     fn from(meta: &Metadata) -> Self {
-        match meta.modified() {
-            Ok(modified) => modified.into(),
-            Err(_) => {
-                // Use system time as fallback if modified time is unavailable
-                std::time::SystemTime::now().into()
+        Self::from_metadata(meta, TimeField::Modified)
+    }
+}
+
+/// The `field` timestamp of `meta`, or [None] when the platform or the file system does not
+/// record it. The birth time comes from `statx` on Linux.
+pub fn time_of(meta: &Metadata, field: TimeField) -> Option<SystemTime> {
+    match field {
+        TimeField::Modified => meta.modified().ok(),
+        TimeField::Accessed => meta.accessed().ok(),
+        TimeField::Birth => meta.created().ok(),
+        #[cfg(unix)]
+        TimeField::Changed => {
+            use std::os::unix::fs::MetadataExt;
+            use std::time::Duration;
+
+            let nanos = Duration::from_nanos(meta.ctime_nsec() as u64);
+            match u64::try_from(meta.ctime()) {
+                Ok(secs) => SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(secs) + nanos),
+                Err(_) => SystemTime::UNIX_EPOCH
+                    .checked_sub(Duration::from_secs(meta.ctime().unsigned_abs()))?
+                    .checked_add(nanos),
             }
         }
+        #[cfg(not(unix))]
+        TimeField::Changed => None,
     }
 }
 
 impl Date {
+    /// The `field` timestamp of `meta`, `Invalid` when it is not recorded
+    pub fn from_metadata(meta: &Metadata, field: TimeField) -> Self {
+        time_of(meta, field).map_or(Date::Invalid, Date::from)
+    }
+
     pub fn render(&self, colors: &Colors, flags: &Flags) -> ColoredString {
         let now = Local::now();
         #[allow(deprecated)]
@@ -94,7 +125,7 @@ impl Date {
 
             format!("{} {}", icon, date_str)
         } else {
-            String::from(" ")
+            String::from("-")
         }
     }
 }
@@ -0,0 +1,540 @@
+//! ELF inspection: the architecture, linking and symbols of executables and shared objects,
+//! from their header, program headers and section table
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::color::{ColoredString, Colors, Elem};
+
+/// The most bytes read for one table or string, past which headers are taken as corrupt
+const MAX_TABLE: u64 = 1 << 20;
+
+const PT_DYNAMIC: u64 = 2;
+const PT_INTERP: u64 = 3;
+const SHT_SYMTAB: u64 = 2;
+const DT_FLAGS_1: u64 = 0x6fff_fffb;
+const DF_1_PIE: u64 = 0x0800_0000;
+
+/// The kind of an ELF file, from the `e_type` of its header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElfKind {
+    Object,
+    Executable,
+    SharedLibrary,
+    Core,
+    Other,
+}
+
+impl ElfKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Object => "object",
+            Self::Executable => "executable",
+            Self::SharedLibrary => "shared-library",
+            Self::Core => "core",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// What the headers of an ELF file tell about it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Elf {
+    /// The machine, like `x86-64` or `aarch64`
+    pub arch: &'static str,
+    /// 32 or 64
+    pub bits: u8,
+    pub big_endian: bool,
+    pub kind: ElfKind,
+    /// A position-independent executable, which is a shared object with an interpreter or
+    /// the PIE flag
+    pub pie: bool,
+    /// An executable without a dynamic interpreter
+    pub static_linked: bool,
+    /// The dynamic loader named by the `PT_INTERP` header
+    pub interpreter: Option<String>,
+    /// Whether the symbol table was removed; `None` when the section headers could not be read
+    /// in full and held none
+    pub stripped: Option<bool>,
+    /// Whether DWARF `.debug_info` is in the file
+    pub debug_info: bool,
+}
+
+/// Reads the fields of an ELF class and byte order
+#[derive(Clone, Copy)]
+struct Layout {
+    wide: bool,
+    big: bool,
+}
+
+impl Layout {
+    fn int(self, bytes: &[u8], at: usize, len: usize) -> Option<u64> {
+        let bytes = bytes.get(at..at + len)?;
+        let fold = |value: u64, byte: &u8| (value << 8) | u64::from(*byte);
+        Some(if self.big {
+            bytes.iter().fold(0, fold)
+        } else {
+            bytes.iter().rev().fold(0, fold)
+        })
+    }
+
+    fn half(self, bytes: &[u8], at: usize) -> Option<u64> {
+        self.int(bytes, at, 2)
+    }
+
+    fn word(self, bytes: &[u8], at: usize) -> Option<u64> {
+        self.int(bytes, at, 4)
+    }
+
+    /// An address or offset, as wide as the class
+    fn addr(self, bytes: &[u8], at: usize) -> Option<u64> {
+        self.int(bytes, at, if self.wide { 8 } else { 4 })
+    }
+
+    /// Where a field is in the 64-bit and the 32-bit structures
+    fn at(self, wide: usize, narrow: usize) -> usize {
+        if self.wide { wide } else { narrow }
+    }
+}
+
+impl Elf {
+    /// Inspect the file at `path`; files that do not start with the ELF magic cost a 4-byte
+    /// read and give `None`, like ELF files whose headers do not parse
+    pub fn read(path: &Path) -> io::Result<Option<Self>> {
+        let mut file = File::open(path)?;
+        let mut magic = [0; 4];
+        match file.read_exact(&mut magic) {
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        if magic != *b"\x7fELF" {
+            return Ok(None);
+        }
+        Self::parse(&mut file)
+    }
+
+    fn parse(file: &mut (impl Read + Seek)) -> io::Result<Option<Self>> {
+        let header = read_at(file, 0, 64)?;
+        let (Some(&class), Some(&data)) = (header.get(4), header.get(5)) else {
+            return Ok(None);
+        };
+        let layout = Layout {
+            wide: class == 2,
+            big: data == 2,
+        };
+        let field = |wide, narrow, len| layout.int(&header, layout.at(wide, narrow), len);
+        let (Some(e_type), Some(machine)) = (layout.half(&header, 16), layout.half(&header, 18))
+        else {
+            return Ok(None);
+        };
+        let (Some(ph_offset), Some(ph_size), Some(ph_count)) = (
+            layout.addr(&header, layout.at(32, 28)),
+            field(54, 42, 2),
+            field(56, 44, 2),
+        ) else {
+            return Ok(None);
+        };
+        let (Some(sh_offset), Some(sh_size), Some(sh_count), Some(names_index)) = (
+            layout.addr(&header, layout.at(40, 32)),
+            field(58, 46, 2),
+            field(60, 48, 2),
+            field(62, 50, 2),
+        ) else {
+            return Ok(None);
+        };
+
+        // The interpreter and the dynamic section, from the program headers
+        let mut interpreter = None;
+        let mut flags = 0;
+        let programs = read_at(file, ph_offset, ph_size * ph_count)?;
+        for program in programs.chunks_exact(ph_size.max(1) as usize) {
+            let offset = layout.addr(program, layout.at(8, 4)).unwrap_or(0);
+            let size = layout.addr(program, layout.at(32, 16)).unwrap_or(0);
+            match layout.word(program, 0) {
+                Some(PT_INTERP) => {
+                    let path = read_at(file, offset, size)?;
+                    let path = path.split(|byte| *byte == 0).next().unwrap_or_default();
+                    interpreter = Some(String::from_utf8_lossy(path).into_owned());
+                }
+                Some(PT_DYNAMIC) => flags = dynamic_flags(file, layout, offset, size)?,
+                _ => {}
+            }
+        }
+
+        // The symbol table and debug sections, from the section headers and their names
+        let mut symbols = false;
+        let mut debug_info = false;
+        let sections = read_at(file, sh_offset, sh_size * sh_count)?;
+        // Files without a section table, or with one past their end, do not tell
+        let read_all = sh_offset > 0 && sh_count > 0 && sections.len() as u64 == sh_size * sh_count;
+        let sections: Vec<_> = sections.chunks_exact(sh_size.max(1) as usize).collect();
+        let names = match sections.get(names_index as usize) {
+            Some(names) => read_at(
+                file,
+                layout.addr(names, layout.at(24, 16)).unwrap_or(0),
+                layout.addr(names, layout.at(32, 20)).unwrap_or(0),
+            )?,
+            None => Vec::new(),
+        };
+        for section in &sections {
+            if layout.word(section, 4) == Some(SHT_SYMTAB) {
+                symbols = true;
+            }
+            let name = layout
+                .word(section, 0)
+                .and_then(|at| names.get(at as usize..));
+            let name = name.and_then(|name| name.split(|byte| *byte == 0).next());
+            if matches!(name, Some(b".debug_info" | b".zdebug_info")) {
+                debug_info = true;
+            }
+        }
+
+        let pie = e_type == 3 && (interpreter.is_some() || flags & DF_1_PIE != 0);
+        let kind = match e_type {
+            1 => ElfKind::Object,
+            2 => ElfKind::Executable,
+            3 if pie => ElfKind::Executable,
+            3 => ElfKind::SharedLibrary,
+            4 => ElfKind::Core,
+            _ => ElfKind::Other,
+        };
+        Ok(Some(Self {
+            arch: arch(machine, layout),
+            bits: if layout.wide { 64 } else { 32 },
+            big_endian: layout.big,
+            kind,
+            pie,
+            static_linked: kind == ElfKind::Executable && interpreter.is_none(),
+            interpreter,
+            stripped: (symbols || read_all).then_some(!symbols),
+            debug_info,
+        }))
+    }
+
+    /// The compact summary of the `binary` block, like `x86-64 pie stripped`
+    pub fn render(&self, colors: &Colors) -> ColoredString {
+        let linking = match (self.kind, self.static_linked, self.pie) {
+            (ElfKind::Executable, true, true) => "static-pie",
+            (ElfKind::Executable, true, false) => "static",
+            (ElfKind::Executable, false, true) => "pie",
+            (ElfKind::Executable, false, false) => "dyn",
+            (ElfKind::SharedLibrary, ..) => "so",
+            (ElfKind::Object, ..) => "obj",
+            (ElfKind::Core, ..) => "core",
+            (ElfKind::Other, ..) => "elf",
+        };
+        let mut parts = vec![
+            colors.colorize(self.arch, &Elem::Binary),
+            colors.colorize(
+                linking,
+                &match self.kind {
+                    ElfKind::Executable => Elem::Exec,
+                    _ => Elem::Read,
+                },
+            ),
+        ];
+        if self.debug_info {
+            parts.push(colors.colorize("debug", &Elem::Write));
+        } else if self.stripped == Some(true) {
+            parts.push(colors.colorize("stripped", &Elem::NoAccess));
+        }
+        let res = parts
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" ");
+        ColoredString::new(Colors::default_style(), res)
+    }
+
+    /// The `--llm` record of the headers
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut json = serde_json::json!({
+            "arch": self.arch,
+            "bits": self.bits,
+            "endian": if self.big_endian { "big" } else { "little" },
+            "type": self.kind.name(),
+            "pie": self.pie,
+            "static": self.static_linked,
+            "debug_info": self.debug_info,
+        });
+        if let Some(stripped) = self.stripped {
+            json["stripped"] = stripped.into();
+        }
+        if let Some(interpreter) = &self.interpreter {
+            json["interpreter"] = interpreter.as_str().into();
+        }
+        json
+    }
+}
+
+/// The `DT_FLAGS_1` entry of the dynamic section, 0 when there is none
+fn dynamic_flags(file: &mut (impl Read + Seek), layout: Layout, offset: u64, size: u64) -> io::Result<u64> {
+    let dynamic = read_at(file, offset, size)?;
+    let entry = layout.at(16, 8);
+    for entry in dynamic.chunks_exact(entry) {
+        match layout.addr(entry, 0) {
+            None | Some(0) => break,
+            Some(DT_FLAGS_1) => return Ok(layout.addr(entry, entry.len() / 2).unwrap_or(0)),
+            Some(_) => {}
+        }
+    }
+    Ok(0)
+}
+
+/// Up to `len` bytes at `offset`, fewer at the end of the file; none past [MAX_TABLE]
+fn read_at(file: &mut (impl Read + Seek), offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    if len > MAX_TABLE {
+        return Ok(Vec::new());
+    }
+    let mut bytes = Vec::with_capacity(len as usize);
+    file.seek(SeekFrom::Start(offset))?;
+    file.take(len).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// The name of an `e_machine`, with the class or byte order where they tell variants apart
+fn arch(machine: u64, layout: Layout) -> &'static str {
+    match (machine, layout.wide, layout.big) {
+        (2, ..) => "sparc",
+        (3, ..) => "x86",
+        (8, ..) => "mips",
+        (20, ..) => "ppc",
+        (21, _, false) => "ppc64le",
+        (21, _, true) => "ppc64",
+        (22, ..) => "s390x",
+        (40, ..) => "arm",
+        (43, ..) => "sparc64",
+        (50, ..) => "ia64",
+        (62, ..) => "x86-64",
+        (183, ..) => "aarch64",
+        (243, true, _) => "riscv64",
+        (243, false, _) => "riscv32",
+        (247, ..) => "bpf",
+        (258, ..) => "loongarch64",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::io::Cursor;
+
+    use tempfile::tempdir;
+
+    use super::{Elf, ElfKind, DF_1_PIE, DT_FLAGS_1, PT_DYNAMIC, PT_INTERP, SHT_SYMTAB};
+    use crate::color::{Colors, ThemeOption};
+
+    const SHT_PROGBITS: u64 = 1;
+    const SHT_STRTAB: u64 = 3;
+
+    /// Write `value` little-endian in `len` bytes at `at`
+    fn put(bytes: &mut Vec<u8>, at: usize, len: usize, value: u64) {
+        if bytes.len() < at + len {
+            bytes.resize(at + len, 0);
+        }
+        bytes[at..at + len].copy_from_slice(&value.to_le_bytes()[..len]);
+    }
+
+    /// A little-endian 64-bit x86-64 ELF file of type `e_type`, with an interpreter and a
+    /// dynamic section when given, and the named sections; without sections there is no
+    /// section table
+    fn elf64(e_type: u64, interpreter: Option<&str>, flags_1: Option<u64>, sections: &[(&str, u64)]) -> Vec<u8> {
+        let mut bytes = b"\x7fELF\x02\x01\x01".to_vec();
+        bytes.resize(64, 0);
+        put(&mut bytes, 16, 2, e_type);
+        put(&mut bytes, 18, 2, 62);
+        put(&mut bytes, 20, 4, 1);
+        put(&mut bytes, 52, 2, 64);
+
+        let programs = [interpreter.map(|_| PT_INTERP), flags_1.map(|_| PT_DYNAMIC)];
+        let programs: Vec<_> = programs.into_iter().flatten().collect();
+        put(&mut bytes, 32, 8, 64);
+        put(&mut bytes, 54, 2, 56);
+        put(&mut bytes, 56, 2, programs.len() as u64);
+        let mut data = 64 + 56 * programs.len();
+        for (index, kind) in programs.into_iter().enumerate() {
+            let header = 64 + 56 * index;
+            let body = match kind {
+                PT_INTERP => format!("{}\0", interpreter.unwrap_or_default()).into_bytes(),
+                _ => [
+                    &DT_FLAGS_1.to_le_bytes()[..],
+                    &flags_1.unwrap_or_default().to_le_bytes(),
+                    &[0; 16],
+                ]
+                .concat(),
+            };
+            put(&mut bytes, header, 4, kind);
+            put(&mut bytes, header + 8, 8, data as u64);
+            put(&mut bytes, header + 32, 8, body.len() as u64);
+            bytes.resize(data, 0);
+            bytes.extend_from_slice(&body);
+            data += body.len();
+        }
+        if sections.is_empty() {
+            return bytes;
+        }
+
+        // A null section, the given ones, then their names
+        let mut names = b"\0".to_vec();
+        let mut table = vec![(0, 0)];
+        for (name, kind) in sections.iter().copied().chain([(".shstrtab", SHT_STRTAB)]) {
+            table.push((names.len() as u64, kind));
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+        let names_at = bytes.len() as u64;
+        bytes.extend_from_slice(&names);
+        let table_at = bytes.len();
+        put(&mut bytes, 40, 8, table_at as u64);
+        put(&mut bytes, 58, 2, 64);
+        put(&mut bytes, 60, 2, table.len() as u64);
+        put(&mut bytes, 62, 2, table.len() as u64 - 1);
+        for (index, (name, kind)) in table.into_iter().enumerate() {
+            let header = table_at + 64 * index;
+            bytes.resize(header + 64, 0);
+            put(&mut bytes, header, 4, name);
+            put(&mut bytes, header + 4, 4, kind);
+            if kind == SHT_STRTAB {
+                put(&mut bytes, header + 24, 8, names_at);
+                put(&mut bytes, header + 32, 8, names.len() as u64);
+            }
+        }
+        bytes
+    }
+
+    fn parse(bytes: &[u8]) -> Option<Elf> {
+        Elf::parse(&mut Cursor::new(bytes)).unwrap()
+    }
+
+    fn render(elf: &Elf) -> String {
+        elf.render(&Colors::new(ThemeOption::NoColor)).to_string()
+    }
+
+    #[test]
+    fn test_pie_executable() {
+        let bytes = elf64(3, Some("/lib64/ld-linux-x86-64.so.2"), None, &[(".text", SHT_PROGBITS), (".symtab", SHT_SYMTAB)]);
+        let elf = parse(&bytes).unwrap();
+        assert_eq!(
+            elf,
+            Elf {
+                arch: "x86-64",
+                bits: 64,
+                big_endian: false,
+                kind: ElfKind::Executable,
+                pie: true,
+                static_linked: false,
+                interpreter: Some("/lib64/ld-linux-x86-64.so.2".to_string()),
+                stripped: Some(false),
+                debug_info: false,
+            }
+        );
+        assert_eq!(render(&elf), "x86-64 pie");
+        assert_eq!(
+            elf.to_json(),
+            serde_json::json!({
+                "arch": "x86-64",
+                "bits": 64,
+                "endian": "little",
+                "type": "executable",
+                "pie": true,
+                "static": false,
+                "stripped": false,
+                "debug_info": false,
+                "interpreter": "/lib64/ld-linux-x86-64.so.2",
+            })
+        );
+    }
+
+    #[test]
+    fn test_linking() {
+        let library = parse(&elf64(3, None, Some(0), &[(".text", SHT_PROGBITS)])).unwrap();
+        assert_eq!((library.kind, library.pie, library.static_linked), (ElfKind::SharedLibrary, false, false));
+        assert_eq!(render(&library), "x86-64 so stripped");
+
+        // The PIE flag without an interpreter
+        let static_pie = parse(&elf64(3, None, Some(DF_1_PIE), &[(".text", SHT_PROGBITS)])).unwrap();
+        assert_eq!((static_pie.kind, static_pie.pie, static_pie.static_linked), (ElfKind::Executable, true, true));
+        assert_eq!(render(&static_pie), "x86-64 static-pie stripped");
+
+        let executable = parse(&elf64(2, Some("/lib/ld.so"), None, &[])).unwrap();
+        assert_eq!(render(&executable), "x86-64 dyn");
+        assert_eq!(render(&parse(&elf64(2, None, None, &[])).unwrap()), "x86-64 static");
+        assert_eq!(render(&parse(&elf64(1, None, None, &[])).unwrap()), "x86-64 obj");
+        assert_eq!(render(&parse(&elf64(4, None, None, &[])).unwrap()), "x86-64 core");
+    }
+
+    #[test]
+    fn test_debug_info() {
+        let elf = parse(&elf64(1, None, None, &[(".debug_info", SHT_PROGBITS), (".symtab", SHT_SYMTAB)])).unwrap();
+        assert!(elf.debug_info);
+        assert_eq!(render(&elf), "x86-64 obj debug");
+        let compressed = parse(&elf64(1, None, None, &[(".zdebug_info", SHT_PROGBITS)])).unwrap();
+        assert!(compressed.debug_info);
+        assert!(!parse(&elf64(1, None, None, &[(".debug_line", SHT_PROGBITS)])).unwrap().debug_info);
+    }
+
+    #[test]
+    fn test_stripped_needs_the_section_table() {
+        // No section table at all
+        let elf = parse(&elf64(2, None, None, &[])).unwrap();
+        assert_eq!(elf.stripped, None);
+        assert_eq!(elf.to_json().get("stripped"), None);
+
+        // A table cut by the end of the file, without a symbol table in what is left
+        let bytes = elf64(2, None, None, &[(".text", SHT_PROGBITS), (".symtab", SHT_SYMTAB)]);
+        let elf = parse(&bytes[..bytes.len() - 100]).unwrap();
+        assert_eq!(elf.stripped, None);
+        assert_eq!(render(&elf), "x86-64 static");
+
+        // The symbol table was read before the cut
+        let bytes = elf64(2, None, None, &[(".symtab", SHT_SYMTAB), (".text", SHT_PROGBITS)]);
+        assert_eq!(parse(&bytes[..bytes.len() - 100]).unwrap().stripped, Some(false));
+    }
+
+    #[test]
+    fn test_big_endian_32_bit() {
+        let mut bytes = b"\x7fELF\x01\x02\x01".to_vec();
+        bytes.resize(52, 0);
+        bytes[16..20].copy_from_slice(&[0, 2, 0, 8]);
+        let elf = parse(&bytes).unwrap();
+        assert_eq!((elf.arch, elf.bits, elf.big_endian), ("mips", 32, true));
+        assert_eq!((elf.kind, elf.static_linked, elf.stripped), (ElfKind::Executable, true, None));
+        assert_eq!(elf.to_json()["endian"], "big");
+    }
+
+    #[test]
+    fn test_truncated_and_malformed_headers() {
+        assert_eq!(parse(b"\x7fELF"), None);
+        assert_eq!(parse(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x02\0"), None);
+        // Cut before the section table fields
+        assert_eq!(parse(&elf64(2, None, None, &[])[..60]), None);
+
+        // Program headers claiming more than is read are taken as corrupt
+        let mut bytes = elf64(3, Some("/lib/ld.so"), None, &[]);
+        put(&mut bytes, 56, 2, 0xffff);
+        put(&mut bytes, 54, 2, 0xffff);
+        let elf = parse(&bytes).unwrap();
+        assert_eq!((elf.interpreter, elf.kind), (None, ElfKind::SharedLibrary));
+
+        // An interpreter past the end of the file is empty
+        let mut bytes = elf64(3, Some("/lib/ld.so"), None, &[]);
+        put(&mut bytes, 64 + 8, 8, 1 << 20);
+        assert_eq!(parse(&bytes).unwrap().interpreter.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_read() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("binary");
+        fs::write(&path, elf64(3, Some("/lib/ld.so"), None, &[])).unwrap();
+        assert_eq!(Elf::read(&path).unwrap().map(|elf| elf.pie), Some(true));
+
+        fs::write(&path, b"#!/bin/sh\n").unwrap();
+        assert_eq!(Elf::read(&path).unwrap(), None);
+        fs::write(&path, b"\x7fE").unwrap();
+        assert_eq!(Elf::read(&path).unwrap(), None);
+        assert!(Elf::read(&dir.path().join("missing")).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use crate::color::{ColoredString, Colors, Elem};
+use std::fs::Metadata;
+use std::sync::OnceLock;
+
+/// The file system holding an entry, looked up in `/proc/self/mountinfo` when rendered
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Filesystem {
+    dev: Option<u64>,
+}
+
+impl From<&Metadata> for Filesystem {
+    #[cfg(unix)]
+    fn from(meta: &Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+
+        Self {
+            dev: Some(meta.dev()),
+        }
+    }
+
+    #[cfg(windows)]
+    fn from(_: &Metadata) -> Self {
+        Self { dev: None }
+    }
+}
+
+impl Filesystem {
+    pub fn new(dev: u64) -> Self {
+        Self { dev: Some(dev) }
+    }
+
+    pub fn render(&self, colors: &Colors) -> ColoredString {
+        match self.dev.and_then(Mount::for_device) {
+            Some(mount) => colors.colorize(
+                format!("{} {}", mount.fs_type, mount.source),
+                &Elem::Filesystem { valid: true },
+            ),
+            None => colors.colorize("?", &Elem::Filesystem { valid: false }),
+        }
+    }
+}
+
+/// The device, type and source of a line of `/proc/self/mountinfo`
+#[derive(Debug)]
+struct Mount {
+    dev: u64,
+    fs_type: String,
+    source: String,
+}
+
+impl Mount {
+    /// The mount of `dev`; the last one listed when it is mounted several times, as the later
+    /// mounts are the visible ones. The table is read once, and is empty outside Linux.
+    fn for_device(dev: u64) -> Option<&'static Mount> {
+        static MOUNTS: OnceLock<Vec<Mount>> = OnceLock::new();
+
+        MOUNTS
+            .get_or_init(|| {
+                std::fs::read_to_string("/proc/self/mountinfo")
+                    .map(|content| content.lines().filter_map(Self::parse).collect())
+                    .unwrap_or_default()
+            })
+            .iter()
+            .rev()
+            .find(|mount| mount.dev == dev)
+    }
+
+    /// Parse `36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue`;
+    /// the optional fields end at the ` - ` separator
+    fn parse(line: &str) -> Option<Self> {
+        let (fields, tail) = line.split_once(" - ")?;
+        let (major, minor) = fields.split(' ').nth(2)?.split_once(':')?;
+        let mut tail = tail.split(' ');
+
+        Some(Self {
+            dev: makedev(major.parse().ok()?, minor.parse().ok()?),
+            fs_type: unescape(tail.next()?),
+            source: unescape(tail.next()?),
+        })
+    }
+}
+
+/// The `dev_t` of a major and minor device number, as encoded by glibc
+fn makedev(major: u64, minor: u64) -> u64 {
+    ((major & 0xffff_f000) << 32)
+        | ((major & 0x0000_0fff) << 8)
+        | ((minor & 0xffff_ff00) << 12)
+        | (minor & 0x0000_00ff)
+}
+
+/// Decode the octal escapes (`\040` for a space) of a mountinfo field
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && let Some(byte) = field
+                .get(i + 1..i + 4)
+                .and_then(|octal| u8::from_str_radix(octal, 8).ok())
+        {
+            decoded.push(byte);
+            i += 4;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -23,6 +23,10 @@ impl From<&Metadata> for INode {
 }
 
 impl INode {
+    pub fn new(index: u64) -> Self {
+        Self { index: Some(index) }
+    }
+
     pub fn render(&self, colors: &Colors) -> ColoredString {
         match self.index {
             Some(i) => colors.colorize(format!(" {}", i), &Elem::INode { valid: true }),
@@ -25,6 +25,12 @@ impl From<&Metadata> for Links {
 }
 
 impl Links {
+    pub fn new(link_count: u64) -> Self {
+        Self {
+            link_count: Some(link_count),
+        }
+    }
+
     /// Renders the link count with appropriate styling
     pub fn render(&self, colors: &Colors) -> ColoredString {
         match self.link_count {
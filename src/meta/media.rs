@@ -0,0 +1,663 @@
+//! Media headers: the dimensions of images and the duration and sample rate of audio and video,
+//! read from the files without external tools
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use super::content_type::SNIFF_LEN;
+
+/// How many chunks, segments or boxes are skipped looking for the one with the fields
+const MAX_SKIPS: usize = 64;
+
+// Matroska elements, with their length markers
+const SEGMENT: u64 = 0x1853_8067;
+const INFO: u64 = 0x1549_a966;
+const TIMESTAMP_SCALE: u64 = 0x2a_d7b1;
+const DURATION: u64 = 0x4489;
+
+/// What the headers of an image, audio or video file tell about it
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Media {
+    /// Pixel dimensions of images, in user units for SVG view boxes
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Play time in seconds
+    pub duration: Option<f64>,
+    /// Audio samples per second
+    pub sample_rate: Option<u32>,
+}
+
+impl Media {
+    /// Read the headers of the file at `path`, of the sniffed `mime` type. Other types than
+    /// the known images, audio and video, and headers that do not parse, give `None`.
+    pub fn read(path: &Path, mime: &str) -> io::Result<Option<Self>> {
+        let header: fn(&[u8]) -> Option<Self> = match mime {
+            "image/png" => png,
+            "image/gif" => gif,
+            "image/webp" => webp,
+            "image/svg+xml" => svg,
+            "audio/flac" => flac,
+            "video/x-matroska" | "video/webm" => matroska,
+            // The fields come after segments of any size, which are skipped rather than read
+            "image/jpeg" => return jpeg(&mut BufReader::new(File::open(path)?)),
+            "audio/wav" => return wav(&mut BufReader::new(File::open(path)?)),
+            "video/mp4" | "video/quicktime" | "audio/mp4" => {
+                return iso_media(&mut BufReader::new(File::open(path)?));
+            }
+            _ => return Ok(None),
+        };
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        File::open(path)?
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut head)?;
+        Ok(header(&head))
+    }
+
+    fn image(width: u64, height: u64) -> Self {
+        Self {
+            width: Some(width as u32),
+            height: Some(height as u32),
+            ..Self::default()
+        }
+    }
+
+    /// The `--llm` record of the headers, with the duration rounded to milliseconds
+    pub fn to_json(self) -> serde_json::Value {
+        let mut json = serde_json::json!({});
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            json["width"] = width.into();
+            json["height"] = height.into();
+        }
+        if let Some(duration) = self.duration {
+            json["duration"] = ((duration * 1000.0).round() / 1000.0).into();
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            json["sample_rate"] = sample_rate.into();
+        }
+        json
+    }
+}
+
+impl fmt::Display for Media {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            parts.push(format!("{width}\u{d7}{height}"));
+        }
+        if let Some(duration) = self.duration {
+            let total = duration.round() as u64;
+            let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+            parts.push(if hours > 0 {
+                format!("{hours}:{minutes:02}:{seconds:02}")
+            } else {
+                format!("{minutes}:{seconds:02}")
+            });
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            parts.push(format!("{} kHz", f64::from(sample_rate) / 1000.0));
+        }
+        f.write_str(&parts.join(" "))
+    }
+}
+
+fn be(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |value, byte| (value << 8) | u64::from(*byte))
+}
+
+fn le(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, byte| (value << 8) | u64::from(*byte))
+}
+
+fn png(head: &[u8]) -> Option<Media> {
+    if head.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    Some(Media::image(be(head.get(16..20)?), be(head.get(20..24)?)))
+}
+
+fn gif(head: &[u8]) -> Option<Media> {
+    Some(Media::image(le(head.get(6..8)?), le(head.get(8..10)?)))
+}
+
+/// WebP images hold one of a lossy, a lossless or an extended header
+fn webp(head: &[u8]) -> Option<Media> {
+    match head.get(12..16)? {
+        b"VP8 " => Some(Media::image(
+            le(head.get(26..28)?) & 0x3fff,
+            le(head.get(28..30)?) & 0x3fff,
+        )),
+        b"VP8L" => {
+            let bits = le(head.get(21..25)?);
+            Some(Media::image(
+                (bits & 0x3fff) + 1,
+                ((bits >> 14) & 0x3fff) + 1,
+            ))
+        }
+        b"VP8X" => Some(Media::image(
+            le(head.get(24..27)?) + 1,
+            le(head.get(27..30)?) + 1,
+        )),
+        _ => None,
+    }
+}
+
+/// The size of the `viewBox` of the root element, or its `width` and `height` in pixels
+fn svg(head: &[u8]) -> Option<Media> {
+    let text = String::from_utf8_lossy(head);
+    let tag = &text[text.find("<svg")?..];
+    let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+    let size = |value: &str| {
+        let size = value.trim().trim_end_matches("px").parse::<f64>().ok()?;
+        (size.is_finite() && size >= 0.0).then(|| size.ceil() as u64)
+    };
+
+    if let Some(view_box) = attribute(tag, "viewBox") {
+        let numbers: Vec<_> = view_box
+            .split(|c: char| c.is_ascii_whitespace() || c == ',')
+            .filter(|number| !number.is_empty())
+            .collect();
+        if let [_, _, width, height] = numbers[..] {
+            return Some(Media::image(size(width)?, size(height)?));
+        }
+    }
+    Some(Media::image(
+        size(attribute(tag, "width")?)?,
+        size(attribute(tag, "height")?)?,
+    ))
+}
+
+/// The quoted value of the attribute `name` of an XML `tag`
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(at) = rest.find(name) {
+        let after = &rest[at + name.len()..];
+        if rest[..at].ends_with(|c: char| c.is_ascii_whitespace())
+            && let Some(value) = after.trim_start().strip_prefix('=')
+        {
+            let value = value.trim_start();
+            let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let value = &value[1..];
+            return value.find(quote).map(|end| &value[..end]);
+        }
+        rest = after;
+    }
+    None
+}
+
+/// The STREAMINFO block comes first, with the sample rate and sample count in one 64-bit field
+fn flac(head: &[u8]) -> Option<Media> {
+    if head.get(4)? & 0x7f != 0 {
+        return None;
+    }
+    let bits = be(head.get(18..26)?);
+    let sample_rate = (bits >> 44) as u32;
+    let samples = bits & 0xf_ffff_ffff;
+    Some(Media {
+        // A count of 0 is an unknown length
+        duration: (sample_rate > 0 && samples > 0).then(|| samples as f64 / f64::from(sample_rate)),
+        sample_rate: Some(sample_rate),
+        ..Media::default()
+    })
+}
+
+/// The Info element of the segment usually comes within the first few KiB, after the seek
+/// head; files with their Info further on are left without a duration
+fn matroska(head: &[u8]) -> Option<Media> {
+    let info = ebml_child(ebml_child(head, SEGMENT)?, INFO)?;
+    // Nanoseconds per tick of the duration
+    let mut scale = 1_000_000;
+    let mut ticks = None;
+    let mut rest = info;
+    while let Some((id, body, next)) = ebml_element(rest) {
+        match (id, body.len()) {
+            (TIMESTAMP_SCALE, _) => scale = be(body),
+            (DURATION, 4) => ticks = Some(f64::from(f32::from_bits(be(body) as u32))),
+            (DURATION, 8) => ticks = Some(f64::from_bits(be(body))),
+            _ => {}
+        }
+        rest = next;
+    }
+    Some(Media {
+        duration: Some(ticks? * scale as f64 / 1e9),
+        ..Media::default()
+    })
+}
+
+/// The body of the first element `id` among the EBML elements in `bytes`
+fn ebml_child(mut bytes: &[u8], id: u64) -> Option<&[u8]> {
+    while let Some((child, body, rest)) = ebml_element(bytes) {
+        if child == id {
+            return Some(body);
+        }
+        bytes = rest;
+    }
+    None
+}
+
+/// The id, the body and the bytes after the EBML element at the start of `bytes`. The body
+/// of an element of unknown size, or cut by the end of `bytes`, is all that is left of them.
+fn ebml_element(bytes: &[u8]) -> Option<(u64, &[u8], &[u8])> {
+    let (id, id_len) = ebml_vint(bytes, true)?;
+    let (size, size_len) = ebml_vint(bytes.get(id_len..)?, false)?;
+    let body = &bytes[id_len + size_len..];
+    let size = usize::try_from(size).unwrap_or(usize::MAX).min(body.len());
+    Some((id, &body[..size], &body[size..]))
+}
+
+/// An EBML variable-length integer and its length; ids keep their length marker
+fn ebml_vint(bytes: &[u8], marker: bool) -> Option<(u64, usize)> {
+    let first = *bytes.first()?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return None;
+    }
+    let first = if marker {
+        u64::from(first)
+    } else {
+        u64::from(first) & (0xff >> len)
+    };
+    let rest = bytes.get(1..len)?;
+    Some((
+        rest.iter()
+            .fold(first, |value, byte| (value << 8) | u64::from(*byte)),
+        len,
+    ))
+}
+
+/// JPEG dimensions are in the start-of-frame segment, after the application segments
+fn jpeg(reader: &mut BufReader<File>) -> io::Result<Option<Media>> {
+    let mut marker = [0; 2];
+    reader.read_exact(&mut marker)?;
+    for _ in 0..MAX_SKIPS {
+        let mut segment = [0; 4];
+        reader.read_exact(&mut segment)?;
+        if segment[0] != 0xff {
+            return Ok(None);
+        }
+        let len = be(&segment[2..4]) as i64;
+        match segment[1] {
+            // The start-of-frame markers, but for the Huffman and arithmetic coding tables
+            0xc0..=0xcf if !matches!(segment[1], 0xc4 | 0xc8 | 0xcc) => {
+                let mut frame = [0; 5];
+                reader.read_exact(&mut frame)?;
+                return Ok(Some(Media::image(be(&frame[3..5]), be(&frame[1..3]))));
+            }
+            // The scan starts, or the image ends, without a frame
+            0xd9 | 0xda => return Ok(None),
+            _ => reader.seek_relative(len - 2)?,
+        }
+    }
+    Ok(None)
+}
+
+/// WAV files hold a format chunk with the rates, and a data chunk whose size gives the duration
+fn wav(reader: &mut BufReader<File>) -> io::Result<Option<Media>> {
+    let mut header = [0; 12];
+    reader.read_exact(&mut header)?;
+    let mut media = Media::default();
+    let mut byte_rate = 0;
+    for _ in 0..MAX_SKIPS {
+        let mut chunk = [0; 8];
+        reader.read_exact(&mut chunk)?;
+        let size = le(&chunk[4..8]);
+        // Chunks are padded to an even size
+        let padded = (size + (size & 1)) as i64;
+        match &chunk[..4] {
+            b"fmt " => {
+                let mut format = [0; 16];
+                reader.read_exact(&mut format)?;
+                media.sample_rate = Some(le(&format[4..8]) as u32);
+                byte_rate = le(&format[8..12]);
+                reader.seek_relative(padded - 16)?;
+            }
+            b"data" => {
+                if byte_rate > 0 {
+                    media.duration = Some(size as f64 / byte_rate as f64);
+                }
+                return Ok(Some(media));
+            }
+            _ => reader.seek_relative(padded)?,
+        }
+    }
+    Ok(None)
+}
+
+/// MP4 and QuickTime files hold the duration in the movie header, the first box of the movie
+/// box, which comes before or after the media data
+fn iso_media(reader: &mut BufReader<File>) -> io::Result<Option<Media>> {
+    let Some(movie) = find_box(reader, b"moov", u64::MAX)? else {
+        return Ok(None);
+    };
+    if find_box(reader, b"mvhd", movie)?.is_none() {
+        return Ok(None);
+    }
+    let mut header = [0; 32];
+    reader.read_exact(&mut header)?;
+    // Version 1 headers have 64-bit times
+    let (timescale, duration) = match header[0] {
+        0 => (be(&header[12..16]), be(&header[16..20])),
+        1 => (be(&header[20..24]), be(&header[24..32])),
+        _ => return Ok(None),
+    };
+    Ok(Some(Media {
+        duration: (timescale > 0).then(|| duration as f64 / timescale as f64),
+        ..Media::default()
+    }))
+}
+
+/// Skip the boxes before the box `kind` within the next `within` bytes, and give the size of
+/// its body, which the reader is left at
+fn find_box(reader: &mut BufReader<File>, kind: &[u8], mut within: u64) -> io::Result<Option<u64>> {
+    for _ in 0..MAX_SKIPS {
+        if within < 8 {
+            return Ok(None);
+        }
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        let (size, header_len) = match be(&header[..4]) {
+            // A 64-bit size follows the type
+            1 => {
+                let mut size = [0; 8];
+                reader.read_exact(&mut size)?;
+                (be(&size), 16)
+            }
+            // The box runs to the end of its parent
+            0 => (within, 8),
+            size => (size, 8),
+        };
+        if size < header_len || size > within {
+            return Ok(None);
+        }
+        if &header[4..] == kind {
+            return Ok(Some(size - header_len));
+        }
+        let Ok(skip) = i64::try_from(size - header_len) else {
+            return Ok(None);
+        };
+        reader.seek_relative(skip)?;
+        within -= size;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::{flac, gif, matroska, png, svg, webp, Media};
+
+    /// The headers of a file holding `bytes`, `None` when reading them failed like the content
+    /// sniffer takes it
+    fn read(bytes: &[u8], mime: &str) -> Option<Media> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("media");
+        fs::write(&path, bytes).unwrap();
+        Media::read(&path, mime).ok().flatten()
+    }
+
+    fn size(media: Option<Media>) -> Option<(u32, u32)> {
+        let media = media?;
+        Some((media.width?, media.height?))
+    }
+
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&[8, 6, 0, 0, 0]);
+        bytes
+    }
+
+    /// An EBML element with a one-byte size
+    fn ebml(id: &[u8], body: &[u8]) -> Vec<u8> {
+        [id, &[0x80 | body.len() as u8], body].concat()
+    }
+
+    /// An ISO base media box
+    fn iso_box(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        [&(body.len() as u32 + 8).to_be_bytes()[..], kind, body].concat()
+    }
+
+    /// A RIFF chunk, padded to an even size
+    fn chunk(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let padding: &[u8] = if body.len() % 2 == 1 { &[0] } else { &[] };
+        [kind, &(body.len() as u32).to_le_bytes()[..], body, padding].concat()
+    }
+
+    fn wav_format(rate: u32, channels: u16, bits: u16) -> Vec<u8> {
+        let block = channels * bits / 8;
+        [
+            &1u16.to_le_bytes()[..],
+            &channels.to_le_bytes(),
+            &rate.to_le_bytes(),
+            &(rate * u32::from(block)).to_le_bytes(),
+            &block.to_le_bytes(),
+            &bits.to_le_bytes(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_png() {
+        assert_eq!(size(png(&png_header(640, 480))), Some((640, 480)));
+        assert_eq!(size(read(&png_header(1, 2), "image/png")), Some((1, 2)));
+        // Cut in the height, or without the header chunk first
+        assert_eq!(png(&png_header(640, 480)[..22]), None);
+        let mut other = png_header(640, 480);
+        other[12..16].copy_from_slice(b"IDAT");
+        assert_eq!(png(&other), None);
+    }
+
+    #[test]
+    fn test_gif() {
+        assert_eq!(size(gif(b"GIF89a\x40\x01\xf0\0\xf7\0\0")), Some((320, 240)));
+        assert_eq!(gif(b"GIF89a\x40\x01\xf0"), None);
+    }
+
+    #[test]
+    fn test_webp() {
+        let riff = |chunk: &[u8], body: &[u8]| [b"RIFF\0\0\0\0WEBP", chunk, b"\0\0\0\0", body].concat();
+        // Lossy frames keep a scale in the top bits of their dimensions
+        let lossy = riff(b"VP8 ", b"\0\0\0\x9d\x01\x2a\x64\x40\x4b\0");
+        assert_eq!(size(webp(&lossy)), Some((100, 75)));
+        let bits: u32 = 399 | (299 << 14);
+        let lossless = riff(b"VP8L", &[&[0x2f][..], &bits.to_le_bytes()].concat());
+        assert_eq!(size(webp(&lossless)), Some((400, 300)));
+        let extended = riff(b"VP8X", b"\x10\0\0\0\xff\x03\0\xff\x02\0");
+        assert_eq!(size(webp(&extended)), Some((1024, 768)));
+        assert_eq!(size(read(&extended, "image/webp")), Some((1024, 768)));
+
+        assert_eq!(webp(&lossy[..28]), None);
+        assert_eq!(webp(&riff(b"ALPH", b"\0\0\0\0\0\0\0\0\0\0")), None);
+        assert_eq!(webp(b"RIFF\0\0\0\0WEBP"), None);
+    }
+
+    #[test]
+    fn test_svg() {
+        let svg = |text: &str| size(svg(text.as_bytes()));
+        assert_eq!(svg(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24">"#), Some((24, 24)));
+        assert_eq!(svg(r#"<?xml version="1.0"?><svg viewBox="0,0,10.5,20">"#), Some((11, 20)));
+        // The view box wins over the size it is shown at
+        assert_eq!(svg(r#"<svg width="100" height="50" viewBox="0 0 10 5">"#), Some((10, 5)));
+        assert_eq!(svg(r#"<svg width="100.5px" height='50'>"#), Some((101, 50)));
+        // A tag cut by the end of the sniffed bytes
+        assert_eq!(svg(r#"<svg width="30" height="40""#), Some((30, 40)));
+        // Attributes that only end with the name
+        assert_eq!(svg(r#"<svg data-width="1" data-height="2">"#), None);
+        assert_eq!(svg(r#"<svg width="-1" height="2">"#), None);
+        assert_eq!(svg(r#"<svg width="100%" height="100%">"#), None);
+        assert_eq!(svg(r#"<svg viewBox="0 0 24">"#), None);
+        assert_eq!(svg("<html></html>"), None);
+    }
+
+    #[test]
+    fn test_flac() {
+        let stream = |rate: u64, samples: u64| {
+            let bits = (rate << 44) | (1 << 41) | (15 << 36) | samples;
+            [b"fLaC\x80\0\0\x22".as_slice(), &[0; 10], &bits.to_be_bytes(), &[0; 16]].concat()
+        };
+        let media = flac(&stream(44_100, 441_000)).unwrap();
+        assert_eq!(media.sample_rate, Some(44_100));
+        assert_eq!(media.duration, Some(10.0));
+        assert_eq!(read(&stream(48_000, 24_000), "audio/flac").unwrap().duration, Some(0.5));
+
+        // An unknown length
+        let media = flac(&stream(44_100, 0)).unwrap();
+        assert_eq!((media.sample_rate, media.duration), (Some(44_100), None));
+        // Cut in the sample count, or another block first
+        assert_eq!(flac(&stream(44_100, 441_000)[..24]), None);
+        let mut padding = stream(44_100, 441_000);
+        padding[4] = 0x81;
+        assert_eq!(flac(&padding), None);
+    }
+
+    #[test]
+    fn test_matroska() {
+        let header = ebml(b"\x1a\x45\xdf\xa3", &ebml(b"\x42\x82", b"webm"));
+        let file = |info: &[u8]| [header.clone(), ebml(b"\x18\x53\x80\x67", &ebml(b"\x15\x49\xa9\x66", info))].concat();
+        let scale = ebml(b"\x2a\xd7\xb1", &1_000_000u32.to_be_bytes()[1..]);
+        let duration = ebml(b"\x44\x89", &5000.0f64.to_bits().to_be_bytes());
+        assert_eq!(matroska(&file(&[scale.clone(), duration.clone()].concat())).unwrap().duration, Some(5.0));
+
+        // Single precision durations, in ticks of a custom scale
+        let short = ebml(b"\x44\x89", &1500.0f32.to_bits().to_be_bytes());
+        let milli = ebml(b"\x2a\xd7\xb1", &[0x0f, 0x42, 0x40]);
+        let media = read(&file(&[milli, short].concat()), "video/webm").unwrap();
+        assert_eq!(media.duration, Some(1.5));
+
+        // A segment of unknown size runs to the end of the bytes
+        let unknown = [
+            header.clone(),
+            b"\x18\x53\x80\x67\x01\xff\xff\xff\xff\xff\xff\xff".to_vec(),
+            ebml(b"\x15\x49\xa9\x66", &duration),
+        ]
+        .concat();
+        assert_eq!(matroska(&unknown).unwrap().duration, Some(5.0));
+
+        // Without a duration, or with one cut short
+        assert_eq!(matroska(&file(&scale)), None);
+        let whole = file(&duration);
+        assert_eq!(matroska(&whole[..whole.len() - 3]), None);
+        assert_eq!(matroska(&header), None);
+        assert_eq!(matroska(b"\x1a\x45"), None);
+    }
+
+    #[test]
+    fn test_jpeg() {
+        let app0 = [b"\xff\xe0\0\x10JFIF\0".as_slice(), &[0; 9]].concat();
+        let huffman = b"\xff\xc4\0\x04\0\0".to_vec();
+        let frame = b"\xff\xc0\0\x11\x08\x01\xe0\x02\x80\x03".to_vec();
+        let jpeg = |segments: &[&[u8]]| [b"\xff\xd8".as_slice(), &segments.concat()].concat();
+
+        let media = read(&jpeg(&[&app0, &huffman, &frame, &[0; 12]]), "image/jpeg");
+        assert_eq!(size(media), Some((640, 480)));
+        // Progressive frames have the same fields
+        let progressive = b"\xff\xc2\0\x11\x08\0\x10\0\x20\x03";
+        assert_eq!(size(read(&jpeg(&[progressive]), "image/jpeg")), Some((32, 16)));
+
+        // The scan starts first, a segment is not where its length says, or the file ends
+        assert_eq!(read(&jpeg(&[&app0, b"\xff\xda\0\x02"]), "image/jpeg"), None);
+        assert_eq!(read(&jpeg(&[b"\xff\xe0\0\x04\0", &frame]), "image/jpeg"), None);
+        assert_eq!(read(&jpeg(&[&app0]), "image/jpeg"), None);
+        assert_eq!(read(&jpeg(&[&app0, &frame[..7]]), "image/jpeg"), None);
+    }
+
+    #[test]
+    fn test_wav() {
+        let wav = |chunks: &[Vec<u8>]| [b"RIFF\0\0\0\0WAVE".as_slice(), &chunks.concat()].concat();
+        let format = chunk(b"fmt ", &wav_format(44_100, 2, 16));
+        let data = [b"data".as_slice(), &352_800u32.to_le_bytes()].concat();
+
+        let media = read(&wav(&[format.clone(), chunk(b"LIST", b"odd"), data.clone()]), "audio/wav").unwrap();
+        assert_eq!(media.sample_rate, Some(44_100));
+        assert_eq!(media.duration, Some(2.0));
+        // A format chunk with an extension after the fields
+        let extended = chunk(b"fmt ", &[wav_format(8_000, 1, 8), vec![0, 0]].concat());
+        let media = read(&wav(&[extended, data.clone()]), "audio/wav").unwrap();
+        assert_eq!((media.sample_rate, media.duration), (Some(8_000), Some(44.1)));
+
+        // The data without a format has no duration
+        let media = read(&wav(&[data]), "audio/wav").unwrap();
+        assert_eq!((media.sample_rate, media.duration), (None, None));
+        // Cut in the format chunk, or before the data
+        assert_eq!(read(&wav(&[format[..20].to_vec()]), "audio/wav"), None);
+        assert_eq!(read(&wav(&[format]), "audio/wav"), None);
+        assert_eq!(read(b"RIFF\0\0", "audio/wav"), None);
+    }
+
+    #[test]
+    fn test_iso_media() {
+        let ftyp = iso_box(b"ftyp", b"isom\0\0\x02\0isom");
+        let movie_header = |version: u8, timescale: u32, duration: u64| {
+            let mut body = vec![version, 0, 0, 0];
+            if version == 1 {
+                body.extend_from_slice(&[0; 16]);
+                body.extend_from_slice(&timescale.to_be_bytes());
+                body.extend_from_slice(&duration.to_be_bytes());
+            } else {
+                body.extend_from_slice(&[0; 8]);
+                body.extend_from_slice(&timescale.to_be_bytes());
+                body.extend_from_slice(&(duration as u32).to_be_bytes());
+            }
+            body.resize(100, 0);
+            iso_box(b"moov", &[iso_box(b"mvhd", &body), iso_box(b"trak", &[0; 8])].concat())
+        };
+        let duration = |bytes: &[u8]| read(bytes, "video/mp4").and_then(|media| media.duration);
+
+        let mdat = iso_box(b"mdat", &[0; 32]);
+        let moov = movie_header(0, 1000, 90_500);
+        assert_eq!(duration(&[ftyp.clone(), mdat.clone(), moov.clone()].concat()), Some(90.5));
+        assert_eq!(duration(&[ftyp.clone(), moov.clone(), mdat].concat()), Some(90.5));
+        assert_eq!(duration(&[ftyp.clone(), movie_header(1, 600, 1_800)].concat()), Some(3.0));
+        // A media data box with a 64-bit size
+        let large = [&1u32.to_be_bytes()[..], b"mdat", &24u64.to_be_bytes(), &[0; 8]].concat();
+        assert_eq!(duration(&[ftyp.clone(), large, moov.clone()].concat()), Some(90.5));
+
+        // An unknown header version, a zero timescale, a box larger than the file, no movie
+        assert_eq!(duration(&[ftyp.clone(), movie_header(2, 1000, 1)].concat()), None);
+        assert_eq!(duration(&[ftyp.clone(), movie_header(0, 0, 1)].concat()), None);
+        assert_eq!(duration(&[ftyp.clone(), moov[..40].to_vec()].concat()), None);
+        assert_eq!(duration(&ftyp), None);
+        let broken = [&4u32.to_be_bytes()[..], b"free"].concat();
+        assert_eq!(duration(&[ftyp, broken, moov].concat()), None);
+    }
+
+    #[test]
+    fn test_unknown_types_are_not_read() {
+        assert_eq!(read(&png_header(1, 1), "text/plain"), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let image = Media::image(640, 480);
+        assert_eq!(image.to_string(), "640\u{d7}480");
+        let audio = Media {
+            duration: Some(90.4),
+            sample_rate: Some(44_100),
+            ..Media::default()
+        };
+        assert_eq!(audio.to_string(), "1:30 44.1 kHz");
+        let video = Media {
+            duration: Some(3725.0),
+            ..Media::image(1920, 1080)
+        };
+        assert_eq!(video.to_string(), "1920\u{d7}1080 1:02:05");
+        assert_eq!(
+            Media {
+                duration: Some(1.23456),
+                ..Media::default()
+            }
+            .to_json(),
+            serde_json::json!({"duration": 1.235})
+        );
+    }
+}
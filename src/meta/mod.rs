@@ -1,11 +1,15 @@
 mod access_control;
+mod content_type;
 mod date;
+mod elf;
+mod filesystem;
 mod filetype;
 pub mod git_file_status;
 mod indicator;
 mod inode;
 mod links;
 mod locale;
+mod media;
 pub mod name;
 pub mod owner;
 mod permissions;
@@ -19,12 +23,16 @@ mod windows_attributes;
 mod windows_utils;
 
 pub use self::access_control::AccessControl;
-pub use self::date::Date;
+pub use self::content_type::ContentType;
+pub use self::date::{time_of, Date};
+pub use self::elf::Elf;
+pub use self::filesystem::Filesystem;
 pub use self::filetype::FileType;
 pub use self::git_file_status::GitFileStatus;
 pub use self::indicator::Indicator;
 pub use self::inode::INode;
 pub use self::links::Links;
+pub use self::media::Media;
 pub use self::name::Name;
 pub use self::owner::{Cache as OwnerCache, Owner};
 pub use self::permissions::Permissions;
@@ -60,6 +68,15 @@ pub struct Meta {
     pub indicator: Indicator,
     pub inode: Option<INode>,
     pub links: Option<Links>,
+    pub filesystem: Option<Filesystem>,
+    /// Lines matching the `--contains` pattern, for the files that were searched
+    pub matches: Option<usize>,
+    /// Digest of the contents, or fingerprint of a directory, with the `hash` block
+    pub hash: Option<String>,
+    /// Dimensions and duration of images, audio and video, with the `media` block
+    pub media: Option<Media>,
+    /// Architecture, linking and symbols of ELF files, with the `binary` block
+    pub binary: Option<Elf>,
     pub content: Option<Vec<Meta>>,
     pub access_control: Option<AccessControl>,
     pub git_status: Option<GitFileStatus>,
@@ -92,7 +109,7 @@ impl Meta {
             .sort(true)
             .skip_hidden(false)
             .follow_links(false)
-            .parallelism(jwalk::Parallelism::RayonNewPool(0));
+            .parallelism(flags.threads.parallelism());
 
         // Pre-compile filtering predicates for better performance
         let flags = Arc::new(flags.clone());
@@ -309,64 +326,6 @@ impl Meta {
         Ok(entries)
     }
 
-    #[allow(dead_code)] // Used by old code path
-    pub fn calculate_total_size(&mut self) {
-        if self.size.is_none() || !matches!(self.file_type, FileType::Directory { .. }) {
-            return;
-        }
-
-        let base_size = self.size.as_ref().map_or(0, |s| s.get_bytes());
-
-        if let Some(ref mut metas) = self.content {
-            let total_size = metas
-                .iter_mut()
-                .filter(|meta| !matches!(meta.name.name.as_str(), "." | ".."))
-                .map(|meta| {
-                    meta.calculate_total_size();
-                    meta.size.as_ref().map_or(0, |s| s.get_bytes())
-                })
-                .fold(base_size, |acc, size| acc.saturating_add(size));
-
-            self.size = Some(Size::new(total_size));
-        } else {
-            // Depth limited the recursion in 'recurse_into'
-            self.size = Some(Size::new(Self::calculate_total_file_size(&self.path)));
-        }
-    }
-
-    #[allow(dead_code)] // Used by old code path
-    fn calculate_total_file_size(path: &Path) -> u64 {
-        let metadata = match path.symlink_metadata() {
-            Ok(meta) => meta,
-            Err(err) => {
-                print_error!("{}: {}.", path.display(), err);
-                return 0;
-            }
-        };
-
-        let file_type = metadata.file_type();
-        if file_type.is_file() {
-            metadata.len()
-        } else if file_type.is_dir() {
-            let mut size = metadata.len();
-
-            let entries = match path.read_dir() {
-                Ok(entries) => entries,
-                Err(err) => {
-                    print_error!("{}: {}.", path.display(), err);
-                    return size;
-                }
-            };
-
-            for entry in entries.flatten() {
-                size = size.saturating_add(Self::calculate_total_file_size(&entry.path()));
-            }
-            size
-        } else {
-            0
-        }
-    }
-
     pub fn from_path(
         path: &Path,
         dereference: bool,
@@ -446,6 +405,11 @@ impl Meta {
             Ok(Self {
                 inode: None,
                 links: None,
+                filesystem: None,
+                matches: None,
+                hash: None,
+                media: None,
+                binary: None,
                 path: path.to_path_buf(),
                 symlink: SymLink::from(path),
                 size: None,
@@ -463,6 +427,11 @@ impl Meta {
             Ok(Self {
                 inode: Some(INode::from(&metadata)),
                 links: Some(Links::from(&metadata)),
+                filesystem: Some(Filesystem::from(&metadata)),
+                matches: None,
+                hash: None,
+                media: None,
+                binary: None,
                 path: path.to_path_buf(),
                 symlink: SymLink::from(path),
                 size: Some(Size::from(&metadata)),
@@ -506,7 +475,8 @@ fn process_entry(
         Display::All | Display::AlmostAll if is_system => return None,
         Display::VisibleOnly => {
             // Apply ignore globs filter only when showing visible files only (default mode)
-            if flags.ignore_globs.is_match(name) {
+            let relative = std::path::Path::new(name);
+            if flags.ignore_globs.is_ignored(&Default::default(), relative, path.is_dir()) {
                 return None;
             }
             if is_hidden || is_system {
@@ -2,6 +2,7 @@ use crate::color::{ColoredString, Colors};
 use crate::flags::HyperlinkOption;
 use crate::icon::Icons;
 use crate::meta::filetype::FileType;
+use crate::meta::ContentType;
 use crate::meta::GitFileStatus;
 use crate::print_error;
 use url::Url;
@@ -25,6 +26,8 @@ pub struct Name {
     path: PathBuf,
     extension: Option<String>,
     file_type: FileType,
+    /// The sniffed type of the contents, when a block or the sort column asked for it
+    content_type: Option<ContentType>,
 }
 
 impl Name {
@@ -44,9 +47,16 @@ impl Name {
             path: PathBuf::from(path),
             extension,
             file_type,
+            content_type: None,
         }
     }
 
+    /// Set the sniffed type of the contents, which icons and render rules fall back on
+    pub fn with_content_type(mut self, content_type: Option<ContentType>) -> Self {
+        self.content_type = content_type;
+        self
+    }
+
     /// Returns the file name as a string slice
     pub fn file_name(&self) -> &str {
         self.path
@@ -178,6 +188,7 @@ impl Name {
         let decision = colors.render_decision(
             &self.file_type,
             self.extension.as_deref(),
+            self.content_type.as_ref().map(|content_type| content_type.mime),
             git_status,
             false, // has_error - future feature
             false, // draw_attention - future feature
@@ -211,6 +222,11 @@ impl Name {
     pub fn file_type(&self) -> FileType {
         self.file_type
     }
+
+    /// Returns the sniffed type of the contents, if they were sniffed
+    pub fn content_type(&self) -> Option<&ContentType> {
+        self.content_type.as_ref()
+    }
 }
 
 impl Ord for Name {
@@ -32,6 +32,12 @@ impl Owner {
     pub fn new(user: String, group: String) -> Self {
         Self { user, group }
     }
+
+    /// Build the owner from raw ids, e.g. stat data kept in the scan cache.
+    #[cfg(unix)]
+    pub fn new(user: u32, group: u32) -> Self {
+        Self { user, group }
+    }
 }
 
 #[cfg(unix)]
@@ -26,7 +26,19 @@ impl From<&Metadata> for Permissions {
     fn from(meta: &Metadata) -> Self {
         use std::os::unix::fs::PermissionsExt;
 
-        let bits = meta.permissions().mode();
+        Self::from_mode(meta.permissions().mode())
+    }
+
+    #[cfg(windows)]
+    fn from(_: &Metadata) -> Self {
+        panic!("Cannot get permissions from metadata on Windows")
+    }
+}
+
+impl Permissions {
+    /// Build permissions from raw unix mode bits, e.g. a git tree entry mode.
+    #[cfg(unix)]
+    pub fn from_mode(bits: u32) -> Self {
         let has_bit = |bit| bits & bit == bit;
 
         Self {
@@ -48,13 +60,6 @@ impl From<&Metadata> for Permissions {
         }
     }
 
-    #[cfg(windows)]
-    fn from(_: &Metadata) -> Self {
-        panic!("Cannot get permissions from metadata on Windows")
-    }
-}
-
-impl Permissions {
     fn bits_to_octal(r: bool, w: bool, x: bool) -> u8 {
         (r as u8) * 4 + (w as u8) * 2 + (x as u8)
     }
@@ -28,7 +28,6 @@ impl From<&Metadata> for Size {
 }
 
 impl Size {
-    #[allow(dead_code)] // Used by old code path
     pub fn new(bytes: u64) -> Self {
         Self { bytes }
     }
@@ -3,7 +3,7 @@ use crate::flags::Flags;
 use std::fs::read_link;
 use std::path::{Path, PathBuf};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct SymLink {
     target: Option<PathBuf>,
     valid: bool,
@@ -36,6 +36,14 @@ impl From<&Path> for SymLink {
 }
 
 impl SymLink {
+    /// Build a symlink from an already known target, e.g. a git blob holding the link path.
+    pub fn from_target(target: PathBuf) -> Self {
+        Self {
+            target: Some(target),
+            valid: true,
+        }
+    }
+
     pub fn symlink_string(&self) -> Option<String> {
         self.target
             .as_ref()
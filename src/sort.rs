@@ -1,5 +1,6 @@
-use crate::flags::{DirGrouping, Flags, SortColumn, SortOrder};
+use crate::flags::{DirGrouping, Flags, SortColumn, SortOrder, TimeField};
 use crate::meta::Meta;
+use crate::stream::FileEntry;
 use std::cmp::Ordering;
 use vsort::compare;
 
@@ -24,6 +25,7 @@ pub fn assemble_sorters(flags: &Flags) -> Vec<(SortOrder, SortFn)> {
         SortColumn::Version => sorters.push((flags.sorting.order, by_version)),
         SortColumn::Extension => sorters.push((flags.sorting.order, by_extension)),
         SortColumn::GitStatus => sorters.push((flags.sorting.order, by_git_status)),
+        SortColumn::Mime => sorters.push((flags.sorting.order, by_mime)),
         SortColumn::None => {}
     }
     sorters
@@ -76,3 +78,237 @@ fn by_extension(a: &Meta, b: &Meta) -> Ordering {
 fn by_git_status(a: &Meta, b: &Meta) -> Ordering {
     a.git_status.cmp(&b.git_status)
 }
+
+/// Entries without a sniffed type, like directories, go first
+fn by_mime(a: &Meta, b: &Meta) -> Ordering {
+    let mime = |meta: &Meta| meta.name.content_type().map(|content_type| content_type.mime);
+    mime(a).cmp(&mime(b)).then(a.name.cmp(&b.name))
+}
+
+pub type EntrySortFn = fn(&FileEntry, &FileEntry) -> Ordering;
+
+/// The sorters of [assemble_sorters] applied to [FileEntry] keys, so the `FileStream` sources
+/// can yield siblings in display order and streaming output can print entries as they arrive
+#[derive(Clone, Debug)]
+pub struct EntryOrder(Vec<(SortOrder, EntrySortFn)>);
+
+impl EntryOrder {
+    pub fn from_flags(flags: &Flags) -> Self {
+        let mut sorters: Vec<(SortOrder, EntrySortFn)> = vec![];
+        match flags.sorting.dir_grouping {
+            DirGrouping::First => sorters.push((SortOrder::Default, entry_with_dirs_first)),
+            DirGrouping::Last => sorters.push((SortOrder::Reverse, entry_with_dirs_first)),
+            DirGrouping::None => {}
+        };
+
+        match flags.sorting.column {
+            SortColumn::Name => sorters.push((flags.sorting.order, entry_by_name)),
+            SortColumn::Size => sorters.push((flags.sorting.order, entry_by_size)),
+            SortColumn::Time => {
+                let by_time: EntrySortFn = match flags.time {
+                    TimeField::Modified => entry_by_modified,
+                    TimeField::Accessed => entry_by_accessed,
+                    TimeField::Changed => entry_by_changed,
+                    TimeField::Birth => entry_by_birth,
+                };
+                sorters.push((flags.sorting.order, by_time))
+            }
+            SortColumn::Version => sorters.push((flags.sorting.order, entry_by_version)),
+            SortColumn::Extension => sorters.push((flags.sorting.order, entry_by_extension)),
+            SortColumn::GitStatus => sorters.push((flags.sorting.order, entry_by_git_status)),
+            SortColumn::Mime => sorters.push((flags.sorting.order, entry_by_mime)),
+            SortColumn::None => {}
+        }
+        // Keep the walk deterministic when names only differ by case, unless the walk order
+        // itself was asked for
+        if flags.sorting.column != SortColumn::None {
+            sorters.push((SortOrder::Default, entry_by_exact_name));
+        }
+        Self(sorters)
+    }
+
+    pub fn cmp(&self, a: &FileEntry, b: &FileEntry) -> Ordering {
+        for (direction, sorter) in self.0.iter() {
+            match (sorter)(a, b) {
+                Ordering::Equal => continue,
+                ordering => {
+                    return match direction {
+                        SortOrder::Reverse => ordering.reverse(),
+                        SortOrder::Default => ordering,
+                    }
+                }
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+impl Default for EntryOrder {
+    fn default() -> Self {
+        Self(vec![(SortOrder::Default, entry_by_name), (SortOrder::Default, entry_by_exact_name)])
+    }
+}
+
+fn entry_with_dirs_first(a: &FileEntry, b: &FileEntry) -> Ordering {
+    b.file_type.is_dirlike().cmp(&a.file_type.is_dirlike())
+}
+
+/// Largest first; entries without a size go first, like [by_size]
+fn entry_by_size(a: &FileEntry, b: &FileEntry) -> Ordering {
+    match (a.size_bytes(), b.size_bytes()) {
+        (Some(a_size), Some(b_size)) => b_size.cmp(&a_size),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn entry_by_name(a: &FileEntry, b: &FileEntry) -> Ordering {
+    a.name.to_lowercase().cmp(&b.name.to_lowercase())
+}
+
+fn entry_by_exact_name(a: &FileEntry, b: &FileEntry) -> Ordering {
+    a.name.cmp(&b.name)
+}
+
+/// Newest first; entries without the timestamp go last
+fn entry_by_time(a: &FileEntry, b: &FileEntry, field: TimeField) -> Ordering {
+    b.time(field)
+        .cmp(&a.time(field))
+        .then_with(|| entry_by_name(a, b))
+}
+
+fn entry_by_modified(a: &FileEntry, b: &FileEntry) -> Ordering {
+    entry_by_time(a, b, TimeField::Modified)
+}
+
+fn entry_by_accessed(a: &FileEntry, b: &FileEntry) -> Ordering {
+    entry_by_time(a, b, TimeField::Accessed)
+}
+
+fn entry_by_changed(a: &FileEntry, b: &FileEntry) -> Ordering {
+    entry_by_time(a, b, TimeField::Changed)
+}
+
+fn entry_by_birth(a: &FileEntry, b: &FileEntry) -> Ordering {
+    entry_by_time(a, b, TimeField::Birth)
+}
+
+fn entry_by_version(a: &FileEntry, b: &FileEntry) -> Ordering {
+    compare(&a.name, &b.name)
+}
+
+fn entry_by_extension(a: &FileEntry, b: &FileEntry) -> Ordering {
+    let extension = |entry: &FileEntry| {
+        std::path::Path::new(&entry.name)
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+    };
+    extension(a).cmp(&extension(b))
+}
+
+fn entry_by_git_status(a: &FileEntry, b: &FileEntry) -> Ordering {
+    a.git_status.cmp(&b.git_status)
+}
+
+fn entry_by_mime(a: &FileEntry, b: &FileEntry) -> Ordering {
+    let mime = |entry: &FileEntry| entry.content_type.as_ref().map(|content_type| content_type.mime);
+    mime(a).cmp(&mime(b)).then_with(|| entry_by_name(a, b))
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use futures::StreamExt;
+    use tempfile::tempdir;
+
+    use super::EntryOrder;
+    use crate::flags::{Flags, SortColumn, SortOrder};
+    use crate::meta::ContentType;
+    use crate::git::GitStatus;
+    use crate::meta::GitFileStatus;
+    use crate::stream::{test_walk, FileEntry};
+
+    /// The children of `root`, in walk order
+    fn children(root: &Path) -> Vec<FileEntry> {
+        let entries = futures::executor::block_on(test_walk(root).collect::<Vec<_>>());
+        entries.into_iter().skip(1).map(Result::unwrap).collect()
+    }
+
+    fn sorted(entries: Vec<FileEntry>, column: SortColumn) -> Vec<String> {
+        sorted_in(entries, column, SortOrder::Default)
+    }
+
+    fn sorted_in(mut entries: Vec<FileEntry>, column: SortColumn, order: SortOrder) -> Vec<String> {
+        let mut flags = Flags::default();
+        flags.sorting.column = column;
+        flags.sorting.order = order;
+        let order = EntryOrder::from_flags(&flags);
+        entries.sort_by(|a, b| order.cmp(a, b));
+        entries.into_iter().map(|entry| entry.name).collect()
+    }
+
+    #[test]
+    fn test_entry_by_git_status() {
+        let dir = tempdir().unwrap();
+        for name in ["a.rs", "b.rs", "c.rs", "d.rs"] {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+        let mut entries = children(dir.path());
+        let status = |index, workdir| Some(GitFileStatus { index, workdir });
+        entries[0].git_status = status(GitStatus::Unmodified, GitStatus::Modified);
+        entries[1].git_status = status(GitStatus::Unmodified, GitStatus::Unmodified);
+        entries[2].git_status = status(GitStatus::NewInIndex, GitStatus::Unmodified);
+        entries[3].git_status = None;
+
+        assert_eq!(sorted(entries, SortColumn::GitStatus), ["d.rs", "b.rs", "a.rs", "c.rs"]);
+    }
+
+    #[test]
+    fn test_entry_by_mime_ties_on_name() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("c.txt"), "text").unwrap();
+        fs::write(dir.path().join("a.txt"), "text").unwrap();
+        fs::write(dir.path().join("b"), b"\x89PNG\r\n\x1a\n").unwrap();
+        let mut entries = children(dir.path());
+        for entry in &mut entries {
+            entry.content_type = ContentType::read(&entry.path).ok();
+        }
+
+        assert_eq!(sorted(entries.clone(), SortColumn::Mime), ["b", "a.txt", "c.txt"]);
+        // The names are reversed with the types
+        assert_eq!(
+            sorted_in(entries, SortColumn::Mime, SortOrder::Reverse),
+            ["c.txt", "a.txt", "b"]
+        );
+    }
+
+    #[test]
+    fn test_entry_by_size_puts_unsized_first() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("big"), "big file").unwrap();
+        fs::write(dir.path().join("small"), "s").unwrap();
+        fs::write(dir.path().join("unsized"), "unsized file").unwrap();
+        let mut entries = children(dir.path());
+        for entry in entries.iter_mut().filter(|entry| entry.name == "unsized") {
+            entry.metadata = None;
+        }
+
+        assert_eq!(sorted(entries, SortColumn::Size), ["unsized", "big", "small"]);
+    }
+
+    #[test]
+    fn test_entry_by_none_keeps_walk_order() {
+        let dir = tempdir().unwrap();
+        for name in ["a", "B", "c"] {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+        let mut entries = children(dir.path());
+        entries.reverse();
+        let walked: Vec<_> = entries.iter().map(|entry| entry.name.clone()).collect();
+
+        assert_eq!(sorted(entries, SortColumn::None), walked);
+    }
+}
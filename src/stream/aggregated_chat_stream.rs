@@ -1,9 +1,15 @@
 use futures::stream::Stream;
+use std::collections::VecDeque;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use serde_json::json;
 
-use crate::stream::{FileEntry, StreamResult, StreamError};
+use crate::flags::{IgnoredChildren, TimeField};
+use crate::git::RepoHead;
+use crate::stream::{
+    CachedStat, FileEntry, LinkError, OutputEvent, StreamResult, StreamError, TruncateReason,
+    Truncation,
+};
 use crate::meta::{Permissions, Size, Date, Owner, INode, Links};
 
 /// Streams JSONL output for LLM consumption
@@ -12,6 +18,8 @@ pub struct AggregatedChatStream {
     source: Pin<Box<dyn Stream<Item = StreamResult<FileEntry>> + Send>>,
     objective: Option<String>,
     current_task: Option<String>,
+    /// Records emitted before the first entry, like the `--repo-info` summary
+    headers: VecDeque<OutputEvent>,
 }
 
 impl AggregatedChatStream {
@@ -24,28 +32,41 @@ impl AggregatedChatStream {
             source: Box::pin(source),
             objective,
             current_task,
+            headers: VecDeque::new(),
         }
     }
+
+    /// Emit `event` ahead of the entries
+    pub fn with_header(mut self, event: OutputEvent) -> Self {
+        self.headers.push_back(event);
+        self
+    }
     
     /// Convert FileEntry to JSON matching format in src/core.rs:181-194
     fn entry_to_json(&self, entry: &FileEntry) -> serde_json::Value {
+        let Some(metadata) = &entry.metadata else {
+            return match &entry.cached {
+                Some(stat) => self.cached_to_json(entry, stat),
+                None => self.object_to_json(entry),
+            };
+        };
+
         // Use From<&Metadata> conversions like in src/meta/ modules
-        let permissions = Permissions::from(&entry.metadata);
-        let size = Size::from(&entry.metadata);
-        let date = Date::from(&entry.metadata);
+        let permissions = Permissions::from(metadata);
+        let size = entry.size.clone().unwrap_or_else(|| Size::from(metadata));
         
         #[cfg(unix)]
-        let owner = Some(Owner::from(&entry.metadata));
+        let owner = Some(Owner::from(metadata));
         #[cfg(not(unix))]
         let owner: Option<Owner> = None;
         
         #[cfg(unix)]
-        let inode = Some(INode::from(&entry.metadata));
+        let inode = Some(INode::from(metadata));
         #[cfg(not(unix))]
         let inode: Option<INode> = None;
         
         #[cfg(unix)]
-        let links = Some(Links::from(&entry.metadata));
+        let links = Some(Links::from(metadata));
         #[cfg(not(unix))]
         let links: Option<Links> = None;
         
@@ -54,32 +75,236 @@ impl AggregatedChatStream {
             "name": entry.name,
             "type": format!("{:?}", entry.file_type),
             "size": size.get_bytes(),
-            "modified": format!("{:?}", date),
+            "modified": time(entry, TimeField::Modified),
+            "accessed": time(entry, TimeField::Accessed),
+            "changed": time(entry, TimeField::Changed),
+            "birth": time(entry, TimeField::Birth),
             "permissions": format!("{:?}", permissions),
             "owner": owner.map(|o| format!("{:?}", o)),
             "symlink": entry.is_symlink,
+            "mount_point": entry.mount_point,
             "inode": inode.map(|i| format!("{:?}", i)),
             "links": links.map(|l| format!("{:?}", l)),
-            "git_status": entry.git_status.as_ref().map(|gs| format!("{:?}", gs)),
+            "git_status": entry.git_status.map(|gs| format!("{:?}", gs.index.max(gs.workdir))),
+            "depth": entry.depth,
+            "objective": self.objective.clone(),
+            "current_task": self.current_task.clone(),
+        })
+    }
+
+    /// Convert an entry served by the scan cache (`--cache`) to JSON, with the same fields as
+    /// an entry that was stat'ed
+    fn cached_to_json(&self, entry: &FileEntry, stat: &CachedStat) -> serde_json::Value {
+        #[cfg(unix)]
+        let (permissions, owner) = (
+            Some(Permissions::from_mode(stat.mode)),
+            Some(Owner::new(stat.uid, stat.gid)),
+        );
+        #[cfg(not(unix))]
+        let (permissions, owner): (Option<Permissions>, Option<Owner>) = (None, None);
+
+        json!({
+            "path": entry.path.to_string_lossy(),
+            "name": entry.name,
+            "type": format!("{:?}", entry.file_type),
+            "size": entry.size_bytes(),
+            "modified": time(entry, TimeField::Modified),
+            "accessed": time(entry, TimeField::Accessed),
+            "changed": time(entry, TimeField::Changed),
+            "birth": time(entry, TimeField::Birth),
+            "permissions": permissions.map(|p| format!("{:?}", p)),
+            "owner": owner.map(|o| format!("{:?}", o)),
+            "symlink": entry.is_symlink,
+            "mount_point": entry.mount_point,
+            "inode": format!("{:?}", INode::new(stat.ino)),
+            "links": format!("{:?}", Links::new(stat.nlink)),
+            "git_status": entry.git_status.map(|gs| format!("{:?}", gs.index.max(gs.workdir))),
+            "depth": entry.depth,
+            "objective": self.objective.clone(),
+            "current_task": self.current_task.clone(),
+        })
+    }
+
+    /// Convert an entry read from a git revision (`--at`) to JSON; mode, size and date come
+    /// from the tree entry, blob header and commit instead of the file system
+    fn object_to_json(&self, entry: &FileEntry) -> serde_json::Value {
+        let object = entry.object.as_ref();
+
+        json!({
+            "path": entry.path.to_string_lossy(),
+            "name": entry.name,
+            "type": format!("{:?}", entry.file_type),
+            "size": entry.size_bytes(),
+            "modified": object.and_then(|o| o.time).map(|t| format!("{:?}", Date::from(t))),
+            "mode": object.map(|o| format!("{:o}", o.mode)),
+            "symlink": entry.is_symlink,
+            "symlink_target": object.and_then(|o| o.link_target.as_ref()).map(|t| t.to_string_lossy()),
+            "object_id": object.map(|o| o.id.to_string()),
             "depth": entry.depth,
             "objective": self.objective.clone(),
             "current_task": self.current_task.clone(),
         })
     }
+
+    /// Convert a header event to JSON; `kind` tells it apart from entry records
+    fn header_to_json(&self, event: &OutputEvent) -> Option<serde_json::Value> {
+        let OutputEvent::DirectoryHeader { path, repo } = event else {
+            return None;
+        };
+
+        let mut json = json!({
+            "kind": "directory",
+            "path": path.to_string_lossy(),
+        });
+        if let Some(repo) = repo {
+            let (branch, detached) = match &repo.head {
+                RepoHead::Branch(name) => (Some(name), None),
+                RepoHead::Detached(id) => (None, Some(id)),
+            };
+            json["kind"] = json!("repository");
+            json["root"] = json!(repo.root.to_string_lossy());
+            json["branch"] = json!(branch);
+            json["detached"] = json!(detached);
+            json["upstream"] = json!(repo.upstream);
+            // Counts the history could not be walked for are left out
+            if let Some(ahead) = repo.ahead {
+                json["ahead"] = json!(ahead);
+            }
+            if let Some(behind) = repo.behind {
+                json["behind"] = json!(behind);
+            }
+            json["stashes"] = json!(repo.stashes);
+            json["operation"] = json!(repo.operation);
+        }
+        Some(json)
+    }
+
+    /// Convert a link that could not be followed to an error record, so `--dereference`
+    /// problems stay in the JSONL output instead of ending the stream
+    fn link_error_to_json(&self, error: &LinkError) -> serde_json::Value {
+        match error {
+            LinkError::Broken { path, target } => json!({
+                "kind": "error",
+                "error": "broken_link",
+                "path": path.to_string_lossy(),
+                "target": target.as_ref().map(|t| t.to_string_lossy()),
+                "message": error.to_string(),
+            }),
+            LinkError::Cycle { path, ancestor } => json!({
+                "kind": "error",
+                "error": "symlink_cycle",
+                "path": path.to_string_lossy(),
+                "ancestor": ancestor.to_string_lossy(),
+                "message": error.to_string(),
+            }),
+        }
+    }
+
+    /// Convert the marker of a directory's children hidden by the ignore rules to a record
+    /// with their count, and with `--explain-ignores` the rule that hid each one
+    fn ignored_to_json(&self, entry: &FileEntry, ignored: &IgnoredChildren) -> serde_json::Value {
+        let mut json = json!({
+            "kind": "ignored",
+            "path": entry.path.to_string_lossy(),
+            "ignored_count": ignored.count,
+            "depth": entry.depth,
+        });
+        if !ignored.entries.is_empty() {
+            json["entries"] = ignored
+                .entries
+                .iter()
+                .map(|(name, rule)| {
+                    json!({
+                        "name": name,
+                        "pattern": rule.pattern,
+                        "source": rule.origin.to_string(),
+                    })
+                })
+                .collect();
+        }
+        json
+    }
+
+    /// Convert a cut by the scan limits to the last record, naming the directories whose
+    /// entries are missing
+    fn truncation_to_json(&self, truncation: &Truncation) -> serde_json::Value {
+        let (reason, limit) = match truncation.reason {
+            TruncateReason::MaxEntries(max) => ("max_entries", json!(max)),
+            TruncateReason::Timeout(timeout) => ("timeout", json!(timeout.as_secs_f64())),
+        };
+        json!({
+            "kind": "truncated",
+            "reason": reason,
+            "limit": limit,
+            "entries": truncation.entries,
+            "directories": truncation
+                .directories
+                .iter()
+                .map(|path| path.to_string_lossy())
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// The `field` timestamp of `entry`, `null` when it is not recorded. Records carry every
+/// timestamp, whichever one `--time` shows.
+fn time(entry: &FileEntry, field: TimeField) -> Option<String> {
+    entry.time(field).map(|time| format!("{:?}", Date::from(time)))
 }
 
 impl Stream for AggregatedChatStream {
     type Item = StreamResult<String>;  // JSONL strings
     
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while let Some(event) = self.headers.pop_front() {
+            if let Some(json) = self.header_to_json(&event) {
+                return Poll::Ready(Some(
+                    serde_json::to_string(&json).map_err(|e| StreamError::Traversal(e.to_string())),
+                ));
+            }
+        }
+
         match self.source.as_mut().poll_next(cx) {
             Poll::Ready(Some(Ok(entry))) => {
-                let json = self.entry_to_json(&entry);
+                let mut json = match &entry.ignored {
+                    Some(ignored) => self.ignored_to_json(&entry, ignored),
+                    None => self.entry_to_json(&entry),
+                };
+                if let Some(content_type) = &entry.content_type {
+                    json["mime"] = content_type.to_json();
+                }
+                if let Some(media) = &entry.media {
+                    json["media"] = media.to_json();
+                }
+                if let Some(binary) = &entry.binary {
+                    json["binary"] = binary.to_json();
+                }
+                if let Some(hash) = &entry.hash {
+                    json["hash"] = json!(hash);
+                }
+                if let Some(matches) = &entry.matches {
+                    json["matches"] = json!(matches.count);
+                    if !matches.lines.is_empty() {
+                        json["match_lines"] = json!(matches.lines);
+                    }
+                }
                 match serde_json::to_string(&json) {
                     Ok(line) => Poll::Ready(Some(Ok(line))),
                     Err(e) => Poll::Ready(Some(Err(StreamError::Traversal(e.to_string())))),
                 }
             }
+            Poll::Ready(Some(Err(StreamError::Link(error)))) => {
+                let json = self.link_error_to_json(&error);
+                Poll::Ready(Some(
+                    serde_json::to_string(&json).map_err(|e| StreamError::Traversal(e.to_string())),
+                ))
+            }
+            Poll::Ready(Some(Err(StreamError::Truncated(truncation)))) => {
+                let json = self.truncation_to_json(&truncation);
+                Poll::Ready(Some(
+                    serde_json::to_string(&json).map_err(|e| StreamError::Traversal(e.to_string())),
+                ))
+            }
             Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
             Poll::Ready(None) => Poll::Ready(None),
             Poll::Pending => Poll::Pending,
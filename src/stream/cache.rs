@@ -0,0 +1,854 @@
+//! Cache source: walks a tree while keeping each directory's listing on disk, and serves the
+//! directories unchanged since the last run from it, with the digests of unchanged files.
+//!
+//! Git statuses are not kept: they change with the index, `HEAD` and the ignore rules as much
+//! as with the files, so the work tree is read again on every run. Sniffed types, media and
+//! ELF headers are read again too.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::flags::{
+    CacheFlag, CacheMode, Display, HashAlgorithm, IgnoreGlobs, IgnoredChildren, SapIgnores, TimeField,
+};
+use crate::git::GitCache;
+use crate::meta::{time_of, FileType};
+use crate::sort::EntryOrder;
+use crate::stream::{
+    is_displayed, is_mount_point, mark_last_sibling, FileEntry, StreamError, StreamResult, WalkOptions,
+};
+
+/// Bumped when the layout of the cache files changes, so older files are ignored
+const VERSION: u32 = 3;
+
+/// How long after a change a stamp is trusted: a directory or file changed again within the
+/// granularity of its file system's timestamps keeps the same stamp, so a stamp this recent
+/// is not cached
+const RACY: Duration = Duration::from_secs(2);
+
+/// The file in the cache directory that saves hold shared and eviction holds exclusively, so
+/// no cache is removed while it is being written
+const LOCK: &str = "lock";
+
+/// The `lstat` data of an entry, in a form that can be kept in the cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedStat {
+    pub dev: u64,
+    pub ino: u64,
+    /// `st_mode`, with the file type bits
+    pub mode: u32,
+    pub nlink: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    /// Number of 512-byte blocks allocated
+    pub blocks: u64,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    pub changed: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+    /// Whether the entry is a symbolic link to a directory
+    pub target_is_dir: bool,
+}
+
+impl CachedStat {
+    #[cfg(unix)]
+    fn new(path: &Path, metadata: &fs::Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+
+        let is_symlink = metadata.file_type().is_symlink();
+        Self {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+            mode: metadata.mode(),
+            nlink: metadata.nlink(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            size: metadata.len(),
+            blocks: metadata.blocks(),
+            modified: time_of(metadata, TimeField::Modified),
+            accessed: time_of(metadata, TimeField::Accessed),
+            changed: time_of(metadata, TimeField::Changed),
+            created: time_of(metadata, TimeField::Birth),
+            target_is_dir: is_symlink && fs::metadata(path).is_ok_and(|target| target.is_dir()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn new(path: &Path, metadata: &fs::Metadata) -> Self {
+        let file_type = metadata.file_type();
+        let is_symlink = file_type.is_symlink();
+        let mode = if is_symlink {
+            0o120777
+        } else if file_type.is_dir() {
+            0o040755
+        } else {
+            0o100644
+        };
+        Self {
+            dev: 0,
+            ino: 0,
+            mode,
+            nlink: 1,
+            uid: 0,
+            gid: 0,
+            size: metadata.len(),
+            blocks: metadata.len().div_ceil(512),
+            modified: time_of(metadata, TimeField::Modified),
+            accessed: time_of(metadata, TimeField::Accessed),
+            changed: time_of(metadata, TimeField::Changed),
+            created: time_of(metadata, TimeField::Birth),
+            target_is_dir: is_symlink && fs::metadata(path).is_ok_and(|target| target.is_dir()),
+        }
+    }
+
+    /// The `field` timestamp, [None] when it is not recorded
+    pub fn time(&self, field: TimeField) -> Option<SystemTime> {
+        match field {
+            TimeField::Modified => self.modified,
+            TimeField::Accessed => self.accessed,
+            TimeField::Changed => self.changed,
+            TimeField::Birth => self.created,
+        }
+    }
+
+    /// The file type encoded in `mode`
+    pub fn file_type(&self) -> FileType {
+        const SETUID: u32 = 0o4000;
+        match self.mode & 0o170000 {
+            0o040000 => FileType::Directory {
+                uid: self.mode & SETUID != 0,
+            },
+            0o100000 => FileType::File {
+                exec: self.mode & 0o111 != 0,
+                uid: self.mode & SETUID != 0,
+            },
+            0o120000 => FileType::SymLink {
+                is_dir: self.target_is_dir,
+            },
+            0o010000 => FileType::Pipe,
+            0o020000 => FileType::CharDevice,
+            0o060000 => FileType::BlockDevice,
+            0o140000 => FileType::Socket,
+            _ => FileType::Special,
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        self.mode & 0o170000 == 0o040000
+    }
+}
+
+/// What tells whether a directory changed since it was cached: any entry added, removed or
+/// renamed in it updates its mtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct DirStamp {
+    dev: u64,
+    ino: u64,
+    modified: Option<SystemTime>,
+}
+
+/// What tells whether a file changed since its digest was cached: its ctime changes with its
+/// contents, even when the mtime is set back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    dev: u64,
+    ino: u64,
+    size: u64,
+    modified: Option<SystemTime>,
+    changed: Option<SystemTime>,
+}
+
+impl FileStamp {
+    fn of(stat: &CachedStat) -> Self {
+        Self {
+            dev: stat.dev,
+            ino: stat.ino,
+            size: stat.size,
+            modified: stat.modified,
+            changed: stat.changed,
+        }
+    }
+}
+
+/// Whether `time` is too recent for the `racy` window, or unknown, for a stamp holding it to
+/// be trusted later
+fn is_racy(time: Option<SystemTime>, racy: Duration) -> bool {
+    time.is_none_or(|time| SystemTime::now().duration_since(time).is_ok_and(|age| age < racy))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedDir {
+    stamp: DirStamp,
+    /// The names `readdir` returned; the children themselves are stat'ed on every run, as a
+    /// file edited in place leaves its directory's mtime alone
+    names: Vec<String>,
+    /// Digests of the regular files among the children, with the stamp they were taken at
+    hashes: HashMap<String, (FileStamp, String)>,
+}
+
+/// The cache file of one input path
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    root: PathBuf,
+    /// Listings by path relative to the root, unfiltered so any flags can reuse them
+    dirs: HashMap<String, CachedDir>,
+}
+
+/// The listings of a tree, loaded from and written back to the cache directory
+struct DirCache {
+    location: PathBuf,
+    file: CacheFile,
+    max_size: u64,
+    racy: Duration,
+    dirty: bool,
+}
+
+impl DirCache {
+    /// Where the caches are kept: `$XDG_CACHE_HOME/sap/scan`, or the platform's equivalent
+    fn directory() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("sap").join("scan"))
+    }
+
+    /// Load the cache of the tree at `root` from `directory`; with `CacheMode::Refresh` it
+    /// starts out empty
+    fn open(directory: &Path, root: &Path, flag: CacheFlag, racy: Duration) -> Option<Self> {
+        let root = fs::canonicalize(root).ok()?;
+        let location = directory.join(format!("{:016x}.json", fnv1a(&root)));
+
+        let file = match flag.mode {
+            CacheMode::Refresh => None,
+            _ => fs::read(&location)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<CacheFile>(&bytes).ok())
+                // A different version, or another root with the same hash
+                .filter(|file| file.version == VERSION && file.root == root),
+        };
+
+        Some(Self {
+            location,
+            file: file.unwrap_or(CacheFile {
+                version: VERSION,
+                root,
+                dirs: HashMap::new(),
+            }),
+            max_size: flag.max_size,
+            racy,
+            dirty: false,
+        })
+    }
+
+    /// The children of the directory at `path`, stat'ed, with their names from the cache when
+    /// the directory's stamp still matches
+    fn listing(
+        &mut self,
+        relative: &Path,
+        path: &Path,
+        stamp: DirStamp,
+    ) -> io::Result<Vec<(OsString, CachedStat)>> {
+        // Paths and names that are not UTF-8 cannot be written out; those are always read
+        let key = relative.to_str().map(str::to_owned);
+        if let Some(dir) = key.as_ref().and_then(|key| self.file.dirs.get(key))
+            && dir.stamp == stamp
+        {
+            return Ok(dir
+                .names
+                .iter()
+                .filter_map(|name| {
+                    let child = path.join(name);
+                    // An entry removed meanwhile updated the mtime, and is read next time
+                    let metadata = fs::symlink_metadata(&child).ok()?;
+                    Some((OsString::from(name), CachedStat::new(&child, &metadata)))
+                })
+                .collect());
+        }
+
+        let children = read_listing(path)?;
+        let names: Option<Vec<String>> = children
+            .iter()
+            .map(|(name, _)| name.to_str().map(str::to_owned))
+            .collect();
+        if let (Some(key), Some(names)) = (key, names)
+            && !is_racy(stamp.modified, self.racy)
+        {
+            // The digests of the files still there stay valid as long as their own stamps
+            let mut hashes = self.file.dirs.remove(&key).map(|dir| dir.hashes).unwrap_or_default();
+            hashes.retain(|name, _| names.contains(name));
+            self.file.dirs.insert(key, CachedDir { stamp, names, hashes });
+            self.dirty = true;
+        }
+        Ok(children)
+    }
+
+    /// The digest with `algorithm` of the file `name` in the directory at `relative`, when the
+    /// file did not change since it was cached
+    fn hash(&self, relative: &str, name: &str, stat: &CachedStat, algorithm: HashAlgorithm) -> Option<String> {
+        let (stamp, digest) = self.file.dirs.get(relative)?.hashes.get(name)?;
+        (*stamp == FileStamp::of(stat) && digest.split(':').next() == Some(algorithm.name()))
+            .then(|| digest.clone())
+    }
+
+    /// Keep the digest of the file `name` in the directory at `relative`, read when it had
+    /// `stat`; only the files of cached listings are kept
+    fn store_hash(&mut self, relative: &str, name: &str, stat: &CachedStat, digest: &str) {
+        if is_racy(stat.modified, self.racy) || is_racy(stat.changed, self.racy) {
+            return;
+        }
+        if let Some(dir) = self.file.dirs.get_mut(relative) {
+            dir.hashes.insert(name.to_string(), (FileStamp::of(stat), digest.to_string()));
+            self.dirty = true;
+        }
+    }
+
+    /// Write the cache back if it changed, or mark it used, then keep the directory under
+    /// its size limit
+    fn save(&self) -> io::Result<()> {
+        let Some(directory) = self.location.parent() else {
+            return Ok(());
+        };
+        if self.dirty {
+            fs::create_dir_all(directory)?;
+            let lock = lock_file(directory)?;
+            lock.lock_shared()?;
+            let bytes = serde_json::to_vec(&self.file).map_err(io::Error::other)?;
+            // Another sap reading or writing the cache meanwhile sees the old or the new file,
+            // never half of one
+            let partial = self.location.with_extension(format!(
+                "json.{}-{:08x}.partial",
+                std::process::id(),
+                rand::random::<u32>()
+            ));
+            fs::write(&partial, bytes)?;
+            fs::rename(&partial, &self.location).inspect_err(|_| {
+                let _ = fs::remove_file(&partial);
+            })?;
+        } else {
+            fs::File::options()
+                .write(true)
+                .open(&self.location)?
+                .set_modified(SystemTime::now())?;
+        }
+        evict(directory, self.max_size)
+    }
+}
+
+impl Drop for DirCache {
+    fn drop(&mut self) {
+        // The cache only saves work; a listing that could not be stored is read next time
+        let _ = self.save();
+    }
+}
+
+/// The scan caches of one run, one per input path, shared by the walks reading directories
+/// through them and by the `ContentHasher` keeping file digests in them. Each is written back
+/// once the last handle is dropped, including when the walk was cut short.
+#[derive(Clone)]
+pub struct ScanCache {
+    flag: CacheFlag,
+    directory: Option<PathBuf>,
+    /// The caches by input path, as given on the command line
+    caches: Arc<Mutex<Vec<(PathBuf, DirCache)>>>,
+    /// How recent a stamp is too recent to keep, [RACY] but in tests
+    racy: Duration,
+}
+
+impl ScanCache {
+    pub fn new(flag: CacheFlag) -> Self {
+        Self {
+            flag,
+            directory: DirCache::directory(),
+            caches: Arc::default(),
+            racy: RACY,
+        }
+    }
+
+    /// Load the cache of the tree at `root`, unless it is open already
+    fn open(&self, root: &Path) {
+        let mut caches = self.caches.lock().unwrap_or_else(|err| err.into_inner());
+        if caches.iter().any(|(input, _)| input == root) {
+            return;
+        }
+        if let Some(cache) = self
+            .directory
+            .as_ref()
+            .and_then(|directory| DirCache::open(directory, root, self.flag, self.racy))
+        {
+            caches.push((root.to_path_buf(), cache));
+        }
+    }
+
+    /// Run `f` with the cache of the input path `root`, or `None` when it has none
+    fn with<T>(&self, root: &Path, f: impl FnOnce(Option<&mut DirCache>) -> T) -> T {
+        let mut caches = self.caches.lock().unwrap_or_else(|err| err.into_inner());
+        let cache = caches.iter_mut().find(|(input, _)| input == root).map(|(_, cache)| cache);
+        f(cache)
+    }
+
+    /// The cache holding the file at `path`, with the key of its directory and its name
+    fn locate<T>(&self, path: &Path, f: impl FnOnce(&mut DirCache, &str, &str) -> Option<T>) -> Option<T> {
+        let mut caches = self.caches.lock().unwrap_or_else(|err| err.into_inner());
+        let (relative, cache) = caches
+            .iter_mut()
+            .filter_map(|(input, cache)| Some((path.strip_prefix(input).ok()?, cache)))
+            .min_by_key(|(relative, _)| relative.components().count())?;
+        let name = relative.file_name()?.to_str()?;
+        let dir = relative.parent()?.to_str()?;
+        f(cache, dir, name)
+    }
+
+    /// The cached digest with `algorithm` of the file at `path`, when `stat` shows it did not
+    /// change since
+    pub fn hash(&self, path: &Path, stat: &CachedStat, algorithm: HashAlgorithm) -> Option<String> {
+        self.locate(path, |cache, dir, name| cache.hash(dir, name, stat, algorithm))
+    }
+
+    /// Keep the digest of the file at `path`, read when it had `stat`
+    pub fn store_hash(&self, path: &Path, stat: &CachedStat, digest: &str) {
+        self.locate(path, |cache, dir, name| {
+            cache.store_hash(dir, name, stat, digest);
+            Some(())
+        });
+    }
+}
+
+/// The [LOCK] file of the cache directory
+fn lock_file(directory: &Path) -> io::Result<fs::File> {
+    fs::File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(directory.join(LOCK))
+}
+
+/// Remove the least recently used caches until the directory fits in `max_size` bytes. While
+/// another sap saves a cache the directory is left alone, and the next run evicts instead.
+fn evict(directory: &Path, max_size: u64) -> io::Result<()> {
+    let lock = lock_file(directory)?;
+    match lock.try_lock() {
+        Ok(()) => {}
+        Err(fs::TryLockError::WouldBlock) => return Ok(()),
+        Err(fs::TryLockError::Error(e)) => return Err(e),
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory)?.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => files.push((metadata.modified()?, metadata.len(), path)),
+            // No save is running, so this is what a sap that was killed left behind
+            Some("partial") => fs::remove_file(path)?,
+            _ => {}
+        }
+    }
+
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    files.sort();
+    for (_, len, path) in files {
+        if total <= max_size {
+            break;
+        }
+        fs::remove_file(path)?;
+        total -= len;
+    }
+    Ok(())
+}
+
+/// Read and `lstat` the children of a directory
+fn read_listing(path: &Path) -> io::Result<Vec<(OsString, CachedStat)>> {
+    let mut children = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let child = entry.path();
+        // An entry gone since `readdir` is left out
+        if let Ok(metadata) = fs::symlink_metadata(&child) {
+            children.push((entry.file_name(), CachedStat::new(&child, &metadata)));
+        }
+    }
+    Ok(children)
+}
+
+/// A stable hash of the root path, naming its cache file
+fn fnv1a(path: &Path) -> u64 {
+    path.as_os_str()
+        .as_encoded_bytes()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Depth-first walk of one input path that reads directories through the cache.
+///
+/// A directory is `stat`ed on every run, and only read again when its (dev, inode, mtime)
+/// differs from the cached one; its children are `lstat`ed either way. Entries are yielded
+/// like `walk_path` yields them: filtered, in display order, with the last sibling flagged.
+pub struct CacheWalk {
+    /// Entries waiting to be yielded, the next one last
+    stack: Vec<FileEntry>,
+    root: PathBuf,
+    root_dev: u64,
+    cache: ScanCache,
+    /// A directory that could not be read, reported after its entry
+    error: Option<StreamError>,
+    max_depth: usize,
+    ignore_globs: IgnoreGlobs,
+    /// The `.sapignore` rules of the directories being walked, by depth
+    sapignores: Vec<SapIgnores>,
+    display: Display,
+    one_file_system: bool,
+    /// Status of the work tree holding the input, when the entries carry it
+    git: Option<GitCache>,
+    order: EntryOrder,
+}
+
+impl CacheWalk {
+    pub fn new(
+        path: &Path,
+        cache: &ScanCache,
+        max_depth: usize,
+        ignore_globs: &IgnoreGlobs,
+        display: Display,
+        options: WalkOptions,
+        order: &EntryOrder,
+    ) -> StreamResult<Self> {
+        // The input itself is followed, like jwalk does for its root
+        let metadata = fs::metadata(path).or_else(|_| fs::symlink_metadata(path))?;
+        let stat = CachedStat::new(path, &metadata);
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string());
+
+        let root_dev = stat.dev;
+        let mut root = FileEntry::from_cached(path.to_path_buf(), name, stat, 0);
+        root.mount_point = metadata.is_dir() && is_mount_point(path, &metadata);
+        let git = options.git.then(|| GitCache::new(path));
+        root.git_status = git.as_ref().and_then(|git| git.status(path, metadata.is_dir()));
+
+        cache.open(path);
+        Ok(Self {
+            stack: vec![root],
+            root: path.to_path_buf(),
+            root_dev,
+            cache: cache.clone(),
+            error: None,
+            max_depth,
+            ignore_globs: ignore_globs.for_root(path),
+            sapignores: Vec::new(),
+            display,
+            one_file_system: options.one_file_system,
+            git,
+            order: order.clone(),
+        })
+    }
+
+    /// The displayed children of `dir`, in display order
+    fn children(&mut self, dir: &FileEntry) -> io::Result<Vec<FileEntry>> {
+        // The cached stat of a directory may be as old as its parent's listing
+        let stat = CachedStat::new(&dir.path, &fs::metadata(&dir.path)?);
+        if self.one_file_system && stat.dev != self.root_dev {
+            return Ok(Vec::new());
+        }
+        let stamp = DirStamp {
+            dev: stat.dev,
+            ino: stat.ino,
+            modified: stat.modified,
+        };
+
+        let relative = dir.path.strip_prefix(&self.root).unwrap_or(&dir.path);
+        let listing = self.cache.with(&self.root, |cache| match cache {
+            Some(cache) => cache.listing(relative, &dir.path, stamp),
+            None => read_listing(&dir.path),
+        })?;
+
+        // The directories above this one are the only ones left on the stack
+        self.sapignores.truncate(dir.depth);
+        let mut sapignores = self.sapignores.last().cloned().unwrap_or_default();
+        sapignores.read(&dir.path, relative);
+
+        let mut ignored = IgnoredChildren::default();
+        let mut children: Vec<FileEntry> = listing
+            .into_iter()
+            .filter(|(name, child)| {
+                name.to_str().is_none_or(|name| {
+                    is_displayed(name, child.is_dir(), self.display)
+                        && !self.ignore_globs.hides(&sapignores, &relative.join(name), child.is_dir(), &mut ignored)
+                })
+            })
+            .map(|(name, child)| {
+                let path = dir.path.join(&name);
+                let mount_point = child.is_dir() && child.dev != stat.dev;
+                let mut entry =
+                    FileEntry::from_cached(path, name.to_string_lossy().to_string(), child, dir.depth + 1);
+                entry.mount_point = mount_point;
+                entry.git_status = self.git.as_ref().and_then(|git| git.status(&entry.path, entry.file_type.is_dirlike()));
+                entry
+            })
+            .collect();
+
+        children.sort_by(|a, b| self.order.cmp(a, b));
+        if ignored.count > 0 {
+            children.push(FileEntry::ignored(&dir.path, dir.depth + 1, ignored));
+        }
+        let mut siblings: Vec<&mut FileEntry> = children.iter_mut().collect();
+        mark_last_sibling(&mut siblings);
+        self.sapignores.push(sapignores);
+        Ok(children)
+    }
+}
+
+impl Iterator for CacheWalk {
+    type Item = StreamResult<FileEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        let entry = self.stack.pop()?;
+
+        if entry.has_children() && entry.depth < self.max_depth {
+            match self.children(&entry) {
+                Ok(children) => self.stack.extend(children.into_iter().rev()),
+                Err(e) => self.error = Some(StreamError::Io(e)),
+            }
+        }
+        Some(Ok(entry))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{self, FileTimes};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use futures::StreamExt;
+    use tempfile::{tempdir, TempDir};
+
+    use super::{evict, lock_file, CachedStat, ScanCache, RACY};
+    use crate::flags::{CacheFlag, CacheMode, Flags, HashAlgorithm, ThreadMode, Threads};
+    use crate::sort::EntryOrder;
+    use crate::stream::{ContentHasher, FileStream, Stat, WalkOptions};
+
+    /// A scan cache kept in a directory of its own
+    fn scan_cache(directory: &Path, mode: CacheMode) -> ScanCache {
+        ScanCache {
+            flag: CacheFlag {
+                mode,
+                ..CacheFlag::default()
+            },
+            directory: Some(directory.to_path_buf()),
+            caches: Arc::default(),
+            racy: RACY,
+        }
+    }
+
+    fn walk(root: &Path, cache: &ScanCache) -> FileStream {
+        let flags = Flags::default();
+        let options = WalkOptions {
+            dereference: false,
+            one_file_system: false,
+            stat: Stat::All,
+            threads: Threads {
+                mode: ThreadMode::Serial,
+                busy_timeout: None,
+            },
+            sniff: false,
+            git: false,
+        };
+        FileStream::from_cache(
+            vec![root.to_path_buf()],
+            cache,
+            usize::MAX,
+            &flags.ignore_globs,
+            flags.display,
+            options,
+            &EntryOrder::from_flags(&flags),
+        )
+    }
+
+    /// The names and sizes below the root, from a walk through a fresh handle on the cache
+    fn listing(root: &Path, directory: &Path) -> Vec<(String, Option<u64>)> {
+        let cache = scan_cache(directory, CacheMode::On);
+        let entries = futures::executor::block_on(walk(root, &cache).collect::<Vec<_>>());
+        entries
+            .into_iter()
+            .skip(1)
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let size = entry.size_bytes();
+                (entry.name, size)
+            })
+            .collect()
+    }
+
+    /// Set the mtime of the file or directory at `path` back past the racy window
+    fn age(path: &Path) -> SystemTime {
+        let time = SystemTime::now() - Duration::from_secs(60);
+        let times = FileTimes::new().set_accessed(time).set_modified(time);
+        fs::File::open(path).unwrap().set_times(times).unwrap();
+        time
+    }
+
+    fn fixture() -> (TempDir, PathBuf, TempDir) {
+        let tree = tempdir().unwrap();
+        let root = tree.path().join("tree");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("a.txt"), "one").unwrap();
+        (tree, root, tempdir().unwrap())
+    }
+
+    #[test]
+    fn test_files_edited_in_place_are_stat_again() {
+        let (_tree, root, directory) = fixture();
+        let time = age(&root);
+        assert_eq!(vec![("a.txt".to_string(), Some(3))], listing(&root, directory.path()));
+
+        // An edit in place leaves the mtime alone; b.txt, added behind the cache's back, shows
+        // the names come from it
+        fs::write(root.join("a.txt"), "one two").unwrap();
+        fs::write(root.join("b.txt"), "").unwrap();
+        fs::File::open(&root).unwrap().set_modified(time).unwrap();
+        assert_eq!(vec![("a.txt".to_string(), Some(7))], listing(&root, directory.path()));
+
+        // A removed entry is left out even before the directory is read again
+        fs::remove_file(root.join("a.txt")).unwrap();
+        fs::File::open(&root).unwrap().set_modified(time).unwrap();
+        assert_eq!(Vec::<(String, Option<u64>)>::new(), listing(&root, directory.path()));
+    }
+
+    #[test]
+    fn test_changed_directories_are_read_again() {
+        let (_tree, root, directory) = fixture();
+        age(&root);
+        listing(&root, directory.path());
+
+        fs::write(root.join("b.txt"), "").unwrap();
+        age(&root);
+        let names: Vec<String> = listing(&root, directory.path()).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(vec!["a.txt", "b.txt"], names);
+    }
+
+    #[test]
+    fn test_racy_directories_are_not_cached() {
+        let (_tree, root, directory) = fixture();
+        listing(&root, directory.path());
+
+        // Changed again within the same second, the directory keeps its mtime
+        let time = fs::metadata(&root).unwrap().modified().unwrap();
+        fs::write(root.join("b.txt"), "").unwrap();
+        fs::File::open(&root).unwrap().set_modified(time).unwrap();
+        assert_eq!(2, listing(&root, directory.path()).len());
+    }
+
+    #[test]
+    fn test_refresh_reads_everything_again() {
+        let (_tree, root, directory) = fixture();
+        let time = age(&root);
+        listing(&root, directory.path());
+
+        fs::write(root.join("b.txt"), "").unwrap();
+        fs::File::open(&root).unwrap().set_modified(time).unwrap();
+        let cache = scan_cache(directory.path(), CacheMode::Refresh);
+        let entries = futures::executor::block_on(walk(&root, &cache).collect::<Vec<_>>());
+        assert_eq!(3, entries.len());
+    }
+
+    #[test]
+    fn test_saves_leave_no_partial_files() {
+        let (_tree, root, directory) = fixture();
+        age(&root);
+        listing(&root, directory.path());
+        listing(&root, directory.path());
+        let files = names(directory.path());
+        assert_eq!(1, files.len());
+        assert!(files[0].ends_with(".json"));
+    }
+
+    #[test]
+    fn test_digests_of_unchanged_files_are_kept() {
+        let (_tree, root, directory) = fixture();
+        let path = root.join("a.txt");
+        age(&path);
+        age(&root);
+
+        let stat = || CachedStat::new(&path, &fs::symlink_metadata(&path).unwrap());
+        let hashed = {
+            // The ctime of a file cannot be set back, so the digest is kept however recent it is
+            let cache = ScanCache {
+                racy: Duration::ZERO,
+                ..scan_cache(directory.path(), CacheMode::On)
+            };
+            let hasher = ContentHasher::new(walk(&root, &cache), HashAlgorithm::Blake3, usize::MAX, true, Some(cache.clone()));
+            let entries = futures::executor::block_on(hasher.collect::<Vec<_>>());
+            entries[1].as_ref().unwrap().hash.clone().unwrap()
+        };
+
+        let cache = scan_cache(directory.path(), CacheMode::On);
+        cache.open(&root);
+        assert_eq!(Some(hashed), cache.hash(&path, &stat(), HashAlgorithm::Blake3));
+        assert_eq!(None, cache.hash(&path, &stat(), HashAlgorithm::Sha256));
+
+        fs::write(&path, "two").unwrap();
+        assert_eq!(None, cache.hash(&path, &stat(), HashAlgorithm::Blake3));
+    }
+
+    /// A cache file of `len` bytes last used `age` seconds ago
+    fn cache_file(directory: &Path, name: &str, len: usize, age: u64) {
+        let path = directory.join(name);
+        fs::write(&path, vec![b' '; len]).unwrap();
+        let time = SystemTime::now() - Duration::from_secs(age);
+        fs::File::open(&path).unwrap().set_times(FileTimes::new().set_modified(time)).unwrap();
+    }
+
+    fn names(directory: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name != "lock")
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let directory = tempdir().unwrap();
+        cache_file(directory.path(), "old.json", 100, 30);
+        cache_file(directory.path(), "new.json", 100, 10);
+        cache_file(directory.path(), "newest.json", 100, 0);
+        cache_file(directory.path(), "old.json.1-0.partial", 100, 30);
+
+        evict(directory.path(), 250).unwrap();
+        assert_eq!(vec!["new.json", "newest.json"], names(directory.path()));
+    }
+
+    #[test]
+    fn test_eviction_waits_for_saves() {
+        let directory = tempdir().unwrap();
+        cache_file(directory.path(), "old.json", 100, 30);
+        cache_file(directory.path(), "new.json.1-0.partial", 100, 0);
+
+        // A save holds the lock shared while it writes
+        let lock = lock_file(directory.path()).unwrap();
+        lock.lock_shared().unwrap();
+        evict(directory.path(), 0).unwrap();
+        assert_eq!(vec!["new.json.1-0.partial", "old.json"], names(directory.path()));
+
+        drop(lock);
+        evict(directory.path(), 0).unwrap();
+        assert_eq!(Vec::<String>::new(), names(directory.path()));
+    }
+}
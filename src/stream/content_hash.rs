@@ -0,0 +1,571 @@
+//! Content hasher: digests file contents on the rayon pool and folds them into Merkle
+//! fingerprints of the directories above them
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures::channel::oneshot;
+use futures::Stream;
+use sha2::{Digest, Sha256};
+
+use crate::flags::HashAlgorithm;
+use crate::meta::FileType;
+use crate::stream::{FileEntry, LinkError, ScanCache, StreamError, StreamResult};
+
+/// How many files are hashed ahead of the entry the stream waits for
+const IN_FLIGHT: usize = 256;
+
+/// A hash being computed with the chosen [HashAlgorithm]
+enum Hasher {
+    Blake3(Box<blake3::Hasher>),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Blake3 => Self::Blake3(Box::default()),
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+        }
+    }
+
+    /// The digest as `algorithm:hex`
+    fn finish(self) -> String {
+        match self {
+            Self::Blake3(hasher) => format!("{}:{}", HashAlgorithm::Blake3.name(), hasher.finalize().to_hex()),
+            Self::Sha256(hasher) => format!("{}:{:x}", HashAlgorithm::Sha256.name(), hasher.finalize()),
+        }
+    }
+}
+
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Blake3(hasher) => {
+                hasher.update(buf);
+            }
+            Self::Sha256(hasher) => hasher.update(buf),
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// What the digest of an entry is taken from
+enum Content {
+    /// A file on disk, read in full
+    File(PathBuf),
+    /// A symbolic link on disk, hashed by its target path
+    Link(PathBuf),
+    /// Known without reading the disk, like the object id of a file read from git
+    Known(Option<String>),
+}
+
+impl Content {
+    /// What to hash for the entry, or `None` for directories and special files, which have
+    /// no contents of their own
+    fn of(entry: &FileEntry, algorithm: HashAlgorithm) -> Option<Self> {
+        let on_disk = entry.metadata.is_some() || entry.cached.is_some();
+        match (entry.file_type, &entry.object) {
+            (FileType::File { .. }, _) if on_disk => Some(Self::File(entry.path.clone())),
+            (FileType::SymLink { .. }, _) if on_disk => Some(Self::Link(entry.path.clone())),
+            // Blobs are not read back from the repository; their id already digests them
+            (FileType::File { .. }, Some(object)) => Some(Self::Known(Some(format!("git:{}", object.id)))),
+            (FileType::SymLink { .. }, Some(object)) => Some(Self::Known(object.link_target.as_ref().map(|target| {
+                let mut hasher = Hasher::new(algorithm);
+                let _ = hasher.write_all(target.as_os_str().as_encoded_bytes());
+                hasher.finish()
+            }))),
+            _ => None,
+        }
+    }
+
+    fn digest(self, algorithm: HashAlgorithm) -> io::Result<Option<String>> {
+        let mut hasher = Hasher::new(algorithm);
+        match self {
+            Self::File(path) => {
+                io::copy(&mut File::open(path)?, &mut hasher)?;
+            }
+            Self::Link(path) => hasher.write_all(std::fs::read_link(path)?.as_os_str().as_encoded_bytes())?,
+            Self::Known(hash) => return Ok(hash),
+        }
+        Ok(Some(hasher.finish()))
+    }
+}
+
+/// An item in walk order, with the hash of its contents while it is being computed
+struct Slot {
+    item: StreamResult<FileEntry>,
+    hashing: Option<oneshot::Receiver<io::Result<Option<String>>>>,
+}
+
+impl Slot {
+    fn ready(item: StreamResult<FileEntry>) -> Self {
+        Self { item, hashing: None }
+    }
+}
+
+/// A child of an open directory, as it goes into the directory's fingerprint
+struct Child {
+    name: String,
+    mode: &'static str,
+    hash: Option<String>,
+}
+
+/// A directory whose subtree is still being hashed
+struct OpenDir {
+    depth: usize,
+    /// Position of the directory in the output
+    seq: usize,
+    children: Vec<Child>,
+    /// Cleared when the walk could not read all of the subtree
+    complete: bool,
+}
+
+/// Sets `FileEntry::hash` to the digest of the contents of files and to the target path of
+/// symbolic links, and directories to a Merkle fingerprint of their children.
+///
+/// A fingerprint hashes one line per child, sorted by name: its git-like mode, its name and
+/// its own hash. It only depends on what the walk lists, so ignored and hidden entries are
+/// left out, and not on the name of the directory itself. Special files are left out too.
+/// A directory with a child that could not be hashed, or below which the walk failed or was
+/// cut, gets no fingerprint.
+///
+/// Files are read on the shared rayon pool, up to [IN_FLIGHT] ahead of the entries being
+/// folded, or on the calling thread with `--threads serial`. With a [ScanCache], the files
+/// unchanged since their digest was cached are not read again. Like the `SizeAggregator`, a
+/// directory and the entries after it are held back until its subtree is hashed, and
+/// entries deeper than `display_depth` are only walked for their hashes.
+pub struct ContentHasher {
+    source: Pin<Box<dyn Stream<Item = StreamResult<FileEntry>> + Send>>,
+    algorithm: HashAlgorithm,
+    display_depth: usize,
+    serial: bool,
+    cache: Option<ScanCache>,
+    queue: VecDeque<Slot>,
+    /// Position of the front of `queue` in the output
+    front_seq: usize,
+    /// Position of the first entry whose hash is not folded into its parent yet
+    cursor: usize,
+    open: Vec<OpenDir>,
+    /// A cut by the scan limits, passed on after the entries held back before it
+    truncation: Option<StreamError>,
+    source_done: bool,
+    is_complete: bool,
+}
+
+impl ContentHasher {
+    pub fn new(
+        source: impl Stream<Item = StreamResult<FileEntry>> + Send + 'static,
+        algorithm: HashAlgorithm,
+        display_depth: usize,
+        serial: bool,
+        cache: Option<ScanCache>,
+    ) -> Self {
+        Self {
+            source: Box::pin(source),
+            algorithm,
+            display_depth,
+            serial,
+            cache,
+            queue: VecDeque::new(),
+            front_seq: 0,
+            cursor: 0,
+            open: Vec::new(),
+            truncation: None,
+            source_done: false,
+            is_complete: false,
+        }
+    }
+
+    fn push(&mut self, mut entry: FileEntry) {
+        let content = match entry.ignored {
+            Some(_) => None,
+            None => Content::of(&entry, self.algorithm),
+        };
+        if let (Some(Content::File(path)), Some(cache), Some(stat)) = (&content, &self.cache, &entry.cached)
+            && let Some(hash) = cache.hash(path, stat, self.algorithm)
+        {
+            entry.hash = Some(hash);
+            self.queue.push_back(Slot::ready(Ok(entry)));
+            return;
+        }
+        match content {
+            None => self.queue.push_back(Slot::ready(Ok(entry))),
+            Some(content) if self.serial => {
+                let error = set_hash(&mut entry, content.digest(self.algorithm));
+                self.remember(&entry);
+                self.queue.push_back(Slot::ready(Ok(entry)));
+                self.queue.extend(error.map(|err| Slot::ready(Err(err))));
+            }
+            Some(content) => {
+                let (sender, receiver) = oneshot::channel();
+                let algorithm = self.algorithm;
+                rayon::spawn(move || {
+                    let _ = sender.send(content.digest(algorithm));
+                });
+                self.queue.push_back(Slot {
+                    item: Ok(entry),
+                    hashing: Some(receiver),
+                });
+            }
+        }
+    }
+
+    /// Keep the digest of a regular file just read in the scan cache
+    fn remember(&self, entry: &FileEntry) {
+        if let (Some(cache), Some(stat), Some(hash), FileType::File { .. }) =
+            (&self.cache, &entry.cached, &entry.hash, entry.file_type)
+        {
+            cache.store_hash(&entry.path, stat, hash);
+        }
+    }
+
+    /// Fingerprint the directories at `depth` or deeper, whose subtrees are complete
+    fn close_dirs(&mut self, depth: usize) {
+        while self.open.last().is_some_and(|dir| dir.depth >= depth) {
+            let Some(mut dir) = self.open.pop() else {
+                break;
+            };
+            let hash = dir
+                .complete
+                .then(|| fingerprint(self.algorithm, &mut dir.children))
+                .flatten();
+            let Some(Slot { item: Ok(entry), .. }) = self.queue.get_mut(dir.seq - self.front_seq) else {
+                continue;
+            };
+            entry.hash = hash.clone();
+            let child = Child {
+                name: entry.name.clone(),
+                mode: "40000",
+                hash,
+            };
+            if let Some(parent) = self.open.last_mut() {
+                parent.children.push(child);
+            }
+        }
+    }
+
+    /// Fold the entry at the cursor, whose hash is known, into the open directories
+    fn fold(&mut self) {
+        let seq = self.cursor;
+        self.cursor += 1;
+        let entry = match &self.queue[seq - self.front_seq].item {
+            Ok(entry) => entry,
+            // A directory link that leads back above itself is not descended into
+            Err(StreamError::Link(LinkError::Cycle { .. })) => {
+                if let Some(dir) = self.open.last_mut() {
+                    dir.complete = false;
+                }
+                return;
+            }
+            Err(StreamError::Link(_) | StreamError::Truncated(_)) => return,
+            // A directory that could not be read leaves its ancestors incomplete
+            Err(_) => {
+                for dir in &mut self.open {
+                    dir.complete = false;
+                }
+                return;
+            }
+        };
+        if entry.ignored.is_some() {
+            return;
+        }
+
+        let depth = entry.depth;
+        let child = match entry.file_type {
+            FileType::Directory { .. } => None,
+            FileType::SymLink { .. } => Some("120000"),
+            FileType::File { exec: true, .. } => Some("100755"),
+            FileType::File { .. } => Some("100644"),
+            _ => return,
+        }
+        .map(|mode| Child {
+            name: entry.name.clone(),
+            mode,
+            hash: entry.hash.clone(),
+        });
+
+        self.close_dirs(depth);
+        match child {
+            None => self.open.push(OpenDir {
+                depth,
+                seq,
+                children: Vec::new(),
+                complete: true,
+            }),
+            Some(child) => {
+                if let Some(parent) = self.open.last_mut() {
+                    parent.children.push(child);
+                }
+            }
+        }
+    }
+
+    /// Wait for the hash of the entry at the cursor; a failed read is reported after it
+    fn poll_cursor(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let index = self.cursor - self.front_seq;
+        let Slot {
+            item: Ok(entry),
+            hashing: Some(receiver),
+        } = &mut self.queue[index]
+        else {
+            return Poll::Ready(());
+        };
+        let Poll::Ready(hashed) = Pin::new(receiver).poll(cx) else {
+            return Poll::Pending;
+        };
+        // A hash that panicked leaves the file without one
+        let error = set_hash(entry, hashed.unwrap_or(Ok(None)));
+        self.queue[index].hashing = None;
+        if let Ok(entry) = &self.queue[index].item {
+            self.remember(entry);
+        }
+        if let Some(err) = error {
+            self.queue.insert(index + 1, Slot::ready(Err(err)));
+        }
+        Poll::Ready(())
+    }
+
+    /// The next item whose hash is final; entries below the display depth are dropped
+    fn release(&mut self) -> Option<StreamResult<FileEntry>> {
+        while self.front_seq < self.cursor
+            && self.open.first().is_none_or(|dir| dir.seq > self.front_seq)
+        {
+            self.front_seq += 1;
+            match self.queue.pop_front().map(|slot| slot.item) {
+                Some(Ok(entry)) if entry.depth > self.display_depth => {}
+                Some(item) => return Some(item),
+                None => {}
+            }
+        }
+        None
+    }
+}
+
+/// The Merkle fingerprint of a directory, or `None` when one of its children has no hash
+fn fingerprint(algorithm: HashAlgorithm, children: &mut [Child]) -> Option<String> {
+    children.sort_by(|a, b| a.name.as_bytes().cmp(b.name.as_bytes()));
+    let mut hasher = Hasher::new(algorithm);
+    for child in children.iter() {
+        let hash = child.hash.as_ref()?;
+        let _ = writeln!(hasher, "{} {}\0{}", child.mode, child.name, hash);
+    }
+    Some(hasher.finish())
+}
+
+/// Set the hash of the entry, or return the error reading it, with the path of the entry
+fn set_hash(entry: &mut FileEntry, hashed: io::Result<Option<String>>) -> Option<StreamError> {
+    match hashed {
+        Ok(hash) => {
+            entry.hash = hash;
+            None
+        }
+        Err(err) => Some(StreamError::Io(io::Error::new(
+            err.kind(),
+            format!("{}: {}", entry.path.display(), err),
+        ))),
+    }
+}
+
+impl Stream for ContentHasher {
+    type Item = StreamResult<FileEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(item) = self.release() {
+                return Poll::Ready(Some(item));
+            }
+            if self.is_complete {
+                return Poll::Ready(self.truncation.take().map(Err));
+            }
+
+            let mut progress = false;
+            while !self.source_done && self.front_seq + self.queue.len() - self.cursor < IN_FLIGHT {
+                match self.source.as_mut().poll_next(cx) {
+                    Poll::Ready(Some(Ok(entry))) => self.push(entry),
+                    Poll::Ready(Some(Err(e @ StreamError::Truncated(_)))) => self.truncation = Some(e),
+                    Poll::Ready(Some(Err(e))) => self.queue.push_back(Slot::ready(Err(e))),
+                    Poll::Ready(None) => self.source_done = true,
+                    Poll::Pending => break,
+                }
+                progress = true;
+            }
+
+            while self.cursor < self.front_seq + self.queue.len() {
+                if self.poll_cursor(cx).is_pending() {
+                    break;
+                }
+                self.fold();
+                progress = true;
+            }
+
+            if self.source_done && self.cursor == self.front_seq + self.queue.len() {
+                // The directories still open when the walk was cut miss some entries
+                if self.truncation.is_some() {
+                    for dir in &mut self.open {
+                        dir.complete = false;
+                    }
+                }
+                self.close_dirs(0);
+                self.is_complete = true;
+                progress = true;
+            }
+
+            if !progress {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use futures::StreamExt;
+    use tempfile::tempdir;
+
+    use super::{fingerprint, Child, ContentHasher, Hasher};
+    use crate::flags::HashAlgorithm;
+    use crate::stream::test_walk;
+
+    const EMPTY_SHA256: &str = "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn child(name: &str, mode: &'static str, hash: Option<&str>) -> Child {
+        Child {
+            name: name.to_string(),
+            mode,
+            hash: hash.map(str::to_string),
+        }
+    }
+
+    fn digest(algorithm: HashAlgorithm, bytes: &[u8]) -> String {
+        let mut hasher = Hasher::new(algorithm);
+        std::io::Write::write_all(&mut hasher, bytes).unwrap();
+        hasher.finish()
+    }
+
+    /// The hash of every entry under `root`, by name
+    fn hashes(root: &Path, algorithm: HashAlgorithm) -> Vec<(String, Option<String>)> {
+        let hasher = ContentHasher::new(test_walk(root), algorithm, usize::MAX, true, None);
+        futures::executor::block_on(hasher.collect::<Vec<_>>())
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                (entry.name, entry.hash)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_digests() {
+        assert_eq!(digest(HashAlgorithm::Sha256, b""), EMPTY_SHA256);
+        assert_eq!(
+            digest(HashAlgorithm::Sha256, b"abc"),
+            "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            digest(HashAlgorithm::Blake3, b""),
+            "blake3:af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_lines() {
+        // An empty directory hashes no lines
+        assert_eq!(fingerprint(HashAlgorithm::Sha256, &mut []).unwrap(), EMPTY_SHA256);
+
+        let mut children = [child("b", "100755", Some("h2")), child("a", "100644", Some("h1"))];
+        assert_eq!(
+            fingerprint(HashAlgorithm::Sha256, &mut children).unwrap(),
+            digest(HashAlgorithm::Sha256, b"100644 a\0h1\n100755 b\0h2\n")
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_child_order() {
+        let mut forward = [
+            child("a", "100644", Some("h1")),
+            child("B", "40000", Some("h2")),
+            child("c", "120000", Some("h3")),
+        ];
+        let mut backward = [
+            child("c", "120000", Some("h3")),
+            child("B", "40000", Some("h2")),
+            child("a", "100644", Some("h1")),
+        ];
+        assert_eq!(
+            fingerprint(HashAlgorithm::Blake3, &mut forward),
+            fingerprint(HashAlgorithm::Blake3, &mut backward)
+        );
+        // Names are sorted by their bytes, upper case first
+        assert_eq!(forward.map(|child| child.name), ["B", "a", "c"]);
+    }
+
+    #[test]
+    fn test_fingerprint_changes_with_each_field() {
+        let print = |name, mode, hash| fingerprint(HashAlgorithm::Blake3, &mut [child(name, mode, Some(hash))]);
+        let base = print("a", "100644", "h1");
+        assert_ne!(base, print("b", "100644", "h1"));
+        assert_ne!(base, print("a", "100755", "h1"));
+        assert_ne!(base, print("a", "100644", "h2"));
+        assert_ne!(base, fingerprint(HashAlgorithm::Sha256, &mut [child("a", "100644", Some("h1"))]));
+    }
+
+    #[test]
+    fn test_fingerprint_needs_every_child_hash() {
+        let mut children = [child("a", "100644", Some("h1")), child("b", "100644", None)];
+        assert_eq!(fingerprint(HashAlgorithm::Blake3, &mut children), None);
+    }
+
+    #[test]
+    fn test_tree_hashes() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a"), "abc").unwrap();
+        fs::write(root.join("sub/b"), "").unwrap();
+
+        let algorithm = HashAlgorithm::Sha256;
+        let sub = fingerprint(algorithm, &mut [child("b", "100644", Some(EMPTY_SHA256))]).unwrap();
+        let a = digest(algorithm, b"abc");
+        let top = fingerprint(
+            algorithm,
+            &mut [child("a", "100644", Some(&a)), child("sub", "40000", Some(&sub))],
+        )
+        .unwrap();
+        assert_eq!(
+            hashes(&root, algorithm),
+            [
+                ("root".to_string(), Some(top)),
+                ("a".to_string(), Some(a)),
+                ("sub".to_string(), Some(sub)),
+                ("b".to_string(), Some(EMPTY_SHA256.to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tree_hash_follows_contents_not_root_name() {
+        let dir = tempdir().unwrap();
+        let (first, second) = (dir.path().join("first"), dir.path().join("second"));
+        for root in [&first, &second] {
+            fs::create_dir_all(root.join("sub")).unwrap();
+            fs::write(root.join("sub/b"), "same").unwrap();
+        }
+        let root_hash = |root: &Path| hashes(root, HashAlgorithm::Blake3).remove(0).1;
+        assert_eq!(root_hash(&first), root_hash(&second));
+
+        fs::write(second.join("sub/b"), "changed").unwrap();
+        assert_ne!(root_hash(&first), root_hash(&second));
+    }
+}
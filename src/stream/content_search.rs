@@ -0,0 +1,257 @@
+//! Content search: reads regular files on the rayon pool and counts the lines matching the
+//! `--contains` pattern
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::future::Future;
+use std::io::Read;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use futures::channel::oneshot;
+use futures::Stream;
+
+use crate::filter::Filter;
+use crate::flags::Contains;
+use crate::meta::FileType;
+use crate::stream::{FileEntry, StreamResult};
+
+/// How many files are searched ahead of the entry the stream waits for
+const IN_FLIGHT: usize = 256;
+
+/// A file holding a NUL byte in its first bytes is taken as binary, like grep does
+const BINARY_PROBE: usize = 8192;
+
+/// How many line numbers are kept with `--contains-lines`
+const MAX_LINES: usize = 10;
+
+/// The lines of a file matching the `--contains` pattern
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentMatches {
+    pub count: usize,
+    /// The first matching line numbers, from 1, with `--contains-lines`
+    pub lines: Vec<u64>,
+}
+
+/// An entry in output order, with the search of its contents still running
+enum Slot {
+    Ready(StreamResult<FileEntry>),
+    Searching(FileEntry, oneshot::Receiver<Option<ContentMatches>>),
+}
+
+/// Sets `FileEntry::matches` on the regular files whose contents could be searched: binary
+/// files, files over the size limit and unreadable files are left without.
+///
+/// Files are read on the shared rayon pool, up to [IN_FLIGHT] ahead of the output, and their
+/// entries are passed on in walk order. With `--threads serial` they are read on the calling
+/// thread. Files a `--where` expression already rules out are not read.
+pub struct ContentSearch {
+    source: Pin<Box<dyn Stream<Item = StreamResult<FileEntry>> + Send>>,
+    options: Arc<Contains>,
+    /// Skips reading the files it rejects
+    prefilter: Option<Filter>,
+    serial: bool,
+    queue: VecDeque<Slot>,
+    source_done: bool,
+}
+
+impl ContentSearch {
+    pub fn new(
+        source: impl Stream<Item = StreamResult<FileEntry>> + Send + 'static,
+        options: &Contains,
+        prefilter: Option<&Filter>,
+        serial: bool,
+    ) -> Self {
+        Self {
+            source: Box::pin(source),
+            options: Arc::new(options.clone()),
+            prefilter: prefilter.cloned(),
+            serial,
+            queue: VecDeque::new(),
+            source_done: false,
+        }
+    }
+
+    fn push(&mut self, mut entry: FileEntry) {
+        let searched = matches!(entry.file_type, FileType::File { .. })
+            && self
+                .prefilter
+                .as_ref()
+                .is_none_or(|filter| filter.matches(&entry));
+        if !searched {
+            self.queue.push_back(Slot::Ready(Ok(entry)));
+        } else if self.serial {
+            entry.matches = search(&entry.path, &self.options);
+            self.queue.push_back(Slot::Ready(Ok(entry)));
+        } else {
+            let (sender, receiver) = oneshot::channel();
+            let options = Arc::clone(&self.options);
+            let path = entry.path.clone();
+            rayon::spawn(move || {
+                let _ = sender.send(search(&path, &options));
+            });
+            self.queue.push_back(Slot::Searching(entry, receiver));
+        }
+    }
+}
+
+/// Count the lines of the file at `path` matching the pattern, or `None` when the file is not
+/// searched
+fn search(path: &Path, options: &Contains) -> Option<ContentMatches> {
+    let regex = options.regex.as_ref()?;
+    let file = File::open(path).ok()?;
+    if file.metadata().ok()?.len() > options.max_size {
+        return None;
+    }
+
+    // The file may have grown since it was stat'ed
+    let mut data = Vec::new();
+    file.take(options.max_size + 1).read_to_end(&mut data).ok()?;
+    if data.len() as u64 > options.max_size || data[..data.len().min(BINARY_PROBE)].contains(&0) {
+        return None;
+    }
+
+    // A match is counted on the line it starts on, so one spanning lines counts once
+    let mut matches = ContentMatches::default();
+    let (mut line, mut scanned) = (1, 0);
+    let mut last = None;
+    for found in regex.find_iter(&data) {
+        line += data[scanned..found.start()].iter().filter(|byte| **byte == b'\n').count() as u64;
+        scanned = found.start();
+        if last == Some(line) {
+            continue;
+        }
+        last = Some(line);
+        matches.count += 1;
+        if options.line_numbers && matches.lines.len() < MAX_LINES {
+            matches.lines.push(line);
+        }
+    }
+    Some(matches)
+}
+
+impl Stream for ContentSearch {
+    type Item = StreamResult<FileEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while !self.source_done && self.queue.len() < IN_FLIGHT {
+            match self.source.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(entry))) => self.push(entry),
+                Poll::Ready(Some(Err(e))) => self.queue.push_back(Slot::Ready(Err(e))),
+                Poll::Ready(None) => self.source_done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        let source_done = self.source_done;
+        match self.queue.front_mut() {
+            None if source_done => return Poll::Ready(None),
+            None => return Poll::Pending,
+            Some(Slot::Ready(_)) => {}
+            Some(Slot::Searching(entry, receiver)) => {
+                let Poll::Ready(matches) = Pin::new(receiver).poll(cx) else {
+                    return Poll::Pending;
+                };
+                // A search that panicked leaves the file unsearched
+                entry.matches = matches.ok().flatten();
+            }
+        }
+
+        match self.queue.pop_front() {
+            Some(Slot::Ready(item)) => Poll::Ready(Some(item)),
+            Some(Slot::Searching(entry, _)) => Poll::Ready(Some(Ok(entry))),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use clap::Parser;
+    use futures::StreamExt;
+    use tempfile::tempdir;
+
+    use super::{search, ContentMatches, ContentSearch};
+    use crate::app::Cli;
+    use crate::config_file::Config;
+    use crate::flags::Contains;
+    use crate::stream::test_walk;
+
+    fn contains(args: &[&str]) -> Contains {
+        let argv = [&["sap", "--contains-lines", "--contains"], args].concat();
+        Contains::configure_from(&Cli::try_parse_from(argv).unwrap(), &Config::with_none()).unwrap()
+    }
+
+    fn matches(lines: &[u64]) -> Option<ContentMatches> {
+        Some(ContentMatches {
+            count: lines.len(),
+            lines: lines.to_vec(),
+        })
+    }
+
+    #[test]
+    fn test_anchored_match_on_line_3() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("main.rs");
+        fs::write(&path, "// header\nfn main() {}\nuse std::io;\n  use std::fs;\n").unwrap();
+        assert_eq!(matches(&[3]), search(&path, &contains(&["^use "])));
+        assert_eq!(matches(&[2]), search(&path, &contains(&["\\{\\}$"])));
+    }
+
+    #[test]
+    fn test_match_spanning_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "one\nfoo\nbar foo\nbar\n").unwrap();
+        assert_eq!(matches(&[2, 3]), search(&path, &contains(&["foo\\nbar"])));
+        assert_eq!(matches(&[]), search(&path, &contains(&["baz"])));
+    }
+
+    #[test]
+    fn test_unsearched_files() {
+        let dir = tempdir().unwrap();
+        let binary = dir.path().join("blob.bin");
+        fs::write(&binary, b"match\0match").unwrap();
+        assert_eq!(None, search(&binary, &contains(&["match"])));
+
+        let large = dir.path().join("large.txt");
+        fs::write(&large, "match\n".repeat(4)).unwrap();
+        assert_eq!(None, search(&large, &contains(&["match", "--contains-max-size", "8"])));
+        assert_eq!(None, search(&dir.path().join("missing"), &contains(&["match"])));
+    }
+
+    fn searched(root: &Path, serial: bool) -> Vec<(String, Option<usize>)> {
+        let stream = ContentSearch::new(test_walk(root), &contains(&["needle"]), None, serial);
+        futures::executor::block_on(stream.collect::<Vec<_>>())
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                (entry.name, entry.matches.map(|matches| matches.count))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stream_keeps_walk_order() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("a.txt"), "needle\nneedle\n").unwrap();
+        fs::write(dir.path().join("src/b.txt"), "hay\n").unwrap();
+        fs::write(dir.path().join("z.txt"), "needle\n").unwrap();
+
+        let root = dir.path().file_name().unwrap().to_string_lossy().to_string();
+        let expected = vec![
+            (root, None),
+            ("a.txt".to_string(), Some(2)),
+            ("src".to_string(), None),
+            ("b.txt".to_string(), Some(0)),
+            ("z.txt".to_string(), Some(1)),
+        ];
+        assert_eq!(expected, searched(dir.path(), true));
+        assert_eq!(expected, searched(dir.path(), false));
+    }
+}
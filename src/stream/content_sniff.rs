@@ -0,0 +1,355 @@
+//! Content sniffer: reads the first bytes of regular files on the rayon pool and tells their
+//! type from them, and the headers of images, audio, video and ELF binaries when asked
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures::channel::oneshot;
+use futures::Stream;
+
+use crate::meta::{ContentType, Elf, FileType, Media};
+use crate::stream::{FileEntry, StreamResult};
+
+/// How many files are sniffed ahead of the entry the stream waits for
+const IN_FLIGHT: usize = 256;
+
+/// An entry in output order, with the sniffing of its contents still running
+enum Slot {
+    Ready(StreamResult<FileEntry>),
+    Sniffing(FileEntry, oneshot::Receiver<Sniffed>),
+}
+
+/// What the `ContentSniffer` reads of each file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SniffOptions {
+    /// The type of the contents, from their first bytes
+    pub content_type: bool,
+    /// The headers of images, audio and video, which need the type
+    pub media: bool,
+    /// The headers of ELF files, which cost a 4-byte read for the other files when their type
+    /// is not sniffed
+    pub binary: bool,
+}
+
+/// What was read of a file
+#[derive(Default)]
+struct Sniffed {
+    content_type: Option<ContentType>,
+    media: Option<Media>,
+    binary: Option<Elf>,
+}
+
+/// Sets `FileEntry::content_type`, `FileEntry::media` and `FileEntry::binary`, as the
+/// [SniffOptions] ask, on the regular files on disk; unreadable files and files read from a git
+/// revision are left without.
+///
+/// Files are read on the shared rayon pool, up to [IN_FLIGHT] ahead of the output, and their
+/// entries are passed on in walk order. With `--threads serial` they are read on the calling
+/// thread.
+pub struct ContentSniffer {
+    source: Pin<Box<dyn Stream<Item = StreamResult<FileEntry>> + Send>>,
+    options: SniffOptions,
+    serial: bool,
+    queue: VecDeque<Slot>,
+    source_done: bool,
+}
+
+impl ContentSniffer {
+    pub fn new(
+        source: impl Stream<Item = StreamResult<FileEntry>> + Send + 'static,
+        options: SniffOptions,
+        serial: bool,
+    ) -> Self {
+        Self {
+            source: Box::pin(source),
+            options,
+            serial,
+            queue: VecDeque::new(),
+            source_done: false,
+        }
+    }
+
+    fn push(&mut self, mut entry: FileEntry) {
+        // The disk walk sniffs files already when they are sorted by type
+        let options = self.options;
+        let sniffed = matches!(entry.file_type, FileType::File { .. })
+            && ((options.content_type && entry.content_type.is_none()) || options.media || options.binary)
+            && (entry.metadata.is_some() || entry.cached.is_some() || entry.object.is_none());
+        if !sniffed {
+            self.queue.push_back(Slot::Ready(Ok(entry)));
+        } else if self.serial {
+            let sniffed = sniff(&entry.path, entry.content_type.take(), options);
+            sniffed.apply(&mut entry);
+            self.queue.push_back(Slot::Ready(Ok(entry)));
+        } else {
+            let (sender, receiver) = oneshot::channel();
+            let path = entry.path.clone();
+            let known = entry.content_type.clone();
+            rayon::spawn(move || {
+                let _ = sender.send(sniff(&path, known, options));
+            });
+            self.queue.push_back(Slot::Sniffing(entry, receiver));
+        }
+    }
+}
+
+impl Sniffed {
+    fn apply(self, entry: &mut FileEntry) {
+        entry.content_type = self.content_type;
+        entry.media = self.media;
+        entry.binary = self.binary;
+    }
+}
+
+/// Sniff the file at `path` unless its type is `known`, then read the headers `options` ask for
+fn sniff(path: &Path, known: Option<ContentType>, options: SniffOptions) -> Sniffed {
+    let content_type = match known {
+        None if options.content_type => ContentType::read(path).ok(),
+        known => known,
+    };
+    let media = match &content_type {
+        Some(content_type) if options.media => Media::read(path, content_type.mime).ok().flatten(),
+        _ => None,
+    };
+    // A sniffed type tells the ELF files apart without reading the others again
+    let binary = match &content_type {
+        _ if !options.binary => None,
+        Some(content_type) if !content_type.is_elf() => None,
+        _ => Elf::read(path).ok().flatten(),
+    };
+    Sniffed {
+        content_type,
+        media,
+        binary,
+    }
+}
+
+impl Stream for ContentSniffer {
+    type Item = StreamResult<FileEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while !self.source_done && self.queue.len() < IN_FLIGHT {
+            match self.source.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(entry))) => self.push(entry),
+                Poll::Ready(Some(Err(e))) => self.queue.push_back(Slot::Ready(Err(e))),
+                Poll::Ready(None) => self.source_done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        let source_done = self.source_done;
+        match self.queue.front_mut() {
+            None if source_done => return Poll::Ready(None),
+            None => return Poll::Pending,
+            Some(Slot::Ready(_)) => {}
+            Some(Slot::Sniffing(entry, receiver)) => {
+                let Poll::Ready(sniffed) = Pin::new(receiver).poll(cx) else {
+                    return Poll::Pending;
+                };
+                // A sniff that panicked leaves the file without a type
+                sniffed.unwrap_or_default().apply(entry);
+            }
+        }
+
+        match self.queue.pop_front() {
+            Some(Slot::Ready(item)) => Poll::Ready(Some(item)),
+            Some(Slot::Sniffing(entry, _)) => Poll::Ready(Some(Ok(entry))),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use futures::StreamExt;
+    use tempfile::tempdir;
+
+    use super::{ContentSniffer, SniffOptions};
+    use crate::stream::{test_walk, FileEntry};
+
+    fn sniffed(root: &Path, options: SniffOptions, serial: bool) -> Vec<FileEntry> {
+        let sniffer = ContentSniffer::new(test_walk(root), options, serial);
+        futures::executor::block_on(sniffer.collect::<Vec<_>>())
+            .into_iter()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    fn fixture(root: &Path) {
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("data"), b"\x00\x01\x02").unwrap();
+        fs::write(root.join("empty"), b"").unwrap();
+        fs::write(root.join("script"), b"#!/bin/sh\necho hi\n").unwrap();
+        fs::write(root.join("sub/page"), b"<!doctype html>").unwrap();
+    }
+
+    #[test]
+    fn test_types_in_walk_order() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fixture(&root);
+
+        let options = SniffOptions {
+            content_type: true,
+            ..SniffOptions::default()
+        };
+        for serial in [true, false] {
+            let types: Vec<(String, Option<&str>)> = sniffed(&root, options, serial)
+                .into_iter()
+                .map(|entry| (entry.name, entry.content_type.map(|content| content.mime)))
+                .collect();
+            assert_eq!(
+                types,
+                [
+                    ("root".to_string(), None),
+                    ("data".to_string(), Some("application/octet-stream")),
+                    ("empty".to_string(), Some("inode/x-empty")),
+                    ("script".to_string(), Some("text/x-shellscript")),
+                    ("sub".to_string(), None),
+                    ("page".to_string(), Some("text/html")),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_nothing_read_without_options() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fixture(&root);
+
+        let entries = sniffed(&root, SniffOptions::default(), true);
+        assert_eq!(entries.len(), 6);
+        assert!(entries.iter().all(|entry| entry.content_type.is_none()));
+    }
+
+    #[test]
+    fn test_media_headers_from_the_sniffed_type() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(&root).unwrap();
+        let mut image = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        image.extend_from_slice(&[0, 0, 0, 3, 0, 0, 0, 2, 8, 6, 0, 0, 0]);
+        fs::write(root.join("image"), image).unwrap();
+        fs::write(root.join("text"), "<svg width=\"1\" height=\"1\">").unwrap();
+
+        let options = SniffOptions {
+            content_type: true,
+            media: true,
+            binary: false,
+        };
+        let media: Vec<(String, Option<String>)> = sniffed(&root, options, true)
+            .into_iter()
+            .map(|entry| (entry.name, entry.media.map(|media| media.to_string())))
+            .collect();
+        assert_eq!(
+            media,
+            [
+                ("root".to_string(), None),
+                ("image".to_string(), Some("3\u{d7}2".to_string())),
+                ("text".to_string(), Some("1\u{d7}1".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_media_files_are_listed_by_default() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(&root).unwrap();
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 0, 3, 0, 0, 0, 2, 8, 6, 0, 0, 0]);
+        fs::write(root.join("logo.png"), png).unwrap();
+        // 8 kHz mono 8-bit samples, two seconds of them
+        let rate = 8_000u32.to_le_bytes();
+        let format = [b"fmt \x10\0\0\0\x01\0\x01\0".as_slice(), &rate, &rate, b"\x01\0\x08\0"].concat();
+        let wav = [b"RIFF\0\0\0\0WAVE".as_slice(), &format, b"data", &16_000u32.to_le_bytes()].concat();
+        fs::write(root.join("tone.wav"), wav).unwrap();
+        // A movie header of 3000 units of a millisecond
+        let mut header = [0; 100];
+        header[12..16].copy_from_slice(&1_000u32.to_be_bytes());
+        header[16..20].copy_from_slice(&3_000u32.to_be_bytes());
+        let movie = [&108u32.to_be_bytes()[..], b"mvhd", &header].concat();
+        let ftyp = b"\0\0\0\x14ftypisom\0\0\x02\0isom";
+        let mp4 = [ftyp.as_slice(), &116u32.to_be_bytes(), b"moov", &movie].concat();
+        fs::write(root.join("clip.mp4"), mp4).unwrap();
+
+        let options = SniffOptions {
+            content_type: true,
+            media: true,
+            binary: false,
+        };
+        let media: Vec<_> = sniffed(&root, options, true)
+            .into_iter()
+            .filter_map(|entry| {
+                let media = entry.media?;
+                Some((entry.name, media.width.zip(media.height), media.duration))
+            })
+            .collect();
+        assert_eq!(
+            media,
+            [
+                ("clip.mp4".to_string(), None, Some(3.0)),
+                ("logo.png".to_string(), Some((3, 2)), None),
+                ("tone.wav".to_string(), None, Some(2.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shared_libraries_are_listed_by_default() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(&root).unwrap();
+        // The header of a little-endian 64-bit x86-64 shared library
+        let mut library = b"\x7fELF\x02\x01\x01".to_vec();
+        library.resize(64, 0);
+        library[16] = 3;
+        library[18] = 62;
+        library[20] = 1;
+        library[52] = 64;
+        fs::write(root.join("libdemo.so"), library).unwrap();
+
+        let options = SniffOptions {
+            binary: true,
+            ..SniffOptions::default()
+        };
+        let binaries: Vec<_> = sniffed(&root, options, true)
+            .into_iter()
+            .filter_map(|entry| Some((entry.name, entry.binary?.to_json()["type"].clone())))
+            .collect();
+        assert_eq!(binaries, [("libdemo.so".to_string(), "shared-library".into())]);
+    }
+
+    #[test]
+    fn test_images_documents_and_archives_are_sniffed_by_default() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("logo.png"), b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR").unwrap();
+        fs::write(root.join("manual.pdf"), b"%PDF-1.7\n").unwrap();
+        fs::write(root.join("release.zip"), b"PK\x03\x04\x14\0\0\0").unwrap();
+
+        let options = SniffOptions {
+            content_type: true,
+            ..SniffOptions::default()
+        };
+        let types: Vec<_> = sniffed(&root, options, true)
+            .into_iter()
+            .filter_map(|entry| Some((entry.name, entry.content_type?.mime)))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("logo.png".to_string(), "image/png"),
+                ("manual.pdf".to_string(), "application/pdf"),
+                ("release.zip".to_string(), "application/zip"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,357 @@
+//! Duplicate finder: groups the walked files by their contents, for `--duplicates`
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use gix::ObjectId;
+use rayon::prelude::*;
+use serde_json::json;
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+use crate::meta::FileType;
+use crate::stream::FileEntry;
+
+/// How many bytes from the start of a file the partial hash reads
+const PARTIAL: usize = 4096;
+
+/// The contents of a file: what is hashed so far, or the object id of a file read from git
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+enum Key {
+    Size(u64),
+    Partial(u64, [u8; 32]),
+    Full(u64, [u8; 32]),
+    Object(u64, ObjectId),
+}
+
+/// A file with the paths of its hard links, in walk order
+#[derive(Debug)]
+struct Candidate {
+    paths: Vec<PathBuf>,
+    object: Option<ObjectId>,
+}
+
+/// Files with the same contents
+#[derive(Debug)]
+pub struct DuplicateGroup {
+    /// Size of each copy, in bytes
+    pub size: u64,
+    /// Hex digest of the contents: the BLAKE3 hash, or the git object id with `--at`
+    pub hash: String,
+    /// The paths of each copy: its first path in walk order, then its other hard links
+    pub files: Vec<Vec<PathBuf>>,
+}
+
+impl DuplicateGroup {
+    /// The bytes all copies but one take
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.files.len() as u64 - 1)
+    }
+
+    /// The `--llm` record of the group; each copy lists its other hard links
+    pub fn to_json(&self) -> serde_json::Value {
+        let files: Vec<serde_json::Value> = self
+            .files
+            .iter()
+            .map(|paths| {
+                let mut file = json!({ "path": paths[0].to_string_lossy() });
+                if paths.len() > 1 {
+                    file["links"] = paths[1..].iter().map(|path| path.to_string_lossy()).collect();
+                }
+                file
+            })
+            .collect();
+        json!({
+            "kind": "duplicates",
+            "size": self.size,
+            "hash": self.hash,
+            "count": self.files.len(),
+            "wasted": self.wasted(),
+            "files": files,
+        })
+    }
+
+    /// The last `--llm` record, with the totals of all groups
+    pub fn summary_to_json(groups: &[DuplicateGroup]) -> serde_json::Value {
+        json!({
+            "kind": "duplicates_summary",
+            "groups": groups.len(),
+            "copies": groups.iter().map(|group| group.files.len() - 1).sum::<usize>(),
+            "wasted": groups.iter().map(DuplicateGroup::wasted).sum::<u64>(),
+        })
+    }
+
+    /// The `--llm` record of a file that could not be read to compare it
+    pub fn error_to_json(path: &Path, error: &io::Error) -> serde_json::Value {
+        json!({
+            "kind": "error",
+            "error": "read",
+            "path": path.to_string_lossy(),
+            "message": error.to_string(),
+        })
+    }
+}
+
+/// Collects the regular files of the walk, then groups those with the same contents.
+///
+/// Files are compared by size first, then by a hash of their first [PARTIAL] bytes, and only
+/// then by a hash of their whole contents, so most files are never read in full; the hashes
+/// are computed on the shared rayon pool. Hard links to one file are the same copy, and empty
+/// files are skipped. Files read from a git revision are compared by object id.
+#[derive(Default)]
+pub struct DuplicateFinder {
+    candidates: Vec<(u64, Candidate)>,
+    /// Position in `candidates` of each (dev, inode) with several links
+    linked: HashMap<(u64, u64), usize>,
+}
+
+impl DuplicateFinder {
+    pub fn push(&mut self, entry: FileEntry) {
+        if !matches!(entry.file_type, FileType::File { .. }) || entry.is_symlink {
+            return;
+        }
+        let (size, links) = match (&entry.metadata, &entry.cached, &entry.object) {
+            #[cfg(unix)]
+            (Some(metadata), _, _) => (metadata.len(), (metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))),
+            #[cfg(not(unix))]
+            (Some(metadata), _, _) => (metadata.len(), None),
+            (None, Some(stat), _) => (stat.size, (stat.nlink > 1).then_some((stat.dev, stat.ino))),
+            (None, None, Some(object)) => (object.size, None),
+            (None, None, None) => return,
+        };
+        if size == 0 {
+            return;
+        }
+
+        if let Some(id) = links
+            && let Some(&index) = self.linked.get(&id)
+        {
+            self.candidates[index].1.paths.push(entry.path);
+            return;
+        }
+        if let Some(id) = links {
+            self.linked.insert(id, self.candidates.len());
+        }
+        let object = match (&entry.metadata, &entry.cached, &entry.object) {
+            (None, None, Some(object)) => Some(object.id),
+            _ => None,
+        };
+        self.candidates.push((
+            size,
+            Candidate {
+                paths: vec![entry.path],
+                object,
+            },
+        ));
+    }
+
+    /// The groups of copies, the most wasteful first, and the files that could not be read
+    pub fn finish(self) -> (Vec<DuplicateGroup>, Vec<(PathBuf, io::Error)>) {
+        let mut errors = Vec::new();
+        let candidates = self.candidates.into_iter().map(|(size, candidate)| (Key::Size(size), candidate));
+
+        let groups = regroup(candidates.collect(), &mut errors, |key, candidate| match (key, candidate.object) {
+            (Key::Size(size), Some(id)) => Ok(Key::Object(size, id)),
+            (Key::Size(size), None) => partial_hash(&candidate.paths[0]).map(|hash| Key::Partial(size, hash)),
+            (key, _) => Ok(key),
+        });
+        let groups = regroup(groups, &mut errors, |key, candidate| match key {
+            // The partial hash covered the whole file
+            Key::Partial(size, hash) if size <= PARTIAL as u64 => Ok(Key::Full(size, hash)),
+            Key::Partial(size, _) => full_hash(&candidate.paths[0]).map(|hash| Key::Full(size, hash)),
+            key => Ok(key),
+        });
+
+        let mut by_key: HashMap<Key, Vec<Candidate>> = HashMap::new();
+        for (key, candidate) in groups {
+            by_key.entry(key).or_default().push(candidate);
+        }
+        let mut duplicates: Vec<DuplicateGroup> = by_key
+            .into_iter()
+            .filter(|(_, copies)| copies.len() > 1)
+            .map(|(key, copies)| {
+                let (size, hash) = match key {
+                    Key::Full(size, hash) => (size, blake3::Hash::from(hash).to_hex().to_string()),
+                    Key::Object(size, id) => (size, id.to_string()),
+                    Key::Size(size) | Key::Partial(size, _) => (size, String::new()),
+                };
+                DuplicateGroup {
+                    size,
+                    hash,
+                    files: copies.into_iter().map(|copy| copy.paths).collect(),
+                }
+            })
+            .collect();
+
+        duplicates.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then_with(|| a.files[0].cmp(&b.files[0])));
+        (duplicates, errors)
+    }
+}
+
+/// Refine the key of the candidates that share theirs with another one, on the rayon pool;
+/// the others cannot have a copy and are dropped, like the files that cannot be read
+fn regroup(
+    candidates: Vec<(Key, Candidate)>,
+    errors: &mut Vec<(PathBuf, io::Error)>,
+    refine: impl Fn(Key, &Candidate) -> io::Result<Key> + Sync,
+) -> Vec<(Key, Candidate)> {
+    let mut counts: HashMap<Key, usize> = HashMap::new();
+    for (key, _) in &candidates {
+        *counts.entry(*key).or_default() += 1;
+    }
+
+    // Collecting keeps the walk order, which the copies of each group are listed in
+    let refined: Vec<_> = candidates
+        .into_par_iter()
+        .filter(|(key, _)| counts[key] > 1)
+        .map(|(key, candidate)| (refine(key, &candidate), candidate))
+        .collect();
+
+    let mut kept = Vec::with_capacity(refined.len());
+    for (key, candidate) in refined {
+        match key {
+            Ok(key) => kept.push((key, candidate)),
+            Err(err) => errors.push((candidate.paths[0].clone(), err)),
+        }
+    }
+    kept
+}
+
+fn partial_hash(path: &Path) -> io::Result<[u8; 32]> {
+    let mut data = Vec::with_capacity(PARTIAL);
+    File::open(path)?.take(PARTIAL as u64).read_to_end(&mut data)?;
+    Ok(*blake3::hash(&data).as_bytes())
+}
+
+fn full_hash(path: &Path) -> io::Result<[u8; 32]> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?)?;
+    Ok(*hasher.finalize().as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use futures::StreamExt;
+    use tempfile::tempdir;
+
+    use super::{DuplicateFinder, DuplicateGroup, PARTIAL};
+    use crate::stream::test_walk;
+
+    fn find(root: &Path) -> DuplicateFinder {
+        let mut finder = DuplicateFinder::default();
+        for entry in futures::executor::block_on(test_walk(root).collect::<Vec<_>>()) {
+            finder.push(entry.unwrap());
+        }
+        finder
+    }
+
+    /// The paths of each copy in each group, relative to `root`
+    fn copies(root: &Path, groups: &[DuplicateGroup]) -> Vec<Vec<Vec<PathBuf>>> {
+        let relative = |path: &PathBuf| path.strip_prefix(root).unwrap().to_path_buf();
+        groups
+            .iter()
+            .map(|group| group.files.iter().map(|paths| paths.iter().map(relative).collect()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_groups_by_contents() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a"), "same").unwrap();
+        fs::write(root.join("sub/b"), "same").unwrap();
+        // Same size, other contents
+        fs::write(root.join("c"), "diff").unwrap();
+        // Empty files are never copies
+        fs::write(root.join("empty"), "").unwrap();
+        fs::write(root.join("sub/empty"), "").unwrap();
+
+        // Large files that only differ past the partial hash
+        let large = vec![b'x'; PARTIAL * 2];
+        let mut other = large.clone();
+        other[PARTIAL + 1] = b'y';
+        fs::write(root.join("large1"), &large).unwrap();
+        fs::write(root.join("large2"), &large).unwrap();
+        fs::write(root.join("large3"), &other).unwrap();
+
+        let (groups, errors) = find(&root).finish();
+        assert!(errors.is_empty());
+        let path = |path: &str| vec![PathBuf::from(path)];
+        // The most wasteful group first, copies in walk order
+        assert_eq!(
+            copies(&root, &groups),
+            [vec![path("large1"), path("large2")], vec![path("a"), path("sub/b")]]
+        );
+        assert_eq!((groups[0].size, groups[0].wasted()), (PARTIAL as u64 * 2, PARTIAL as u64 * 2));
+        assert_eq!(groups[1].hash, blake3::hash(b"same").to_hex().to_string());
+        assert_eq!(groups[0].hash, blake3::hash(&large).to_hex().to_string());
+
+        let summary = DuplicateGroup::summary_to_json(&groups);
+        assert_eq!(summary["copies"], 2);
+        assert_eq!(summary["wasted"], PARTIAL as u64 * 2 + 4);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hard_links_are_one_copy() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a"), "same").unwrap();
+        fs::hard_link(root.join("a"), root.join("a-link")).unwrap();
+
+        // Links alone are no copies
+        let (groups, _) = find(&root).finish();
+        assert!(groups.is_empty());
+
+        fs::write(root.join("b"), "same").unwrap();
+        let (groups, _) = find(&root).finish();
+        assert_eq!(
+            copies(&root, &groups),
+            [vec![vec![PathBuf::from("a"), PathBuf::from("a-link")], vec![PathBuf::from("b")]]]
+        );
+        assert_eq!(groups[0].wasted(), 4);
+        assert_eq!(groups[0].to_json()["files"][0]["links"][0], root.join("a-link").to_string_lossy().as_ref());
+    }
+
+    #[test]
+    fn test_unreadable_files_are_reported() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a"), "same").unwrap();
+        fs::write(root.join("b"), "same").unwrap();
+        fs::write(root.join("c"), "same").unwrap();
+
+        // Removed between the walk and the comparison
+        let finder = find(&root);
+        fs::remove_file(root.join("b")).unwrap();
+        let (groups, errors) = finder.finish();
+        assert_eq!(copies(&root, &groups), [vec![vec![PathBuf::from("a")], vec![PathBuf::from("c")]]]);
+        let errors: Vec<_> = errors.into_iter().map(|(path, _)| path).collect();
+        assert_eq!(errors, [root.join("b")]);
+    }
+
+    #[test]
+    fn test_image_assets_are_compared_by_default() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(root.join("assets")).unwrap();
+        let image = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR";
+        fs::write(root.join("logo.png"), image).unwrap();
+        fs::write(root.join("assets/logo-copy.png"), image).unwrap();
+
+        let (groups, _) = find(&root).finish();
+        assert_eq!(
+            copies(&root, &groups),
+            [vec![vec![PathBuf::from("assets/logo-copy.png")], vec![PathBuf::from("logo.png")]]]
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! Entry filter: keeps the entries matching the `--where` expression or the `--contains`
+//! pattern, and the directories leading to them
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures::Stream;
+
+use crate::filter::Filter;
+use crate::stream::{FileEntry, StreamError, StreamResult};
+
+/// A directory whose subtree is still being walked
+struct Frame {
+    depth: usize,
+    path: PathBuf,
+    /// The directory's entry while it is held back; `None` once it was passed on, like the
+    /// roots, or when the root is hidden by the display mode
+    dir: Option<FileEntry>,
+    matched: bool,
+    /// The kept entries below the directory, in walk order
+    rows: Vec<FileEntry>,
+    /// Position in `rows` of the last kept child
+    last_child: Option<usize>,
+}
+
+/// Passes on the entries a [Filter] selects, and with `contents` only the files whose contents
+/// matched, with the directories above them so trees and `-R` sections keep their structure.
+/// Directories given as inputs are always kept.
+///
+/// Whether a directory stays is known once the walk leaves it, so it and its subtree are held
+/// back until then. `last_sibling` is set again among the kept entries, which holds an entry
+/// back until its next kept sibling shows up; below a root, each finished child subtree is
+/// released as soon as the next one is kept.
+pub struct EntryFilter {
+    source: Pin<Box<dyn Stream<Item = StreamResult<FileEntry>> + Send>>,
+    filter: Option<Filter>,
+    /// Whether only the files with `--contains` matches are kept
+    contents: bool,
+    frames: Vec<Frame>,
+    pending: VecDeque<StreamResult<FileEntry>>,
+    is_complete: bool,
+}
+
+impl EntryFilter {
+    pub fn new(
+        source: impl Stream<Item = StreamResult<FileEntry>> + Send + 'static,
+        filter: Option<Filter>,
+        contents: bool,
+    ) -> Self {
+        Self {
+            source: Box::pin(source),
+            filter,
+            contents,
+            frames: Vec::new(),
+            pending: VecDeque::new(),
+            is_complete: false,
+        }
+    }
+
+    fn matches(&self, entry: &FileEntry) -> bool {
+        if self.contents && entry.matches.as_ref().is_none_or(|matches| matches.count == 0) {
+            return false;
+        }
+        self.filter.as_ref().is_none_or(|filter| filter.matches(entry))
+    }
+
+    fn process_entry(&mut self, entry: FileEntry) {
+        // The ignored children counted are not the ones the filter kept
+        if entry.ignored.is_some() {
+            return;
+        }
+        // Leave the directories the entry is not in, and the previous input
+        self.close_frames(entry.depth);
+        if self.frames.first().is_some_and(|root| !entry.path.starts_with(&root.path)) {
+            self.close_frames(0);
+        }
+
+        if self.frames.is_empty() {
+            let root = entry.path.ancestors().nth(entry.depth).unwrap_or(Path::new(""));
+            // The root's own entry was hidden by the display mode
+            if entry.depth > 0 {
+                self.frames.push(Frame {
+                    depth: entry.depth - 1,
+                    path: root.to_path_buf(),
+                    dir: None,
+                    matched: true,
+                    rows: Vec::new(),
+                    last_child: None,
+                });
+            }
+        }
+
+        let matched = self.matches(&entry);
+        if entry.file_type.is_dirlike() {
+            let (depth, path) = (entry.depth, entry.path.clone());
+            let dir = if depth == 0 {
+                self.pending.push_back(Ok(entry));
+                None
+            } else {
+                Some(entry)
+            };
+            self.frames.push(Frame {
+                depth,
+                path,
+                dir,
+                matched,
+                rows: Vec::new(),
+                last_child: None,
+            });
+        } else if matched {
+            self.keep(vec![entry]);
+        }
+    }
+
+    /// Add a kept child, with its subtree, to the innermost open directory
+    fn keep(&mut self, mut subtree: Vec<FileEntry>) {
+        let Some(frame) = self.frames.last_mut() else {
+            self.pending.extend(subtree.into_iter().map(Ok));
+            return;
+        };
+
+        if let Some(last) = frame.last_child {
+            frame.rows[last].last_sibling = false;
+        }
+        subtree[0].last_sibling = true;
+        frame.last_child = Some(frame.rows.len());
+        frame.rows.extend(subtree);
+
+        // Below a directory that was passed on, the previous children are final
+        if frame.dir.is_none()
+            && let Some(last) = frame.last_child
+            && last > 0
+        {
+            self.pending.extend(frame.rows.drain(..last).map(Ok));
+            frame.last_child = Some(0);
+        }
+    }
+
+    /// Leave the directories at `depth` or deeper, keeping those that matched or contain a
+    /// kept entry
+    fn close_frames(&mut self, depth: usize) {
+        while self.frames.last().is_some_and(|frame| frame.depth >= depth) {
+            let Some(frame) = self.frames.pop() else {
+                break;
+            };
+            match frame.dir {
+                None => self.pending.extend(frame.rows.into_iter().map(Ok)),
+                Some(dir) if frame.matched || !frame.rows.is_empty() => {
+                    self.keep(std::iter::once(dir).chain(frame.rows).collect());
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+impl Stream for EntryFilter {
+    type Item = StreamResult<FileEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Poll::Ready(Some(item));
+            }
+            if self.is_complete {
+                return Poll::Ready(None);
+            }
+
+            match self.source.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(entry))) => self.process_entry(entry),
+                // The entries held back come before the cut
+                Poll::Ready(Some(Err(e @ StreamError::Truncated(_)))) => {
+                    self.close_frames(0);
+                    self.pending.push_back(Err(e));
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => {
+                    self.is_complete = true;
+                    self.close_frames(0);
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+    use std::task::{Context, Poll};
+
+    use clap::Parser;
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+    use tempfile::tempdir;
+
+    use super::EntryFilter;
+    use crate::app::Cli;
+    use crate::config_file::Config;
+    use crate::filter::Filter;
+    use crate::flags::Contains;
+    use crate::stream::{
+        test_walk, ContentSearch, FileEntry, StreamError, StreamResult, TruncateReason, Truncation,
+    };
+
+    /// `root/` holding `a/x.rs`, `a/y.txt`, `b/z.txt` and `c.rs`, with `needle` in `y.txt`
+    fn fixture(root: &Path) {
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("a/x.rs"), "hay\n").unwrap();
+        fs::write(root.join("a/y.txt"), "needle\n").unwrap();
+        fs::write(root.join("b/z.txt"), "hay\n").unwrap();
+        fs::write(root.join("c.rs"), "hay\n").unwrap();
+    }
+
+    fn walk(root: &Path) -> Vec<FileEntry> {
+        futures::executor::block_on(test_walk(root).collect::<Vec<_>>())
+            .into_iter()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    /// The names and `last_sibling` of the entries kept
+    fn kept(stream: EntryFilter) -> Vec<(String, bool)> {
+        futures::executor::block_on(stream.collect::<Vec<_>>())
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                (entry.name, entry.last_sibling)
+            })
+            .collect()
+    }
+
+    fn filtered(root: &Path, source: &str) -> Vec<(String, bool)> {
+        let filter = Filter::parse(source).unwrap();
+        kept(EntryFilter::new(test_walk(root), Some(filter), false))
+    }
+
+    /// The entries the filter passes on without waiting for more input
+    fn ready(filter: &mut EntryFilter) -> Vec<String> {
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        let mut names = Vec::new();
+        while let Poll::Ready(Some(Ok(entry))) = filter.poll_next_unpin(&mut cx) {
+            names.push(entry.name);
+        }
+        names
+    }
+
+    fn owned(rows: &[(&str, bool)]) -> Vec<(String, bool)> {
+        rows.iter().map(|(name, last)| (name.to_string(), *last)).collect()
+    }
+
+    #[test]
+    fn test_directories_leading_to_matches_are_kept() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fixture(&root);
+
+        assert_eq!(
+            filtered(&root, "ext == rs"),
+            owned(&[("root", true), ("a", false), ("x.rs", true), ("c.rs", true)])
+        );
+        // A matching directory stays without matching children
+        assert_eq!(filtered(&root, "name == b"), owned(&[("root", true), ("b", true)]));
+        // The input directory is kept when nothing matches
+        assert_eq!(filtered(&root, "name == none"), owned(&[("root", true)]));
+    }
+
+    #[test]
+    fn test_only_files_with_content_matches() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fixture(&root);
+
+        let argv = ["sap", "--contains", "needle"];
+        let contains = Contains::configure_from(&Cli::try_parse_from(argv).unwrap(), &Config::with_none()).unwrap();
+        let search = ContentSearch::new(test_walk(&root), &contains, None, true);
+        assert_eq!(
+            kept(EntryFilter::new(search, None, true)),
+            owned(&[("root", true), ("a", true), ("y.txt", true)])
+        );
+
+        // Both the expression and the contents have to match
+        let search = ContentSearch::new(test_walk(&root), &contains, None, true);
+        let filter = Filter::parse("ext == rs").unwrap();
+        assert_eq!(kept(EntryFilter::new(search, Some(filter), true)), owned(&[("root", true)]));
+    }
+
+    #[test]
+    fn test_subtrees_are_released_once_finished() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fixture(&root);
+
+        let (sender, receiver) = mpsc::unbounded::<StreamResult<FileEntry>>();
+        let mut filter = EntryFilter::new(receiver, Some(Filter::parse("ext == rs || ext == txt").unwrap()), false);
+        let mut entries = walk(&root).into_iter();
+        let mut send = |count: usize| {
+            for entry in entries.by_ref().take(count) {
+                sender.unbounded_send(Ok(entry)).unwrap();
+            }
+        };
+
+        // The root is passed on at once
+        send(4);
+        assert_eq!(ready(&mut filter), ["root"]);
+        // `a` is kept once the walk leaves it, and waits for its next kept sibling
+        send(2);
+        assert_eq!(ready(&mut filter), Vec::<String>::new());
+        send(1);
+        assert_eq!(ready(&mut filter), ["a", "x.rs", "y.txt", "b", "z.txt"]);
+        // The last child waits for the end of the walk
+        drop(sender);
+        assert_eq!(ready(&mut filter), ["c.rs"]);
+    }
+
+    #[test]
+    fn test_held_entries_come_before_a_cut() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fixture(&root);
+
+        let (sender, receiver) = mpsc::unbounded::<StreamResult<FileEntry>>();
+        let mut filter = EntryFilter::new(receiver, Some(Filter::parse("ext == rs").unwrap()), false);
+        for entry in walk(&root).into_iter().take(3) {
+            sender.unbounded_send(Ok(entry)).unwrap();
+        }
+        let truncation = Truncation {
+            reason: TruncateReason::MaxEntries(3),
+            entries: 3,
+            directories: vec![root.join("a")],
+        };
+        sender.unbounded_send(Err(StreamError::Truncated(truncation))).unwrap();
+        drop(sender);
+
+        let items = futures::executor::block_on(filter.by_ref().collect::<Vec<_>>());
+        let names: Vec<_> = items
+            .iter()
+            .map(|item| match item {
+                Ok(entry) => entry.name.clone(),
+                Err(err) => err.to_string(),
+            })
+            .collect();
+        assert_eq!(names[..3], ["root", "a", "x.rs"]);
+        assert!(matches!(items[3], Err(StreamError::Truncated(_))));
+        assert_eq!(items.len(), 4);
+    }
+}
@@ -0,0 +1,313 @@
+//! Grid accumulator: groups the walk into one section per listed directory, like `ls -R`
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures::Stream;
+
+use crate::git::GitCache;
+use crate::stream::{
+    Accumulator, AccumulatorAction, FileEntry, OutputEvent, StreamError, StreamResult,
+};
+
+/// The rows of a listed directory, or of the files given as inputs when `path` is `None`
+struct Section {
+    id: usize,
+    path: Option<PathBuf>,
+    rows: Vec<FileEntry>,
+    complete: bool,
+    /// Whether the scan limits cut the walk before all the rows were seen
+    truncated: bool,
+}
+
+/// Emits a `DirectoryHeader`, the rows and a `SectionEnd` for every listed directory once all
+/// its children were walked.
+///
+/// Sections are emitted in walk order, so a directory whose children are complete still waits
+/// for the sections before it. The sources flag the last child of each directory, which closes
+/// its section without waiting for the walk to leave the directory.
+pub struct GridAccumulator {
+    source: Pin<Box<dyn Stream<Item = StreamResult<FileEntry>> + Send>>,
+    /// Directories at this depth are listed but not read, so they get no section
+    max_depth: usize,
+    /// Whether each section starts with `.` and `..` rows, like `ls -a`
+    dot_entries: bool,
+    /// Whether the `.` and `..` rows carry their git status, like the walked entries
+    git: bool,
+    /// Status of the work tree holding the current input, for the `.` and `..` rows
+    repo: Option<GitCache>,
+    /// Sections not emitted yet, in walk order
+    sections: VecDeque<Section>,
+    /// Depth and id of the sections still receiving rows, innermost last
+    open: Vec<(usize, usize)>,
+    /// Id of the section collecting the files given as inputs, until a directory input follows
+    input_files: Option<usize>,
+    next_id: usize,
+    pending: VecDeque<OutputEvent>,
+    is_complete: bool,
+}
+
+impl GridAccumulator {
+    pub fn new(
+        source: impl Stream<Item = StreamResult<FileEntry>> + Send + 'static,
+        max_depth: usize,
+        dot_entries: bool,
+        git: bool,
+    ) -> Self {
+        Self {
+            source: Box::pin(source),
+            max_depth,
+            dot_entries,
+            git,
+            repo: None,
+            sections: VecDeque::new(),
+            open: Vec::new(),
+            input_files: None,
+            next_id: 0,
+            pending: VecDeque::new(),
+            is_complete: false,
+        }
+    }
+
+    fn section_mut(&mut self, id: usize) -> Option<&mut Section> {
+        self.sections.iter_mut().find(|section| section.id == id)
+    }
+
+    fn push_section(&mut self, path: Option<PathBuf>) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.sections.push_back(Section {
+            id,
+            path,
+            rows: Vec::new(),
+            complete: false,
+            truncated: false,
+        });
+        id
+    }
+
+    /// Open the section of a listed directory, from its walked entry when there is one
+    fn push_dir_section(&mut self, dir: Option<&FileEntry>, path: PathBuf, depth: usize) -> usize {
+        let id = self.push_section(Some(path.clone()));
+        if self.dot_entries {
+            if self.git && depth == 0 {
+                self.repo = Some(GitCache::new(&path));
+            }
+            let rows = dot_entries(dir, &path, depth + 1, self.repo.as_ref());
+            if let Some(section) = self.section_mut(id) {
+                section.rows.extend(rows);
+            }
+        }
+        id
+    }
+
+    fn complete(&mut self, id: usize) {
+        if let Some(section) = self.section_mut(id) {
+            section.complete = true;
+        }
+    }
+
+    /// The section listing `entry`'s parent, opened on the spot when the parent itself was
+    /// not walked (like a root hidden by the display mode)
+    fn parent_section(&mut self, entry: &FileEntry) -> usize {
+        if let Some(&(depth, id)) = self.open.last()
+            && depth + 1 == entry.depth
+        {
+            return id;
+        }
+
+        let parent = entry.path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let id = self.push_dir_section(None, parent, entry.depth - 1);
+        self.open.push((entry.depth - 1, id));
+        id
+    }
+
+    /// Move the complete sections at the front of the queue to the pending events
+    fn drain(&mut self) -> Vec<OutputEvent> {
+        let mut events = Vec::new();
+        while self.sections.front().is_some_and(|section| section.complete) {
+            let Some(section) = self.sections.pop_front() else {
+                break;
+            };
+
+            if let Some(path) = section.path {
+                events.push(OutputEvent::DirectoryHeader { path, repo: None });
+            }
+            events.extend(section.rows.into_iter().map(|entry| OutputEvent::FileRow { entry }));
+            if section.truncated {
+                events.push(OutputEvent::Truncated {
+                    prefix: String::new(),
+                });
+            }
+            events.push(OutputEvent::SectionEnd);
+        }
+        events
+    }
+}
+
+/// The `.` and `..` rows of the directory at `path`: `.` is the directory's own entry when it
+/// was walked, with its size and git status, and `..` is stat'ed. With `git`, the rows that
+/// were not walked get the status of the directory, rolled up from the changes below it. A
+/// row that cannot be stat'ed is left out.
+fn dot_entries(
+    dir: Option<&FileEntry>,
+    path: &Path,
+    depth: usize,
+    git: Option<&GitCache>,
+) -> Vec<FileEntry> {
+    let stat = |path: PathBuf, name: &str| {
+        let metadata = std::fs::metadata(&path).ok()?;
+        let mut entry = FileEntry::from_metadata(path, name.to_string(), metadata, depth, false);
+        entry.git_status = git.and_then(|git| git.status(&entry.path, true));
+        Some(entry)
+    };
+
+    let current = match dir {
+        Some(dir) => Some(FileEntry {
+            name: ".".to_string(),
+            depth,
+            last_sibling: false,
+            link_error: None,
+            ..dir.clone()
+        }),
+        None => stat(path.to_path_buf(), "."),
+    };
+    let parent = stat(path.join(".."), "..");
+
+    current.into_iter().chain(parent).collect()
+}
+
+impl Accumulator for GridAccumulator {
+    fn process_entry(&mut self, entry: FileEntry) -> AccumulatorAction {
+        // Directories at this depth or deeper cannot receive more rows
+        while let Some(&(depth, id)) = self.open.last()
+            && depth >= entry.depth
+        {
+            self.complete(id);
+            self.open.pop();
+        }
+
+        let is_dir = entry.file_type.is_dirlike();
+        let listed = is_dir && entry.depth < self.max_depth;
+
+        if entry.depth == 0 {
+            if listed {
+                if let Some(id) = self.input_files.take() {
+                    self.complete(id);
+                }
+                let id = self.push_dir_section(Some(&entry), entry.path.clone(), 0);
+                self.open.push((0, id));
+            } else {
+                let id = match self.input_files {
+                    Some(id) => id,
+                    None => {
+                        let id = self.push_section(None);
+                        self.input_files = Some(id);
+                        id
+                    }
+                };
+                if let Some(section) = self.section_mut(id) {
+                    section.rows.push(entry);
+                }
+            }
+        } else {
+            let parent = self.parent_section(&entry);
+            let (depth, path, last_sibling) = (entry.depth, entry.path.clone(), entry.last_sibling);
+            if listed {
+                let id = self.push_dir_section(Some(&entry), path, depth);
+                self.open.push((depth, id));
+            }
+            if let Some(section) = self.section_mut(parent) {
+                section.rows.push(entry);
+                section.complete |= last_sibling;
+            }
+        }
+
+        let events = self.drain();
+        if events.is_empty() {
+            AccumulatorAction::Buffer
+        } else {
+            AccumulatorAction::Emit(events)
+        }
+    }
+}
+
+impl Stream for GridAccumulator {
+    type Item = StreamResult<OutputEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            if self.is_complete {
+                return Poll::Ready(None);
+            }
+
+            match self.source.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(entry))) => {
+                    if let AccumulatorAction::Emit(events) = self.process_entry(entry) {
+                        self.pending.extend(events);
+                    }
+                }
+                Poll::Ready(Some(Err(StreamError::Truncated(truncation)))) => {
+                    for section in self.sections.iter_mut() {
+                        section.truncated = section
+                            .path
+                            .as_ref()
+                            .is_some_and(|path| truncation.directories.contains(path));
+                    }
+                    return Poll::Ready(Some(Err(StreamError::Truncated(truncation))));
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => {
+                    self.is_complete = true;
+                    for section in self.sections.iter_mut() {
+                        section.complete = true;
+                    }
+                    let events = self.drain();
+                    self.pending.extend(events);
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use futures::StreamExt;
+    use tempfile::tempdir;
+
+    use super::GridAccumulator;
+    use crate::git::GitStatus;
+    use crate::stream::{test_walk, OutputEvent};
+
+    #[test]
+    fn test_dot_entries_roll_up_git_status() {
+        let dir = tempdir().unwrap();
+        gix::init(dir.path()).unwrap();
+        fs::create_dir_all(dir.path().join("src/deep")).unwrap();
+        fs::write(dir.path().join("src/deep/new.rs"), "").unwrap();
+        fs::write(dir.path().join("top.rs"), "").unwrap();
+
+        // The status of `..`, rolled up from the changes below it
+        let parent_status = |root: &Path| {
+            let grid = GridAccumulator::new(test_walk(root), 1, true, true);
+            let events = futures::executor::block_on(grid.collect::<Vec<_>>());
+            events.into_iter().find_map(|event| match event {
+                Ok(OutputEvent::FileRow { entry }) if entry.name == ".." => Some(entry.git_status),
+                _ => None,
+            })
+        };
+
+        let status = parent_status(&dir.path().join("src/deep")).unwrap().unwrap();
+        assert_eq!(status.workdir, GitStatus::NewInWorkdir);
+        // Above the work tree
+        assert_eq!(parent_status(dir.path()), Some(None));
+    }
+}
@@ -0,0 +1,541 @@
+//! Index source: enumerates tracked files from the git index instead of walking the disk
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use gix::bstr::{BStr, ByteSlice};
+use gix::ObjectId;
+
+use crate::flags::{Display, IgnoreGlobs, IgnoredChildren, SapIgnores};
+use crate::git::GitCache;
+use crate::meta::FileType;
+#[cfg(unix)]
+use crate::meta::Permissions;
+use crate::sort::EntryOrder;
+use crate::stream::revision::{git_err, locate};
+use crate::stream::{
+    is_displayed, mark_last_sibling, FileEntry, GitObjectInfo, Stat, StreamResult, WalkOptions,
+};
+
+/// A file known to the index, or an untracked file found by `gix` status
+#[derive(Debug)]
+struct IndexFile {
+    id: Option<ObjectId>,
+    mode: u32,
+    size: u64,
+    mtime: Option<SystemTime>,
+}
+
+/// A node of the directory hierarchy synthesized from index paths
+enum Node {
+    Dir(BTreeMap<String, Node>),
+    File(IndexFile),
+}
+
+/// Depth-first walk over the directories implied by index paths, siblings in display order
+pub struct IndexWalk {
+    /// Entries waiting to be yielded, with the children to expand for directories
+    stack: Vec<(FileEntry, Option<BTreeMap<String, Node>>)>,
+    /// The listed directory
+    root: PathBuf,
+    /// The children the ignore rules hid, by directory path from `root`
+    ignored: HashMap<PathBuf, IgnoredChildren>,
+    stat: bool,
+    /// Status of the work tree, when the entries carry it
+    git: Option<GitCache>,
+    order: EntryOrder,
+}
+
+impl IndexWalk {
+    /// Read the index of the repository containing `path`.
+    ///
+    /// Unless `options.stat` is `Stat::All` no file is touched beyond the index itself: mode,
+    /// size and mtime come from the index entry's cached stat data. With `options.git` the
+    /// entries carry their status; the other options do not apply.
+    pub fn new(
+        path: &Path,
+        untracked: bool,
+        max_depth: usize,
+        ignore_globs: &IgnoreGlobs,
+        display: Display,
+        options: WalkOptions,
+        order: &EntryOrder,
+    ) -> StreamResult<Self> {
+        let (repo, rela_path) = locate(path)?;
+        let prefix = gix::path::to_unix_separators_on_windows(gix::path::into_bstr(
+            rela_path.as_path(),
+        ))
+        .into_owned();
+
+        let mut tree = BTreeMap::new();
+        let ignore_globs = ignore_globs.for_root(path);
+        let mut ignores = DirIgnores {
+            root: path,
+            globs: &ignore_globs,
+            dirs: HashMap::new(),
+            pruned: HashSet::new(),
+            ignored: HashMap::new(),
+        };
+        // The index entry of the listed path itself, when it is a file
+        let mut root_file = None;
+        let mut insert = |rela: &BStr, file: IndexFile| {
+            if rela == prefix.as_bstr() {
+                root_file = Some(file);
+                return;
+            }
+            let Some(relative) = strip_prefix(rela, prefix.as_bstr()) else {
+                return;
+            };
+            insert_path(&mut tree, relative, file, max_depth, &mut ignores, display);
+        };
+
+        let index = repo.index_or_empty().map_err(git_err)?;
+        let mut previous: Option<&BStr> = None;
+        for entry in index.entries() {
+            let rela = entry.path(&index);
+            // Conflicted entries appear once per stage
+            if previous == Some(rela) {
+                continue;
+            }
+            previous = Some(rela);
+
+            let mtime = SystemTime::UNIX_EPOCH
+                + Duration::new(u64::from(entry.stat.mtime.secs), entry.stat.mtime.nsecs);
+            insert(
+                rela,
+                IndexFile {
+                    id: Some(entry.id),
+                    mode: entry.mode.bits(),
+                    size: u64::from(entry.stat.size),
+                    mtime: Some(mtime),
+                },
+            );
+        }
+
+        if untracked {
+            for rela in untracked_paths(&repo)? {
+                insert(
+                    rela.as_bstr(),
+                    IndexFile {
+                        id: None,
+                        mode: 0o100_644,
+                        size: 0,
+                        mtime: None,
+                    },
+                );
+            }
+        }
+
+        // Name the root like jwalk does
+        let root_name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .to_string();
+        let mut walk = Self {
+            stack: Vec::new(),
+            root: path.to_path_buf(),
+            ignored: ignores.ignored,
+            stat: options.stat == Stat::All,
+            git: options.git.then(|| GitCache::new(path)),
+            order: order.clone(),
+        };
+        // A file outside the index is listed from its metadata, like an untracked one
+        if root_file.is_none() && !path.is_dir() {
+            root_file = Some(IndexFile {
+                id: None,
+                mode: 0o100_644,
+                size: 0,
+                mtime: None,
+            });
+        }
+        let root = walk.entry(path.to_path_buf(), root_name, 0, root_file.as_ref());
+        let children = root_file.is_none().then_some(tree);
+        walk.stack.push((root, children));
+        Ok(walk)
+    }
+
+    /// Queue the children of `parent` in display order, so the first one is popped next
+    fn push_children(&mut self, parent: &Path, depth: usize, children: BTreeMap<String, Node>) {
+        let mut pending: Vec<(FileEntry, Option<BTreeMap<String, Node>>)> = children
+            .into_iter()
+            .map(|(name, node)| {
+                let path = parent.join(&name);
+                match node {
+                    Node::Dir(grandchildren) => {
+                        (self.entry(path, name, depth, None), Some(grandchildren))
+                    }
+                    Node::File(file) => (self.entry(path, name, depth, Some(&file)), None),
+                }
+            })
+            .collect();
+
+        pending.sort_by(|a, b| self.order.cmp(&a.0, &b.0));
+        let relative = parent.strip_prefix(&self.root).unwrap_or(parent);
+        if let Some(ignored) = self.ignored.remove(relative)
+            && ignored.count > 0
+        {
+            pending.push((FileEntry::ignored(parent, depth, ignored), None));
+        }
+        let mut entries: Vec<&mut FileEntry> = pending.iter_mut().map(|(entry, _)| entry).collect();
+        mark_last_sibling(&mut entries);
+
+        self.stack.extend(pending.into_iter().rev());
+    }
+
+    fn entry(&self, path: PathBuf, name: String, depth: usize, file: Option<&IndexFile>) -> FileEntry {
+        let metadata = if self.stat || file.is_some_and(|f| f.id.is_none()) {
+            std::fs::symlink_metadata(&path).ok()
+        } else {
+            None
+        };
+
+        let file_type = match (&metadata, file) {
+            (Some(metadata), _) => {
+                #[cfg(unix)]
+                let file_type = FileType::new(metadata, None, &Permissions::from(metadata));
+                #[cfg(windows)]
+                let file_type = FileType::new(metadata, None, &path);
+                file_type
+            }
+            (None, Some(file)) => file_type_from_mode(file.mode),
+            (None, None) => FileType::Directory { uid: false },
+        };
+        let git_status = self
+            .git
+            .as_ref()
+            .and_then(|git| git.status(&path, file_type.is_dirlike()));
+
+        FileEntry {
+            is_symlink: matches!(file_type, FileType::SymLink { .. }),
+            object: file.and_then(|f| {
+                f.id.map(|id| GitObjectInfo {
+                    id,
+                    rela_path: PathBuf::new(),
+                    mode: f.mode,
+                    size: f.size,
+                    time: f.mtime,
+                    link_target: None,
+                })
+            }),
+            path,
+            name,
+            file_type,
+            metadata,
+            depth,
+            last_sibling: true,
+            link_error: None,
+            mount_point: false,
+            git_status,
+            size: None,
+            cached: None,
+            matches: None,
+            hash: None,
+            content_type: None,
+            media: None,
+            binary: None,
+            ignored: None,
+        }
+    }
+}
+
+impl Iterator for IndexWalk {
+    type Item = StreamResult<FileEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (entry, children) = self.stack.pop()?;
+        if let Some(children) = children {
+            self.push_children(&entry.path, entry.depth + 1, children);
+        }
+        Some(Ok(entry))
+    }
+}
+
+/// The part of `rela` below `prefix`, or `None` when `rela` lies outside of it
+fn strip_prefix<'a>(rela: &'a BStr, prefix: &BStr) -> Option<&'a BStr> {
+    if prefix.is_empty() {
+        return Some(rela);
+    }
+    rela.strip_prefix(prefix.as_bytes())
+        .and_then(|rest| rest.strip_prefix(b"/"))
+        .map(|rest| rest.as_bstr())
+}
+
+/// The ignore globs with the `.sapignore` files of the work tree, each read once, when the
+/// first index path below its directory shows up
+struct DirIgnores<'a> {
+    /// The listed directory in the work tree
+    root: &'a Path,
+    globs: &'a IgnoreGlobs,
+    /// The rules in effect in each directory, by path from `root`
+    dirs: HashMap<PathBuf, SapIgnores>,
+    /// The paths found ignored, which index paths below them skip
+    pruned: HashSet<PathBuf>,
+    /// The children the rules hid, by directory path from `root`, as `IgnoreGlobs::report`
+    /// asks
+    ignored: HashMap<PathBuf, IgnoredChildren>,
+}
+
+impl DirIgnores<'_> {
+    /// Whether the entry at `relative`, a path from `root`, is ignored
+    fn is_ignored(&mut self, relative: &Path, is_dir: bool) -> bool {
+        if self.pruned.contains(relative) {
+            return true;
+        }
+        let parent = relative.parent().unwrap_or(Path::new(""));
+        self.sapignores(parent);
+        let ignored = self.ignored.entry(parent.to_path_buf()).or_default();
+        if !self.globs.hides(&self.dirs[parent], relative, is_dir, ignored) {
+            return false;
+        }
+        self.pruned.insert(relative.to_path_buf());
+        true
+    }
+
+    /// The rules in effect in the directory at `dir`, reading its `.sapignore` and those
+    /// above it the first time
+    fn sapignores(&mut self, dir: &Path) -> &SapIgnores {
+        if !self.dirs.contains_key(dir) {
+            let mut sapignores = match dir.parent() {
+                Some(parent) => self.sapignores(parent).clone(),
+                None => SapIgnores::default(),
+            };
+            sapignores.read(&self.root.join(dir), dir);
+            self.dirs.insert(dir.to_path_buf(), sapignores);
+        }
+        &self.dirs[dir]
+    }
+}
+
+/// Insert `relative` into the hierarchy, creating its parent directories.
+///
+/// Components beyond `max_depth` are dropped but their ancestors are kept, so a directory at the
+/// depth limit still shows up. A component matching the ignore globs or hidden by the display
+/// mode prunes the whole path, like jwalk's `process_read_dir` filter does.
+fn insert_path(
+    tree: &mut BTreeMap<String, Node>,
+    relative: &BStr,
+    file: IndexFile,
+    max_depth: usize,
+    ignores: &mut DirIgnores,
+    display: Display,
+) {
+    let components: Vec<String> = relative
+        .split_str("/")
+        .map(|c| c.to_str_lossy().into_owned())
+        .collect();
+    let last = components.len() - 1;
+
+    let mut current = tree;
+    let mut path = PathBuf::new();
+    for (idx, component) in components.into_iter().enumerate() {
+        let is_dir = idx != last;
+        path.push(&component);
+        if !is_displayed(&component, is_dir, display) || ignores.is_ignored(&path, is_dir) {
+            return;
+        }
+        if idx >= max_depth {
+            return;
+        }
+
+        if !is_dir {
+            current.insert(component, Node::File(file));
+            return;
+        }
+
+        let node = current
+            .entry(component)
+            .or_insert_with(|| Node::Dir(BTreeMap::new()));
+        current = match node {
+            Node::Dir(children) => children,
+            // A submodule or a file replaced by a directory in the worktree
+            Node::File(_) => return,
+        };
+    }
+}
+
+/// Untracked files that are not ignored, relative to the repository root
+fn untracked_paths(repo: &gix::Repository) -> StreamResult<Vec<gix::bstr::BString>> {
+    let iter = repo
+        .status(gix::progress::Discard)
+        .map_err(git_err)?
+        .untracked_files(gix::status::UntrackedFiles::Files)
+        .into_iter(Vec::new())
+        .map_err(git_err)?;
+
+    let mut paths = Vec::new();
+    for item in iter {
+        if let gix::status::Item::IndexWorktree(
+            gix::status::index_worktree::Item::DirectoryContents { entry, .. },
+        ) = item.map_err(git_err)?
+            && entry.status == gix::dir::entry::Status::Untracked
+        {
+            paths.push(entry.rela_path);
+        }
+    }
+    Ok(paths)
+}
+
+fn file_type_from_mode(mode: u32) -> FileType {
+    match mode & 0o170_000 {
+        0o040_000 | 0o160_000 => FileType::Directory { uid: false },
+        0o120_000 => FileType::SymLink { is_dir: false },
+        _ => FileType::File {
+            uid: false,
+            exec: mode & 0o111 != 0,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use tempfile::tempdir;
+
+    use super::IndexWalk;
+    use crate::flags::{Display, Flags, ThreadMode, Threads};
+    use crate::sort::EntryOrder;
+    use crate::stream::{Stat, WalkOptions};
+
+    /// A repository in `dir` whose index and work tree hold `files`, each containing its path
+    fn repository(dir: &Path, files: &[&str]) {
+        let repo = gix::init(dir).unwrap();
+        let mut state = gix::index::State::new(repo.object_hash());
+        for file in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, file).unwrap();
+            let id = repo.write_blob(file.as_bytes()).unwrap().detach();
+            state.dangerously_push_entry(
+                gix::index::entry::Stat::default(),
+                id,
+                gix::index::entry::Flags::empty(),
+                gix::index::entry::Mode::FILE,
+                (*file).into(),
+            );
+        }
+        state.sort_entries();
+        let mut index = gix::index::File::from_state(state, repo.index_path());
+        index.write(gix::index::write::Options::default()).unwrap();
+    }
+
+    /// The path from `root`, depth and whether it is a directory of each entry of the walk
+    fn walk(root: &Path, untracked: bool, max_depth: usize) -> Vec<(String, usize, bool)> {
+        let flags = Flags::default();
+        let options = WalkOptions {
+            dereference: false,
+            one_file_system: false,
+            stat: Stat::TypesOnly {
+                exec_bit: false,
+                link_target: false,
+            },
+            threads: Threads {
+                mode: ThreadMode::Serial,
+                busy_timeout: None,
+            },
+            sniff: false,
+            git: false,
+        };
+        let order = EntryOrder::from_flags(&flags);
+        IndexWalk::new(root, untracked, max_depth, &flags.ignore_globs, Display::VisibleOnly, options, &order)
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let path = entry.path.strip_prefix(root).unwrap().to_string_lossy().into_owned();
+                (path, entry.depth, entry.file_type.is_dirlike())
+            })
+            .collect()
+    }
+
+    fn dir(path: &str, depth: usize) -> (String, usize, bool) {
+        (path.to_string(), depth, true)
+    }
+
+    fn file(path: &str, depth: usize) -> (String, usize, bool) {
+        (path.to_string(), depth, false)
+    }
+
+    #[test]
+    fn test_directories_from_index_paths() {
+        let tmp = tempdir().unwrap();
+        repository(tmp.path(), &["readme.md", "src/main.rs", "src/cli/args.rs", "docs/guide.md"]);
+
+        assert_eq!(
+            walk(tmp.path(), false, usize::MAX),
+            [
+                dir("", 0),
+                dir("docs", 1),
+                file("docs/guide.md", 2),
+                file("readme.md", 1),
+                dir("src", 1),
+                dir("src/cli", 2),
+                file("src/cli/args.rs", 3),
+                file("src/main.rs", 2),
+            ]
+        );
+        // A subdirectory of the work tree lists its part of the index
+        assert_eq!(
+            walk(&tmp.path().join("src"), false, usize::MAX),
+            [dir("", 0), dir("cli", 1), file("cli/args.rs", 2), file("main.rs", 1)]
+        );
+    }
+
+    #[test]
+    fn test_depth_keeps_the_directories_at_the_limit() {
+        let tmp = tempdir().unwrap();
+        repository(tmp.path(), &["src/main.rs", "src/cli/args.rs"]);
+
+        assert_eq!(
+            walk(tmp.path(), false, 2),
+            [dir("", 0), dir("src", 1), dir("src/cli", 2), file("src/main.rs", 2)]
+        );
+        assert_eq!(walk(tmp.path(), false, 1), [dir("", 0), dir("src", 1)]);
+    }
+
+    #[test]
+    fn test_ignored_paths_are_pruned() {
+        let tmp = tempdir().unwrap();
+        repository(
+            tmp.path(),
+            &["Cargo.toml", "src/lib.rs", "src/gen/out.rs", "target/debug/app", ".github/ci.yml"],
+        );
+        fs::write(tmp.path().join(".sapignore"), "gen/\n").unwrap();
+
+        // The project's build output, a .sapignore pattern and a hidden directory
+        assert_eq!(
+            walk(tmp.path(), false, usize::MAX),
+            [dir("", 0), file("Cargo.toml", 1), dir("src", 1), file("src/lib.rs", 2)]
+        );
+    }
+
+    #[test]
+    fn test_untracked_files_that_git_does_not_ignore() {
+        let tmp = tempdir().unwrap();
+        repository(tmp.path(), &["src/lib.rs"]);
+        fs::write(tmp.path().join(".gitignore"), "*.log\n").unwrap();
+        fs::write(tmp.path().join("debug.log"), "").unwrap();
+        fs::write(tmp.path().join("notes.txt"), "").unwrap();
+
+        assert_eq!(
+            walk(tmp.path(), false, usize::MAX),
+            [dir("", 0), dir("src", 1), file("src/lib.rs", 2)]
+        );
+        assert_eq!(
+            walk(tmp.path(), true, usize::MAX),
+            [dir("", 0), file("notes.txt", 1), dir("src", 1), file("src/lib.rs", 2)]
+        );
+    }
+
+    #[test]
+    fn test_file_root() {
+        let tmp = tempdir().unwrap();
+        repository(tmp.path(), &["src/lib.rs"]);
+        fs::write(tmp.path().join("notes.txt"), "").unwrap();
+
+        assert_eq!(walk(&tmp.path().join("src/lib.rs"), false, usize::MAX), [file("", 0)]);
+        assert_eq!(walk(&tmp.path().join("notes.txt"), false, usize::MAX), [file("", 0)]);
+    }
+}
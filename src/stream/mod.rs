@@ -1,17 +1,42 @@
 use futures::{Stream, StreamExt};
 use jwalk::DirEntry;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
 mod aggregated_chat_stream;
+mod cache;
+mod content_hash;
+mod content_sniff;
+mod content_search;
+mod duplicates;
+mod entry_filter;
+mod grid_accumulator;
+mod index;
 // mod llm_stream;
+mod revision;
+mod scan_limit;
+mod size_aggregator;
 mod tree_accumulator;
 
 pub use aggregated_chat_stream::AggregatedChatStream;
+pub use cache::{CacheWalk, CachedStat, ScanCache};
+pub use content_hash::ContentHasher;
+pub use content_sniff::{ContentSniffer, SniffOptions};
+pub use content_search::{ContentMatches, ContentSearch};
+pub use duplicates::{DuplicateFinder, DuplicateGroup};
+pub use entry_filter::EntryFilter;
+pub use grid_accumulator::GridAccumulator;
+pub use index::IndexWalk;
+pub use revision::{GitObjectInfo, RevisionWalk};
+pub use scan_limit::{ScanLimit, TruncateReason, Truncation};
+pub use size_aggregator::SizeAggregator;
+pub use tree_accumulator::TreeAccumulator;
 
-use crate::git::GitStatusInfo;
-use crate::meta::{FileType, Permissions};
+use crate::flags::IgnoredChildren;
+use crate::git::{GitCache, RepoInfo};
+use crate::meta::{FileType, GitFileStatus, Permissions};
+use crate::sort::EntryOrder;
 
 /// A file system entry discovered during traversal
 #[derive(Debug, Clone)]
@@ -19,74 +44,302 @@ pub struct FileEntry {
     pub path: PathBuf,
     pub name: String,
     pub file_type: FileType,
-    pub metadata: std::fs::Metadata,
+    /// File system metadata; `None` for entries read from a git revision, and for entries the
+    /// walk did not stat because the listing only needs their `readdir` file type
+    pub metadata: Option<std::fs::Metadata>,
     pub depth: usize,
     pub is_symlink: bool,
+    /// Whether this is the last of its siblings in walk order, known to the source once it
+    /// has read and sorted the parent directory; always true for the roots
+    pub last_sibling: bool,
+    /// Why a symbolic link could not be followed with `--dereference`; reported by the walk
+    /// as a `StreamError::Link` right after the entry
+    pub link_error: Option<LinkError>,
+    /// Whether this directory is the root of a file system mounted below its parent
+    pub mount_point: bool,
 
-    // Lazy-loaded fields populated by transformers (planned for future optimization)
-    pub git_status: Option<GitStatusInfo>,
-    #[allow(dead_code)]
-    pub permissions: Option<crate::meta::Permissions>,
-    #[allow(dead_code)]
+    /// Git status in the work tree, looked up by the source when the listing reads it; for a
+    /// directory, the highest status below it
+    pub git_status: Option<GitFileStatus>,
+
+    // Fields filled in by later stages of the stream
+    /// Size set by the `SizeAggregator`, like a directory's total with `--total-size`
     pub size: Option<crate::meta::Size>,
-    #[allow(dead_code)]
-    pub modified: Option<crate::meta::Date>,
+
+    /// Stat data kept in the scan cache (`--cache`), used in place of `metadata`
+    pub cached: Option<CachedStat>,
+
+    /// Blob/tree information when the entry comes from a git revision (`--at`)
+    pub object: Option<GitObjectInfo>,
+
+    /// Lines matching the `--contains` pattern, set by the `ContentSearch` for the files it
+    /// searched
+    pub matches: Option<ContentMatches>,
+
+    /// Digest of the contents, or the Merkle fingerprint of a directory, set by the
+    /// `ContentHasher` as `algorithm:hex`
+    pub hash: Option<String>,
+
+    /// Type of the contents sniffed from their first bytes by the `ContentSniffer`
+    pub content_type: Option<crate::meta::ContentType>,
+
+    /// Dimensions and duration read from the headers of images, audio and video by the
+    /// `ContentSniffer`
+    pub media: Option<crate::meta::Media>,
+
+    /// Headers of ELF files read by the `ContentSniffer`
+    pub binary: Option<crate::meta::Elf>,
+
+    /// Set on the entry a source lists after the visible children of a directory to stand
+    /// for the children the ignore rules hid, when `IgnoreGlobs::report` asks for them
+    pub ignored: Option<IgnoredChildren>,
 }
 
 impl FileEntry {
+    /// The entry standing for the children of the directory at `dir` hidden by the ignore
+    /// rules, at the depth of its children
+    pub fn ignored(dir: &Path, depth: usize, ignored: IgnoredChildren) -> Self {
+        Self {
+            path: dir.to_path_buf(),
+            name: format!("\u{2026} +{} ignored", ignored.count),
+            file_type: FileType::Special,
+            metadata: None,
+            depth,
+            is_symlink: false,
+            last_sibling: true,
+            link_error: None,
+            mount_point: false,
+            git_status: None,
+            size: None,
+            cached: None,
+            object: None,
+            matches: None,
+            hash: None,
+            content_type: None,
+            media: None,
+            binary: None,
+            ignored: Some(ignored),
+        }
+    }
+
     pub fn from_jwalk(
-        entry: DirEntry<((), ())>,
+        entry: DirEntry<WalkState>,
         base_depth: usize,
+        dereference: bool,
     ) -> Result<Self, std::io::Error> {
-        let path = entry.path();
-        let name = entry.file_name().to_string_lossy().to_string();
         let metadata = entry.metadata()?;
-        let permissions = Permissions::from(&metadata);
-        let file_type = FileType::new(&metadata, None, &permissions);
-        let depth = entry.depth() - base_depth;
+        Ok(Self::from_metadata(
+            entry.path(),
+            entry.file_name().to_string_lossy().to_string(),
+            metadata,
+            entry.depth() - base_depth,
+            dereference,
+        ))
+    }
+
+    /// Build an entry from its `lstat` data. Symbolic links are resolved to their target's
+    /// metadata with `dereference`, and flagged with a `LinkError` when the target is missing.
+    fn from_metadata(
+        path: PathBuf,
+        name: String,
+        metadata: std::fs::Metadata,
+        depth: usize,
+        dereference: bool,
+    ) -> Self {
         let is_symlink = metadata.file_type().is_symlink();
+        let mut metadata = metadata;
+        let mut target_metadata = None;
+        let mut link_error = None;
+
+        if is_symlink {
+            match std::fs::metadata(&path) {
+                Ok(target) if dereference => metadata = target,
+                Ok(target) => target_metadata = Some(target),
+                Err(_) if dereference => {
+                    link_error = Some(LinkError::Broken {
+                        target: std::fs::read_link(&path).ok(),
+                        path: path.clone(),
+                    });
+                }
+                Err(_) => {}
+            }
+        }
+
+        let permissions = Permissions::from(&metadata);
+        let file_type = FileType::new(&metadata, target_metadata.as_ref(), &permissions);
 
-        Ok(FileEntry {
+        FileEntry {
             path,
             name,
             file_type,
-            metadata,
+            metadata: Some(metadata),
             depth,
             is_symlink,
+            last_sibling: true,
+            link_error,
+            mount_point: false,
             git_status: None,
-            permissions: None,
             size: None,
-            modified: None,
-        })
+            cached: None,
+            object: None,
+            matches: None,
+            hash: None,
+            content_type: None,
+            media: None,
+            binary: None,
+            ignored: None,
+        }
+    }
+
+    /// Build an entry from the file type `readdir` reported, without stat'ing it. Regular files
+    /// are still stat'ed for their exec bit with `exec_bit`, links for their target's type with
+    /// `link_target`, and other special files always, to tell them apart.
+    fn from_file_type(
+        path: PathBuf,
+        name: String,
+        file_type: std::fs::FileType,
+        depth: usize,
+        exec_bit: bool,
+        link_target: bool,
+    ) -> Self {
+        let readdir_type = if file_type.is_dir() {
+            FileType::Directory { uid: false }
+        } else if file_type.is_file() {
+            FileType::File { exec: false, uid: false }
+        } else if file_type.is_symlink() {
+            FileType::SymLink { is_dir: false }
+        } else {
+            FileType::Special
+        };
+
+        let needs_stat = match readdir_type {
+            FileType::File { .. } => exec_bit,
+            FileType::SymLink { .. } => link_target,
+            FileType::Directory { .. } => false,
+            _ => true,
+        };
+        // An entry gone since `readdir` keeps what it reported
+        if needs_stat && let Ok(metadata) = std::fs::symlink_metadata(&path) {
+            return Self::from_metadata(path, name, metadata, depth, false);
+        }
+
+        FileEntry {
+            path,
+            name,
+            file_type: readdir_type,
+            metadata: None,
+            depth,
+            is_symlink: file_type.is_symlink(),
+            last_sibling: true,
+            link_error: None,
+            mount_point: false,
+            git_status: None,
+            size: None,
+            cached: None,
+            object: None,
+            matches: None,
+            hash: None,
+            content_type: None,
+            media: None,
+            binary: None,
+            ignored: None,
+        }
     }
 
-    /// Convert FileEntry to Meta using already-loaded metadata
-    pub fn to_meta(&self, permission_flag: crate::flags::PermissionFlag) -> crate::meta::Meta {
+    /// Build an entry from stat data served by the scan cache
+    fn from_cached(path: PathBuf, name: String, stat: CachedStat, depth: usize) -> Self {
+        let file_type = stat.file_type();
+        FileEntry {
+            file_type,
+            is_symlink: matches!(file_type, FileType::SymLink { .. }),
+            path,
+            name,
+            metadata: None,
+            depth,
+            last_sibling: true,
+            link_error: None,
+            mount_point: false,
+            git_status: None,
+            size: None,
+            cached: Some(stat),
+            object: None,
+            matches: None,
+            hash: None,
+            content_type: None,
+            media: None,
+            binary: None,
+            ignored: None,
+        }
+    }
+
+    /// Whether the walk descends into the entry: a directory, or with `-L` a link to one that
+    /// could be followed
+    pub fn has_children(&self) -> bool {
+        self.file_type.is_dirlike()
+            && !matches!(self.file_type, FileType::SymLink { .. })
+            && self.link_error.is_none()
+    }
+
+    /// Size in bytes from the file system, or from the object header for git entries, unless
+    /// the `SizeAggregator` set it already
+    pub fn size_bytes(&self) -> Option<u64> {
+        if let Some(size) = &self.size {
+            return Some(size.get_bytes());
+        }
+        match (&self.metadata, &self.cached, &self.object) {
+            (Some(metadata), _, _) => Some(metadata.len()),
+            (None, Some(stat), _) => Some(stat.size),
+            (None, None, Some(object)) => Some(object.size),
+            (None, None, None) => None,
+        }
+    }
+
+    /// The `field` timestamp from the file system, or the commit/index time for git entries
+    pub fn time(&self, field: crate::flags::TimeField) -> Option<std::time::SystemTime> {
+        match (&self.metadata, &self.cached, &self.object) {
+            (Some(metadata), _, _) => crate::meta::time_of(metadata, field),
+            (None, Some(stat), _) => stat.time(field),
+            (None, None, Some(object)) => object.time,
+            (None, None, None) => None,
+        }
+    }
+
+    /// Convert FileEntry to Meta using already-loaded metadata. Only the `fields` a listing
+    /// shows are filled in; the others would cost syscalls (xattrs, `readlink`, owner data)
+    pub fn to_meta(&self, fields: &MetaFields) -> crate::meta::Meta {
         use crate::meta::*;
 
+        let Some(metadata) = &self.metadata else {
+            return match &self.cached {
+                Some(stat) => self.cached_to_meta(stat, fields),
+                None => self.object_to_meta(fields),
+            };
+        };
+
         #[cfg(unix)]
-        let (owner, permissions) = match permission_flag {
+        let (owner, permissions) = match fields.permission {
             crate::flags::PermissionFlag::Disable => (None, None),
             _ => (
-                Some(Owner::from(&self.metadata)),
-                Some(Permissions::from(&self.metadata)),
+                fields.owner.then(|| Owner::from(metadata)),
+                fields.permissions.then(|| Permissions::from(metadata)),
             ),
         };
         #[cfg(unix)]
         let permissions_or_attributes = permissions.map(crate::meta::permissions_or_attributes::PermissionsOrAttributes::Permissions);
 
         #[cfg(windows)]
-        let (owner, permissions_or_attributes) = match permission_flag {
+        let (owner, permissions_or_attributes) = match fields.permission {
             crate::flags::PermissionFlag::Disable => (None, None),
             crate::flags::PermissionFlag::Attributes => {
                 use crate::meta::permissions_or_attributes::get_attributes;
                 (
                     None,
                     Some(crate::meta::permissions_or_attributes::PermissionsOrAttributes::WindowsAttributes(get_attributes(
-                        &self.metadata,
+                        metadata,
                     ))),
                 )
             },
+            _ if !(fields.owner || fields.permissions) => (None, None),
             _ => {
                 #[cfg(windows)]
                 match crate::meta::windows_utils::get_file_data(&self.path) {
@@ -101,31 +354,216 @@ impl FileEntry {
             }
         };
 
-        // Use FileEntry.name if it differs from path-derived name (for special entries like . and ..)
-        let name = if self.name == "." || self.name == ".." {
-            // Create Name directly with our custom name
-            Name::new(&std::path::PathBuf::from(&self.name), self.file_type)
-        } else {
-            Name::new(&self.path, self.file_type)
+        Meta {
+            inode: fields.inode.then(|| INode::from(metadata)),
+            links: fields.links.then(|| Links::from(metadata)),
+            filesystem: fields.filesystem.then(|| Filesystem::from(metadata)),
+            matches: self.matches.as_ref().map(|matches| matches.count),
+            hash: self.hash.clone(),
+            media: self.media,
+            binary: self.binary.clone(),
+            path: self.path.clone(),
+            symlink: if self.is_symlink && fields.symlink {
+                SymLink::from(self.path.as_path())
+            } else {
+                SymLink::default()
+            },
+            size: fields
+                .size
+                .then(|| self.size.clone().unwrap_or_else(|| Size::from(metadata))),
+            date: fields.date.then(|| Date::from_metadata(metadata, fields.time)),
+            indicator: if self.mount_point {
+                Indicator::mount_point()
+            } else {
+                Indicator::from(self.file_type)
+            },
+            owner,
+            permissions_or_attributes,
+            name: self.display_name(),
+            file_type: self.file_type,
+            content: None,
+            access_control: fields
+                .access_control
+                .then(|| AccessControl::for_path(&self.path)),
+            git_status: self.git_status,
+        }
+    }
+
+    /// Convert an entry served by the scan cache to Meta
+    fn cached_to_meta(&self, stat: &CachedStat, fields: &MetaFields) -> crate::meta::Meta {
+        use crate::meta::*;
+
+        #[cfg(unix)]
+        let (owner, permissions_or_attributes) = match fields.permission {
+            crate::flags::PermissionFlag::Disable => (None, None),
+            _ => (
+                fields.owner.then(|| Owner::new(stat.uid, stat.gid)),
+                fields
+                    .permissions
+                    .then(|| PermissionsOrAttributes::Permissions(Permissions::from_mode(stat.mode))),
+            ),
         };
+        #[cfg(not(unix))]
+        let (owner, permissions_or_attributes) = (None, None);
 
         Meta {
-            inode: Some(INode::from(&self.metadata)),
-            links: Some(Links::from(&self.metadata)),
+            inode: fields.inode.then(|| INode::new(stat.ino)),
+            links: fields.links.then(|| Links::new(stat.nlink)),
+            filesystem: fields.filesystem.then(|| Filesystem::new(stat.dev)),
+            matches: self.matches.as_ref().map(|matches| matches.count),
+            hash: self.hash.clone(),
+            media: self.media,
+            binary: self.binary.clone(),
             path: self.path.clone(),
-            symlink: SymLink::from(self.path.as_path()),
-            size: Some(Size::from(&self.metadata)),
-            date: Some(Date::from(&self.metadata)),
-            indicator: Indicator::from(self.file_type),
+            symlink: if self.is_symlink && fields.symlink {
+                SymLink::from(self.path.as_path())
+            } else {
+                SymLink::default()
+            },
+            size: fields
+                .size
+                .then(|| self.size.clone().unwrap_or_else(|| Size::new(stat.size))),
+            date: fields
+                .date
+                .then(|| stat.time(fields.time).map_or(Date::Invalid, Date::from)),
+            indicator: if self.mount_point {
+                Indicator::mount_point()
+            } else {
+                Indicator::from(self.file_type)
+            },
             owner,
             permissions_or_attributes,
-            name,
+            name: self.display_name(),
             file_type: self.file_type,
             content: None,
-            access_control: Some(AccessControl::for_path(&self.path)),
-            git_status: self.git_status.as_ref().map(|info| GitFileStatus::from_gix_status(info)),
+            access_control: fields
+                .access_control
+                .then(|| AccessControl::for_path(&self.path)),
+            git_status: self.git_status,
         }
     }
+
+    /// Convert an entry read from a git revision to Meta; there is no inode, owner or ACL
+    fn object_to_meta(&self, fields: &MetaFields) -> crate::meta::Meta {
+        use crate::meta::*;
+
+        let object = self.object.as_ref();
+
+        #[cfg(unix)]
+        let permissions_or_attributes = match fields.permission {
+            crate::flags::PermissionFlag::Disable => None,
+            _ => object.map(|o| PermissionsOrAttributes::Permissions(Permissions::from_mode(o.mode))),
+        };
+        #[cfg(not(unix))]
+        let permissions_or_attributes = {
+            let _ = fields;
+            None
+        };
+
+        Meta {
+            inode: None,
+            links: None,
+            filesystem: None,
+            matches: None,
+            hash: self.hash.clone(),
+            media: self.media,
+            binary: self.binary.clone(),
+            path: self.path.clone(),
+            symlink: object
+                .and_then(|o| o.link_target.clone())
+                .map(SymLink::from_target)
+                .unwrap_or_default(),
+            size: self.size.clone().or_else(|| object.map(|o| Size::new(o.size))),
+            date: object.and_then(|o| o.time).map(Date::from),
+            indicator: Indicator::from(self.file_type),
+            owner: None,
+            permissions_or_attributes,
+            name: self.display_name(),
+            file_type: self.file_type,
+            content: None,
+            access_control: None,
+            git_status: None,
+        }
+    }
+
+    /// Use FileEntry.name if it differs from path-derived name (for special entries like . and ..)
+    fn display_name(&self) -> crate::meta::Name {
+        let name = if self.name == "." || self.name == ".." {
+            // Create Name directly with our custom name
+            crate::meta::Name::new(&PathBuf::from(&self.name), self.file_type)
+        } else {
+            crate::meta::Name::new(&self.path, self.file_type)
+        };
+        name.with_content_type(self.content_type.clone())
+    }
+}
+
+/// The `Meta` fields a listing displays or sorts by, derived from the active `Blocks`, sort
+/// column and layout
+#[derive(Debug, Clone, Copy)]
+pub struct MetaFields {
+    pub permission: crate::flags::PermissionFlag,
+    pub owner: bool,
+    pub permissions: bool,
+    pub access_control: bool,
+    pub symlink: bool,
+    pub inode: bool,
+    pub links: bool,
+    pub filesystem: bool,
+    pub size: bool,
+    pub date: bool,
+    /// Which timestamp `date` holds
+    pub time: crate::flags::TimeField,
+}
+
+impl MetaFields {
+    pub fn from_flags(flags: &crate::flags::Flags) -> Self {
+        use crate::flags::blocks::Block;
+        use crate::flags::{Layout, SortColumn};
+
+        let shows = |block| flags.blocks.0.contains(&block);
+        Self {
+            permission: flags.permission,
+            owner: shows(Block::User) || shows(Block::Group),
+            permissions: shows(Block::Permission),
+            access_control: shows(Block::Permission) || shows(Block::Context),
+            // Mirrors the symlink arrow condition of `display::get_output`
+            symlink: shows(Block::Name)
+                && !(flags.no_symlink.0 || flags.dereference.0 || flags.layout == Layout::Grid),
+            inode: shows(Block::INode),
+            links: shows(Block::Links),
+            filesystem: shows(Block::Filesystem),
+            size: shows(Block::Size)
+                || shows(Block::SizeValue)
+                || flags.sorting.column == SortColumn::Size,
+            date: shows(Block::Date) || flags.sorting.column == SortColumn::Time,
+            time: flags.time,
+        }
+    }
+}
+
+/// Apply the `Display` mode to an entry name, shared by every `FileStream` source
+fn is_displayed(name: &str, is_dir: bool, display: crate::flags::Display) -> bool {
+    use crate::flags::Display;
+    match display {
+        Display::VisibleOnly => !name.starts_with('.'),
+        Display::AlmostAll => name != "." && name != "..",
+        Display::All | Display::SystemProtected => true,
+        Display::DirectoryOnly => is_dir,
+    }
+}
+
+/// A symbolic link that could not be followed with `--dereference`
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum LinkError {
+    #[error("{}: broken symbolic link{}", path.display(), target.as_ref().map(|t| format!(" to {}", t.display())).unwrap_or_default())]
+    Broken {
+        path: PathBuf,
+        target: Option<PathBuf>,
+    },
+
+    #[error("{}: symbolic link cycle, already visited as {}", path.display(), ancestor.display())]
+    Cycle { path: PathBuf, ancestor: PathBuf },
 }
 
 /// Result type for stream operations
@@ -138,11 +576,299 @@ pub enum StreamError {
     Io(#[from] std::io::Error),
 
     #[error("Git error: {0}")]
-    #[allow(dead_code)]
     Git(String),
 
     #[error("Traversal error: {0}")]
     Traversal(String),
+
+    #[error("{0}")]
+    Link(#[from] LinkError),
+
+    /// The walk was cut by a scan limit; always the last item of the stream
+    #[error("{0}")]
+    Truncated(Truncation),
+}
+
+/// How much the walk learns about each entry beyond its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stat {
+    /// Every entry is `lstat`ed
+    All,
+    /// Entries keep the file type `readdir` reported (`d_type`); files are only stat'ed for
+    /// their exec bit and links for their target's type when the output colors them
+    TypesOnly { exec_bit: bool, link_target: bool },
+}
+
+/// Options of the on-disk walk
+#[derive(Debug, Clone, Copy)]
+pub struct WalkOptions {
+    /// Follow symbolic links to their target (`-L`)
+    pub dereference: bool,
+    /// Do not descend into other file systems (`-x`)
+    pub one_file_system: bool,
+    pub stat: Stat,
+    /// Read directories on the shared thread pool, or serially
+    pub threads: crate::flags::Threads,
+    /// Sniff the contents of files while reading their directory, to sort them by type
+    pub sniff: bool,
+    /// Look up the git status of entries while reading their directory, to sort, filter and
+    /// color them by it
+    pub git: bool,
+}
+
+/// jwalk client state: each directory read carries the (dev, inode) and path of the
+/// directories above it, to detect cycles through followed links, and the `.sapignore` rules
+/// read above it; each child carries the `FileEntry` built while reading its parent
+type WalkState = (ReadDirState, Option<FileEntry>);
+
+#[derive(Clone, Debug, Default)]
+pub struct ReadDirState {
+    ancestors: Vec<(DirId, PathBuf)>,
+    sapignores: crate::flags::SapIgnores,
+}
+
+/// Identity of a directory on disk: (dev, inode)
+type DirId = (u64, u64);
+
+#[cfg(unix)]
+fn dir_id(metadata: &std::fs::Metadata) -> Option<DirId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(_metadata: &std::fs::Metadata) -> Option<DirId> {
+    None
+}
+
+/// Whether the directory at `path` is the root of a mounted file system: its device differs
+/// from its parent's, or it is its own parent like `/`
+fn is_mount_point(path: &std::path::Path, metadata: &std::fs::Metadata) -> bool {
+    let parent = std::fs::metadata(path.join("..")).ok();
+    match (dir_id(metadata), parent.as_ref().and_then(dir_id)) {
+        (Some(id), Some(parent)) => id.0 != parent.0 || id == parent,
+        _ => false,
+    }
+}
+
+/// Walk a single input path on disk with jwalk.
+///
+/// With `dereference`, directory links are descended into like directories, unless their
+/// target is one of the directories being walked above them. With `one_file_system`,
+/// directories on another device than `path` are listed but not descended into.
+fn walk_path(
+    path: PathBuf,
+    max_depth: usize,
+    ignore_globs: &crate::flags::IgnoreGlobs,
+    display_mode: crate::flags::Display,
+    options: WalkOptions,
+    order: &EntryOrder,
+) -> impl Stream<Item = StreamResult<FileEntry>> + Send + use<> {
+    let WalkOptions {
+        dereference,
+        one_file_system,
+        stat,
+        threads,
+        sniff,
+        git,
+    } = options;
+    // Create jwalk walker for this path, with the built-in patterns of its projects
+    let ignore_globs_for_callback = ignore_globs.for_root(&path);
+    let git = git.then(|| std::sync::Arc::new(GitCache::new(&path)));
+    let root_git = git.clone();
+    let order = order.clone();
+    let root_dev = std::fs::metadata(&path).ok().as_ref().and_then(dir_id).map(|(dev, _)| dev);
+    let root = path.clone();
+    let walker = jwalk::WalkDirGeneric::<WalkState>::new(&path)
+        .max_depth(max_depth)
+        .skip_hidden(false)
+        .follow_links(false)
+        .parallelism(threads.parallelism())
+        .process_read_dir(move |depth, dir_path, state, children| {
+            // Filter out ignored and hidden entries during traversal (prevents descending). The
+            // root comes through here alone, without a depth: named on the command line, it is
+            // listed even when hidden or ignored
+            let relative = dir_path.strip_prefix(&root).unwrap_or(Path::new(""));
+            state.sapignores.read(dir_path, relative);
+            let mut ignored = IgnoredChildren::default();
+            children.retain_mut(|dir_entry_result| {
+                if depth.is_none() {
+                    return true;
+                }
+                let Ok(dir_entry) = dir_entry_result else {
+                    return true;
+                };
+                let Some(name) = dir_entry.file_name.to_str() else {
+                    return true;
+                };
+                let is_dir = dir_entry.file_type.is_dir();
+                if !is_displayed(name, is_dir, display_mode) {
+                    return false;
+                }
+                let first = ignored.count == 0;
+                if !ignore_globs_for_callback.hides(&state.sapignores, &relative.join(name), is_dir, &mut ignored) {
+                    return true;
+                }
+                // The first ignored child is kept, without its contents, to stand for all of
+                // them once they are counted
+                let carrier = first && ignored.count > 0;
+                if carrier {
+                    dir_entry.read_children_path = None;
+                    dir_entry.client_state = Some(FileEntry::ignored(dir_path, dir_entry.depth, IgnoredChildren::default()));
+                }
+                carrier
+            });
+
+            // The directory's own identity, for cycles and mount points; links are followed
+            // however little the entries are stat'ed
+            let dir = match stat {
+                Stat::TypesOnly { .. } if !dereference => None,
+                _ => std::fs::metadata(dir_path).ok().as_ref().and_then(dir_id),
+            };
+            if dereference && let Some(id) = dir {
+                state.ancestors.push((id, dir_path.to_path_buf()));
+            }
+
+            // Stat the children here, on the walker's threads, so they can be put in display
+            // order before jwalk yields them
+            for dir_entry in children.iter_mut().flatten() {
+                if dir_entry.client_state.is_some() {
+                    let ignored = std::mem::take(&mut ignored);
+                    dir_entry.client_state = Some(FileEntry::ignored(dir_path, dir_entry.depth, ignored));
+                    continue;
+                }
+                let name = dir_entry.file_name.to_string_lossy().to_string();
+                // A followed link takes its target's metadata, which also tells its cycles
+                let mut file_entry = match stat {
+                    Stat::TypesOnly { exec_bit, link_target }
+                        if !(dereference && dir_entry.file_type.is_symlink()) =>
+                    {
+                        FileEntry::from_file_type(
+                            dir_entry.path(),
+                            name,
+                            dir_entry.file_type,
+                            dir_entry.depth,
+                            exec_bit,
+                            link_target,
+                        )
+                    }
+                    _ => {
+                        let Ok(metadata) = dir_entry.metadata() else {
+                            continue;
+                        };
+                        FileEntry::from_metadata(dir_entry.path(), name, metadata, dir_entry.depth, dereference)
+                    }
+                };
+
+                if sniff && matches!(file_entry.file_type, FileType::File { .. }) {
+                    file_entry.content_type = crate::meta::ContentType::read(&file_entry.path).ok();
+                }
+                if let Some(git) = &git {
+                    file_entry.git_status = git.status(&file_entry.path, file_entry.file_type.is_dirlike());
+                }
+
+                if dereference && file_entry.is_symlink && file_entry.file_type.is_dirlike() {
+                    let visited = file_entry.metadata.as_ref().and_then(dir_id).and_then(|id| {
+                        state.ancestors.iter().find(|(ancestor, _)| *ancestor == id)
+                    });
+                    match visited {
+                        Some((_, ancestor)) => {
+                            file_entry.link_error = Some(LinkError::Cycle {
+                                path: file_entry.path.clone(),
+                                ancestor: ancestor.clone(),
+                            });
+                        }
+                        None => dir_entry.read_children_path = Some(file_entry.path.as_path().into()),
+                    }
+                }
+
+                if file_entry.file_type.is_dirlike()
+                    && let Some(metadata) = &file_entry.metadata
+                    && let Some((dev, _)) = dir_id(metadata)
+                {
+                    // The root, read alone, is compared with its own parent
+                    let mount_point = match depth {
+                        None => is_mount_point(&file_entry.path, metadata),
+                        Some(_) => dir.is_some_and(|(parent, _)| parent != dev),
+                    };
+                    file_entry.mount_point = mount_point && !file_entry.is_symlink;
+                    if one_file_system && root_dev.is_some_and(|root| root != dev) {
+                        dir_entry.read_children_path = None;
+                    }
+                }
+                dir_entry.client_state = Some(file_entry);
+            }
+
+            // Entries that could not be read go last, in their read order
+            children.sort_by(|a, b| {
+                let a = a.as_ref().ok().and_then(|dir_entry| dir_entry.client_state.as_ref());
+                let b = b.as_ref().ok().and_then(|dir_entry| dir_entry.client_state.as_ref());
+                match (a, b) {
+                    // The ignored children are listed after the visible ones
+                    (Some(a), Some(b)) => a
+                        .ignored
+                        .is_some()
+                        .cmp(&b.ignored.is_some())
+                        .then_with(|| order.cmp(a, b)),
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (None, None) => std::cmp::Ordering::Equal,
+                }
+            });
+
+            let mut entries: Vec<&mut FileEntry> = children
+                .iter_mut()
+                .flatten()
+                .filter_map(|dir_entry| dir_entry.client_state.as_mut())
+                .collect();
+            mark_last_sibling(&mut entries);
+        });
+    
+    // jwalk always starts at depth 0 for the root path
+    let base_depth = 0;
+    let walker_iter = walker.into_iter().flat_map(move |entry_result| {
+        let file_entry = match entry_result {
+            // Children were filtered and stat'ed while reading their parent
+            Ok(mut entry) => match entry.client_state.take() {
+                Some(file_entry) => Ok(file_entry),
+                None => FileEntry::from_jwalk(entry, base_depth, dereference)
+                    .map(|mut file_entry| {
+                        file_entry.mount_point = file_entry.file_type.is_dirlike()
+                            && !file_entry.is_symlink
+                            && file_entry
+                                .metadata
+                                .as_ref()
+                                .is_some_and(|metadata| is_mount_point(&file_entry.path, metadata));
+                        if let Some(git) = &root_git {
+                            file_entry.git_status =
+                                git.status(&file_entry.path, file_entry.file_type.is_dirlike());
+                        }
+                        file_entry
+                    })
+                    .map_err(StreamError::Io),
+            },
+            Err(e) => Err(StreamError::Traversal(e.to_string())),
+        };
+
+        // A link that could not be followed is listed, then reported
+        match file_entry {
+            Ok(mut file_entry) => match file_entry.link_error.take() {
+                Some(error) => vec![Ok(file_entry), Err(StreamError::Link(error))],
+                None => vec![Ok(file_entry)],
+            },
+            Err(e) => vec![Err(e)],
+        }
+    });
+
+    futures::stream::iter(walker_iter)
+}
+
+/// Flag the last of a directory's children, already in display order
+fn mark_last_sibling(siblings: &mut [&mut FileEntry]) {
+    let count = siblings.len();
+    for (idx, entry) in siblings.iter_mut().enumerate() {
+        entry.last_sibling = idx + 1 == count;
+    }
 }
 
 /// Core file stream that produces entries from directory traversal
@@ -157,92 +883,158 @@ impl FileStream {
         max_depth: usize,
         ignore_globs: &crate::flags::IgnoreGlobs,
         display: crate::flags::Display,
+        options: WalkOptions,
+        order: &EntryOrder,
     ) -> Self {
         let ignore_globs = ignore_globs.clone();
+        let order = order.clone();
         
         // Create a stream that processes all paths
-        let stream = futures::stream::iter(paths.into_iter())
-            .flat_map(move |path| {
-                let ignore_globs = ignore_globs.clone();
-                let display_mode = display;
-                
-                // Create jwalk walker for this path
-                let ignore_globs_for_callback = ignore_globs.clone();
-                let walker = jwalk::WalkDir::new(&path)
-                    .max_depth(max_depth)
-                    .sort(true)
-                    .skip_hidden(false)
-                    .follow_links(false)
-                    .parallelism(jwalk::Parallelism::RayonNewPool(0))
-                    .process_read_dir(move |_depth, _path, _state, children| {
-                        // Filter out ignored entries during traversal (prevents descending)
-                        children.retain(|dir_entry_result| {
-                            dir_entry_result.as_ref().map(|dir_entry| {
-                                dir_entry.file_name.to_str()
-                                    .map(|name| !ignore_globs_for_callback.is_match(std::ffi::OsStr::new(name)))
-                                    .unwrap_or(true)
-                            }).unwrap_or(true)
-                        });
-                    });
-                
-                // jwalk always starts at depth 0 for the root path
-                let base_depth = 0;
-                let walker_iter = walker.into_iter();
-                
-                // For Display::All in non-tree modes, prepend .. and . entries
-                // (Tree mode doesn't need these - they interfere with hierarchy)
-                let special_entries: Vec<StreamResult<FileEntry>> = Vec::new();
-                
-                // Chain special entries with walker stream
-                futures::stream::iter(special_entries)
-                    .chain(
-                        futures::stream::iter(walker_iter)
-                            .filter_map(move |entry_result| {
-                                match entry_result {
-                                    Ok(entry) => {
-                                        // Apply display mode filter (ignore_globs now in process_read_dir)
-                                        if let Some(name) = entry.file_name().to_str() {
-                                            // Apply display mode filter
-                                            use crate::flags::Display;
-                                            match display_mode {
-                                                Display::VisibleOnly => {
-                                                    if name.starts_with('.') {
-                                                        return futures::future::ready(None);
-                                                    }
-                                                }
-                                                Display::AlmostAll => {
-                                                    if name == "." || name == ".." {
-                                                        return futures::future::ready(None);
-                                                    }
-                                                }
-                                                Display::All => {}
-                                                Display::DirectoryOnly => {
-                                                    if !entry.file_type().is_dir() {
-                                                        return futures::future::ready(None);
-                                                    }
-                                                }
-                                                Display::SystemProtected => {}
-                                            }
-                                        }
-                                        
-                                        // Convert to FileEntry
-                                        match FileEntry::from_jwalk(entry, base_depth) {
-                                            Ok(file_entry) => futures::future::ready(Some(Ok(file_entry))),
-                                            Err(e) => futures::future::ready(Some(Err(StreamError::Io(e)))),
-                                        }
-                                    }
-                                    Err(e) => futures::future::ready(Some(Err(StreamError::Traversal(e.to_string())))),
-                                }
-                            })
-                    )
-            });
+        let stream = futures::stream::iter(paths)
+            .flat_map(move |path| walk_path(path, max_depth, &ignore_globs, display, options, &order));
 
+        Self::from_source(stream)
+    }
+
+    /// Create a file stream walking the given paths through the scan cache. Only
+    /// `options.one_file_system` and `options.git` apply: every entry is stat'ed, on the
+    /// calling thread, and links are not followed.
+    pub fn from_cache(
+        paths: Vec<PathBuf>,
+        cache: &ScanCache,
+        max_depth: usize,
+        ignore_globs: &crate::flags::IgnoreGlobs,
+        display: crate::flags::Display,
+        options: WalkOptions,
+        order: &EntryOrder,
+    ) -> Self {
+        let cache = cache.clone();
+        let ignore_globs = ignore_globs.clone();
+        let order = order.clone();
+
+        let stream = futures::stream::iter(paths).flat_map(move |path| {
+            let walk = CacheWalk::new(
+                &path,
+                &cache,
+                max_depth,
+                &ignore_globs,
+                display,
+                options,
+                &order,
+            );
+            let entries: Box<dyn Iterator<Item = StreamResult<FileEntry>> + Send> = match walk {
+                Ok(walk) => Box::new(walk),
+                Err(e) => Box::new(std::iter::once(Err(e))),
+            };
+            futures::stream::iter(entries)
+        });
+
+        Self::from_source(stream)
+    }
+
+    /// Create a file stream listing the given paths as they exist at a git revision
+    pub fn from_revision(
+        paths: Vec<PathBuf>,
+        revision: &crate::flags::Revision,
+        max_depth: usize,
+        ignore_globs: &crate::flags::IgnoreGlobs,
+        display: crate::flags::Display,
+        order: &EntryOrder,
+    ) -> Self {
+        let rev = revision.rev.clone().unwrap_or_else(|| "HEAD".to_string());
+        let last_modified = revision.last_modified;
+        let ignore_globs = ignore_globs.clone();
+        let order = order.clone();
+
+        let stream = futures::stream::iter(paths).flat_map(move |path| {
+            let entries: Box<dyn Iterator<Item = StreamResult<FileEntry>> + Send> =
+                match RevisionWalk::new(&path, &rev, max_depth, &ignore_globs, display, &order) {
+                    Ok(walk) if last_modified => Box::new(walk.with_last_modified().into_iter()),
+                    Ok(walk) => Box::new(walk),
+                    Err(e) => Box::new(std::iter::once(Err(e))),
+                };
+            futures::stream::iter(entries)
+        });
+
+        Self::from_source(stream)
+    }
+
+    /// Create a file stream listing the files tracked in the git index below the given paths.
+    ///
+    /// Inputs outside a work tree are walked on disk when `tracked.fallback` is set, and
+    /// reported as errors otherwise. Unless `options.stat` is `Stat::All`, entries rely on the
+    /// index's cached stat data. `options.git` looks up their status; the other options only
+    /// apply to the fallback walk.
+    pub fn from_index(
+        paths: Vec<PathBuf>,
+        tracked: crate::flags::Tracked,
+        max_depth: usize,
+        ignore_globs: &crate::flags::IgnoreGlobs,
+        display: crate::flags::Display,
+        options: WalkOptions,
+        order: &EntryOrder,
+    ) -> Self {
+        let ignore_globs = ignore_globs.clone();
+        let order = order.clone();
+
+        let stream = futures::stream::iter(paths).flat_map(move |path| {
+            let walk = IndexWalk::new(
+                &path,
+                tracked.untracked,
+                max_depth,
+                &ignore_globs,
+                display,
+                options,
+                &order,
+            );
+            let entries: Pin<Box<dyn Stream<Item = StreamResult<FileEntry>> + Send>> = match walk {
+                Ok(walk) => Box::pin(futures::stream::iter(walk)),
+                Err(_) if tracked.fallback => {
+                    Box::pin(walk_path(path, max_depth, &ignore_globs, display, options, &order))
+                }
+                Err(e) => Box::pin(futures::stream::once(futures::future::ready(Err(e)))),
+            };
+            entries
+        });
+
+        Self::from_source(stream)
+    }
+
+    /// Wrap any stream of entries so it can feed the display and LLM pipelines
+    pub fn from_source(
+        source: impl Stream<Item = StreamResult<FileEntry>> + Send + 'static,
+    ) -> Self {
         FileStream {
-            inner: Box::pin(stream),
+            inner: Box::pin(source),
         }
     }
 }
 
+/// The serial walk of `root` with the default flags, which the tests of the stages feed on
+#[cfg(test)]
+pub(crate) fn test_walk(root: &Path) -> FileStream {
+    let flags = crate::flags::Flags::default();
+    let options = WalkOptions {
+        dereference: false,
+        one_file_system: false,
+        stat: Stat::All,
+        threads: crate::flags::Threads {
+            mode: crate::flags::ThreadMode::Serial,
+            busy_timeout: None,
+        },
+        sniff: false,
+        git: false,
+    };
+    FileStream::new(
+        vec![root.to_path_buf()],
+        usize::MAX,
+        &flags.ignore_globs,
+        flags.display,
+        options,
+        &EntryOrder::from_flags(&flags),
+    )
+}
+
 impl Stream for FileStream {
     type Item = StreamResult<FileEntry>;
 
@@ -251,55 +1043,255 @@ impl Stream for FileStream {
     }
 }
 
-/// Output events emitted by accumulators (planned for future streaming optimization)
-#[allow(dead_code)]
+/// Output events emitted by accumulators
 #[derive(Debug)]
 pub enum OutputEvent {
-    /// Header for a directory in human-readable output
+    /// Header for a directory, with the repository summary when `--repo-info` is given
     DirectoryHeader {
         path: PathBuf,
-        file_count: usize,
-        dir_count: usize,
-        total_size: u64,
+        repo: Option<RepoInfo>,
     },
 
-    /// A formatted file/directory entry for display
+    /// A file/directory entry of the current section, formatted by `display`
     FileRow {
         entry: FileEntry,
-        formatted: Vec<String>, // Pre-formatted columns
     },
 
-    /// Tree node with hierarchy information
+    /// End of a section's rows, which are laid out together in a grid
+    SectionEnd,
+
+    /// Tree node, drawn after `prefix`, the edges of its ancestors and its own
     TreeNode {
         entry: FileEntry,
-        is_last: bool,
         prefix: String,
     },
 
-    /// Stream completion event
-    StreamComplete {
-        total_files: usize,
-        total_dirs: usize,
+    /// Marks a directory whose listing was cut by a scan limit, after its rows; in a tree it
+    /// is the directory's last row, drawn after `prefix`
+    Truncated {
+        prefix: String,
+    },
+
+    /// Stands for the children of a directory hidden by the ignore rules, with `--show-ignored`
+    /// or `--explain-ignores`; in a tree it is the directory's last row, drawn after `prefix`
+    Ignored {
+        ignored: IgnoredChildren,
+        prefix: String,
     },
 }
 
-/// Trait for accumulator implementations (planned for future streaming optimization)
-#[allow(dead_code)]
+/// Trait for accumulators turning the entry stream into output events
 pub trait Accumulator: Stream<Item = StreamResult<OutputEvent>> + Unpin {
     /// Process a file entry
     fn process_entry(&mut self, entry: FileEntry) -> AccumulatorAction;
 }
 
-/// Actions an accumulator can take when processing an entry (planned for future streaming optimization)
-#[allow(dead_code)]
+/// What an accumulator does with an entry it processed
 #[derive(Debug)]
 pub enum AccumulatorAction {
-    /// Buffer the entry for later emission
+    /// Hold the entry until its section is complete; nothing can be emitted yet
     Buffer,
 
     /// Emit one or more events immediately
     Emit(Vec<OutputEvent>),
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use futures::StreamExt;
+    use tempfile::tempdir;
+
+    use super::{FileEntry, FileStream, LinkError, MetaFields, Stat, StreamError, StreamResult, WalkOptions};
+    use crate::flags::blocks::{Block, Blocks};
+    use crate::flags::{Dereference, Flags, Layout, SortColumn, ThreadMode, Threads};
+    use crate::meta::FileType;
+    use crate::sort::EntryOrder;
 
-    /// Request transformation via rules engine
-    Transform,
+    /// A serial walk of `root` with `options` changed from names only
+    fn walk(root: &Path, options: impl FnOnce(&mut WalkOptions)) -> Vec<StreamResult<FileEntry>> {
+        walk_to(root, usize::MAX, options)
+    }
+
+    fn walk_to(
+        root: &Path,
+        max_depth: usize,
+        options: impl FnOnce(&mut WalkOptions),
+    ) -> Vec<StreamResult<FileEntry>> {
+        let flags = Flags::default();
+        let mut walk_options = WalkOptions {
+            dereference: false,
+            one_file_system: false,
+            stat: Stat::TypesOnly {
+                exec_bit: false,
+                link_target: false,
+            },
+            threads: Threads {
+                mode: ThreadMode::Serial,
+                busy_timeout: None,
+            },
+            sniff: false,
+            git: false,
+        };
+        options(&mut walk_options);
+        let stream = FileStream::new(
+            vec![root.to_path_buf()],
+            max_depth,
+            &flags.ignore_globs,
+            flags.display,
+            walk_options,
+            &EntryOrder::from_flags(&flags),
+        );
+        futures::executor::block_on(stream.collect::<Vec<_>>())
+    }
+
+    /// The paths of the entries below `root`, the root itself as an empty one
+    fn paths(root: &Path, results: &[StreamResult<FileEntry>]) -> Vec<String> {
+        results
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+            .map(|entry| entry.path.strip_prefix(root).unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    fn link_errors(results: Vec<StreamResult<FileEntry>>) -> Vec<LinkError> {
+        results
+            .into_iter()
+            .filter_map(|result| match result {
+                Err(StreamError::Link(error)) => Some(error),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dereference_stops_at_link_cycles() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(root.join("a")).unwrap();
+        std::os::unix::fs::symlink(&root, root.join("a/up")).unwrap();
+        std::os::unix::fs::symlink(root.join("a"), root.join("b")).unwrap();
+
+        // The entries are not stat'ed otherwise, the links still are
+        let results = walk(&root, |options| options.dereference = true);
+        assert_eq!(paths(&root, &results), ["", "a", "a/up", "b", "b/up"]);
+        let ancestors: Vec<_> = link_errors(results)
+            .into_iter()
+            .map(|error| match error {
+                LinkError::Cycle { ancestor, .. } => ancestor,
+                error => panic!("{}", error),
+            })
+            .collect();
+        assert_eq!(ancestors, [root.clone(), root.clone()]);
+
+        // Without -L the links are listed and not followed
+        let results = walk(&root, |_| {});
+        assert_eq!(paths(&root, &results), ["", "a", "a/up", "b"]);
+        assert!(link_errors(results).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dereference_reports_broken_links() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir(&root).unwrap();
+        std::os::unix::fs::symlink(root.join("missing"), root.join("broken")).unwrap();
+
+        let results = walk(&root, |options| options.dereference = true);
+        assert_eq!(paths(&root, &results), ["", "broken"]);
+        match link_errors(results).as_slice() {
+            [LinkError::Broken { path, target }] => {
+                assert_eq!(path, &root.join("broken"));
+                assert_eq!(target.as_ref(), Some(&root.join("missing")));
+            }
+            errors => panic!("{:?}", errors),
+        }
+    }
+
+    #[test]
+    fn test_meta_fields_follow_blocks_and_sort() {
+        let mut flags = Flags {
+            blocks: Blocks(vec![Block::Permission, Block::User, Block::SizeValue, Block::Name]),
+            layout: Layout::OneLine,
+            ..Flags::default()
+        };
+        flags.sorting.column = SortColumn::Time;
+        let fields = MetaFields::from_flags(&flags);
+        assert!(fields.permissions && fields.access_control && fields.owner && fields.size);
+        // Sorted by, not shown
+        assert!(fields.date);
+        assert!(!fields.inode && !fields.links && !fields.filesystem);
+        assert!(fields.symlink);
+
+        flags.blocks = Blocks(vec![Block::Name]);
+        flags.sorting.column = SortColumn::Name;
+        flags.dereference = Dereference(true);
+        let fields = MetaFields::from_flags(&flags);
+        assert!(!fields.permissions && !fields.access_control && !fields.owner);
+        assert!(!fields.size && !fields.date);
+        // Followed links have no arrow
+        assert!(!fields.symlink);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_types_only_walk_does_not_stat() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(root.join("dir")).unwrap();
+        fs::write(root.join("file"), "").unwrap();
+        std::os::unix::fs::symlink(root.join("dir"), root.join("link")).unwrap();
+
+        let entries: Vec<FileEntry> = walk(&root, |_| {}).into_iter().skip(1).flatten().collect();
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|entry| entry.metadata.is_none() && entry.cached.is_none()));
+        let link = entries.iter().find(|entry| entry.name == "link").unwrap();
+        // Without a stat the target's type is not known
+        assert_eq!(link.file_type, FileType::SymLink { is_dir: false });
+
+        // Colors and icons stat links for their target
+        let results = walk(&root, |options| {
+            options.stat = Stat::TypesOnly {
+                exec_bit: false,
+                link_target: true,
+            }
+        });
+        let link = results.iter().flatten().find(|entry| entry.name == "link").unwrap();
+        assert_eq!(link.file_type, FileType::SymLink { is_dir: true });
+        let mut others = results.iter().flatten().skip(1).filter(|entry| entry.name != "link");
+        assert!(others.clone().count() == 2 && others.all(|entry| entry.metadata.is_none()));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_mount_points_are_marked_and_not_crossed() {
+        use std::os::unix::fs::MetadataExt;
+
+        let root = Path::new("/");
+        // Only where /proc is mounted, as it usually is
+        if fs::metadata(root).unwrap().dev() == fs::metadata("/proc").unwrap().dev() {
+            return;
+        }
+        let all = |options: &mut WalkOptions| options.stat = Stat::All;
+
+        let entries: Vec<FileEntry> = walk_to(root, 1, all).into_iter().flatten().collect();
+        // The root is its own parent
+        assert!(entries[0].mount_point);
+        assert!(entries.iter().any(|entry| entry.name == "proc" && entry.mount_point));
+
+        let entries: Vec<FileEntry> = walk_to(root, 2, |options| {
+            all(options);
+            options.one_file_system = true;
+        })
+        .into_iter()
+        .flatten()
+        .collect();
+        // Listed, but not descended into
+        assert!(entries.iter().any(|entry| entry.path == Path::new("/proc")));
+        assert!(!entries.iter().any(|entry| entry.path.parent() == Some(Path::new("/proc"))));
+    }
 }
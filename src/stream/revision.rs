@@ -0,0 +1,534 @@
+//! Revision source: lists a directory as it exists in a git tree object instead of on disk
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use gix::bstr::ByteSlice;
+use gix::object::tree::{EntryKind, EntryMode};
+use gix::ObjectId;
+
+use crate::flags::ignore_globs::SAPIGNORE;
+use crate::flags::{Display, IgnoreGlobs, IgnoredChildren, SapIgnores};
+use crate::meta::FileType;
+use crate::sort::EntryOrder;
+use crate::stream::{is_displayed, mark_last_sibling, FileEntry, StreamError, StreamResult};
+
+/// Object information for an entry read from git (a tree or the index) rather than from the
+/// file system
+#[derive(Debug, Clone)]
+pub struct GitObjectInfo {
+    pub id: ObjectId,
+    /// Path relative to the repository root, used to look the entry up in history
+    pub rela_path: PathBuf,
+    /// Unix mode synthesized from the tree entry mode
+    pub mode: u32,
+    /// Object size from the object header, without decompressing the blob
+    pub size: u64,
+    /// Commit date of the revision or of the last commit that changed the entry, or the
+    /// index mtime for tracked files
+    pub time: Option<SystemTime>,
+    pub link_target: Option<PathBuf>,
+}
+
+/// A tree entry waiting to be yielded, with the tree to descend into for directories
+struct Pending {
+    entry: FileEntry,
+    tree: Option<ObjectId>,
+}
+
+/// Depth-first walk over a git tree, yielding siblings in display order like the disk walk
+pub struct RevisionWalk {
+    repo: gix::Repository,
+    commit_id: ObjectId,
+    commit_time: Option<SystemTime>,
+    stack: Vec<Pending>,
+    /// Path of the walked directory relative to the repository root
+    root_rela_path: PathBuf,
+    max_depth: usize,
+    ignore_globs: IgnoreGlobs,
+    /// The `.sapignore` rules of the trees being walked, by depth, as committed
+    sapignores: Vec<SapIgnores>,
+    display: Display,
+    order: EntryOrder,
+}
+
+impl RevisionWalk {
+    pub fn new(
+        path: &Path,
+        rev: &str,
+        max_depth: usize,
+        ignore_globs: &IgnoreGlobs,
+        display: Display,
+        order: &EntryOrder,
+    ) -> StreamResult<Self> {
+        let (repo, rela_path) = locate(path)?;
+
+        // The commit and its tree borrow the repository until they are dropped
+        let (commit_id, commit_time, tree_id, mode) = {
+            let commit = repo
+                .rev_parse_single(rev)
+                .map_err(git_err)?
+                .object()
+                .map_err(git_err)?
+                .peel_to_commit()
+                .map_err(git_err)?;
+            let commit_time = Some(commit_time(&commit)?);
+            let tree = commit.tree().map_err(git_err)?;
+
+            let (tree_id, mode) = if rela_path.as_os_str().is_empty() {
+                (tree.id, EntryKind::Tree.into())
+            } else {
+                match tree.lookup_entry_by_path(&rela_path).map_err(git_err)? {
+                    Some(entry) => (entry.object_id(), entry.mode()),
+                    None => {
+                        return Err(StreamError::Git(format!(
+                            "'{}' does not exist at {}",
+                            path.display(),
+                            rev
+                        )));
+                    }
+                }
+            };
+            (commit.id, commit_time, tree_id, mode)
+        };
+
+        // The projects are detected from the marker files committed at the revision
+        let names: Vec<String> = match repo.find_tree(tree_id) {
+            Ok(tree) if mode.is_tree() => tree
+                .iter()
+                .filter_map(Result::ok)
+                .map(|entry| entry.filename().to_str_lossy().into_owned())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let ignore_globs = ignore_globs.for_projects(|marker| names.iter().any(|name| name == marker));
+
+        let mut walk = Self {
+            repo,
+            commit_id,
+            commit_time,
+            stack: Vec::new(),
+            root_rela_path: rela_path.clone(),
+            max_depth,
+            ignore_globs,
+            sapignores: Vec::new(),
+            display,
+            order: order.clone(),
+        };
+
+        // Name the root like jwalk does
+        let name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .to_string();
+        let root = walk.pending_entry(path.to_path_buf(), rela_path, name, mode, tree_id, 0)?;
+        walk.stack.push(root);
+
+        Ok(walk)
+    }
+
+    /// Drain the walk and date every entry by the last first-parent commit that changed it.
+    ///
+    /// This needs every path up front, so unlike the plain walk it is not lazy.
+    pub fn with_last_modified(mut self) -> Vec<StreamResult<FileEntry>> {
+        let mut results: Vec<StreamResult<FileEntry>> = self.by_ref().collect();
+        let mut entries: Vec<&mut FileEntry> =
+            results.iter_mut().filter_map(|r| r.as_mut().ok()).collect();
+
+        if let Err(e) = self.assign_last_modified(&mut entries) {
+            results.push(Err(e));
+        }
+        results
+    }
+
+    fn assign_last_modified(&self, entries: &mut [&mut FileEntry]) -> StreamResult<()> {
+        // index into `entries` keyed by repository-relative path
+        let mut pending: HashMap<PathBuf, usize> = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| entry.object.as_ref().map(|object| (object.rela_path.clone(), idx)))
+            .collect();
+
+        let walk = self
+            .repo
+            .rev_walk([self.commit_id])
+            .first_parent_only()
+            .all()
+            .map_err(git_err)?;
+
+        for info in walk {
+            let commit = info.map_err(git_err)?.object().map_err(git_err)?;
+            let time = commit_time(&commit)?;
+            let tree = commit.tree().map_err(git_err)?;
+            let parent_tree = match commit.parent_ids().next() {
+                Some(parent) => Some(
+                    self.repo
+                        .find_commit(parent.detach())
+                        .map_err(git_err)?
+                        .tree()
+                        .map_err(git_err)?,
+                ),
+                None => None,
+            };
+
+            // The diff only reads the subtrees whose ids differ. A changed path changed the
+            // directories above it too, and the first commit to change an entry dates it.
+            let changes = self
+                .repo
+                .diff_tree_to_tree(parent_tree.as_ref(), &tree, gix::diff::Options::default())
+                .map_err(git_err)?;
+            for change in &changes {
+                let path = gix::path::from_bstr(change.location());
+                for changed in path.ancestors() {
+                    if let Some(idx) = pending.remove(changed)
+                        && let Some(object) = entries[idx].object.as_mut()
+                    {
+                        object.time = Some(time);
+                    }
+                }
+            }
+
+            if pending.is_empty() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push_children(
+        &mut self,
+        tree_id: ObjectId,
+        parent_path: &Path,
+        parent_rela_path: &Path,
+        depth: usize,
+    ) -> StreamResult<()> {
+        let tree = self.repo.find_tree(tree_id).map_err(git_err)?;
+        let mut children = Vec::new();
+        for entry in tree.iter() {
+            let entry = entry.map_err(git_err)?;
+            let name = entry.filename().to_str_lossy().into_owned();
+            children.push((name, entry.mode(), entry.oid().to_owned()));
+        }
+        drop(tree);
+
+        // The trees above this one are the only ones left on the stack
+        self.sapignores.truncate(depth - 1);
+        let mut sapignores = self.sapignores.last().cloned().unwrap_or_default();
+        let relative = parent_rela_path
+            .strip_prefix(&self.root_rela_path)
+            .unwrap_or(parent_rela_path);
+        if let Some((_, _, id)) = children.iter().find(|(name, mode, _)| {
+            name == SAPIGNORE && matches!(mode.kind(), EntryKind::Blob | EntryKind::BlobExecutable)
+        }) {
+            let blob = self.repo.find_object(*id).map_err(git_err)?;
+            sapignores.push(&parent_path.join(SAPIGNORE), relative, &blob.data.to_str_lossy());
+        }
+
+        let mut ignored = IgnoredChildren::default();
+        children.retain(|(name, mode, _)| {
+            is_displayed(name, mode.is_tree(), self.display)
+                && !self.ignore_globs.hides(&sapignores, &relative.join(name), mode.is_tree(), &mut ignored)
+        });
+        self.sapignores.push(sapignores);
+
+        let mut pending = children
+            .into_iter()
+            .map(|(name, mode, id)| {
+                self.pending_entry(
+                    parent_path.join(&name),
+                    parent_rela_path.join(&name),
+                    name,
+                    mode,
+                    id,
+                    depth,
+                )
+            })
+            .collect::<StreamResult<Vec<_>>>()?;
+
+        pending.sort_by(|a, b| self.order.cmp(&a.entry, &b.entry));
+        if ignored.count > 0 {
+            pending.push(Pending {
+                entry: FileEntry::ignored(parent_path, depth, ignored),
+                tree: None,
+            });
+        }
+        let mut entries: Vec<&mut FileEntry> = pending.iter_mut().map(|p| &mut p.entry).collect();
+        mark_last_sibling(&mut entries);
+
+        self.stack.extend(pending.into_iter().rev());
+        Ok(())
+    }
+
+    fn pending_entry(
+        &self,
+        path: PathBuf,
+        rela_path: PathBuf,
+        name: String,
+        mode: EntryMode,
+        id: ObjectId,
+        depth: usize,
+    ) -> StreamResult<Pending> {
+        let (file_type, unix_mode, tree) = match mode.kind() {
+            EntryKind::Tree => (FileType::Directory { uid: false }, 0o040_755, Some(id)),
+            EntryKind::Blob => (FileType::File { uid: false, exec: false }, 0o100_644, None),
+            EntryKind::BlobExecutable => {
+                (FileType::File { uid: false, exec: true }, 0o100_755, None)
+            }
+            EntryKind::Link => (FileType::SymLink { is_dir: false }, 0o120_777, None),
+            // Submodules point at a commit in another repository, so there is nothing to read
+            EntryKind::Commit => (FileType::Directory { uid: false }, 0o040_755, None),
+        };
+
+        let size = match mode.kind() {
+            EntryKind::Commit => 0,
+            _ => self.repo.find_header(id).map_err(git_err)?.size(),
+        };
+
+        let link_target = match mode.kind() {
+            EntryKind::Link => {
+                let blob = self.repo.find_object(id).map_err(git_err)?;
+                Some(gix::path::from_bstr(blob.data.as_bstr()).into_owned())
+            }
+            _ => None,
+        };
+
+        Ok(Pending {
+            entry: FileEntry {
+                path,
+                name,
+                file_type,
+                metadata: None,
+                depth,
+                is_symlink: mode.is_link(),
+                last_sibling: true,
+                link_error: None,
+                mount_point: false,
+                git_status: None,
+                size: None,
+                cached: None,
+                matches: None,
+                hash: None,
+                content_type: None,
+                media: None,
+                binary: None,
+                ignored: None,
+                object: Some(GitObjectInfo {
+                    id,
+                    rela_path,
+                    mode: unix_mode,
+                    size,
+                    time: self.commit_time,
+                    link_target,
+                }),
+            },
+            tree,
+        })
+    }
+}
+
+impl Iterator for RevisionWalk {
+    type Item = StreamResult<FileEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Pending { entry, tree } = self.stack.pop()?;
+
+        if let Some(tree_id) = tree
+            && entry.depth < self.max_depth
+        {
+            let rela_path = entry
+                .object
+                .as_ref()
+                .map(|object| object.rela_path.clone())
+                .unwrap_or_default();
+            if let Err(e) = self.push_children(tree_id, &entry.path, &rela_path, entry.depth + 1) {
+                return Some(Err(e));
+            }
+        }
+        Some(Ok(entry))
+    }
+}
+
+/// Find the repository containing `path` and the path relative to its work tree.
+///
+/// The path may be a file, or no longer exist in the working tree, so discovery starts from
+/// the nearest existing directory and the remainder is resolved lexically.
+pub(super) fn locate(path: &Path) -> StreamResult<(gix::Repository, PathBuf)> {
+    let mut normalized = PathBuf::new();
+    for component in std::path::absolute(path)?.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+
+    let existing = normalized
+        .ancestors()
+        .find(|p| p.is_dir())
+        .unwrap_or_else(|| Path::new("/"));
+    let remainder = normalized.strip_prefix(existing).unwrap_or(Path::new(""));
+
+    let repo = gix::discover(existing).map_err(git_err)?;
+    let workdir = repo.workdir().ok_or_else(|| {
+        StreamError::Git(format!("{}: bare repositories have no work tree", path.display()))
+    })?;
+    let workdir = std::fs::canonicalize(workdir)?;
+    let resolved = std::fs::canonicalize(existing)?.join(remainder);
+
+    let rela_path = resolved
+        .strip_prefix(&workdir)
+        .map_err(|_| {
+            StreamError::Git(format!("{} is outside the repository", path.display()))
+        })?
+        .to_path_buf();
+
+    Ok((repo, rela_path))
+}
+
+fn commit_time(commit: &gix::Commit<'_>) -> StreamResult<SystemTime> {
+    let seconds = commit.time().map_err(git_err)?.seconds;
+    Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds.max(0) as u64))
+}
+
+pub(super) fn git_err(err: impl std::fmt::Display) -> StreamError {
+    StreamError::Git(err.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use gix::object::tree::EntryKind;
+    use gix::objs::tree::Entry;
+    use gix::ObjectId;
+    use tempfile::tempdir;
+
+    use super::RevisionWalk;
+    use crate::flags::{Display, Flags};
+    use crate::sort::EntryOrder;
+
+    /// Write the tree of `files`, paths with their contents
+    fn tree(repo: &gix::Repository, files: &[(&str, &str)]) -> ObjectId {
+        let mut entries = Vec::new();
+        let mut dirs: BTreeMap<&str, Vec<(&str, &str)>> = BTreeMap::new();
+        for (path, contents) in files {
+            match path.split_once('/') {
+                Some((dir, rest)) => dirs.entry(dir).or_default().push((rest, contents)),
+                None => entries.push(Entry {
+                    mode: EntryKind::Blob.into(),
+                    filename: (*path).into(),
+                    oid: repo.write_blob(contents.as_bytes()).unwrap().detach(),
+                }),
+            }
+        }
+        for (dir, files) in dirs {
+            entries.push(Entry {
+                mode: EntryKind::Tree.into(),
+                filename: dir.into(),
+                oid: tree(repo, &files),
+            });
+        }
+        entries.sort();
+        repo.write_object(gix::objs::Tree { entries }).unwrap().detach()
+    }
+
+    /// Commit `files`, paths with their contents, on top of `parent` at `seconds` past the
+    /// epoch, and detach HEAD there
+    fn commit(
+        repo: &gix::Repository,
+        parent: Option<ObjectId>,
+        files: &[(&str, &str)],
+        seconds: i64,
+    ) -> ObjectId {
+        let signature = gix::actor::Signature {
+            name: "sap".into(),
+            email: "sap@example.com".into(),
+            time: gix::date::Time::new(seconds, 0),
+        };
+        let commit = gix::objs::Commit {
+            tree: tree(repo, files),
+            parents: parent.into_iter().collect(),
+            author: signature.clone(),
+            committer: signature,
+            encoding: None,
+            message: "commit".into(),
+            extra_headers: Vec::new(),
+        };
+        let id = repo.write_object(&commit).unwrap().detach();
+        fs::write(repo.git_dir().join("HEAD"), format!("{id}\n")).unwrap();
+        id
+    }
+
+    /// A repository whose first commit, at 1000 s, holds a.txt and src/lib.rs, and whose
+    /// second, at 2000 s, changes src/lib.rs and adds b.txt
+    fn history(dir: &Path) {
+        let repo = gix::init(dir).unwrap();
+        let first = commit(&repo, None, &[("a.txt", "a"), ("src/lib.rs", "lib")], 1000);
+        let files = [("a.txt", "a"), ("b.txt", "b"), ("src/lib.rs", "lib, changed")];
+        commit(&repo, Some(first), &files, 2000);
+    }
+
+    /// The path from `root` and the date in seconds of each entry at `rev`
+    fn walk(root: &Path, rev: &str, last_modified: bool) -> Vec<(String, u64)> {
+        let flags = Flags::default();
+        let order = EntryOrder::from_flags(&flags);
+        let walk = RevisionWalk::new(root, rev, usize::MAX, &flags.ignore_globs, Display::VisibleOnly, &order)
+            .unwrap();
+        let entries = if last_modified { walk.with_last_modified() } else { walk.collect() };
+        entries
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let time = entry.object.and_then(|object| object.time).unwrap();
+                let seconds = time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or(Duration::ZERO);
+                let path = entry.path.strip_prefix(root).unwrap().to_string_lossy().into_owned();
+                (path, seconds.as_secs())
+            })
+            .collect()
+    }
+
+    fn at(path: &str, seconds: u64) -> (String, u64) {
+        (path.to_string(), seconds)
+    }
+
+    #[test]
+    fn test_earlier_revision() {
+        let tmp = tempdir().unwrap();
+        history(tmp.path());
+
+        // Entries carry the date of the listed commit
+        assert_eq!(
+            walk(tmp.path(), "HEAD~1", false),
+            [at("", 1000), at("a.txt", 1000), at("src", 1000), at("src/lib.rs", 1000)]
+        );
+        assert_eq!(
+            walk(tmp.path(), "HEAD", false),
+            [at("", 2000), at("a.txt", 2000), at("b.txt", 2000), at("src", 2000), at("src/lib.rs", 2000)]
+        );
+        // A directory that does not exist in the work tree
+        assert_eq!(walk(&tmp.path().join("src"), "HEAD~1", false), [at("", 1000), at("lib.rs", 1000)]);
+    }
+
+    #[test]
+    fn test_last_modified() {
+        let tmp = tempdir().unwrap();
+        history(tmp.path());
+
+        assert_eq!(
+            walk(tmp.path(), "HEAD", true),
+            [at("", 2000), at("a.txt", 1000), at("b.txt", 2000), at("src", 2000), at("src/lib.rs", 2000)]
+        );
+        assert_eq!(
+            walk(tmp.path(), "HEAD~1", true),
+            [at("", 1000), at("a.txt", 1000), at("src", 1000), at("src/lib.rs", 1000)]
+        );
+    }
+}
@@ -0,0 +1,346 @@
+//! Scan limits: stops the walk after a number of entries or a wall-clock time
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use futures::channel::mpsc;
+use futures::{SinkExt, Stream, StreamExt};
+
+use crate::flags::ScanLimits;
+use crate::stream::{FileEntry, StreamError, StreamResult};
+
+/// How many entries a source driven on its own thread reads ahead of the output
+const READ_AHEAD: usize = 256;
+
+/// The limit a walk hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TruncateReason {
+    MaxEntries(usize),
+    Timeout(Duration),
+}
+
+/// Where a walk was cut: the directories whose children were not all listed
+#[derive(Debug, Clone)]
+pub struct Truncation {
+    pub reason: TruncateReason,
+    pub entries: usize,
+    pub directories: Vec<PathBuf>,
+}
+
+impl std::fmt::Display for Truncation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "listing truncated after {} entries: ", self.entries)?;
+        match self.reason {
+            TruncateReason::MaxEntries(max) => write!(f, "--max-entries {max} reached"),
+            TruncateReason::Timeout(timeout) => {
+                write!(f, "--timeout {}s reached", timeout.as_secs_f64())
+            }
+        }
+    }
+}
+
+/// A directory whose children are still being walked
+struct OpenDir {
+    depth: usize,
+    path: PathBuf,
+    last_sibling: bool,
+}
+
+/// Passes entries through until a [ScanLimits] is hit, then drops the walk, which cancels it,
+/// and yields a `StreamError::Truncated` naming the directories left incomplete.
+///
+/// With a timeout, the source is driven on a thread of its own, as the walks block while
+/// reading directories: a walk stalled on a slow directory, or a source that stops yielding,
+/// is cut when a timer wakes the task at the deadline.
+pub struct ScanLimit {
+    source: Option<Pin<Box<dyn Stream<Item = StreamResult<FileEntry>> + Send>>>,
+    limits: ScanLimits,
+    /// Directories at this depth are listed but not read, so they are never incomplete
+    max_depth: usize,
+    deadline: Option<Instant>,
+    /// Wakes the task at the deadline, set the first time the source has nothing to yield
+    timer: Option<Pin<Box<tokio::time::Sleep>>>,
+    entries: usize,
+    open: Vec<OpenDir>,
+    truncated: Arc<AtomicBool>,
+}
+
+impl ScanLimit {
+    pub fn new(
+        source: impl Stream<Item = StreamResult<FileEntry>> + Send + 'static,
+        limits: ScanLimits,
+        max_depth: usize,
+    ) -> Self {
+        let source: Pin<Box<dyn Stream<Item = StreamResult<FileEntry>> + Send>> =
+            match limits.timeout {
+                Some(_) => Box::pin(detach(source)),
+                None => Box::pin(source),
+            };
+        Self {
+            source: Some(source),
+            limits,
+            max_depth,
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            timer: None,
+            entries: 0,
+            open: Vec::new(),
+            truncated: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Set once the walk was cut, to be checked after the stream was consumed
+    pub fn truncated(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.truncated)
+    }
+
+    fn limit_reached(&self) -> Option<TruncateReason> {
+        if let Some(max) = self.limits.max_entries
+            && self.entries >= max
+        {
+            return Some(TruncateReason::MaxEntries(max));
+        }
+        match (self.deadline, self.limits.timeout) {
+            (Some(deadline), Some(timeout)) if Instant::now() >= deadline => {
+                Some(TruncateReason::Timeout(timeout))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the deadline passed while the source had nothing to yield; otherwise the timer
+    /// wakes the task once it does
+    fn deadline_passed(&mut self, cx: &mut Context<'_>) -> bool {
+        let Some(deadline) = self.deadline else {
+            return false;
+        };
+        let timer = self
+            .timer
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep_until(deadline.into())));
+        timer.as_mut().poll(cx).is_ready()
+    }
+
+    /// Drop the walk, which cancels it, and describe where it was cut
+    fn cut(&mut self, reason: TruncateReason) -> StreamError {
+        self.source = None;
+        self.truncated.store(true, Ordering::Relaxed);
+        StreamError::Truncated(Truncation {
+            reason,
+            entries: self.entries,
+            directories: self.incomplete(),
+        })
+    }
+
+    /// The open directories that miss some of their own children. One whose last child was
+    /// listed is only incomplete below that child.
+    fn incomplete(&mut self) -> Vec<PathBuf> {
+        let open = std::mem::take(&mut self.open);
+        let children_listed: Vec<bool> = open
+            .iter()
+            .skip(1)
+            .map(|dir| dir.last_sibling)
+            .chain([false])
+            .collect();
+        open.into_iter()
+            .zip(children_listed)
+            .filter(|(_, listed)| !listed)
+            .map(|(dir, _)| dir.path)
+            .collect()
+    }
+
+    /// Keep the stack of incomplete directories up to date; the sources yield depth-first and
+    /// flag each directory's last child
+    fn track(&mut self, entry: &FileEntry) {
+        while self.open.last().is_some_and(|dir| dir.depth >= entry.depth) {
+            self.open.pop();
+        }
+
+        if entry.has_children() && entry.depth < self.max_depth {
+            self.open.push(OpenDir {
+                depth: entry.depth,
+                path: entry.path.clone(),
+                last_sibling: entry.last_sibling,
+            });
+        } else if entry.last_sibling {
+            // The parent is complete, and so is every ancestor it was the last child of
+            while let Some(dir) = self.open.pop() {
+                if !dir.last_sibling {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Stream for ScanLimit {
+    type Item = StreamResult<FileEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let Some(source) = self.source.as_mut() else {
+            return Poll::Ready(None);
+        };
+        let polled = source.as_mut().poll_next(cx);
+
+        match polled {
+            // The marker of a directory's ignored children is not a scanned entry
+            Poll::Ready(Some(Ok(entry))) if entry.ignored.is_some() => {
+                self.track(&entry);
+                Poll::Ready(Some(Ok(entry)))
+            }
+            Poll::Ready(Some(Ok(entry))) => {
+                if let Some(reason) = self.limit_reached() {
+                    return Poll::Ready(Some(Err(self.cut(reason))));
+                }
+                self.entries += 1;
+                self.track(&entry);
+                Poll::Ready(Some(Ok(entry)))
+            }
+            Poll::Ready(None) => {
+                self.source = None;
+                Poll::Ready(None)
+            }
+            Poll::Pending => {
+                let timeout = self.limits.timeout;
+                match timeout {
+                    Some(timeout) if self.deadline_passed(cx) => {
+                        Poll::Ready(Some(Err(self.cut(TruncateReason::Timeout(timeout)))))
+                    }
+                    _ => Poll::Pending,
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+/// Drive `source` on a thread of its own, the entries passed on through a channel. Dropping
+/// the receiving stream ends the thread, and drops the source, at the next entry it reads.
+fn detach(
+    source: impl Stream<Item = StreamResult<FileEntry>> + Send + 'static,
+) -> impl Stream<Item = StreamResult<FileEntry>> + Send {
+    let (mut sender, receiver) = mpsc::channel(READ_AHEAD);
+    std::thread::spawn(move || {
+        futures::executor::block_on(async move {
+            let mut source = std::pin::pin!(source);
+            while let Some(item) = source.next().await {
+                if sender.send(item).await.is_err() {
+                    break;
+                }
+            }
+        });
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::task::Poll;
+    use std::time::Duration;
+
+    use futures::{stream, Stream, StreamExt};
+    use tempfile::tempdir;
+
+    use super::{ScanLimit, TruncateReason, Truncation};
+    use crate::flags::ScanLimits;
+    use crate::stream::{test_walk, FileEntry, StreamError, StreamResult};
+
+    /// The names listed, and the truncation ending the stream
+    fn split(items: Vec<StreamResult<FileEntry>>) -> (Vec<String>, Option<Truncation>) {
+        let mut names = Vec::new();
+        let mut truncation = None;
+        for item in items {
+            match item {
+                Ok(entry) => names.push(entry.name),
+                Err(StreamError::Truncated(cut)) => truncation = Some(cut),
+                Err(err) => panic!("unexpected error: {err}"),
+            }
+        }
+        (names, truncation)
+    }
+
+    fn fixture() -> (tempfile::TempDir, String) {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("a")).unwrap();
+        fs::write(dir.path().join("a/x"), "").unwrap();
+        fs::write(dir.path().join("a/y"), "").unwrap();
+        fs::write(dir.path().join("b.txt"), "").unwrap();
+        let root = dir.path().file_name().unwrap().to_string_lossy().to_string();
+        (dir, root)
+    }
+
+    async fn cut_at_timeout(
+        root: &Path,
+        stall: impl Stream<Item = StreamResult<FileEntry>> + Send + 'static,
+    ) -> (Vec<String>, Option<Truncation>) {
+        let limits = ScanLimits {
+            max_entries: None,
+            timeout: Some(Duration::from_millis(100)),
+        };
+        let limit = ScanLimit::new(test_walk(root).take(2).chain(stall), limits, usize::MAX);
+        let truncated = limit.truncated();
+        let items = tokio::time::timeout(Duration::from_secs(5), limit.collect::<Vec<_>>())
+            .await
+            .expect("the stream ends at the deadline");
+        assert!(truncated.load(std::sync::atomic::Ordering::Relaxed));
+        split(items)
+    }
+
+    #[tokio::test]
+    async fn test_stalled_source_is_cut_at_the_deadline() {
+        let (dir, root) = fixture();
+        let (names, truncation) = cut_at_timeout(dir.path(), stream::pending()).await;
+        assert_eq!(vec![root, "a".to_string()], names);
+        let truncation = truncation.unwrap();
+        assert_eq!(TruncateReason::Timeout(Duration::from_millis(100)), truncation.reason);
+        assert_eq!(2, truncation.entries);
+        assert_eq!(vec![dir.path().to_path_buf(), dir.path().join("a")], truncation.directories);
+    }
+
+    #[tokio::test]
+    async fn test_blocked_source_is_cut_at_the_deadline() {
+        let (dir, root) = fixture();
+        let blocked = stream::poll_fn(|_| {
+            std::thread::sleep(Duration::from_secs(30));
+            Poll::Ready(None)
+        });
+        let (names, truncation) = cut_at_timeout(dir.path(), blocked).await;
+        assert_eq!(vec![root, "a".to_string()], names);
+        assert_eq!(2, truncation.unwrap().entries);
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let (dir, root) = fixture();
+        let limits = ScanLimits {
+            max_entries: Some(3),
+            timeout: None,
+        };
+        let limit = ScanLimit::new(test_walk(dir.path()), limits, usize::MAX);
+        let (names, truncation) = split(futures::executor::block_on(limit.collect::<Vec<_>>()));
+        assert_eq!(vec![root, "a".to_string(), "x".to_string()], names);
+        let truncation = truncation.unwrap();
+        assert_eq!(TruncateReason::MaxEntries(3), truncation.reason);
+        let expected: Vec<PathBuf> = vec![dir.path().to_path_buf(), dir.path().join("a")];
+        assert_eq!(expected, truncation.directories);
+    }
+
+    #[test]
+    fn test_whole_walk_below_the_limits() {
+        let (dir, _) = fixture();
+        let limits = ScanLimits {
+            max_entries: Some(5),
+            timeout: None,
+        };
+        let limit = ScanLimit::new(test_walk(dir.path()), limits, usize::MAX);
+        let truncated = limit.truncated();
+        let (names, truncation) = split(futures::executor::block_on(limit.collect::<Vec<_>>()));
+        assert_eq!(5, names.len());
+        assert!(truncation.is_none());
+        assert!(!truncated.load(std::sync::atomic::Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,301 @@
+//! Size aggregator: totals directory sizes bottom-up from the entries already being walked
+
+use std::collections::{HashSet, VecDeque};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures::Stream;
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+use crate::flags::SizeMode;
+use crate::meta::{FileType, Size};
+use crate::stream::{FileEntry, StreamError, StreamResult};
+
+/// A directory whose subtree is still being walked
+struct OpenDir {
+    depth: usize,
+    /// Position of the directory in the output, `None` when it is only walked for its size
+    seq: Option<usize>,
+    total: u64,
+}
+
+/// Sets `FileEntry::size` to the size in the chosen [SizeMode] and, with `total`, directories
+/// to the size of everything below them.
+///
+/// A directory's total is known once the walk leaves it, so with `total` it and every entry
+/// after it are held back until then. An input directory is the first entry of its walk:
+/// nothing of it is passed on before its whole subtree was walked and its total is known, and
+/// the next input starts it over. Without `total`, entries are passed on as they come. Entries
+/// deeper than `display_depth` are only walked for their sizes, and not held. Hard links are
+/// counted once.
+pub struct SizeAggregator {
+    source: Pin<Box<dyn Stream<Item = StreamResult<FileEntry>> + Send>>,
+    mode: SizeMode,
+    total: bool,
+    display_depth: usize,
+    queue: VecDeque<FileEntry>,
+    /// Position of the front of `queue` in the output
+    front_seq: usize,
+    open: Vec<OpenDir>,
+    /// (dev, inode) of the files with several links that were counted already
+    linked: HashSet<(u64, u64)>,
+    /// A cut by the scan limits, passed on after the entries held back before it
+    truncation: Option<StreamError>,
+    is_complete: bool,
+}
+
+impl SizeAggregator {
+    pub fn new(
+        source: impl Stream<Item = StreamResult<FileEntry>> + Send + 'static,
+        mode: SizeMode,
+        total: bool,
+        display_depth: usize,
+    ) -> Self {
+        Self {
+            source: Box::pin(source),
+            mode,
+            total,
+            display_depth,
+            queue: VecDeque::new(),
+            front_seq: 0,
+            open: Vec::new(),
+            linked: HashSet::new(),
+            truncation: None,
+            is_complete: false,
+        }
+    }
+
+    /// Size of the entry itself, without its children
+    fn own_size(&self, entry: &FileEntry) -> u64 {
+        match (&entry.metadata, &entry.cached, self.mode) {
+            #[cfg(unix)]
+            (Some(metadata), _, SizeMode::Disk) => metadata.blocks() * 512,
+            (Some(metadata), _, _) => metadata.len(),
+            (None, Some(stat), SizeMode::Disk) => stat.blocks * 512,
+            (None, _, _) => entry.size_bytes().unwrap_or(0),
+        }
+    }
+
+    /// Whether the entry is another link to a file that was counted already
+    #[cfg(unix)]
+    fn is_counted_link(&mut self, entry: &FileEntry) -> bool {
+        match (&entry.metadata, &entry.cached) {
+            (Some(metadata), _) if metadata.nlink() > 1 && !metadata.is_dir() => {
+                !self.linked.insert((metadata.dev(), metadata.ino()))
+            }
+            (None, Some(stat)) if stat.nlink > 1 && !entry.file_type.is_dirlike() => {
+                !self.linked.insert((stat.dev, stat.ino))
+            }
+            _ => false,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn is_counted_link(&mut self, _entry: &FileEntry) -> bool {
+        false
+    }
+
+    /// Total the directories at `depth` or deeper, whose subtrees are complete
+    fn close_dirs(&mut self, depth: usize) {
+        while self.open.last().is_some_and(|dir| dir.depth >= depth) {
+            let Some(dir) = self.open.pop() else {
+                break;
+            };
+            if let Some(seq) = dir.seq
+                && let Some(entry) = self.queue.get_mut(seq - self.front_seq)
+            {
+                entry.size = Some(Size::new(dir.total));
+            }
+            if let Some(parent) = self.open.last_mut() {
+                parent.total = parent.total.saturating_add(dir.total);
+            }
+        }
+    }
+
+    fn process_entry(&mut self, mut entry: FileEntry) {
+        self.close_dirs(entry.depth);
+
+        let own = self.own_size(&entry);
+        let counted = if self.is_counted_link(&entry) { 0 } else { own };
+        entry.size = Some(Size::new(own));
+
+        let displayed = entry.depth <= self.display_depth;
+        let seq = displayed.then(|| self.front_seq + self.queue.len());
+
+        if self.total && matches!(entry.file_type, FileType::Directory { .. }) {
+            self.open.push(OpenDir {
+                depth: entry.depth,
+                seq,
+                total: counted,
+            });
+        } else if let Some(parent) = self.open.last_mut() {
+            parent.total = parent.total.saturating_add(counted);
+        }
+
+        if displayed {
+            self.queue.push_back(entry);
+        }
+    }
+
+    /// The next entry whose size is final
+    fn release(&mut self) -> Option<FileEntry> {
+        let held = self.open.iter().find_map(|dir| dir.seq);
+        if self.queue.is_empty() || held.is_some_and(|seq| seq <= self.front_seq) {
+            return None;
+        }
+        self.front_seq += 1;
+        self.queue.pop_front()
+    }
+}
+
+impl Stream for SizeAggregator {
+    type Item = StreamResult<FileEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(entry) = self.release() {
+                return Poll::Ready(Some(Ok(entry)));
+            }
+            if self.is_complete {
+                return Poll::Ready(self.truncation.take().map(Err));
+            }
+
+            match self.source.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(entry))) => self.process_entry(entry),
+                Poll::Ready(Some(Err(e @ StreamError::Truncated(_)))) => self.truncation = Some(e),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => {
+                    self.is_complete = true;
+                    self.close_dirs(0);
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+    use std::task::{Context, Poll};
+
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+    use tempfile::tempdir;
+
+    use super::SizeAggregator;
+    use crate::flags::SizeMode;
+    use crate::stream::{test_walk, FileEntry, StreamResult};
+
+    fn walk(root: &Path) -> Vec<FileEntry> {
+        futures::executor::block_on(test_walk(root).collect::<Vec<_>>())
+            .into_iter()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    /// The entries the aggregator passes on without waiting for more input
+    fn ready(aggregator: &mut SizeAggregator) -> Vec<String> {
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        let mut names = Vec::new();
+        while let Poll::Ready(Some(entry)) = aggregator.poll_next_unpin(&mut cx) {
+            names.push(entry.unwrap().name);
+        }
+        names
+    }
+
+    fn fixture(root: &Path) {
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a"), [0; 10]).unwrap();
+        fs::write(root.join("sub/b"), [0; 20]).unwrap();
+    }
+
+    #[test]
+    fn test_totals() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fixture(&root);
+        let len = |path: &Path| fs::metadata(path).unwrap().len();
+
+        let source = futures::stream::iter(walk(&root).into_iter().map(Ok));
+        let aggregator = SizeAggregator::new(source, SizeMode::Apparent, true, usize::MAX);
+        let sizes: Vec<(String, u64)> = futures::executor::block_on(aggregator.collect::<Vec<_>>())
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                (entry.name.clone(), entry.size_bytes().unwrap())
+            })
+            .collect();
+
+        let sub = len(&root.join("sub")) + 20;
+        assert_eq!(
+            sizes,
+            [
+                ("root".to_string(), len(&root) + 10 + sub),
+                ("a".to_string(), 10),
+                ("sub".to_string(), sub),
+                ("b".to_string(), 20),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_input_directories_are_held_until_walked() {
+        let dir = tempdir().unwrap();
+        let (first, second) = (dir.path().join("first"), dir.path().join("second"));
+        fixture(&first);
+        fixture(&second);
+
+        let (sender, receiver) = mpsc::unbounded::<StreamResult<FileEntry>>();
+        let mut aggregator = SizeAggregator::new(receiver, SizeMode::Apparent, true, usize::MAX);
+        for entry in walk(&first) {
+            sender.unbounded_send(Ok(entry)).unwrap();
+            assert_eq!(ready(&mut aggregator), Vec::<String>::new());
+        }
+
+        // The next input closes the first one, which is passed on whole
+        let mut second = walk(&second).into_iter();
+        sender.unbounded_send(Ok(second.next().unwrap())).unwrap();
+        assert_eq!(ready(&mut aggregator), ["first", "a", "sub", "b"]);
+        for entry in second {
+            sender.unbounded_send(Ok(entry)).unwrap();
+        }
+        assert_eq!(ready(&mut aggregator), Vec::<String>::new());
+
+        drop(sender);
+        assert_eq!(ready(&mut aggregator), ["second", "a", "sub", "b"]);
+    }
+
+    #[test]
+    fn test_entries_pass_without_totals() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fixture(&root);
+
+        let (sender, receiver) = mpsc::unbounded::<StreamResult<FileEntry>>();
+        let mut aggregator = SizeAggregator::new(receiver, SizeMode::Apparent, false, usize::MAX);
+        for entry in walk(&root) {
+            let name = entry.name.clone();
+            sender.unbounded_send(Ok(entry)).unwrap();
+            assert_eq!(ready(&mut aggregator), [name]);
+        }
+    }
+
+    #[test]
+    fn test_entries_below_display_depth_are_only_counted() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fixture(&root);
+
+        let source = futures::stream::iter(walk(&root).into_iter().map(Ok));
+        let aggregator = SizeAggregator::new(source, SizeMode::Apparent, true, 1);
+        let entries = futures::executor::block_on(aggregator.collect::<Vec<_>>());
+        let names: Vec<String> = entries.iter().map(|entry| entry.as_ref().unwrap().name.clone()).collect();
+        assert_eq!(names, ["root", "a", "sub"]);
+
+        let sub = entries[2].as_ref().unwrap().size_bytes().unwrap();
+        assert_eq!(sub, fs::metadata(root.join("sub")).unwrap().len() + 20);
+    }
+}
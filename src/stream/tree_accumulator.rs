@@ -1,131 +1,226 @@
-//! Tree accumulator (replaced by in-memory tree building in Core)
-#![allow(dead_code)]
+//! Tree accumulator: turns the walk into tree rows as entries arrive
 
-use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use futures::Stream;
 
-use crate::stream::{FileEntry, StreamResult};
+use crate::stream::{
+    Accumulator, AccumulatorAction, FileEntry, OutputEvent, StreamError, StreamResult, Truncation,
+};
 
-/// Tree accumulator that buffers entries and emits formatted tree output
+const EDGE: &str = "\u{251c}\u{2500}\u{2500} "; // "├── "
+const LINE: &str = "\u{2502}   "; // "│   "
+const CORNER: &str = "\u{2514}\u{2500}\u{2500} "; // "└── "
+const BLANK: &str = "    ";
+
+/// Emits a `TreeNode` for every entry as soon as it is walked.
+///
+/// The sources yield each directory's children in display order and flag the last one, so the
+/// tree edges of an entry only depend on its ancestors, which were all emitted before it.
 pub struct TreeAccumulator {
     source: Pin<Box<dyn Stream<Item = StreamResult<FileEntry>> + Send>>,
-    buffer: HashMap<PathBuf, Vec<FileEntry>>,
-    pending_output: Vec<String>,
+    /// Depth, `last_sibling` and path of the emitted ancestors of the next entry
+    ancestors: Vec<(usize, bool, PathBuf)>,
+    pending: VecDeque<OutputEvent>,
     is_complete: bool,
-    root: PathBuf,
 }
 
 impl TreeAccumulator {
-    pub fn new(
-        source: impl Stream<Item = StreamResult<FileEntry>> + Send + 'static,
-        root: PathBuf,
-    ) -> Self {
+    pub fn new(source: impl Stream<Item = StreamResult<FileEntry>> + Send + 'static) -> Self {
         Self {
             source: Box::pin(source),
-            buffer: HashMap::new(),
-            pending_output: Vec::new(),
+            ancestors: Vec::new(),
+            pending: VecDeque::new(),
             is_complete: false,
-            root,
         }
     }
-    
-    /// Recursively render tree from buffered entries
-    fn render_tree(&self, path: &PathBuf, depth: usize, _is_last_sibling: bool, prefix: &str) -> Vec<String> {
-        let mut lines = Vec::new();
-        
-        if let Some(entries) = self.buffer.get(path) {
-            let mut sorted = entries.clone();
-            // Sort: directories first, then alphabetically (like src/sort.rs)
-            sorted.sort_by(|a, b| {
-                let a_is_dir = matches!(a.file_type, crate::meta::FileType::Directory { .. });
-                let b_is_dir = matches!(b.file_type, crate::meta::FileType::Directory { .. });
-                match (a_is_dir, b_is_dir) {
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                    _ => a.name.cmp(&b.name),
-                }
-            });
-            
-            for (i, entry) in sorted.iter().enumerate() {
-                let is_last = i == sorted.len() - 1;
-                
-                // Build tree characters
-                let branch = if is_last { "└── " } else { "├── " };
-                let icon = if matches!(entry.file_type, crate::meta::FileType::Directory { .. }) {
-                    "📁"
-                } else {
-                    "📄"
-                };
-                
-                lines.push(format!("{}{}{} {}", prefix, branch, icon, entry.name));
-                
-                // Recurse into directories
-                if matches!(entry.file_type, crate::meta::FileType::Directory { .. }) {
-                    let new_prefix = if is_last {
-                        format!("{}    ", prefix)  // Spaces for last branch
-                    } else {
-                        format!("{}│   ", prefix)  // Vertical bar for continuing branch
-                    };
-                    lines.extend(self.render_tree(&entry.path, depth + 1, is_last, &new_prefix));
-                }
-            }
+
+    /// The edges inherited from the ancestors: a line below each one with siblings left
+    fn prefix(&self) -> String {
+        Self::edges(&self.ancestors)
+    }
+
+    fn edges(ancestors: &[(usize, bool, PathBuf)]) -> String {
+        let mut prefix = String::new();
+        // The roots are drawn without edges
+        for (_, last_sibling, _) in ancestors.iter().skip(1) {
+            prefix.push_str(if *last_sibling { BLANK } else { LINE });
         }
-        
-        lines
+        prefix
+    }
+
+    /// A last row for each cut directory, innermost first so the rows close the tree
+    fn truncated(&self, truncation: &Truncation) -> Vec<OutputEvent> {
+        (0..self.ancestors.len())
+            .rev()
+            .filter(|&i| truncation.directories.contains(&self.ancestors[i].2))
+            .map(|i| OutputEvent::Truncated {
+                prefix: Self::edges(&self.ancestors[..=i]) + CORNER,
+            })
+            .collect()
+    }
+}
+
+impl Accumulator for TreeAccumulator {
+    fn process_entry(&mut self, entry: FileEntry) -> AccumulatorAction {
+        // Leave the directories this entry is not part of; an entry whose parent was hidden
+        // (like a dot-directory root) becomes a root itself
+        while self
+            .ancestors
+            .last()
+            .is_some_and(|(depth, _, _)| *depth >= entry.depth)
+        {
+            self.ancestors.pop();
+        }
+
+        let prefix = if self.ancestors.is_empty() {
+            String::new()
+        } else {
+            let edge = if entry.last_sibling { CORNER } else { EDGE };
+            self.prefix() + edge
+        };
+
+        if let Some(ignored) = entry.ignored {
+            return AccumulatorAction::Emit(vec![OutputEvent::Ignored { ignored, prefix }]);
+        }
+
+        self.ancestors
+            .push((entry.depth, entry.last_sibling, entry.path.clone()));
+
+        AccumulatorAction::Emit(vec![OutputEvent::TreeNode {
+            entry,
+            prefix,
+        }])
     }
 }
 
 impl Stream for TreeAccumulator {
-    type Item = StreamResult<String>;
-    
+    type Item = StreamResult<OutputEvent>;
+
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        // Drain pending output first
-        if let Some(line) = self.pending_output.pop() {
-            return Poll::Ready(Some(Ok(line)));
-        }
-        
-        // Buffer entries from source until exhausted
         loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            if self.is_complete {
+                return Poll::Ready(None);
+            }
+
             match self.source.as_mut().poll_next(cx) {
                 Poll::Ready(Some(Ok(entry))) => {
-                    // Buffer by parent directory
-                    let parent = entry.path.parent()
-                        .unwrap_or(&self.root)
-                        .to_path_buf();
-                    self.buffer.entry(parent).or_default().push(entry);
-                }
-                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
-                Poll::Ready(None) => {
-                    // Source exhausted - render tree once
-                    if !self.is_complete {
-                        self.is_complete = true;
-                        
-                        // Find the actual root path in the buffer (usually the input path like "./src")
-                        // The buffer contains entries organized by their parent paths
-                        let actual_root = self.buffer.keys()
-                            .filter(|p| p.parent() == Some(self.root.as_path()) || **p == self.root)
-                            .min()
-                            .cloned()
-                            .unwrap_or_else(|| self.root.clone());
-                        
-                        self.pending_output = self.render_tree(&actual_root, 0, true, "");
-                        self.pending_output.reverse();  // Pop from end for efficiency
+                    if let AccumulatorAction::Emit(events) = self.process_entry(entry) {
+                        self.pending.extend(events);
                     }
-                    
-                    // Emit pending or signal completion
-                    return match self.pending_output.pop() {
-                        Some(line) => Poll::Ready(Some(Ok(line))),
-                        None => Poll::Ready(None),
-                    };
                 }
-                Poll::Pending => {
-                    // No more entries available yet
-                    return Poll::Pending;
+                Poll::Ready(Some(Err(StreamError::Truncated(truncation)))) => {
+                    let events = self.truncated(&truncation);
+                    self.pending.extend(events);
+                    return Poll::Ready(Some(Err(StreamError::Truncated(truncation))));
                 }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => self.is_complete = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use futures::StreamExt;
+    use tempfile::tempdir;
+
+    use super::TreeAccumulator;
+    use crate::flags::IgnoredChildren;
+    use crate::stream::{
+        test_walk, FileEntry, OutputEvent, StreamError, StreamResult, TruncateReason, Truncation,
+    };
+
+    /// The rows of the tree, each as its edges followed by its name or note
+    fn rows(source: impl futures::Stream<Item = StreamResult<FileEntry>> + Send + 'static) -> Vec<String> {
+        let events = futures::executor::block_on(TreeAccumulator::new(source).collect::<Vec<_>>());
+        events
+            .into_iter()
+            .filter_map(|event| match event {
+                Ok(OutputEvent::TreeNode { entry, prefix }) => Some(prefix + &entry.name),
+                Ok(OutputEvent::Ignored { ignored, prefix }) => Some(format!("{}+{}", prefix, ignored.count)),
+                Ok(OutputEvent::Truncated { prefix }) => Some(prefix + "truncated"),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn entries(root: &Path) -> Vec<FileEntry> {
+        let entries = futures::executor::block_on(test_walk(root).collect::<Vec<_>>());
+        entries.into_iter().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn test_rows_follow_the_walk_with_last_siblings_closed() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/c"), "").unwrap();
+        fs::write(root.join("a/d"), "").unwrap();
+        fs::write(root.join("e"), "").unwrap();
+
+        assert_eq!(
+            rows(test_walk(&root)),
+            [
+                "root",
+                "\u{251c}\u{2500}\u{2500} a",
+                "\u{2502}   \u{251c}\u{2500}\u{2500} b",
+                "\u{2502}   \u{2502}   \u{2514}\u{2500}\u{2500} c",
+                "\u{2502}   \u{2514}\u{2500}\u{2500} d",
+                "\u{2514}\u{2500}\u{2500} e",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ignored_and_truncated_rows_close_their_directory() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::write(root.join("a/b"), "").unwrap();
+        fs::write(root.join("c"), "").unwrap();
+
+        // `a/b` is followed by the row of the children hidden in `a`, and the walk is cut in
+        // the root after `c`
+        let mut source: Vec<StreamResult<FileEntry>> = Vec::new();
+        for mut entry in entries(&root) {
+            let hidden_in = (entry.name == "b").then(|| entry.path.parent().unwrap().to_path_buf());
+            entry.last_sibling &= hidden_in.is_none();
+            source.push(Ok(entry));
+            if let Some(path) = hidden_in {
+                let ignored = IgnoredChildren {
+                    count: 3,
+                    ..IgnoredChildren::default()
+                };
+                source.push(Ok(FileEntry::ignored(&path, 2, ignored)));
             }
         }
+        source.push(Err(StreamError::Truncated(Truncation {
+            reason: TruncateReason::MaxEntries(4),
+            entries: 4,
+            directories: vec![root.clone()],
+        })));
+
+        assert_eq!(
+            rows(futures::stream::iter(source)),
+            [
+                "root",
+                "\u{251c}\u{2500}\u{2500} a",
+                "\u{2502}   \u{251c}\u{2500}\u{2500} b",
+                "\u{2502}   \u{2514}\u{2500}\u{2500} +3",
+                "\u{2514}\u{2500}\u{2500} c",
+                "\u{2514}\u{2500}\u{2500} truncated",
+            ]
+        );
     }
 }
@@ -6,7 +6,7 @@ use serde::{de::IntoDeserializer, Deserialize};
 use std::fmt;
 
 // Custom color deserialize
-fn deserialize_color<'de, D>(deserializer: D) -> Result<Color, D::Error>
+pub(super) fn deserialize_color<'de, D>(deserializer: D) -> Result<Color, D::Error>
 where
     D: serde::de::Deserializer<'de>,
 {
@@ -96,7 +96,10 @@ pub struct ColorTheme {
     #[serde(deserialize_with = "deserialize_color")]
     pub tree_edge: Color,
     pub links: Links,
+    pub filesystem: Filesystem,
+    pub content: Content,
     pub git_status: GitStatus,
+    pub git_repo: super::git::GitRepoColors,
 
     #[serde(skip)]
     pub file_type: FileType,
@@ -252,6 +255,40 @@ pub struct Links {
     pub invalid: Color,
 }
 
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct Filesystem {
+    #[serde(deserialize_with = "deserialize_color")]
+    pub valid: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub invalid: Color,
+}
+
+/// The blocks read from the contents of files
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct Content {
+    /// The `--contains` match counts
+    #[serde(deserialize_with = "deserialize_color")]
+    pub matches: Color,
+    /// The first digits of the `hash` block
+    #[serde(deserialize_with = "deserialize_color")]
+    pub hash: Color,
+    /// The sniffed types of the `mime` block
+    #[serde(deserialize_with = "deserialize_color")]
+    pub mime: Color,
+    /// The dimensions and durations of the `media` block
+    #[serde(deserialize_with = "deserialize_color")]
+    pub media: Color,
+    /// The machine of the `binary` block
+    #[serde(deserialize_with = "deserialize_color")]
+    pub binary: Color,
+}
+
 #[derive(Debug, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
@@ -428,6 +465,27 @@ impl Default for Links {
     }
 }
 
+impl Default for Filesystem {
+    fn default() -> Self {
+        Filesystem {
+            valid: Color::Rgb { r: 179, g: 172, b: 255 }, // CYRUP hint #b3acff
+            invalid: Color::AnsiValue(245),                // Grey
+        }
+    }
+}
+
+impl Default for Content {
+    fn default() -> Self {
+        Content {
+            matches: Color::AnsiValue(13), // Pink
+            hash: Color::AnsiValue(13),    // Pink
+            mime: Color::Cyan,
+            media: Color::Cyan,
+            binary: Color::Cyan,
+        }
+    }
+}
+
 impl Default for GitStatus {
     fn default() -> Self {
         GitStatus {
@@ -510,8 +568,11 @@ impl ColorTheme {
             size: Size::cyrup_theme(),
             inode: INode::default(),
             links: Links::default(),
+            filesystem: Filesystem::default(),
+            content: Content::default(),
             tree_edge: Color::Rgb { r: 127, g: 127, b: 127 }, // CYRUP muted grey #7f7f7f
             git_status: Default::default(),
+            git_repo: Default::default(),
             render_rules: Self::default_render_rules(),
         }
     }
@@ -527,8 +588,11 @@ impl ColorTheme {
             size: Size::cyrup_theme(),
             inode: INode::default(),
             links: Links::default(),
+            filesystem: Filesystem::default(),
+            content: Content::default(),
             tree_edge: Color::Rgb { r: 100, g: 100, b: 100 }, // Darker grey for light bg
             git_status: Default::default(),
+            git_repo: Default::default(),
             render_rules: Self::default_render_rules(),
         }
     }
@@ -1,5 +1,8 @@
+use crossterm::style::Color;
 use serde::Deserialize;
 
+use super::color::deserialize_color;
+
 #[derive(Debug, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
@@ -15,6 +18,10 @@ pub struct GitThemeSymbols {
     pub ignored: String,
     pub typechange: String,
     pub conflicted: String,
+    pub ahead: String,
+    pub behind: String,
+    pub stash: String,
+    pub detached: String,
 }
 
 impl Default for GitThemeSymbols {
@@ -30,6 +37,49 @@ impl Default for GitThemeSymbols {
             ignored: "I".into(),
             typechange: "T".into(),
             conflicted: "C".into(),
+            ahead: "\u{21e1}".into(),    // "⇡"
+            behind: "\u{21e3}".into(),   // "⇣"
+            stash: "\u{2261}".into(),    // "≡"
+            detached: "\u{27a6}".into(), // "➦"
+        }
+    }
+}
+
+/// Colors of the repository header shown by `--repo-info`
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct GitRepoColors {
+    #[serde(deserialize_with = "deserialize_color")]
+    pub root: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub branch: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub detached: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub upstream: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub ahead: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub behind: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub stash: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub operation: Color,
+}
+
+impl Default for GitRepoColors {
+    fn default() -> Self {
+        GitRepoColors {
+            root: Color::AnsiValue(245), // Grey
+            branch: Color::DarkGreen,
+            detached: Color::DarkYellow,
+            upstream: Color::DarkCyan,
+            ahead: Color::DarkGreen,
+            behind: Color::DarkRed,
+            stash: Color::DarkMagenta,
+            operation: Color::DarkRed,
         }
     }
 }
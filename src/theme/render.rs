@@ -48,6 +48,8 @@ pub struct RenderRule {
 pub struct RuleMatchers {
     pub file_types: Option<Vec<FileType>>,
     pub extensions: Option<Vec<String>>,
+    /// Sniffed MIME types, like `text/x-python`, or whole families, like `image/*`
+    pub mime_types: Option<Vec<String>>,
     pub git_statuses: Option<Vec<GitStatus>>,
     pub error_status: Option<ErrorStatus>,
     pub highlight: Option<Highlight>,
@@ -84,6 +86,7 @@ impl RenderRule {
         &self,
         file_type: &FileType,
         extension: Option<&str>,
+        mime: Option<&str>,
         git_status: Option<GitStatus>,
         error_status: ErrorStatus,
         highlight: Highlight,
@@ -106,6 +109,22 @@ impl RenderRule {
             }
         }
 
+        // Check sniffed type match
+        if let Some(ref types) = self.matchers.mime_types {
+            let matches = |pattern: &String, mime: &str| match pattern.strip_suffix("/*") {
+                Some(family) => mime.strip_prefix(family).is_some_and(|rest| rest.starts_with('/')),
+                None => pattern == mime,
+            };
+            match mime {
+                Some(mime) => {
+                    if !types.iter().any(|t| matches(t, mime)) {
+                        return false;
+                    }
+                }
+                None => return false,
+            }
+        }
+
         // Check git status match
         if let Some(ref statuses) = self.matchers.git_statuses {
             match git_status {